@@ -12,7 +12,10 @@ use syn::{Data, DeriveInput, Expr, Fields, Lit, parse_macro_input};
 ///     Dup,
 ///     #[stack_effect(consumes = 2, produces = 1)]
 ///     Add,
-///     // Variants without attributes default to consumes = 0, produces = 0
+///     // Floats are counted separately, on the float stack
+///     #[stack_effect(consumes = 1, float_produces = 1)]
+///     IntToFloat,
+///     // Variants without attributes default to consuming and producing nothing
 ///     Nop,
 /// }
 /// ```
@@ -36,23 +39,27 @@ pub fn derive_stack_effect(input: TokenStream) -> TokenStream {
         // Parse stack effect attributes
         let mut consumes = 0;
         let mut produces = 0;
+        let mut float_consumes = 0;
+        let mut float_produces = 0;
 
         for attr in &variant.attrs {
             if attr.path().is_ident("stack_effect") {
                 let _ = attr.parse_nested_meta(|meta| {
-                    if meta.path.is_ident("consumes") {
-                        let value: Expr = meta.value()?.parse()?;
-                        if let Expr::Lit(expr_lit) = value {
-                            if let Lit::Int(lit_int) = expr_lit.lit {
-                                consumes = lit_int.base10_parse::<usize>().unwrap_or(0);
-                            }
-                        }
+                    let count = if meta.path.is_ident("consumes") {
+                        &mut consumes
                     } else if meta.path.is_ident("produces") {
-                        let value: Expr = meta.value()?.parse()?;
-                        if let Expr::Lit(expr_lit) = value {
-                            if let Lit::Int(lit_int) = expr_lit.lit {
-                                produces = lit_int.base10_parse::<usize>().unwrap_or(0);
-                            }
+                        &mut produces
+                    } else if meta.path.is_ident("float_consumes") {
+                        &mut float_consumes
+                    } else if meta.path.is_ident("float_produces") {
+                        &mut float_produces
+                    } else {
+                        return Ok(());
+                    };
+                    let value: Expr = meta.value()?.parse()?;
+                    if let Expr::Lit(expr_lit) = value {
+                        if let Lit::Int(lit_int) = expr_lit.lit {
+                            *count = lit_int.base10_parse::<usize>().unwrap_or(0);
                         }
                     }
                     Ok(())
@@ -71,6 +78,8 @@ pub fn derive_stack_effect(input: TokenStream) -> TokenStream {
             #pattern => StackEffect {
                 consumes: #consumes,
                 produces: #produces,
+                float_consumes: #float_consumes,
+                float_produces: #float_produces,
             },
         });
     }
@@ -87,4 +96,3 @@ pub fn derive_stack_effect(input: TokenStream) -> TokenStream {
 
     TokenStream::from(expanded)
 }
-
@@ -3,9 +3,10 @@
 //! These are implemented as methods on RuntimeContext and are called
 //! directly from generated code.
 
-use crate::context::RuntimeContext;
-use crate::error::{ForthError, ForthResult, SourceLocation};
+use crate::context::{BASE_ADDRESS, FLOAT_SIZE, HOLD_SIZE, RuntimeContext, SYSTEM_SPACE_SIZE};
+use crate::error::{ForthError, ForthResult, SourceLocation, throw_code};
 use std::io::{self, Write};
+use std::ops::Range;
 
 impl RuntimeContext {
     // =========================================================================
@@ -197,7 +198,7 @@ impl RuntimeContext {
                 location: self.current_location.clone(),
             });
         }
-        self.push(a / b)
+        self.push(a.wrapping_div(b))
     }
 
     /// MOD: Remainder of second divided by top.
@@ -210,7 +211,7 @@ impl RuntimeContext {
                 location: self.current_location.clone(),
             });
         }
-        self.push(a % b)
+        self.push(a.wrapping_rem(b))
     }
 
     /// /MOD: Division with remainder.
@@ -223,22 +224,22 @@ impl RuntimeContext {
                 location: self.current_location.clone(),
             });
         }
-        self.stack.push(a % b);
-        self.push(a / b)
+        self.stack.push(a.wrapping_rem(b));
+        self.push(a.wrapping_div(b))
     }
 
     /// NEGATE: Negate top element.
     /// ( a -- -a )
     pub fn negate(&mut self) -> ForthResult<()> {
         let a = self.pop()?;
-        self.push(-a)
+        self.push(a.wrapping_neg())
     }
 
     /// ABS: Absolute value.
     /// ( a -- |a| )
     pub fn abs(&mut self) -> ForthResult<()> {
         let a = self.pop()?;
-        self.push(a.abs())
+        self.push(a.wrapping_abs())
     }
 
     /// MIN: Minimum of top two.
@@ -285,6 +286,128 @@ impl RuntimeContext {
         self.push(a >> 1)
     }
 
+    // =========================================================================
+    // Double-Cell Operations
+    // =========================================================================
+
+    /// Pop a double-cell number: the high cell on top, the low cell below it.
+    pub fn pop_double(&mut self) -> ForthResult<i128> {
+        let high = self.pop()?;
+        let low = self.pop()?;
+        Ok(((high as i128) << self.cell_bits) | self.unsigned(low) as i128)
+    }
+
+    /// Pop a double-cell number as an unsigned one.
+    pub fn pop_unsigned_double(&mut self) -> ForthResult<u128> {
+        let high = self.pop()?;
+        let low = self.pop()?;
+        Ok((self.unsigned(high) << self.cell_bits) | self.unsigned(low))
+    }
+
+    /// Push a double-cell number, low cell first, wrapped into two cells.
+    pub fn push_double(&mut self, value: i128) -> ForthResult<()> {
+        self.push(value as i64)?;
+        self.push((value >> self.cell_bits) as i64)
+    }
+
+    /// D+: Add two doubles.
+    /// ( d1 d2 -- d1+d2 )
+    pub fn d_plus(&mut self) -> ForthResult<()> {
+        let b = self.pop_double()?;
+        let a = self.pop_double()?;
+        self.push_double(a.wrapping_add(b))
+    }
+
+    /// D-: Subtract top double from second.
+    /// ( d1 d2 -- d1-d2 )
+    pub fn d_minus(&mut self) -> ForthResult<()> {
+        let b = self.pop_double()?;
+        let a = self.pop_double()?;
+        self.push_double(a.wrapping_sub(b))
+    }
+
+    /// DNEGATE: Negate a double.
+    /// ( d -- -d )
+    pub fn dnegate(&mut self) -> ForthResult<()> {
+        let a = self.pop_double()?;
+        self.push_double(a.wrapping_neg())
+    }
+
+    /// M*: Signed product of two cells as a double.
+    /// ( n1 n2 -- d )
+    pub fn m_star(&mut self) -> ForthResult<()> {
+        let b = self.pop()?;
+        let a = self.pop()?;
+        self.push_double(a as i128 * b as i128)
+    }
+
+    /// UM*: Unsigned product of two cells as a double.
+    /// ( u1 u2 -- ud )
+    pub fn um_star(&mut self) -> ForthResult<()> {
+        let b = self.pop()?;
+        let a = self.pop()?;
+        self.push_double((self.unsigned(a) * self.unsigned(b)) as i128)
+    }
+
+    /// UM/MOD: Unsigned division of a double by a cell.
+    /// ( ud u -- rem quot )
+    pub fn um_slash_mod(&mut self) -> ForthResult<()> {
+        let divisor = self.pop()?;
+        let divisor = self.unsigned(divisor);
+        let dividend = self.pop_unsigned_double()?;
+        if divisor == 0 {
+            return Err(ForthError::DivisionByZero {
+                location: self.current_location.clone(),
+            });
+        }
+        self.push((dividend % divisor) as i64)?;
+        self.push((dividend / divisor) as i64)
+    }
+
+    /// SM/REM: Symmetric division of a double by a cell; the quotient is
+    /// truncated towards zero.
+    /// ( d n -- rem quot )
+    pub fn sm_slash_rem(&mut self) -> ForthResult<()> {
+        let (remainder, quotient) = self.divide_double(false)?;
+        self.push(remainder)?;
+        self.push(quotient)
+    }
+
+    /// FM/MOD: Floored division of a double by a cell; the quotient is
+    /// rounded towards negative infinity.
+    /// ( d n -- rem quot )
+    pub fn fm_slash_mod(&mut self) -> ForthResult<()> {
+        let (remainder, quotient) = self.divide_double(true)?;
+        self.push(remainder)?;
+        self.push(quotient)
+    }
+
+    // Internal helper for SM/REM and FM/MOD
+    fn divide_double(&mut self, floored: bool) -> ForthResult<(i64, i64)> {
+        let divisor = self.pop()? as i128;
+        let dividend = self.pop_double()?;
+        if divisor == 0 {
+            return Err(ForthError::DivisionByZero {
+                location: self.current_location.clone(),
+            });
+        }
+        let mut quotient = dividend.wrapping_div(divisor);
+        let mut remainder = dividend.wrapping_rem(divisor);
+        if floored && remainder != 0 && (remainder < 0) != (divisor < 0) {
+            quotient -= 1;
+            remainder += divisor;
+        }
+        Ok((remainder as i64, quotient as i64))
+    }
+
+    /// D.: Print and remove a double in BASE.
+    /// ( d -- )
+    pub fn print_double(&mut self) -> ForthResult<()> {
+        let a = self.pop_double()?;
+        let text = self.format_signed(a)?;
+        self.print_string(&format!("{} ", text))
+    }
+
     // =========================================================================
     // Comparison Operations
     // =========================================================================
@@ -405,36 +528,31 @@ impl RuntimeContext {
     /// ( a n -- a>>n )
     pub fn rshift(&mut self) -> ForthResult<()> {
         let n = self.pop()? as u32;
-        let a = self.pop()? as u64;
-        self.push((a >> n) as i64)
+        let a = self.pop()?;
+        self.push((self.unsigned(a) >> n) as i64)
     }
 
     // =========================================================================
     // I/O Operations
     // =========================================================================
 
-    /// . (DOT): Print and remove top of stack.
+    /// . (DOT): Print and remove top of stack in BASE.
     /// ( a -- )
     pub fn print_top(&mut self) -> ForthResult<()> {
         let a = self.pop()?;
-        print!("{} ", a);
-        io::stdout().flush().map_err(|e| ForthError::IOError {
-            message: e.to_string(),
-            location: self.current_location.clone(),
-        })
+        let text = self.format_signed(a as i128)?;
+        self.print_string(&format!("{} ", text))
     }
 
-    /// .S: Print the entire stack without modifying it.
+    /// .S: Print the entire stack in BASE without modifying it.
     /// ( -- )
     pub fn print_stack(&mut self) -> ForthResult<()> {
-        print!("<{}> ", self.stack.len());
-        for val in &self.stack {
-            print!("{} ", val);
+        let mut text = format!("<{}> ", self.stack.len());
+        for &val in &self.stack {
+            text.push_str(&self.format_signed(val as i128)?);
+            text.push(' ');
         }
-        io::stdout().flush().map_err(|e| ForthError::IOError {
-            message: e.to_string(),
-            location: self.current_location.clone(),
-        })
+        self.print_string(&text)
     }
 
     /// EMIT: Print a character.
@@ -448,7 +566,7 @@ impl RuntimeContext {
                 location: self.current_location.clone(),
             })
         } else {
-            Err(ForthError::RuntimeError {
+            Err(ForthError::InvalidNumericArgument {
                 message: format!("Invalid character code: {}", c),
                 location: self.current_location.clone(),
             })
@@ -485,8 +603,7 @@ impl RuntimeContext {
         })
     }
 
-    /// TYPE: Print a string (address and length on stack - simplified for REPL).
-    /// In our implementation, we'll use a different approach with print_string.
+    /// Print `s` as it is; TYPE prints a string from data space instead.
     pub fn print_string(&mut self, s: &str) -> ForthResult<()> {
         print!("{}", s);
         io::stdout().flush().map_err(|e| ForthError::IOError {
@@ -508,48 +625,495 @@ impl RuntimeContext {
         self.push(buf[0] as i64)
     }
 
+    // =========================================================================
+    // Number Output
+    // =========================================================================
+
+    /// The radix in BASE, which every word that prints a number uses.
+    pub fn base(&self) -> ForthResult<u32> {
+        let base = self.read_cell(BASE_ADDRESS)?;
+        if (2..=36).contains(&base) {
+            Ok(base as u32)
+        } else {
+            Err(ForthError::InvalidNumericArgument {
+                message: format!("Invalid BASE: {}", base),
+                location: self.current_location.clone(),
+            })
+        }
+    }
+
+    // Internal helper for the signed printing words
+    fn format_signed(&self, n: i128) -> ForthResult<String> {
+        Ok(format_number(n.unsigned_abs(), n < 0, self.base()?))
+    }
+
+    /// U.: Print and remove top of stack as an unsigned number.
+    /// ( u -- )
+    pub fn print_unsigned(&mut self) -> ForthResult<()> {
+        let u = self.pop()?;
+        let text = format_number(self.unsigned(u), false, self.base()?);
+        self.print_string(&format!("{} ", text))
+    }
+
+    /// .R: Print n right-aligned in a field `width` characters wide.
+    /// ( n width -- )
+    pub fn print_aligned(&mut self) -> ForthResult<()> {
+        let width = self.pop()?;
+        let n = self.pop()?;
+        let text = self.format_signed(n as i128)?;
+        self.print_string(&format!("{:>1$}", text, width.max(0) as usize))
+    }
+
+    /// U.R: Print u right-aligned in a field `width` characters wide.
+    /// ( u width -- )
+    pub fn print_unsigned_aligned(&mut self) -> ForthResult<()> {
+        let width = self.pop()?;
+        let u = self.pop()?;
+        let text = format_number(self.unsigned(u), false, self.base()?);
+        self.print_string(&format!("{:>1$}", text, width.max(0) as usize))
+    }
+
+    /// <#: Start pictured numeric output.
+    /// ( -- )
+    pub fn less_number_sign(&mut self) -> ForthResult<()> {
+        self.held = SYSTEM_SPACE_SIZE;
+        Ok(())
+    }
+
+    /// HOLD: Add a character to the front of the pictured output.
+    /// ( char -- )
+    pub fn hold(&mut self) -> ForthResult<()> {
+        let c = self.pop()?;
+        self.hold_char(c as u8)
+    }
+
+    // Internal helper for HOLD and the words that hold digits and signs
+    fn hold_char(&mut self, c: u8) -> ForthResult<()> {
+        if self.held <= SYSTEM_SPACE_SIZE - HOLD_SIZE {
+            return Err(ForthError::PicturedOutputOverflow {
+                location: self.current_location.clone(),
+            });
+        }
+        self.held -= 1;
+        self.data[self.held] = c;
+        Ok(())
+    }
+
+    /// #: Hold the least significant digit of ud in BASE.
+    /// ( ud1 -- ud2 )
+    pub fn number_sign(&mut self) -> ForthResult<()> {
+        let ud = self.pop_unsigned_double()?;
+        let rest = self.hold_digit(ud)?;
+        self.push_double(rest as i128)
+    }
+
+    /// #S: Hold digits of ud until none are left, always at least one.
+    /// ( ud -- 0 0 )
+    pub fn number_sign_s(&mut self) -> ForthResult<()> {
+        let mut ud = self.pop_unsigned_double()?;
+        loop {
+            ud = self.hold_digit(ud)?;
+            if ud == 0 {
+                break;
+            }
+        }
+        self.push_double(0)
+    }
+
+    // Internal helper for # and #S: hold the last digit, return the rest
+    fn hold_digit(&mut self, ud: u128) -> ForthResult<u128> {
+        let base = self.base()? as u128;
+        self.hold_char(digit_char((ud % base) as u32))?;
+        Ok(ud / base)
+    }
+
+    /// SIGN: Hold a minus sign if n is negative.
+    /// ( n -- )
+    pub fn sign(&mut self) -> ForthResult<()> {
+        let n = self.pop()?;
+        if n < 0 { self.hold_char(b'-') } else { Ok(()) }
+    }
+
+    /// #>: End pictured numeric output, leaving it as a string.
+    /// ( xd -- c-addr u )
+    pub fn number_sign_greater(&mut self) -> ForthResult<()> {
+        self.pop_double()?;
+        self.push(self.held as i64)?;
+        self.push((SYSTEM_SPACE_SIZE - self.held) as i64)
+    }
+
     // =========================================================================
     // Memory Operations
     // =========================================================================
 
-    /// !: Store value to variable.
-    /// ( value -- ) with variable name
-    pub fn store(&mut self, var: &str) -> ForthResult<()> {
+    /// !: Store a cell.
+    /// ( x addr -- )
+    pub fn store(&mut self) -> ForthResult<()> {
+        let addr = self.pop()?;
         let value = self.pop()?;
-        self.memory.insert(var.to_string(), value);
-        Ok(())
+        self.write_cell(addr, value)
     }
 
-    /// @: Fetch value from variable.
-    /// ( -- value ) with variable name
-    pub fn fetch(&mut self, var: &str) -> ForthResult<()> {
-        let value =
-            self.memory
-                .get(var)
-                .copied()
-                .ok_or_else(|| ForthError::InvalidMemoryAccess {
-                    variable: var.to_string(),
-                    location: self.current_location.clone(),
-                })?;
+    /// @: Fetch a cell.
+    /// ( addr -- x )
+    pub fn fetch(&mut self) -> ForthResult<()> {
+        let addr = self.pop()?;
+        let value = self.read_cell(addr)?;
         self.push(value)
     }
 
-    /// +!: Add to variable.
-    /// ( n -- ) with variable name
-    pub fn add_store(&mut self, var: &str) -> ForthResult<()> {
+    /// +!: Add to a cell.
+    /// ( n addr -- )
+    pub fn add_store(&mut self) -> ForthResult<()> {
+        let addr = self.pop()?;
         let n = self.pop()?;
-        let current =
-            self.memory
-                .get(var)
-                .copied()
-                .ok_or_else(|| ForthError::InvalidMemoryAccess {
-                    variable: var.to_string(),
-                    location: self.current_location.clone(),
-                })?;
-        self.memory.insert(var.to_string(), current + n);
+        let current = self.read_cell(addr)?;
+        self.write_cell(addr, current.wrapping_add(n))
+    }
+
+    /// C!: Store the low byte of a cell.
+    /// ( c addr -- )
+    pub fn c_store(&mut self) -> ForthResult<()> {
+        let addr = self.pop()?;
+        let value = self.pop()?;
+        let range = self.data_range(addr, 1)?;
+        self.data[range.start] = value as u8;
         Ok(())
     }
 
+    /// C@: Fetch a byte.
+    /// ( addr -- c )
+    pub fn c_fetch(&mut self) -> ForthResult<()> {
+        let addr = self.pop()?;
+        let range = self.data_range(addr, 1)?;
+        self.push(self.data[range.start] as i64)
+    }
+
+    /// HERE: Address of the next free byte of data space.
+    /// ( -- addr )
+    pub fn here(&mut self) -> ForthResult<()> {
+        self.push(self.data.len() as i64)
+    }
+
+    /// ALLOT: Reserve n bytes of data space, or release them if n is negative.
+    /// ( n -- )
+    pub fn allot(&mut self) -> ForthResult<()> {
+        let n = self.pop()?;
+        self.resize_data(n)
+    }
+
+    /// ,: Append a cell to data space.
+    /// ( x -- )
+    pub fn comma(&mut self) -> ForthResult<()> {
+        let value = self.pop()?;
+        let addr = self.data.len() as i64;
+        self.resize_data(self.cell_size() as i64)?;
+        self.write_cell(addr, value)
+    }
+
+    /// C,: Append a byte to data space.
+    /// ( c -- )
+    pub fn c_comma(&mut self) -> ForthResult<()> {
+        let value = self.pop()?;
+        self.resize_data(1)?;
+        *self.data.last_mut().unwrap() = value as u8;
+        Ok(())
+    }
+
+    /// ALIGN: Pad data space to a cell boundary.
+    /// ( -- )
+    pub fn align(&mut self) -> ForthResult<()> {
+        let padding = self.data.len().next_multiple_of(self.cell_size()) - self.data.len();
+        self.resize_data(padding as i64)
+    }
+
+    /// CREATE: Align data space and give `name` the current HERE.
+    /// ( -- )
+    pub fn create(&mut self, name: &str) -> ForthResult<()> {
+        self.align()?;
+        self.data_fields
+            .insert(name.to_string(), self.data.len() as i64);
+        Ok(())
+    }
+
+    /// Supply the name for the next CREATE inside a defining word.
+    /// ( -- )
+    pub fn supply_name(&mut self, name: &str) {
+        self.supplied_name = Some(name.to_string());
+    }
+
+    /// CREATE inside a defining word: like `create`, using the supplied name.
+    /// ( -- )
+    pub fn create_supplied(&mut self) -> ForthResult<()> {
+        let name = self
+            .supplied_name
+            .clone()
+            .ok_or_else(|| ForthError::RuntimeError {
+                message: "CREATE has no name to define".to_string(),
+                location: self.current_location.clone(),
+            })?;
+        self.create(&name)
+    }
+
+    /// Push the address of a CREATEd word or variable.
+    /// ( -- addr )
+    pub fn push_data_field(&mut self, name: &str) -> ForthResult<()> {
+        let addr = self.data_field(name)?;
+        self.push(addr)
+    }
+
+    // =========================================================================
+    // String Operations
+    // =========================================================================
+
+    /// Lay `text` down in data space after a count byte, the first time it is
+    /// asked for, and return the address of the count byte. Generated code
+    /// interns its literals before it runs, so they do not land in the middle
+    /// of data the program is building.
+    pub fn intern_string(&mut self, text: &str) -> ForthResult<i64> {
+        if let Some(&addr) = self.strings.get(text) {
+            return Ok(addr);
+        }
+        let addr = self.data.len() as i64;
+        self.resize_data(1 + text.len() as i64)?;
+        let start = addr as usize;
+        self.data[start] = text.len() as u8;
+        self.data[start + 1..].copy_from_slice(text.as_bytes());
+        self.strings.insert(text.to_string(), addr);
+        Ok(addr)
+    }
+
+    /// S": Push the address and length of a string literal.
+    /// ( -- c-addr u )
+    pub fn push_string(&mut self, text: &str) -> ForthResult<()> {
+        let addr = self.intern_string(text)?;
+        self.push(addr + 1)?;
+        self.push(text.len() as i64)
+    }
+
+    /// C": Push the address of a counted string literal.
+    /// ( -- c-addr )
+    pub fn push_counted_string(&mut self, text: &str) -> ForthResult<()> {
+        let addr = self.intern_string(text)?;
+        self.push(addr)
+    }
+
+    /// TYPE: Print a string.
+    /// ( c-addr u -- )
+    pub fn type_string(&mut self) -> ForthResult<()> {
+        let len = self.pop()?;
+        let addr = self.pop()?;
+        let range = self.data_range(addr, len as usize)?;
+        let mut stdout = io::stdout();
+        stdout
+            .write_all(&self.data[range])
+            .and_then(|()| stdout.flush())
+            .map_err(|e| ForthError::IOError {
+                message: e.to_string(),
+                location: self.current_location.clone(),
+            })
+    }
+
+    /// COMPARE: Compare two strings; -1, 0 or 1 as the first sorts before,
+    /// the same as or after the second.
+    /// ( c-addr1 u1 c-addr2 u2 -- n )
+    pub fn compare(&mut self) -> ForthResult<()> {
+        let (first, second) = self.pop_string_pair()?;
+        self.push(compare_bytes(&self.data[first], &self.data[second]))
+    }
+
+    /// SEARCH: Find the second string in the first, leaving the rest of the
+    /// first from the match on, or all of it if there is no match.
+    /// ( c-addr1 u1 c-addr2 u2 -- c-addr3 u3 flag )
+    pub fn search(&mut self) -> ForthResult<()> {
+        let (haystack, needle) = self.pop_string_pair()?;
+        match search_bytes(&self.data[haystack.clone()], &self.data[needle]) {
+            Some(offset) => {
+                self.push((haystack.start + offset) as i64)?;
+                self.push((haystack.len() - offset) as i64)?;
+                self.push(-1)
+            }
+            None => {
+                self.push(haystack.start as i64)?;
+                self.push(haystack.len() as i64)?;
+                self.push(0)
+            }
+        }
+    }
+
+    // Internal helper for the two strings COMPARE and SEARCH take
+    fn pop_string_pair(&mut self) -> ForthResult<(Range<usize>, Range<usize>)> {
+        let len2 = self.pop()?;
+        let addr2 = self.pop()?;
+        let len1 = self.pop()?;
+        let addr1 = self.pop()?;
+        Ok((
+            self.data_range(addr1, len1 as usize)?,
+            self.data_range(addr2, len2 as usize)?,
+        ))
+    }
+
+    /// -TRAILING: Shorten a string to leave out its trailing spaces.
+    /// ( c-addr u1 -- c-addr u2 )
+    pub fn minus_trailing(&mut self) -> ForthResult<()> {
+        let len = self.pop()?;
+        let addr = self.pop()?;
+        let range = self.data_range(addr, len as usize)?;
+        self.push(addr)?;
+        self.push(trimmed_len(&self.data[range]) as i64)
+    }
+
+    /// CMOVE: Copy bytes one at a time from the lowest address up, so a
+    /// destination just above the source repeats its first bytes.
+    /// ( c-addr1 c-addr2 u -- )
+    pub fn cmove(&mut self) -> ForthResult<()> {
+        let (from, to) = self.pop_move()?;
+        for i in 0..from.len() {
+            self.data[to.start + i] = self.data[from.start + i];
+        }
+        Ok(())
+    }
+
+    /// MOVE: Copy bytes as if through a buffer, so overlapping ranges are
+    /// copied intact.
+    /// ( addr1 addr2 u -- )
+    pub fn move_bytes(&mut self) -> ForthResult<()> {
+        let (from, to) = self.pop_move()?;
+        self.data.copy_within(from, to.start);
+        Ok(())
+    }
+
+    // Internal helper for the source and destination CMOVE and MOVE take
+    fn pop_move(&mut self) -> ForthResult<(Range<usize>, Range<usize>)> {
+        let len = self.pop()?;
+        let to = self.pop()?;
+        let from = self.pop()?;
+        Ok((
+            self.data_range(from, len as usize)?,
+            self.data_range(to, len as usize)?,
+        ))
+    }
+
+    // =========================================================================
+    // Floating-Point Operations
+    // =========================================================================
+
+    /// F+: Add the top two floats.
+    /// ( F: r1 r2 -- r3 )
+    pub fn fadd(&mut self) -> ForthResult<()> {
+        let b = self.fpop()?;
+        let a = self.fpop()?;
+        self.fpush(a + b)
+    }
+
+    /// F-: Subtract the top float from the one below it.
+    /// ( F: r1 r2 -- r3 )
+    pub fn fsub(&mut self) -> ForthResult<()> {
+        let b = self.fpop()?;
+        let a = self.fpop()?;
+        self.fpush(a - b)
+    }
+
+    /// F*: Multiply the top two floats.
+    /// ( F: r1 r2 -- r3 )
+    pub fn fmul(&mut self) -> ForthResult<()> {
+        let b = self.fpop()?;
+        let a = self.fpop()?;
+        self.fpush(a * b)
+    }
+
+    /// F/: Divide; dividing by zero gives an infinity or NaN.
+    /// ( F: r1 r2 -- r3 )
+    pub fn fdiv(&mut self) -> ForthResult<()> {
+        let b = self.fpop()?;
+        let a = self.fpop()?;
+        self.fpush(a / b)
+    }
+
+    /// FDUP: Duplicate the top float.
+    /// ( F: r -- r r )
+    pub fn fdup(&mut self) -> ForthResult<()> {
+        let r = self.fpop()?;
+        self.fstack.push(r);
+        self.fpush(r)
+    }
+
+    /// FSWAP: Swap the top two floats.
+    /// ( F: r1 r2 -- r2 r1 )
+    pub fn fswap(&mut self) -> ForthResult<()> {
+        let b = self.fpop()?;
+        let a = self.fpop()?;
+        self.fstack.push(b);
+        self.fpush(a)
+    }
+
+    /// FDROP: Remove the top float.
+    /// ( F: r -- )
+    pub fn fdrop(&mut self) -> ForthResult<()> {
+        self.fpop()?;
+        Ok(())
+    }
+
+    /// F<: Compare the top two floats.
+    /// ( -- flag ) ( F: r1 r2 -- )
+    pub fn f_less(&mut self) -> ForthResult<()> {
+        let b = self.fpop()?;
+        let a = self.fpop()?;
+        self.push(if a < b { -1 } else { 0 })
+    }
+
+    /// F0=: Test the top float for zero.
+    /// ( -- flag ) ( F: r -- )
+    pub fn f_zero_eq(&mut self) -> ForthResult<()> {
+        let r = self.fpop()?;
+        self.push(if r == 0.0 { -1 } else { 0 })
+    }
+
+    /// S>F: Convert a cell to a float.
+    /// ( n -- ) ( F: -- r )
+    pub fn s_to_f(&mut self) -> ForthResult<()> {
+        let n = self.pop()?;
+        self.fpush(n as f64)
+    }
+
+    /// F>S: Convert a float to a cell, truncating towards zero.
+    /// ( -- n ) ( F: r -- )
+    pub fn f_to_s(&mut self) -> ForthResult<()> {
+        let r = self.fpop()?;
+        self.push(r as i64)
+    }
+
+    /// F@: Fetch a float.
+    /// ( addr -- ) ( F: -- r )
+    pub fn f_fetch(&mut self) -> ForthResult<()> {
+        let addr = self.pop()?;
+        let range = self.data_range(addr, FLOAT_SIZE)?;
+        let mut bytes = [0u8; FLOAT_SIZE];
+        bytes.copy_from_slice(&self.data[range]);
+        self.fpush(f64::from_ne_bytes(bytes))
+    }
+
+    /// F!: Store a float.
+    /// ( addr -- ) ( F: r -- )
+    pub fn f_store(&mut self) -> ForthResult<()> {
+        let addr = self.pop()?;
+        let r = self.fpop()?;
+        let range = self.data_range(addr, FLOAT_SIZE)?;
+        self.data[range].copy_from_slice(&r.to_ne_bytes());
+        Ok(())
+    }
+
+    /// F.: Print and remove the top float.
+    /// ( F: r -- )
+    pub fn print_float(&mut self) -> ForthResult<()> {
+        let r = self.fpop()?;
+        print!("{} ", format_float(r));
+        io::stdout().flush().map_err(|e| ForthError::IOError {
+            message: e.to_string(),
+            location: self.current_location.clone(),
+        })
+    }
+
     // =========================================================================
     // Return Stack Operations
     // =========================================================================
@@ -632,6 +1196,152 @@ impl RuntimeContext {
 
         result
     }
+
+    /// ' name: Push the execution token of a registered word, giving it one
+    /// the first time.
+    /// ( -- xt )
+    pub fn push_xt(&mut self, name: &str) -> ForthResult<()> {
+        let index = match self.xts.iter().position(|word| word == name) {
+            Some(index) => index,
+            None => {
+                self.xts.push(name.to_string());
+                self.xts.len() - 1
+            }
+        };
+        self.push(index as i64 + 1)
+    }
+
+    /// EXECUTE: Run the word an execution token names.
+    /// ( i*x xt -- j*x )
+    pub fn execute(&mut self) -> ForthResult<()> {
+        let xt = self.pop()?;
+        self.execute_xt(xt)
+    }
+
+    fn execute_xt(&mut self, xt: i64) -> ForthResult<()> {
+        let name = usize::try_from(xt - 1)
+            .ok()
+            .and_then(|index| self.xts.get(index))
+            .cloned()
+            .ok_or_else(|| ForthError::InvalidExecutionToken {
+                xt,
+                location: self.current_location.clone(),
+            })?;
+        self.call_word(&name)
+    }
+
+    // =========================================================================
+    // Exceptions
+    // =========================================================================
+
+    /// CATCH: Run an execution token, pushing 0 if it returns, or the code
+    /// of what it threw with the stacks put back to the depths they had
+    /// before.
+    /// ( i*x xt -- j*x 0 | i*x n )
+    pub fn catch(&mut self) -> ForthResult<()> {
+        let xt = self.pop()?;
+        let depth = self.stack.len();
+        let float_depth = self.fstack.len();
+        let return_depth = self.rstack.len();
+        match self.execute_xt(xt) {
+            Ok(()) => self.push(0),
+            Err(error) => {
+                self.stack.resize(depth, 0);
+                self.fstack.resize(float_depth, 0.0);
+                self.rstack.truncate(return_depth);
+                self.push(error.code())
+            }
+        }
+    }
+
+    /// THROW: Unwind to the innermost CATCH with code n, unless n is 0.
+    /// ( n -- )
+    pub fn throw(&mut self) -> ForthResult<()> {
+        match self.pop()? {
+            0 => Ok(()),
+            code => Err(ForthError::Throw {
+                code,
+                location: self.current_location.clone(),
+            }),
+        }
+    }
+
+    /// ABORT: THROW -1.
+    /// ( -- )
+    pub fn abort(&mut self) -> ForthResult<()> {
+        Err(ForthError::Throw {
+            code: throw_code::ABORT,
+            location: self.current_location.clone(),
+        })
+    }
+
+    /// ABORT": THROW -2 with `message` if the flag is true.
+    /// ( flag -- )
+    pub fn abort_quote(&mut self, message: &str) -> ForthResult<()> {
+        if self.pop()? == 0 {
+            return Ok(());
+        }
+        Err(ForthError::AbortQuote {
+            message: message.to_string(),
+            location: self.current_location.clone(),
+        })
+    }
 }
 
 use std::io::Read;
+
+/// COMPARE's result for two strings: -1, 0 or 1 as `a` sorts before, the
+/// same as or after `b`, byte by byte with a prefix sorting first.
+pub fn compare_bytes(a: &[u8], b: &[u8]) -> i64 {
+    a.cmp(b) as i64
+}
+
+/// Where SEARCH finds `needle` in `haystack`; an empty needle matches at the
+/// start.
+pub fn search_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Length of `text` without its trailing spaces, as -TRAILING leaves it
+pub fn trimmed_len(text: &[u8]) -> usize {
+    text.iter().rposition(|&c| c != b' ').map_or(0, |i| i + 1)
+}
+
+/// `magnitude` in `base`, from 2 to 36, after a minus sign when `negative`.
+pub fn format_number(magnitude: u128, negative: bool, base: u32) -> String {
+    let mut digits = Vec::new();
+    let mut rest = magnitude;
+    loop {
+        digits.push(digit_char((rest % base as u128) as u32));
+        rest /= base as u128;
+        if rest == 0 {
+            break;
+        }
+    }
+    if negative {
+        digits.push(b'-');
+    }
+    digits.iter().rev().map(|&c| c as char).collect()
+}
+
+/// The character for `digit` in a printed number; digits past 9 are
+/// upper-case letters.
+pub fn digit_char(digit: u32) -> u8 {
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ"[digit as usize]
+}
+
+/// A float as F. prints it: the fewest digits that read back as the same
+/// value, never in exponent form, with a trailing `.` when it is whole.
+pub fn format_float(r: f64) -> String {
+    let text = r.to_string();
+    if r.is_finite() && !text.contains('.') {
+        text + "."
+    } else {
+        text
+    }
+}
@@ -2,6 +2,7 @@
 
 use crate::error::{ForthError, ForthResult, SourceLocation};
 use std::collections::HashMap;
+use std::ops::Range;
 
 /// Function pointer type for user-defined words.
 /// Note: We use regular Rust ABI here since both the runtime and dynamically
@@ -11,43 +12,111 @@ pub type WordFn = fn(&mut RuntimeContext) -> ForthResult<()>;
 /// Maximum stack size to prevent runaway programs.
 pub const DEFAULT_MAX_STACK_SIZE: usize = 10_000;
 
+/// Maximum data space size in bytes, the same limit the compiled backends use.
+pub const DEFAULT_DATA_SPACE_SIZE: usize = 1 << 20;
+
+/// Size of the widest cell in data space; `RuntimeContext::cell_size` is the
+/// size in use.
+pub const CELL_SIZE: usize = std::mem::size_of::<i64>();
+
+/// Cell width in bits when none is given.
+pub const DEFAULT_CELL_BITS: u32 = 64;
+
+/// Size of a float in data space.
+pub const FLOAT_SIZE: usize = std::mem::size_of::<f64>();
+
+/// Address of BASE, the first cell of data space.
+pub const BASE_ADDRESS: i64 = 0;
+
+/// Room for pictured numeric output: a double cell in binary with a sign
+/// and other held characters besides.
+pub const HOLD_SIZE: usize = 256;
+
+/// Bytes at the start of data space, below the first HERE: BASE, given
+/// eight bytes whatever the cell size, then the buffer that pictured numeric
+/// output is built in from its end down. Every backend lays it out the same.
+pub const SYSTEM_SPACE_SIZE: usize = 8 + HOLD_SIZE;
+
 /// Runtime context containing all execution state.
 ///
 /// This struct is passed to all compiled words and contains:
 /// - The main data stack
+/// - The floating-point stack
 /// - The return stack (for control flow)
-/// - Variable storage
+/// - The data space used by CREATE, VARIABLE, ALLOT and friends
 /// - Registered user-defined words
-#[derive(Default)]
 pub struct RuntimeContext {
     /// Main data stack.
     pub stack: Vec<i64>,
 
+    /// Floating-point stack, kept apart from the data stack.
+    pub fstack: Vec<f64>,
+
     /// Return stack (for control flow, loop indices, etc.).
     pub rstack: Vec<i64>,
 
-    /// Variable storage (name -> value).
-    pub memory: HashMap<String, i64>,
+    /// Data space, addressed in bytes. HERE is its length.
+    pub data: Vec<u8>,
+
+    /// Addresses given to CREATEd words and variables (name -> address).
+    pub data_fields: HashMap<String, i64>,
+
+    /// String literals laid down in data space (text -> address of the
+    /// count byte before it).
+    pub strings: HashMap<String, i64>,
+
+    /// Start of the pictured numeric output held so far; HOLD lays each
+    /// character down just below it.
+    pub held: usize,
+
+    /// Maximum data space size in bytes (0 = unlimited).
+    pub max_data_size: usize,
+
+    /// Name for the next CREATE run inside a defining word, supplied by the
+    /// code that called the defining word.
+    pub supplied_name: Option<String>,
 
     /// Registered user-defined words (name -> function pointer).
     pub words: HashMap<String, WordFn>,
 
+    /// Names of the words execution tokens were taken of; a word's token is
+    /// its index plus one, so 0 is none.
+    pub xts: Vec<String>,
+
     /// Maximum stack size (0 = unlimited).
     pub max_stack_size: usize,
 
+    /// Cell width in bits, 32 or 64. Cells are held in an `i64` either way,
+    /// wrapped into this width when pushed and stored.
+    pub cell_bits: u32,
+
     /// Current execution location (for error reporting).
     pub current_location: SourceLocation,
 }
 
+impl Default for RuntimeContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RuntimeContext {
     /// Create a new runtime context with default settings.
     pub fn new() -> Self {
         Self {
             stack: Vec::new(),
+            fstack: Vec::new(),
             rstack: Vec::new(),
-            memory: HashMap::new(),
+            data: Self::system_space(DEFAULT_CELL_BITS),
+            data_fields: HashMap::new(),
+            strings: HashMap::new(),
+            held: SYSTEM_SPACE_SIZE,
+            max_data_size: DEFAULT_DATA_SPACE_SIZE,
+            supplied_name: None,
             words: HashMap::new(),
+            xts: Vec::new(),
             max_stack_size: DEFAULT_MAX_STACK_SIZE,
+            cell_bits: DEFAULT_CELL_BITS,
             current_location: SourceLocation::default(),
         }
     }
@@ -60,6 +129,41 @@ impl RuntimeContext {
         }
     }
 
+    /// Create a new runtime context with `cell_bits` wide cells, 32 or 64.
+    pub fn with_cell_bits(cell_bits: u32) -> Self {
+        Self {
+            data: Self::system_space(cell_bits),
+            cell_bits,
+            ..Self::new()
+        }
+    }
+
+    // Data space as a program starts with it, holding BASE set to decimal
+    fn system_space(cell_bits: u32) -> Vec<u8> {
+        let mut data = vec![0; SYSTEM_SPACE_SIZE];
+        let base = cell_bytes(10, cell_bits);
+        data[..base.len()].copy_from_slice(&base);
+        data
+    }
+
+    /// Size of a cell in data space.
+    pub fn cell_size(&self) -> usize {
+        (self.cell_bits / 8) as usize
+    }
+
+    /// Two's-complement wrap of `value` into a cell.
+    pub fn wrap(&self, value: i64) -> i64 {
+        match self.cell_bits {
+            32 => value as i32 as i64,
+            _ => value,
+        }
+    }
+
+    /// `value` read as an unsigned cell.
+    pub fn unsigned(&self, value: i64) -> u128 {
+        value as u128 & ((1u128 << self.cell_bits) - 1)
+    }
+
     /// Set the current execution location (for error reporting).
     pub fn set_location(&mut self, location: SourceLocation) {
         self.current_location = location;
@@ -85,10 +189,57 @@ impl RuntimeContext {
         self.words.contains_key(name)
     }
 
-    /// Declare a variable (initialize to 0 if not present).
-    pub fn declare_variable(&mut self, name: impl Into<String>) {
-        let name = name.into();
-        self.memory.entry(name).or_insert(0);
+    /// Look up the address a CREATEd word or variable was given.
+    pub fn data_field(&self, name: &str) -> ForthResult<i64> {
+        self.data_fields
+            .get(name)
+            .copied()
+            .ok_or_else(|| ForthError::UndefinedWord {
+                name: name.to_string(),
+                location: self.current_location.clone(),
+            })
+    }
+
+    /// Byte range of `len` bytes at `addr`, which must lie below HERE.
+    pub(crate) fn data_range(&self, addr: i64, len: usize) -> ForthResult<Range<usize>> {
+        usize::try_from(addr)
+            .ok()
+            .map(|start| start..start.saturating_add(len))
+            .filter(|range| range.end <= self.data.len())
+            .ok_or_else(|| ForthError::InvalidMemoryAccess {
+                address: addr,
+                location: self.current_location.clone(),
+            })
+    }
+
+    /// Read the cell stored at `addr`.
+    pub fn read_cell(&self, addr: i64) -> ForthResult<i64> {
+        let range = self.data_range(addr, self.cell_size())?;
+        let bytes = &self.data[range];
+        Ok(match self.cell_bits {
+            32 => i32::from_ne_bytes(bytes.try_into().unwrap()) as i64,
+            _ => i64::from_ne_bytes(bytes.try_into().unwrap()),
+        })
+    }
+
+    /// Write a cell at `addr`.
+    pub(crate) fn write_cell(&mut self, addr: i64, value: i64) -> ForthResult<()> {
+        let range = self.data_range(addr, self.cell_size())?;
+        self.data[range].copy_from_slice(&cell_bytes(value, self.cell_bits));
+        Ok(())
+    }
+
+    /// Move HERE by `n` bytes, zero-filling newly reserved space.
+    pub(crate) fn resize_data(&mut self, n: i64) -> ForthResult<()> {
+        let new_len = (self.data.len() as i64)
+            .checked_add(n)
+            .and_then(|len| usize::try_from(len).ok())
+            .filter(|&len| self.max_data_size == 0 || len <= self.max_data_size)
+            .ok_or_else(|| ForthError::DataSpaceOverflow {
+                location: self.current_location.clone(),
+            })?;
+        self.data.resize(new_len, 0);
+        Ok(())
     }
 
     /// Get stack depth.
@@ -96,6 +247,11 @@ impl RuntimeContext {
         self.stack.len()
     }
 
+    /// Get float stack depth.
+    pub fn float_depth(&self) -> usize {
+        self.fstack.len()
+    }
+
     /// Check if stack has at least n elements.
     pub fn has_n(&self, n: usize) -> bool {
         self.stack.len() >= n
@@ -119,10 +275,10 @@ impl RuntimeContext {
         Ok(())
     }
 
-    /// Push a value onto the stack.
+    /// Push a value onto the stack, wrapped into a cell.
     pub fn push(&mut self, value: i64) -> ForthResult<()> {
         self.check_overflow()?;
-        self.stack.push(value);
+        self.stack.push(self.wrap(value));
         Ok(())
     }
 
@@ -141,6 +297,27 @@ impl RuntimeContext {
             })
     }
 
+    /// Push a value onto the float stack.
+    pub fn fpush(&mut self, value: f64) -> ForthResult<()> {
+        if self.max_stack_size > 0 && self.fstack.len() >= self.max_stack_size {
+            return Err(ForthError::StackOverflow {
+                location: self.current_location.clone(),
+                max_size: self.max_stack_size,
+            });
+        }
+        self.fstack.push(value);
+        Ok(())
+    }
+
+    /// Pop a value from the float stack.
+    pub fn fpop(&mut self) -> ForthResult<f64> {
+        self.fstack
+            .pop()
+            .ok_or_else(|| ForthError::FloatStackUnderflow {
+                location: self.current_location.clone(),
+            })
+    }
+
     /// Peek at the nth element from the top (0 = top).
     pub fn peek_n(&self, n: usize) -> ForthResult<i64> {
         let len = self.stack.len();
@@ -150,3 +327,11 @@ impl RuntimeContext {
         Ok(self.stack[len - 1 - n])
     }
 }
+
+// `value` as the native-endian bytes of a `cell_bits` wide cell
+fn cell_bytes(value: i64, cell_bits: u32) -> Vec<u8> {
+    match cell_bits {
+        32 => (value as i32).to_ne_bytes().to_vec(),
+        _ => value.to_ne_bytes().to_vec(),
+    }
+}
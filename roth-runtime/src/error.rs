@@ -64,6 +64,9 @@ pub enum ForthError {
         max_size: usize,
     },
 
+    /// Attempted to pop from an empty float stack.
+    FloatStackUnderflow { location: SourceLocation },
+
     /// Attempted to pop from an empty return stack.
     ReturnStackUnderflow { location: SourceLocation },
 
@@ -76,9 +79,24 @@ pub enum ForthError {
         location: SourceLocation,
     },
 
-    /// Invalid memory access (variable not found).
+    /// EXECUTE of a cell that is no word's execution token.
+    InvalidExecutionToken { xt: i64, location: SourceLocation },
+
+    /// Memory access outside the allocated data space.
     InvalidMemoryAccess {
-        variable: String,
+        address: i64,
+        location: SourceLocation,
+    },
+
+    /// ALLOT or `,` would grow the data space past its limit or below zero.
+    DataSpaceOverflow { location: SourceLocation },
+
+    /// HOLD ran past the start of the pictured numeric output buffer.
+    PicturedOutputOverflow { location: SourceLocation },
+
+    /// A number out of the range a word accepts, such as BASE outside 2..=36.
+    InvalidNumericArgument {
+        message: String,
         location: SourceLocation,
     },
 
@@ -93,6 +111,76 @@ pub enum ForthError {
         message: String,
         location: SourceLocation,
     },
+
+    /// THROW with a code of the program's own, or ABORT.
+    Throw { code: i64, location: SourceLocation },
+
+    /// ABORT" with its message.
+    AbortQuote {
+        message: String,
+        location: SourceLocation,
+    },
+}
+
+/// Standard THROW codes, as CATCH pushes them for each error.
+pub mod throw_code {
+    pub const ABORT: i64 = -1;
+    pub const ABORT_QUOTE: i64 = -2;
+    pub const STACK_OVERFLOW: i64 = -3;
+    pub const STACK_UNDERFLOW: i64 = -4;
+    pub const RETURN_STACK_UNDERFLOW: i64 = -6;
+    pub const DATA_SPACE_OVERFLOW: i64 = -8;
+    pub const INVALID_MEMORY_ADDRESS: i64 = -9;
+    pub const DIVISION_BY_ZERO: i64 = -10;
+    pub const UNDEFINED_WORD: i64 = -13;
+    pub const PICTURED_OUTPUT_OVERFLOW: i64 = -17;
+    pub const UNSUPPORTED_OPERATION: i64 = -21;
+    pub const INVALID_NUMERIC_ARGUMENT: i64 = -24;
+    pub const FILE_IO: i64 = -37;
+    pub const FLOAT_STACK_UNDERFLOW: i64 = -45;
+
+    /// Every code above with its name, for generated programs to define.
+    pub const ALL: &[(&str, i64)] = &[
+        ("ABORT", ABORT),
+        ("ABORT_QUOTE", ABORT_QUOTE),
+        ("STACK_OVERFLOW", STACK_OVERFLOW),
+        ("STACK_UNDERFLOW", STACK_UNDERFLOW),
+        ("RETURN_STACK_UNDERFLOW", RETURN_STACK_UNDERFLOW),
+        ("DATA_SPACE_OVERFLOW", DATA_SPACE_OVERFLOW),
+        ("INVALID_MEMORY_ADDRESS", INVALID_MEMORY_ADDRESS),
+        ("DIVISION_BY_ZERO", DIVISION_BY_ZERO),
+        ("UNDEFINED_WORD", UNDEFINED_WORD),
+        ("PICTURED_OUTPUT_OVERFLOW", PICTURED_OUTPUT_OVERFLOW),
+        ("UNSUPPORTED_OPERATION", UNSUPPORTED_OPERATION),
+        ("INVALID_NUMERIC_ARGUMENT", INVALID_NUMERIC_ARGUMENT),
+        ("FILE_IO", FILE_IO),
+        ("FLOAT_STACK_UNDERFLOW", FLOAT_STACK_UNDERFLOW),
+    ];
+}
+
+impl ForthError {
+    /// The THROW code CATCH pushes for this error.
+    pub fn code(&self) -> i64 {
+        match self {
+            ForthError::StackUnderflow { .. } => throw_code::STACK_UNDERFLOW,
+            ForthError::StackOverflow { .. } => throw_code::STACK_OVERFLOW,
+            ForthError::FloatStackUnderflow { .. } => throw_code::FLOAT_STACK_UNDERFLOW,
+            ForthError::ReturnStackUnderflow { .. } => throw_code::RETURN_STACK_UNDERFLOW,
+            ForthError::DivisionByZero { .. } => throw_code::DIVISION_BY_ZERO,
+            ForthError::UndefinedWord { .. } | ForthError::InvalidExecutionToken { .. } => {
+                throw_code::UNDEFINED_WORD
+            }
+            ForthError::InvalidMemoryAccess { .. } => throw_code::INVALID_MEMORY_ADDRESS,
+            ForthError::DataSpaceOverflow { .. } => throw_code::DATA_SPACE_OVERFLOW,
+            ForthError::PicturedOutputOverflow { .. } => throw_code::PICTURED_OUTPUT_OVERFLOW,
+            ForthError::InvalidNumericArgument { .. } => throw_code::INVALID_NUMERIC_ARGUMENT,
+            ForthError::IOError { .. } => throw_code::FILE_IO,
+            // No standard code describes a generic error better
+            ForthError::RuntimeError { .. } => throw_code::UNSUPPORTED_OPERATION,
+            ForthError::Throw { code, .. } => *code,
+            ForthError::AbortQuote { .. } => throw_code::ABORT_QUOTE,
+        }
+    }
 }
 
 impl fmt::Display for ForthError {
@@ -104,6 +192,9 @@ impl fmt::Display for ForthError {
             ForthError::StackOverflow { location, max_size } => {
                 write!(f, "Stack overflow (max {} elements) {}", max_size, location)
             }
+            ForthError::FloatStackUnderflow { location } => {
+                write!(f, "Float stack underflow {}", location)
+            }
             ForthError::ReturnStackUnderflow { location } => {
                 write!(f, "Return stack underflow {}", location)
             }
@@ -113,12 +204,20 @@ impl fmt::Display for ForthError {
             ForthError::UndefinedWord { name, location } => {
                 write!(f, "Undefined word '{}' {}", name, location)
             }
-            ForthError::InvalidMemoryAccess { variable, location } => {
-                write!(
-                    f,
-                    "Invalid memory access: variable '{}' not found {}",
-                    variable, location
-                )
+            ForthError::InvalidExecutionToken { xt, location } => {
+                write!(f, "Invalid execution token: {} {}", xt, location)
+            }
+            ForthError::InvalidMemoryAccess { address, location } => {
+                write!(f, "Invalid memory address: {} {}", address, location)
+            }
+            ForthError::DataSpaceOverflow { location } => {
+                write!(f, "Data space overflow {}", location)
+            }
+            ForthError::PicturedOutputOverflow { location } => {
+                write!(f, "Pictured numeric output overflow {}", location)
+            }
+            ForthError::InvalidNumericArgument { message, location } => {
+                write!(f, "{} {}", message, location)
             }
             ForthError::IOError { message, location } => {
                 write!(f, "I/O error: {} {}", message, location)
@@ -126,6 +225,15 @@ impl fmt::Display for ForthError {
             ForthError::RuntimeError { message, location } => {
                 write!(f, "Runtime error: {} {}", message, location)
             }
+            ForthError::Throw { code, location } if *code == throw_code::ABORT => {
+                write!(f, "Aborted {}", location)
+            }
+            ForthError::Throw { code, location } => {
+                write!(f, "Uncaught exception {} {}", code, location)
+            }
+            ForthError::AbortQuote { message, location } => {
+                write!(f, "{} {}", message, location)
+            }
         }
     }
 }
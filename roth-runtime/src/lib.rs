@@ -26,5 +26,11 @@ pub mod context;
 pub mod error;
 
 // Re-export main types at crate root
-pub use context::{RuntimeContext, WordFn, DEFAULT_MAX_STACK_SIZE};
-pub use error::{ForthError, ForthResult, Position, SourceLocation};
+pub use builtins::{
+    compare_bytes, digit_char, format_float, format_number, search_bytes, trimmed_len,
+};
+pub use context::{
+    BASE_ADDRESS, CELL_SIZE, DEFAULT_CELL_BITS, DEFAULT_DATA_SPACE_SIZE, DEFAULT_MAX_STACK_SIZE,
+    FLOAT_SIZE, HOLD_SIZE, RuntimeContext, SYSTEM_SPACE_SIZE, WordFn,
+};
+pub use error::{ForthError, ForthResult, Position, SourceLocation, throw_code};
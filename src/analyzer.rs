@@ -1,10 +1,60 @@
-use crate::types::{AstNode, ParseError};
-use std::collections::HashMap;
+use crate::diagnostics::{Warning, WarningKind, closest_match};
+use crate::expander::{COMPILE_TIME_WORDS, CONDITIONAL_WORDS};
+use crate::parser::DEFINING_WORDS;
+use crate::types::{AstNode, BeginKind, ParseError, ParseErrors, Position};
+use std::collections::{HashMap, HashSet};
+
+/// Words that only mean something where they are compiled, so have no
+/// execution token
+const COMPILE_ONLY_WORDS: &[&str] = &[
+    "IF",
+    "ELSE",
+    "THEN",
+    "DO",
+    "?DO",
+    "LOOP",
+    "+LOOP",
+    "I",
+    "J",
+    "K",
+    "LEAVE",
+    "UNLOOP",
+    "BEGIN",
+    "WHILE",
+    "REPEAT",
+    "UNTIL",
+    "AGAIN",
+    "EXIT",
+    "RECURSE",
+    ">R",
+    "R>",
+    "R@",
+    "DOES>",
+    "TO",
+    "IS",
+    "ACTION-OF",
+    "'",
+    "[']",
+];
 
 pub struct SemanticAnalyzer {
     defined_words: HashMap<String, bool>,
     builtin_words: HashMap<String, bool>,
     defined_variables: HashMap<String, bool>,
+    defined_constants: HashMap<String, bool>,
+    defined_values: HashMap<String, bool>,
+    deferred_words: HashMap<String, bool>,
+    immediate_words: HashMap<String, bool>,
+    in_immediate_definition: bool, // POSTPONE is only meaningful in IMMEDIATE words
+    current_definition: Option<String>,
+    definitions: Vec<(String, Position)>, // Colon definitions in this program, in order
+    names: HashMap<String, Position>,     // Where each name in this program was last defined
+    used_words: HashSet<String>,          // Words used other than by their own definition
+    forward_references: bool,             // Definitions may call words defined after them
+    program_definitions: HashSet<String>, // Colon definitions anywhere in this program
+    program_names: HashSet<String>,       // Names defined other than with : anywhere in it
+    errors: Vec<ParseError>,
+    warnings: Vec<Warning>,
 }
 
 impl SemanticAnalyzer {
@@ -13,26 +63,193 @@ impl SemanticAnalyzer {
             defined_words: HashMap::new(),
             builtin_words: HashMap::new(),
             defined_variables: HashMap::new(),
+            defined_constants: HashMap::new(),
+            defined_values: HashMap::new(),
+            deferred_words: HashMap::new(),
+            immediate_words: HashMap::new(),
+            in_immediate_definition: false,
+            current_definition: None,
+            definitions: Vec::new(),
+            names: HashMap::new(),
+            used_words: HashSet::new(),
+            forward_references: false,
+            program_definitions: HashSet::new(),
+            program_names: HashSet::new(),
+            errors: Vec::new(),
+            warnings: Vec::new(),
         };
 
         // Register builtin words
         let builtins = vec![
-            "+", "-", "*", "/", "DUP", "DROP", "SWAP", "OVER", ".", ".S", "CR", "ROT", "?DO", "DO",
-            "LOOP", "I", "J", "LEAVE", "UNLOOP", "IF", "ELSE", "THEN", "BEGIN", "WHILE", "REPEAT",
-            "UNTIL", "=", "<", ">", "<=", ">=", "<>", "0=", "0<", "0>", "0<>", "AND", "OR", "XOR",
-            "NOT", "INVERT", "MOD", "ABS", "NEGATE", "MIN", "MAX", "EMIT", "KEY", "SPACE",
-            "SPACES", "TYPE", "!", "@", "C!", "C@", "ALLOT", "HERE", "VARIABLE", "CONSTANT",
-            "2DUP", "2DROP", "2SWAP", "2OVER", "NIP", "TUCK", "PICK", "ROLL", "RECURSE", "1-",
-            "1+", "2+", "2-", "BL", "?DUP", ">R", "R>", "R@", "/MOD", "*/", "*/MOD", "WITHIN",
-            "TRUE", "FALSE",
+            "+",
+            "-",
+            "*",
+            "/",
+            "DUP",
+            "DROP",
+            "SWAP",
+            "OVER",
+            ".",
+            ".S",
+            "CR",
+            "ROT",
+            "?DO",
+            "DO",
+            "LOOP",
+            "+LOOP",
+            "I",
+            "J",
+            "K",
+            "LEAVE",
+            "UNLOOP",
+            "EXIT",
+            "IF",
+            "ELSE",
+            "THEN",
+            "BEGIN",
+            "WHILE",
+            "REPEAT",
+            "UNTIL",
+            "AGAIN",
+            "=",
+            "<",
+            ">",
+            "<=",
+            ">=",
+            "<>",
+            "0=",
+            "0<",
+            "0>",
+            "0<>",
+            "AND",
+            "OR",
+            "XOR",
+            "NOT",
+            "INVERT",
+            "MOD",
+            "ABS",
+            "NEGATE",
+            "MIN",
+            "MAX",
+            "EMIT",
+            "KEY",
+            "SPACE",
+            "SPACES",
+            "TYPE",
+            "!",
+            "@",
+            "C!",
+            "C@",
+            "ALLOT",
+            "HERE",
+            "VARIABLE",
+            "CONSTANT",
+            "2DUP",
+            "2DROP",
+            "2SWAP",
+            "2OVER",
+            "NIP",
+            "TUCK",
+            "PICK",
+            "ROLL",
+            "RECURSE",
+            "1-",
+            "1+",
+            "2+",
+            "2-",
+            "BL",
+            "?DUP",
+            ">R",
+            "R>",
+            "R@",
+            "/MOD",
+            "*/",
+            "*/MOD",
+            "WITHIN",
+            "TRUE",
+            "FALSE",
+            "CREATE",
+            ",",
+            "C,",
+            "CELLS",
+            "CELL+",
+            "ALIGN",
+            "+!",
+            "TO",
+            "2@",
+            "2!",
+            "DOES>",
+            "F+",
+            "F-",
+            "F*",
+            "F/",
+            "F.",
+            "FDUP",
+            "FSWAP",
+            "FDROP",
+            "S>F",
+            "F>S",
+            "F@",
+            "F!",
+            "F<",
+            "F0=",
+            "D+",
+            "D-",
+            "DNEGATE",
+            "D.",
+            "M*",
+            "UM*",
+            "UM/MOD",
+            "SM/REM",
+            "FM/MOD",
+            "COUNT",
+            "COMPARE",
+            "SEARCH",
+            "/STRING",
+            "-TRAILING",
+            "CMOVE",
+            "MOVE",
+            "U.",
+            ".R",
+            "U.R",
+            "<#",
+            "#",
+            "#S",
+            "HOLD",
+            "SIGN",
+            "#>",
+            "BASE",
+            "HEX",
+            "DECIMAL",
+            "THROW",
+            "ABORT",
+            "CATCH",
+            "EXECUTE",
+            "IS",
+            "ACTION-OF",
+            "'",
+            "[']",
         ];
-        for word in builtins {
+        let special_words = DEFINING_WORDS
+            .iter()
+            .chain(COMPILE_TIME_WORDS)
+            .chain(CONDITIONAL_WORDS)
+            .copied();
+        for word in builtins.into_iter().chain(special_words) {
             analyzer.builtin_words.insert(word.to_string(), true);
         }
 
         analyzer
     }
 
+    /// Let colon definitions call words defined later in the program, as a
+    /// whole file is compiled before any of it runs. Top-level code still
+    /// only sees what is defined before it.
+    pub fn with_forward_references(mut self) -> Self {
+        self.forward_references = true;
+        self
+    }
+
     /// Add a user-defined word to the analyzer (for REPL context).
     pub fn add_user_word(&mut self, name: String) {
         self.defined_words.insert(name, true);
@@ -43,49 +260,325 @@ impl SemanticAnalyzer {
         self.defined_variables.insert(name, true);
     }
 
-    pub fn analyze(&mut self, ast: &AstNode) -> Result<(), ParseError> {
-        match ast {
-            AstNode::Program(nodes) => {
-                for node in nodes {
-                    self.analyze(node)?;
+    /// Add a CONSTANT or 2CONSTANT to the analyzer (for REPL context).
+    pub fn add_constant(&mut self, name: String) {
+        self.defined_constants.insert(name, true);
+    }
+
+    /// Add a VALUE to the analyzer (for REPL context).
+    pub fn add_value(&mut self, name: String) {
+        self.defined_values.insert(name, true);
+    }
+
+    /// Add a DEFERred word to the analyzer (for REPL context).
+    pub fn add_deferred_word(&mut self, name: String) {
+        self.defined_words.insert(name.clone(), true);
+        self.deferred_words.insert(name, true);
+    }
+
+    /// Mark a definition as IMMEDIATE, allowing POSTPONE in its body.
+    pub fn add_immediate_word(&mut self, name: String) {
+        self.immediate_words.insert(name, true);
+    }
+
+    /// Check `ast`, reporting every error in it. Warnings are kept for
+    /// `warnings`, whether or not there are errors.
+    pub fn analyze(&mut self, ast: &AstNode) -> Result<(), ParseErrors> {
+        self.warnings.clear();
+        self.program_definitions.clear();
+        self.program_names.clear();
+        if let AstNode::Program(nodes) = ast {
+            for node in nodes {
+                match node {
+                    AstNode::Definition { name, .. } => {
+                        self.program_definitions.insert(name.clone());
+                    }
+                    AstNode::VariableDeclaration { name, .. }
+                    | AstNode::Create { name, .. }
+                    | AstNode::DefinedBy { name, .. }
+                    | AstNode::Constant { name, .. }
+                    | AstNode::Value { name, .. }
+                    | AstNode::Defer { name, .. } => {
+                        self.program_names.insert(name.clone());
+                    }
+                    _ => {}
                 }
             }
+        }
+        self.check(ast);
+
+        // A definition that is later replaced is reported as shadowed instead
+        let definitions = std::mem::take(&mut self.definitions);
+        for (i, (name, position)) in definitions.iter().enumerate() {
+            let replaced = definitions[i + 1..].iter().any(|(later, _)| later == name);
+            if !replaced && !self.used_words.contains(name) {
+                self.warnings.push(Warning::new(
+                    WarningKind::Unused,
+                    ParseError::new(format!("{} is never used", name), position.clone()),
+                ));
+            }
+        }
+        ParseErrors::result(std::mem::take(&mut self.errors))
+    }
+
+    /// Warnings from the last `analyze`
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    fn check(&mut self, node: &AstNode) {
+        match node {
+            AstNode::Program(nodes) => self.check_body(nodes),
             AstNode::Definition {
                 name,
                 body,
                 position,
+                ..
             } => {
                 if self.builtin_words.contains_key(name) {
-                    return Err(ParseError {
-                        message: format!("Cannot redefine builtin word: {}", name),
-                        position: position.clone(),
-                    });
+                    self.errors.push(
+                        ParseError::new(
+                            format!("Cannot redefine builtin word: {}", name),
+                            position.clone(),
+                        )
+                        .with_help("give the definition another name"),
+                    );
+                } else {
+                    self.check_shadowing(name, position);
                 }
 
                 // Add the word BEFORE analyzing the body to allow recursive definitions
                 self.defined_words.insert(name.clone(), true);
+                self.definitions.push((name.clone(), position.clone()));
 
-                for node in body {
-                    self.analyze(node)?;
-                }
+                self.in_immediate_definition = self.immediate_words.contains_key(name);
+                self.current_definition = Some(name.clone());
+                self.check_body(body);
+                self.current_definition = None;
+                self.in_immediate_definition = false;
             }
             AstNode::Word(name, position) => {
-                if !self.builtin_words.contains_key(name)
-                    && !self.defined_words.contains_key(name)
-                    && !self.defined_variables.contains_key(name)
-                {
-                    return Err(ParseError {
-                        message: format!("Undefined word: {}", name),
-                        position: position.clone(),
-                    });
+                let forward_reference = self.forward_references
+                    && self.current_definition.is_some()
+                    && self.program_definitions.contains(name);
+                if self.is_defined(name) || forward_reference {
+                    if self.current_definition.as_ref() != Some(name) {
+                        self.used_words.insert(name.clone());
+                    }
+                } else {
+                    self.errors.push(self.undefined(name, position));
                 }
             }
-            AstNode::VariableDeclaration { name, .. } => {
+            AstNode::VariableDeclaration { name, position, .. }
+            | AstNode::Create { name, position } => {
+                self.check_shadowing(name, position);
                 self.defined_variables.insert(name.clone(), true);
             }
-            AstNode::Number(_, _) => {}
-            AstNode::StringLiteral(_, _) => {}
+            AstNode::Does { body, .. } => self.check_body(body),
+            AstNode::DefinedBy {
+                word,
+                name,
+                position,
+            } => {
+                if self.defined_words.contains_key(word) {
+                    self.used_words.insert(word.clone());
+                } else {
+                    self.errors.push(self.undefined(word, position));
+                }
+                self.check_shadowing(name, position);
+                self.defined_variables.insert(name.clone(), true);
+            }
+            AstNode::Constant { name, position, .. } => {
+                self.check_shadowing(name, position);
+                self.defined_constants.insert(name.clone(), true);
+            }
+            AstNode::Value { name, position } => {
+                self.check_shadowing(name, position);
+                self.defined_values.insert(name.clone(), true);
+            }
+            AstNode::Postpone { name, position } => {
+                if self.in_immediate_definition {
+                    self.check(&AstNode::Word(name.clone(), position.clone()));
+                } else {
+                    self.errors.push(
+                        ParseError::new(
+                            "POSTPONE is only supported in IMMEDIATE words".to_string(),
+                            position.clone(),
+                        )
+                        .with_help(
+                            "mark the definition IMMEDIATE by following its ';' with IMMEDIATE",
+                        ),
+                    );
+                }
+            }
+            AstNode::Defer { name, position } => {
+                self.check_shadowing(name, position);
+                self.defined_words.insert(name.clone(), true);
+                self.deferred_words.insert(name.clone(), true);
+            }
+            AstNode::Tick { name, position } => {
+                let compile_only = COMPILE_ONLY_WORDS.contains(&name.as_str())
+                    || DEFINING_WORDS.contains(&name.as_str())
+                    || COMPILE_TIME_WORDS.contains(&name.as_str())
+                    || CONDITIONAL_WORDS.contains(&name.as_str());
+                if compile_only {
+                    self.errors.push(
+                        ParseError::new(
+                            format!("Cannot take the execution token of {}", name),
+                            position.clone(),
+                        )
+                        .with_help(format!("{} only works where it is compiled", name)),
+                    );
+                } else {
+                    self.check(&AstNode::Word(name.clone(), position.clone()));
+                }
+            }
+            AstNode::Is { name, position } | AstNode::ActionOf { name, position } => {
+                if !self.deferred_words.contains_key(name) {
+                    let word = if matches!(node, AstNode::Is { .. }) {
+                        "IS"
+                    } else {
+                        "ACTION-OF"
+                    };
+                    self.errors.push(
+                        ParseError::new(
+                            format!("{} needs a DEFERred word, but {} is not one", word, name),
+                            position.clone(),
+                        )
+                        .with_help(format!("declare it with DEFER {}", name)),
+                    );
+                }
+            }
+            AstNode::To { name, position } => {
+                if !self.defined_values.contains_key(name) {
+                    let error = ParseError::new(
+                        format!("TO needs a VALUE, but {} is not one", name),
+                        position.clone(),
+                    );
+                    self.errors
+                        .push(if self.defined_variables.contains_key(name) {
+                            error.with_help(format!("store into a VARIABLE with {} !", name))
+                        } else {
+                            error
+                        });
+                }
+            }
+            AstNode::If {
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                self.check_body(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.check_body(else_branch);
+                }
+            }
+            AstNode::DoLoop { body, .. } => self.check_body(body),
+            AstNode::Begin { body, kind, .. } => {
+                self.check_body(body);
+                if let BeginKind::WhileRepeat(loop_body) = kind {
+                    self.check_body(loop_body);
+                }
+            }
+            AstNode::Number(_, _) | AstNode::DoubleNumber(_, _) | AstNode::Float(_, _) => {}
+            AstNode::StringLiteral(_, _)
+            | AstNode::DotQuote(_, _)
+            | AstNode::CQuote(_, _)
+            | AstNode::AbortQuote(_, _) => {}
+            // The parser only makes these for names declared in the definition
+            AstNode::Locals { .. } | AstNode::Local { .. } | AstNode::ToLocal { .. } => {}
+        }
+    }
+
+    /// Check a sequence of nodes, warning about anything after an EXIT
+    fn check_body(&mut self, nodes: &[AstNode]) {
+        for (i, node) in nodes.iter().enumerate() {
+            self.check(node);
+            if let AstNode::Word(w, exit) = node
+                && w == "EXIT"
+                && self.current_definition.is_some()
+                && let Some(position) = nodes.get(i + 1).and_then(AstNode::position)
+            {
+                self.warnings.push(Warning::new(
+                    WarningKind::Unreachable,
+                    ParseError::new("Unreachable code after EXIT", position.clone())
+                        .with_note("any code after this EXIT is never run", exit.clone()),
+                ));
+                for node in &nodes[i + 1..] {
+                    self.check(node);
+                }
+                return;
+            }
+        }
+    }
+
+    /// Whether `name` is a builtin word, which can't be defined again
+    pub fn is_builtin(&self, name: &str) -> bool {
+        self.builtin_words.contains_key(name)
+    }
+
+    /// Whether `name` is a word, variable, constant or VALUE
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.builtin_words.contains_key(name)
+            || self.defined_words.contains_key(name)
+            || self.defined_variables.contains_key(name)
+            || self.defined_constants.contains_key(name)
+            || self.defined_values.contains_key(name)
+    }
+
+    /// Warn when `name`, being defined at `position`, replaces an earlier
+    /// user definition
+    fn check_shadowing(&mut self, name: &str, position: &Position) {
+        let earlier = self.names.insert(name.to_string(), position.clone());
+        if !self.is_defined(name) || self.builtin_words.contains_key(name) {
+            return;
+        }
+        let warning = ParseError::new(
+            format!("{} is redefined, replacing the earlier definition", name),
+            position.clone(),
+        );
+        self.warnings.push(Warning::new(
+            WarningKind::Shadow,
+            match earlier {
+                Some(earlier) => warning.with_note(format!("{} was defined here", name), earlier),
+                None => warning,
+            },
+        ));
+    }
+
+    /// The error for a use of undefined word `name`, pointing out a later
+    /// definition of it or else suggesting a known word with a similar name
+    fn undefined(&self, name: &str, position: &Position) -> ParseError {
+        if self.program_definitions.contains(name) {
+            return ParseError::new(
+                format!("{} is used before it is defined", name),
+                position.clone(),
+            )
+            .with_help(format!(
+                "move the definition of {} earlier, or declare it first with DEFER {}",
+                name, name
+            ));
+        }
+        // Only colon definitions can be used by words defined before them
+        if self.program_names.contains(name) {
+            return ParseError::new(
+                format!("{} is used before it is defined", name),
+                position.clone(),
+            )
+            .with_help(format!("move the definition of {} earlier", name));
+        }
+        let known = self
+            .builtin_words
+            .keys()
+            .chain(self.defined_words.keys())
+            .chain(self.defined_variables.keys())
+            .chain(self.defined_constants.keys())
+            .chain(self.defined_values.keys())
+            .map(String::as_str);
+        let error = ParseError::new(format!("Undefined word: {}", name), position.clone());
+        match closest_match(name, known) {
+            Some(similar) => error.with_help(format!("did you mean {}?", similar)),
+            None => error,
         }
-        Ok(())
     }
 }
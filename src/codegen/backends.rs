@@ -7,6 +7,7 @@ use crate::codegen::languages::{CLanguage, RustLanguage};
 use crate::codegen::templates::TemplateEngine;
 use crate::codegen::translators::{CTranslator, RustTranslator};
 use crate::ir::IRProgram;
+use crate::types::CellWidth;
 
 pub struct ModularRustBackend {
     emitter: RustEmitter,
@@ -229,23 +230,26 @@ impl Backend for DebugBackend {
 
 pub fn create_target_info(name: &str) -> TargetInfo {
     match name {
-        "rust" | "rust-modular" => TargetInfo {
+        "rust" | "rust-modular" | "rust-ir" | "ir-debug-rust" => TargetInfo {
             name: "rust".to_string(),
             architecture: "x86_64".to_string(),
             pointer_size: 8,
             endianness: "little".to_string(),
+            cell_width: CellWidth::default(),
         },
-        "c" | "c-modular" => TargetInfo {
+        "c" | "c-modular" | "c-ir" | "ir-debug-c" => TargetInfo {
             name: "c".to_string(),
             architecture: "x86_64".to_string(),
             pointer_size: 8,
             endianness: "little".to_string(),
+            cell_width: CellWidth::default(),
         },
         _ => TargetInfo {
             name: name.to_string(),
             architecture: "unknown".to_string(),
             pointer_size: 8,
             endianness: "little".to_string(),
+            cell_width: CellWidth::default(),
         },
     }
 }
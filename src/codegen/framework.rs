@@ -1,4 +1,5 @@
 use crate::ir::{IRFunction, IRInstruction, IRProgram, IRValue};
+use crate::types::CellWidth;
 use std::collections::{HashMap, HashSet};
 use std::fmt;
 
@@ -52,6 +53,7 @@ pub struct TargetInfo {
     pub architecture: String,
     pub pointer_size: usize,
     pub endianness: String,
+    pub cell_width: CellWidth, // Stack cell width used from the lexer to the runtime
 }
 
 #[derive(Debug, Clone)]
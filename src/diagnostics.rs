@@ -0,0 +1,595 @@
+use crate::source::SourceMap;
+use crate::types::{ParseError, Position};
+use colored::{ColoredString, Colorize};
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Kinds of warning, each switched on and off with `-W`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WarningKind {
+    Unused,      // A definition that nothing uses
+    Shadow,      // A definition reusing the name of an earlier one
+    Unreachable, // Code after EXIT
+    StackEffect, // Paths reaching the same point with different stack depths
+    Signature,   // A body that does not match its stack-effect comment
+}
+
+impl WarningKind {
+    pub const ALL: [WarningKind; 5] = [
+        WarningKind::Unused,
+        WarningKind::Shadow,
+        WarningKind::Unreachable,
+        WarningKind::StackEffect,
+        WarningKind::Signature,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WarningKind::Unused => "unused",
+            WarningKind::Shadow => "shadow",
+            WarningKind::Unreachable => "unreachable",
+            WarningKind::StackEffect => "stack-effect",
+            WarningKind::Signature => "signature",
+        }
+    }
+}
+
+/// A problem that does not stop compilation
+#[derive(Debug, Clone)]
+pub struct Warning {
+    pub kind: WarningKind,
+    pub diagnostic: ParseError,
+}
+
+impl Warning {
+    pub fn new(kind: WarningKind, diagnostic: ParseError) -> Self {
+        Self { kind, diagnostic }
+    }
+}
+
+/// Which warnings are reported, and whether they fail compilation, as set by
+/// `-W` flags
+#[derive(Debug, Clone)]
+pub struct WarningOptions {
+    enabled: HashSet<WarningKind>,
+    pub as_errors: bool,
+}
+
+impl Default for WarningOptions {
+    /// Everything but unused definitions, which libraries are full of
+    fn default() -> Self {
+        Self {
+            enabled: WarningKind::ALL
+                .into_iter()
+                .filter(|&kind| kind != WarningKind::Unused)
+                .collect(),
+            as_errors: false,
+        }
+    }
+}
+
+impl WarningOptions {
+    /// Apply one `-W` flag: a warning name to turn it on, `no-` and a name to
+    /// turn it off, `all`, `none`, or `error` to fail on any warning
+    pub fn apply(&mut self, flag: &str) -> Result<(), String> {
+        let (enable, name) = match flag.strip_prefix("no-") {
+            Some(name) => (false, name),
+            None => (true, flag),
+        };
+        let kinds: Vec<WarningKind> = match name {
+            "error" => {
+                self.as_errors = enable;
+                return Ok(());
+            }
+            "all" => WarningKind::ALL.to_vec(),
+            "none" if enable => {
+                self.enabled.clear();
+                return Ok(());
+            }
+            _ => match WarningKind::ALL
+                .into_iter()
+                .find(|kind| kind.name() == name)
+            {
+                Some(kind) => vec![kind],
+                None => {
+                    let names: Vec<_> = WarningKind::ALL.iter().map(|kind| kind.name()).collect();
+                    return Err(format!(
+                        "Unknown warning: -W{}. Available warnings: all, none, error, {}",
+                        flag,
+                        names.join(", ")
+                    ));
+                }
+            },
+        };
+        for kind in kinds {
+            if enable {
+                self.enabled.insert(kind);
+            } else {
+                self.enabled.remove(&kind);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_enabled(&self, kind: WarningKind) -> bool {
+        self.enabled.contains(&kind)
+    }
+}
+
+/// Renders errors and warnings with the source lines they point at:
+///
+/// ```text
+/// error: ';' inside IF (expected ELSE or THEN)
+///  --> square.fs:2:9
+///   |
+/// 1 | : ABS DUP 0 < IF NEGATE
+///   |               -- IF opened here
+/// 2 |   DROP ;
+///   |        ^
+///   |
+///   = help: close the IF with ELSE or THEN first
+/// ```
+pub struct DiagnosticRenderer<'a> {
+    sources: &'a SourceMap,
+    color: bool,
+}
+
+/// An underlined place in a snippet
+struct Label<'a> {
+    position: &'a Position,
+    primary: bool,
+    message: Option<&'a str>,
+}
+
+impl<'a> DiagnosticRenderer<'a> {
+    pub fn new(sources: &'a SourceMap) -> Self {
+        Self {
+            sources,
+            color: false,
+        }
+    }
+
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Render every error in `errors`, separated by blank lines
+    pub fn render_all(&self, severity: Severity, errors: &[ParseError]) -> String {
+        errors
+            .iter()
+            .map(|error| self.render(severity, error))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Print `errors` found during `stage`, returning the message compilation
+    /// fails with
+    pub fn report_errors(&self, stage: &str, errors: &[ParseError]) -> String {
+        eprintln!("{}", self.render_all(Severity::Error, errors));
+        match errors.len() {
+            1 => format!("1 error during {}", stage),
+            n => format!("{} errors during {}", n, stage),
+        }
+    }
+
+    /// Print the warnings `options` enables, failing like `report_errors` if
+    /// warnings are errors. Warnings in the bundled library are left out.
+    pub fn report_warnings(
+        &self,
+        options: &WarningOptions,
+        warnings: &[Warning],
+    ) -> Result<(), String> {
+        let mut enabled: Vec<_> = warnings
+            .iter()
+            .filter(|warning| options.is_enabled(warning.kind))
+            .filter(|warning| !self.sources.is_bundled(warning.diagnostic.position.file))
+            .collect();
+        enabled.sort_by_key(|warning| {
+            let position = &warning.diagnostic.position;
+            (position.file, position.line, position.column)
+        });
+        for warning in &enabled {
+            eprintln!("{}", self.render_warning(warning, options.as_errors));
+        }
+        match enabled.len() {
+            0 => Ok(()),
+            _ if !options.as_errors => Ok(()),
+            1 => Err("1 warning treated as an error".to_string()),
+            n => Err(format!("{} warnings treated as errors", n)),
+        }
+    }
+
+    pub fn render(&self, severity: Severity, error: &ParseError) -> String {
+        self.render_tagged(severity, error, None)
+    }
+
+    /// Render `warning` as an error when warnings are errors, naming the flag
+    /// that controls it
+    pub fn render_warning(&self, warning: &Warning, as_error: bool) -> String {
+        let (severity, tag) = if as_error {
+            (Severity::Error, format!("-Werror={}", warning.kind.name()))
+        } else {
+            (Severity::Warning, format!("-W{}", warning.kind.name()))
+        };
+        self.render_tagged(severity, &warning.diagnostic, Some(&tag))
+    }
+
+    fn render_tagged(&self, severity: Severity, error: &ParseError, tag: Option<&str>) -> String {
+        let mut labels = vec![Label {
+            position: &error.position,
+            primary: true,
+            message: None,
+        }];
+        for note in &error.notes {
+            labels.push(Label {
+                position: &note.position,
+                primary: false,
+                message: Some(&note.message),
+            });
+        }
+        // The primary label's file comes first, then other files in turn
+        labels.sort_by_key(|label| {
+            let position = label.position;
+            let other_file = position.file != error.position.file;
+            (
+                other_file,
+                position.file,
+                position.line,
+                !label.primary,
+                position.column,
+            )
+        });
+
+        let last_line = labels.iter().map(|label| label.position.line).max();
+        let pad = " ".repeat(last_line.unwrap_or(1).to_string().len());
+        let bar = self.paint(&format!("{} |", pad), |s| s.blue().bold());
+
+        let (name, mark_color): (&str, fn(&str) -> ColoredString) = match severity {
+            Severity::Error => ("error", |s| s.red().bold()),
+            Severity::Warning => ("warning", |s| s.yellow().bold()),
+        };
+        let mut out = format!(
+            "{}: {}",
+            self.paint(name, mark_color),
+            self.paint(&error.message, |s| s.bold())
+        );
+        if let Some(tag) = tag {
+            out.push_str(&format!(" [{}]", tag));
+        }
+        out.push('\n');
+
+        let mut shown = false;
+        let mut start = 0;
+        while start < labels.len() {
+            let file = labels[start].position.file;
+            let end = start
+                + labels[start..]
+                    .iter()
+                    .take_while(|label| label.position.file == file)
+                    .count();
+            let (arrow, position) = if start == 0 {
+                ("-->", &error.position)
+            } else {
+                (":::", labels[start].position)
+            };
+            out.push_str(&format!(
+                "{}{} {}\n",
+                pad,
+                self.paint(arrow, |s| s.blue().bold()),
+                self.sources.locate(position)
+            ));
+            shown |= self.render_snippet(&mut out, &labels[start..end], &pad, mark_color);
+            start = end;
+        }
+
+        let including = self.sources.include_chain(error.position.file);
+        let remarks = including
+            .iter()
+            .map(|position| {
+                let location = self.sources.locate(position);
+                let file_and_line = location
+                    .rsplit_once(':')
+                    .map_or(&*location, |(rest, _)| rest);
+                ("note", format!("included from {}", file_and_line))
+            })
+            .chain(error.help.iter().map(|help| ("help", help.clone())));
+        for (i, (kind, message)) in remarks.enumerate() {
+            if i == 0 && shown {
+                out.push_str(&format!("{}\n", bar));
+            }
+            out.push_str(&format!(
+                "{} {} {}: {}\n",
+                pad,
+                self.paint("=", |s| s.blue().bold()),
+                self.paint(kind, |s| s.bold()),
+                message
+            ));
+        }
+        out
+    }
+
+    /// Add the source lines `labels`, all in one file, point at with their
+    /// underlines. Returns whether any line could be shown.
+    fn render_snippet(
+        &self,
+        out: &mut String,
+        labels: &[Label],
+        pad: &str,
+        mark_color: fn(&str) -> ColoredString,
+    ) -> bool {
+        let bar = self.paint(&format!("{} |", pad), |s| s.blue().bold());
+        let Some(file) = labels
+            .first()
+            .and_then(|label| self.sources.file(label.position.file))
+        else {
+            return false;
+        };
+        let lines: Vec<&str> = file.text.lines().collect();
+
+        let mut shown_line = None;
+        for label in labels {
+            let line_number = label.position.line;
+            let Some(line) = line_number.checked_sub(1).and_then(|i| lines.get(i)) else {
+                continue;
+            };
+            let line = line.replace('\t', " ");
+
+            if shown_line != Some(line_number) {
+                match shown_line {
+                    None => out.push_str(&format!("{}\n", bar)),
+                    Some(shown) if line_number > shown + 1 => {
+                        out.push_str(&format!("{}\n", self.paint("...", |s| s.blue().bold())));
+                    }
+                    _ => {}
+                }
+                let number = format!("{:>width$} |", line_number, width = pad.len());
+                out.push_str(&format!(
+                    "{} {}\n",
+                    self.paint(&number, |s| s.blue().bold()),
+                    line
+                ));
+                shown_line = Some(line_number);
+            }
+
+            let column = label.position.column.max(1) - 1;
+            let length = token_length(&line, column);
+            let marks = if label.primary { "^" } else { "-" }.repeat(length);
+            let mut underline = format!("{}{}", " ".repeat(column), marks);
+            if let Some(message) = label.message {
+                underline.push(' ');
+                underline.push_str(message);
+            }
+            let underline = if label.primary {
+                self.paint(&underline, mark_color)
+            } else {
+                self.paint(&underline, |s| s.blue().bold())
+            };
+            out.push_str(&format!("{} {}\n", bar, underline));
+        }
+        shown_line.is_some()
+    }
+
+    fn paint(&self, text: &str, style: fn(&str) -> ColoredString) -> String {
+        if self.color {
+            style(text).to_string()
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// Words shorter than this get no suggestion, since nearly every short word
+/// is an edit or two away from some one- or two-character builtin
+const MIN_SUGGESTED_LENGTH: usize = 3;
+
+/// The candidate closest to `word` by edit distance, ignoring case, if it is
+/// close enough to be a likely typo: a third of the shorter of the two names
+/// may differ
+pub fn closest_match<'a>(word: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let word = word.to_uppercase();
+    let length = word.chars().count();
+    if length < MIN_SUGGESTED_LENGTH {
+        return None;
+    }
+    candidates
+        .filter_map(|candidate| {
+            let limit = length.min(candidate.chars().count()) / 3;
+            let distance = edit_distance(&word, &candidate.to_uppercase());
+            (distance <= limit).then_some((distance, candidate))
+        })
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// Optimal string alignment distance between `a` and `b`: Levenshtein
+/// distance, with swapping two adjacent characters as one edit
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // Rows for the prefixes of `a` one and two characters shorter
+    let mut before: Vec<usize> = Vec::new();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let substitution = previous[j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            row[j] = substitution.min(row[j - 1] + 1).min(previous[j] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(before[j - 2] + 1);
+            }
+        }
+        before = std::mem::replace(&mut previous, row);
+    }
+    previous[b.len()]
+}
+
+/// Characters to underline for the token starting at `column` of `line`: a
+/// whole comment or string, a definition's ':' with its name, otherwise up to
+/// the next whitespace
+fn token_length(line: &str, column: usize) -> usize {
+    let rest: Vec<char> = line.chars().skip(column).collect();
+    let length = match rest.first() {
+        Some('(') => rest
+            .iter()
+            .position(|&c| c == ')')
+            .map_or(rest.len(), |i| i + 1),
+        Some(':') if rest.get(1).is_some_and(|c| c.is_whitespace()) => {
+            let name = 1 + rest[1..].iter().take_while(|c| c.is_whitespace()).count();
+            name + rest[name..]
+                .iter()
+                .take_while(|c| !c.is_whitespace())
+                .count()
+        }
+        Some('"') => rest[1..]
+            .iter()
+            .position(|&c| c == '"')
+            .map_or(rest.len(), |i| i + 2),
+        _ => rest.iter().take_while(|c| !c.is_whitespace()).count(),
+    };
+    length.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(line: usize, column: usize) -> Position {
+        Position {
+            line,
+            column,
+            offset: 0,
+            file: 0,
+        }
+    }
+
+    #[test]
+    fn test_render_underlines_token_and_note() {
+        let source = ": ABS DUP 0 < IF NEGATE\n  DROP ;\n";
+        let error = ParseError::new("';' inside IF (expected ELSE or THEN)", at(2, 8))
+            .with_note("IF opened here", at(1, 15))
+            .with_help("close the IF with ELSE or THEN first");
+
+        let mut sources = SourceMap::new();
+        sources.add(Some("abs.fs".to_string()), source.to_string(), None);
+        let rendered = DiagnosticRenderer::new(&sources).render(Severity::Error, &error);
+        assert_eq!(
+            rendered,
+            "error: ';' inside IF (expected ELSE or THEN)
+ --> abs.fs:2:8
+  |
+1 | : ABS DUP 0 < IF NEGATE
+  |               -- IF opened here
+2 |   DROP ;
+  |        ^
+  |
+  = help: close the IF with ELSE or THEN first
+"
+        );
+    }
+
+    #[test]
+    fn test_closest_match_ignores_case_and_far_words() {
+        let words = ["DUP", "DROP", "SWAP", "SQUARE", "OVER"];
+        assert_eq!(closest_match("dup", words.into_iter()), Some("DUP"));
+        assert_eq!(closest_match("SQAURE", words.into_iter()), Some("SQUARE"));
+        assert_eq!(closest_match("DRIP", words.into_iter()), Some("DROP"));
+        assert_eq!(closest_match("ROT", words.into_iter()), None);
+
+        // Short names are a small edit away from too many words
+        let words = ["*", "+", ".", "DUP", "OVER"];
+        assert_eq!(closest_match("X", words.into_iter()), None);
+        assert_eq!(closest_match("**", words.into_iter()), None);
+        assert_eq!(closest_match("DUPP", words.into_iter()), Some("DUP"));
+        assert_eq!(closest_match("D.", words.into_iter()), None);
+        assert_eq!(closest_match("OVERLAP", words.into_iter()), None);
+
+        // Swapped neighbours are one edit
+        let words = ["SWAP", "DROP", "ROT"];
+        assert_eq!(closest_match("SWPA", words.into_iter()), Some("SWAP"));
+        assert_eq!(closest_match("ORT", words.into_iter()), Some("ROT"));
+        assert_eq!(edit_distance("CA", "ABC"), 3);
+    }
+
+    #[test]
+    fn test_warning_flags() {
+        let mut options = WarningOptions::default();
+        assert!(!options.is_enabled(WarningKind::Unused));
+        assert!(options.is_enabled(WarningKind::Shadow));
+
+        options.apply("all").unwrap();
+        options.apply("no-shadow").unwrap();
+        options.apply("error").unwrap();
+        assert!(options.is_enabled(WarningKind::Unused));
+        assert!(!options.is_enabled(WarningKind::Shadow));
+        assert!(options.as_errors);
+        assert!(options.apply("unsued").is_err());
+    }
+
+    #[test]
+    fn test_render_skips_lines_between_labels() {
+        let source = ": BAD\n  BEGIN\n  1\n  2\n  IF UNTIL ;\n";
+        let error = ParseError::new("UNTIL does not match IF (expected ELSE or THEN)", at(5, 6))
+            .with_note("IF opened here", at(5, 3))
+            .with_note("BEGIN opened here", at(2, 3));
+
+        let sources = SourceMap::unnamed(source);
+        let rendered = DiagnosticRenderer::new(&sources).render(Severity::Warning, &error);
+        assert_eq!(
+            rendered,
+            "warning: UNTIL does not match IF (expected ELSE or THEN)
+ --> 5:6
+  |
+2 |   BEGIN
+  |   ----- BEGIN opened here
+...
+5 |   IF UNTIL ;
+  |      ^^^^^
+  |   -- IF opened here
+"
+        );
+    }
+
+    #[test]
+    fn test_render_shows_include_chain() {
+        let mut sources = SourceMap::new();
+        let app = "\\ App\n\nINCLUDE lib.rt\n";
+        sources.add(Some("app.fs".to_string()), app.to_string(), None);
+        let include = Position {
+            file: 0,
+            ..at(3, 1)
+        };
+        sources.add(
+            Some("lib.rt".to_string()),
+            ": TWICE 2 SQAURE ;\n".to_string(),
+            Some(include),
+        );
+
+        let error = ParseError::new(
+            "Undefined word: SQAURE",
+            Position {
+                file: 1,
+                ..at(1, 11)
+            },
+        )
+        .with_help("did you mean SQUARE?");
+        let rendered = DiagnosticRenderer::new(&sources).render(Severity::Error, &error);
+        assert_eq!(
+            rendered,
+            "error: Undefined word: SQAURE
+ --> lib.rt:1:11
+  |
+1 | : TWICE 2 SQAURE ;
+  |           ^^^^^^
+  |
+  = note: included from app.fs:3
+  = help: did you mean SQUARE?
+"
+        );
+    }
+}
@@ -0,0 +1,993 @@
+use crate::analyzer::SemanticAnalyzer;
+use crate::ir::IRProgram;
+use crate::ir_interpreter::{Compiled, IRInterpreter};
+use crate::ir_lowering::IRLowering;
+use crate::parser::{DEFINING_WORDS, Parser};
+use crate::types::{AstNode, Cell, CellWidth, ParseError, ParseErrors, Position, Token, TokenType};
+use std::collections::{HashMap, HashSet};
+
+/// Words handled while expanding, before the parser sees the tokens
+pub(crate) const COMPILE_TIME_WORDS: &[&str] = &["IMMEDIATE", "POSTPONE", "LITERAL", "[", "]"];
+
+/// Conditional compilation words, handled while expanding at the top level
+/// as well as in definitions
+pub(crate) const CONDITIONAL_WORDS: &[&str] =
+    &["[IF]", "[ELSE]", "[THEN]", "[DEFINED]", "[UNDEFINED]"];
+
+/// Words that can combine values worked out at compile time, in the
+/// condition for an `[IF]` or the value of a CONSTANT, with how many values
+/// each takes. Each leaves one value.
+const COMPILE_TIME_OPERATORS: &[(&str, usize)] = &[
+    ("=", 2),
+    ("<>", 2),
+    ("<", 2),
+    (">", 2),
+    ("<=", 2),
+    (">=", 2),
+    ("0=", 1),
+    ("0<>", 1),
+    ("AND", 2),
+    ("OR", 2),
+    ("XOR", 2),
+    ("INVERT", 1),
+    ("+", 2),
+    ("-", 2),
+    ("*", 2),
+    ("/", 2),
+    ("MOD", 2),
+    ("NEGATE", 1),
+    ("LSHIFT", 2),
+    ("RSHIFT", 2),
+];
+
+/// Words besides the defining words that take the name of a word after them
+const NAME_TAKING_WORDS: &[&str] = &[
+    "'",
+    "[']",
+    "POSTPONE",
+    "TO",
+    "IS",
+    "ACTION-OF",
+    "[DEFINED]",
+    "[UNDEFINED]",
+];
+
+/// Definition that `[ ... ]` is compiled into so that it can be run
+const INTERPRET_FUNCTION: &str = "__INTERPRET";
+
+/// Runs compile-time code in the token stream before it is parsed.
+///
+/// Inside a definition, IMMEDIATE words and `[ ... ]` run in an
+/// `IRInterpreter`, and the words and numbers they compile with POSTPONE and
+/// LITERAL take their place. IMMEDIATE definitions only exist at compile time,
+/// so they are left out of the expanded tokens.
+///
+/// `[IF]` takes its flag from the words before it, run at compile time, or
+/// else from what `[ ... ]` left on the stack. Those words can only be
+/// numbers, constants, including `-D` and backend constants, and the words in
+/// `COMPILE_TIME_OPERATORS`. The code it skips is dropped before it is parsed.
+/// `[DEFINED] name` and `[UNDEFINED] name` become a flag saying whether `name`
+/// is a word.
+///
+/// The lexer reads anything spelled as a number in the current radix as one,
+/// so a number that names a builtin or an earlier definition, such as `ADD`
+/// or `D.` after HEX, is turned back into that word first, as are the names
+/// after defining words.
+///
+/// The value of a CONSTANT or 2CONSTANT is worked out the same way. It is left as the numbers the parser expects,
+/// or, when it needs other words, to be taken from the stack as it runs.
+#[derive(Debug, Clone)]
+pub struct Expander {
+    source: Vec<Token>, // Everything expanded so far, IMMEDIATE definitions included
+    immediate_words: HashSet<String>,
+    interpreter: IRInterpreter,
+    program: Option<(usize, IRProgram)>, // `source` lowered, with its length at the time
+    cell_width: CellWidth,
+    defines: Vec<(String, Cell)>,
+    constants: HashMap<String, usize>, // Each CONSTANT or 2CONSTANT, with its cells
+    names: HashSet<String>,            // Every name defined so far
+    defining_words: HashSet<String>,   // Colon definitions that CREATE, so take a name
+    open_conditionals: Vec<Position>,  // Each [IF] still waiting for its [THEN]
+}
+
+impl Expander {
+    pub fn new() -> Self {
+        Self {
+            source: Vec::new(),
+            immediate_words: HashSet::new(),
+            interpreter: IRInterpreter::default(),
+            program: None,
+            cell_width: CellWidth::default(),
+            defines: Vec::new(),
+            constants: HashMap::new(),
+            names: HashSet::new(),
+            defining_words: HashSet::new(),
+            open_conditionals: Vec::new(),
+        }
+    }
+
+    /// Run compile-time code with cells `cell_width` wide
+    pub fn with_cell_width(mut self, cell_width: CellWidth) -> Self {
+        self.cell_width = cell_width;
+        self.interpreter = IRInterpreter::new(cell_width);
+        self
+    }
+
+    /// Define constants before any source, such as `-D` flags from the command
+    /// line. Later stages learn of them through `defines`.
+    pub fn with_defines(mut self, defines: Vec<(String, Cell)>) -> Self {
+        for (name, value) in &defines {
+            let position = Position {
+                line: 0,
+                column: 0,
+                offset: 0,
+                file: 0,
+            };
+            let token = |token_type, raw: String| Token {
+                token_type,
+                position: position.clone(),
+                raw,
+            };
+            self.source.extend([
+                token(TokenType::Number(*value), value.to_string()),
+                token(
+                    TokenType::Word("CONSTANT".to_string()),
+                    "CONSTANT".to_string(),
+                ),
+                token(TokenType::Word(name.clone()), name.clone()),
+            ]);
+            self.constants.insert(name.clone(), 1);
+            self.names.insert(name.clone());
+        }
+        self.defines.extend(defines);
+        self
+    }
+
+    /// Constants defined with `with_defines`
+    pub fn defines(&self) -> &[(String, Cell)] {
+        &self.defines
+    }
+
+    pub fn expand(&mut self, tokens: &[Token]) -> Result<Vec<Token>, ParseErrors> {
+        let tokens = &self.words_for_names(tokens);
+        let mut output = Vec::new();
+        let mut position = 0;
+        let mut condition_start = 0; // Where the flag for an [IF] may begin in `output`
+
+        while position < tokens.len() {
+            let token = &tokens[position];
+            match &token.token_type {
+                TokenType::StartDefinition => {
+                    let definition;
+                    (definition, position) = self.expand_definition(tokens, position)?;
+                    let immediate = tokens
+                        .get(position)
+                        .is_some_and(|next| is_word(next, "IMMEDIATE"));
+                    if let Some(Token {
+                        token_type: TokenType::Word(name),
+                        ..
+                    }) = definition.get(1)
+                    {
+                        if immediate {
+                            self.immediate_words.insert(name.clone());
+                        } else {
+                            self.immediate_words.remove(name);
+                        }
+                        self.constants.remove(name);
+                    }
+
+                    self.source.extend(definition.iter().cloned());
+                    if immediate {
+                        position += 1;
+                        // Report mistakes in it now rather than where it is used
+                        self.lower_source()?;
+                    } else {
+                        output.extend(definition);
+                    }
+                    condition_start = output.len();
+                }
+                TokenType::Word(w) if CONDITIONAL_WORDS.contains(&w.as_str()) => {
+                    position = self.conditional(
+                        tokens,
+                        position,
+                        &mut output,
+                        &mut condition_start,
+                        true,
+                    )?;
+                }
+                TokenType::Word(w) if w == "IMMEDIATE" => {
+                    return Err(Self::misplaced_immediate(token).into());
+                }
+                TokenType::Word(w) if COMPILE_TIME_WORDS.contains(&w.as_str()) => {
+                    return Err(ParseError::new(
+                        format!("{} outside of a definition", w),
+                        token.position.clone(),
+                    )
+                    .into());
+                }
+                TokenType::Word(w) if self.immediate_words.contains(w) => {
+                    return Err(ParseError::new(
+                        format!("{} is IMMEDIATE, so it can only be used in a definition", w),
+                        token.position.clone(),
+                    )
+                    .into());
+                }
+                TokenType::Word(w) if w == "CONSTANT" || w == "2CONSTANT" => {
+                    self.constant_value(tokens, position, &mut output, condition_start)?;
+                    self.source.push(token.clone());
+                    output.push(token.clone());
+                    position += 1;
+                }
+                _ => {
+                    // A constant's name given another meaning no longer has
+                    // its value
+                    if let TokenType::Word(w) = &token.token_type
+                        && DEFINING_WORDS.contains(&w.as_str())
+                        && let Some(TokenType::Word(name)) =
+                            tokens.get(position + 1).map(|t| &t.token_type)
+                    {
+                        self.constants.remove(name);
+                    }
+                    self.source.push(token.clone());
+                    output.push(token.clone());
+                    position += 1;
+                }
+            }
+        }
+
+        if let Some(open) = self.open_conditionals.pop() {
+            return Err(Self::unterminated_conditional(open).into());
+        }
+        Ok(output)
+    }
+
+    /// Expand the definition starting at `start`, returning its tokens and the
+    /// position after its ';'
+    fn expand_definition(
+        &mut self,
+        tokens: &[Token],
+        start: usize,
+    ) -> Result<(Vec<Token>, usize), ParseErrors> {
+        let mut definition = vec![tokens[start].clone()];
+        let mut position = start + 1;
+
+        // The name is never run, even if it is an IMMEDIATE word being redefined
+        if let Some(name) = tokens.get(position)
+            && matches!(name.token_type, TokenType::Word(_))
+        {
+            definition.push(name.clone());
+            position += 1;
+        }
+        let mut condition_start = definition.len();
+
+        while let Some(token) = tokens.get(position) {
+            position += 1;
+            let TokenType::Word(w) = &token.token_type else {
+                definition.push(token.clone());
+                if token.token_type == TokenType::EndDefinition {
+                    break;
+                }
+                continue;
+            };
+
+            match w.as_str() {
+                "POSTPONE" => match tokens.get(position) {
+                    // Postponing an IMMEDIATE word runs it when this one runs
+                    Some(next) if self.is_immediate(next) => {
+                        definition.push(next.clone());
+                        position += 1;
+                    }
+                    Some(next) => {
+                        definition.push(token.clone());
+                        definition.push(next.clone());
+                        position += 1;
+                    }
+                    None => definition.push(token.clone()),
+                },
+                "[" => {
+                    let length = tokens[position..]
+                        .iter()
+                        .position(|t| is_word(t, "]"))
+                        .ok_or_else(|| {
+                            ParseError::new(
+                                "[ without matching ]".to_string(),
+                                token.position.clone(),
+                            )
+                        })?;
+                    let segment = &tokens[position..position + length];
+                    position += length + 1;
+                    let compiled = self.interpret(segment, &token.position, "[ ... ]")?;
+                    definition.extend(compiled_tokens(compiled, &token.position));
+                    condition_start = definition.len();
+                }
+                "]" => {
+                    return Err(ParseError::new(
+                        "] without matching [".to_string(),
+                        token.position.clone(),
+                    )
+                    .into());
+                }
+                "LITERAL" => {
+                    let value = self.interpreter.pop().ok_or_else(|| {
+                        ParseError::new(
+                            "LITERAL needs a value on the stack".to_string(),
+                            token.position.clone(),
+                        )
+                    })?;
+                    definition.extend(compiled_tokens(
+                        vec![Compiled::Literal(value)],
+                        &token.position,
+                    ));
+                }
+                "IMMEDIATE" => return Err(Self::misplaced_immediate(token).into()),
+                _ if CONDITIONAL_WORDS.contains(&w.as_str()) => {
+                    position = self.conditional(
+                        tokens,
+                        position - 1,
+                        &mut definition,
+                        &mut condition_start,
+                        false,
+                    )?;
+                }
+                _ if self.immediate_words.contains(w) => {
+                    let compiled = self.run(w, &token.position)?;
+                    definition.extend(compiled_tokens(compiled, &token.position));
+                    condition_start = definition.len();
+                }
+                _ => definition.push(token.clone()),
+            }
+        }
+
+        Ok((definition, position))
+    }
+
+    fn is_immediate(&self, token: &Token) -> bool {
+        matches!(&token.token_type, TokenType::Word(w) if self.immediate_words.contains(w))
+    }
+
+    /// Read each number in `tokens` spelled as the name of a builtin or of a
+    /// word defined before it as that word, and read the name after a defining
+    /// word or a word such as `'` as a name even when it is spelled as a number
+    fn words_for_names(&mut self, tokens: &[Token]) -> Vec<Token> {
+        let analyzer = SemanticAnalyzer::new();
+        let mut resolved: Vec<Token> = Vec::with_capacity(tokens.len());
+        let mut definition: Option<String> = None; // Colon definition being read
+        for token in tokens {
+            let (names, defines) = match resolved.last().map(|t| &t.token_type) {
+                Some(TokenType::StartDefinition) => (true, true),
+                Some(TokenType::Word(w)) => {
+                    let defines =
+                        DEFINING_WORDS.contains(&w.as_str()) || self.defining_words.contains(w);
+                    (defines || NAME_TAKING_WORDS.contains(&w.as_str()), defines)
+                }
+                _ => (false, false),
+            };
+            let mut token = token.clone();
+            let spelled = token.raw.to_uppercase();
+            if matches!(
+                token.token_type,
+                TokenType::Number(_) | TokenType::DoubleNumber(_)
+            ) && (names || analyzer.is_builtin(&spelled) || self.names.contains(&spelled))
+            {
+                token.token_type = TokenType::Word(spelled);
+            }
+
+            match &token.token_type {
+                TokenType::Word(name) if defines => {
+                    self.names.insert(name.clone());
+                    if resolved.last().map(|t| &t.token_type) == Some(&TokenType::StartDefinition) {
+                        self.defining_words.remove(name);
+                        definition = Some(name.clone());
+                    }
+                }
+                TokenType::Word(w) if w == "CREATE" => {
+                    if let Some(name) = &definition {
+                        self.defining_words.insert(name.clone());
+                    }
+                }
+                TokenType::EndDefinition => definition = None,
+                _ => {}
+            }
+            resolved.push(token);
+        }
+        resolved
+    }
+
+    fn misplaced_immediate(token: &Token) -> ParseError {
+        ParseError::new(
+            "IMMEDIATE must directly follow a definition".to_string(),
+            token.position.clone(),
+        )
+    }
+
+    /// Handle the conditional compilation word at `position`, given `output`
+    /// expanded so far, whose tokens from `condition_start` on may be the flag
+    /// for an `[IF]`. `top_level` output is also part of the
+    /// source. Returns the position to carry on from.
+    fn conditional(
+        &mut self,
+        tokens: &[Token],
+        position: usize,
+        output: &mut Vec<Token>,
+        condition_start: &mut usize,
+        top_level: bool,
+    ) -> Result<usize, ParseErrors> {
+        let token = &tokens[position];
+        let TokenType::Word(word) = &token.token_type else {
+            unreachable!("conditional compilation words are words");
+        };
+        let next = match word.as_str() {
+            "[DEFINED]" | "[UNDEFINED]" => {
+                let Some(TokenType::Word(name)) = tokens.get(position + 1).map(|t| &t.token_type)
+                else {
+                    return Err(ParseError::new(
+                        format!("{} needs the name of a word", word),
+                        token.position.clone(),
+                    )
+                    .with_help(format!("write {} NAME", word))
+                    .into());
+                };
+                let flag = if self.is_defined(name)? == (word == "[DEFINED]") {
+                    -1
+                } else {
+                    0
+                };
+                let flag = Token {
+                    token_type: TokenType::Number(flag),
+                    position: token.position.clone(),
+                    raw: flag.to_string(),
+                };
+                if top_level {
+                    self.source.push(flag.clone());
+                }
+                output.push(flag);
+                // The flag can be part of the condition for an [IF]
+                return Ok(position + 2);
+            }
+            "[IF]" => {
+                let length = self.operand_length(
+                    &output[*condition_start..],
+                    token,
+                    1,
+                    "condition for [IF]",
+                )?;
+                let condition: Vec<Token> = output.drain(output.len() - length..).collect();
+                if top_level {
+                    self.source.truncate(self.source.len() - length);
+                }
+                self.open_conditionals.push(token.position.clone());
+                if self.condition(&condition, &token.position)? {
+                    position + 1
+                } else {
+                    self.skip_conditional(tokens, position, true)?
+                }
+            }
+            "[ELSE]" | "[THEN]" => {
+                if self.open_conditionals.is_empty() {
+                    return Err(ParseError::new(
+                        format!("{} without matching [IF]", word),
+                        token.position.clone(),
+                    )
+                    .into());
+                }
+                if word == "[ELSE]" {
+                    self.skip_conditional(tokens, position, false)?
+                } else {
+                    self.open_conditionals.pop();
+                    position + 1
+                }
+            }
+            _ => unreachable!("{} is not a conditional compilation word", word),
+        };
+        *condition_start = output.len();
+        Ok(next)
+    }
+
+    /// How many of the tokens at the end of `expanded` leave the `values`
+    /// values taken by the compile-time word `word`, as part of `what`. Only
+    /// the tokens that together leave them are taken, so the code before them
+    /// is left alone. They can be numbers, constants and compile-time
+    /// operators.
+    fn operand_length(
+        &self,
+        expanded: &[Token],
+        word: &Token,
+        values: usize,
+        what: &str,
+    ) -> Result<usize, ParseErrors> {
+        let mut needed = values as isize; // Values still to find
+        let mut length = 0;
+        for token in expanded.iter().rev() {
+            let Some((takes, leaves)) = self.operand_effect(token) else {
+                return Err(ParseError::new(
+                    format!("{} cannot be part of the {}", token.raw, what),
+                    token.position.clone(),
+                )
+                .with_note(
+                    format!("{} is here", token_word(word)),
+                    word.position.clone(),
+                )
+                .with_help(if token_word(word) == "[IF]" {
+                    "build the flag from numbers, constants, [DEFINED] NAME, \
+                     [UNDEFINED] NAME and comparisons"
+                } else {
+                    "build the value from numbers, constants and arithmetic"
+                })
+                .into());
+            };
+            needed += takes as isize - leaves as isize;
+            length += 1;
+            if needed == 0 {
+                return Ok(length);
+            }
+            if needed < 0 {
+                return Err(ParseError::new(
+                    format!(
+                        "The {} leaves more than {} {}",
+                        what,
+                        values,
+                        if values == 1 { "value" } else { "values" }
+                    ),
+                    token.position.clone(),
+                )
+                .with_note(
+                    format!("{} is here", token_word(word)),
+                    word.position.clone(),
+                )
+                .into());
+            }
+        }
+        if length == 0 {
+            return Ok(0);
+        }
+        Err(ParseError::new(
+            format!("The {} is missing values", what),
+            word.position.clone(),
+        )
+        .with_help(format!(
+            "put the whole {} before its {}",
+            what.split(' ').next().unwrap_or(what),
+            token_word(word)
+        ))
+        .into())
+    }
+
+    /// How many values `token` takes and leaves, if it can be part of a value
+    /// worked out at compile time
+    fn operand_effect(&self, token: &Token) -> Option<(usize, usize)> {
+        let name = match &token.token_type {
+            TokenType::Number(_) => return Some((0, 1)),
+            TokenType::DoubleNumber(_) => return Some((0, 2)),
+            TokenType::Word(name) => name,
+            _ => return None,
+        };
+        if let Some(cells) = self.constants.get(name) {
+            return Some((0, *cells));
+        }
+        COMPILE_TIME_OPERATORS
+            .iter()
+            .find(|(operator, _)| operator == name)
+            .map(|(_, takes)| (*takes, 1))
+    }
+
+    /// Work out the value of the CONSTANT or 2CONSTANT at `position` from the
+    /// end of `output`, expanded since `value_start`, and leave it there as
+    /// numbers. The constant's name is remembered for later values. A value
+    /// that can't be worked out this way, such as an address or an execution
+    /// token, is left to be taken from the stack when the program runs.
+    fn constant_value(
+        &mut self,
+        tokens: &[Token],
+        position: usize,
+        output: &mut Vec<Token>,
+        value_start: usize,
+    ) -> Result<(), ParseErrors> {
+        let token = &tokens[position];
+        let word = token_word(token);
+        let cells = if word == "2CONSTANT" { 2 } else { 1 };
+        let what = format!("value of {}", word);
+        let length = self
+            .operand_length(&output[value_start..], token, cells, &what)
+            .unwrap_or(0);
+        if let Some(Token {
+            token_type: TokenType::Word(name),
+            ..
+        }) = tokens.get(position + 1)
+        {
+            if length == 0 {
+                self.constants.remove(name);
+                return Ok(());
+            }
+            self.constants.insert(name.clone(), cells);
+        }
+
+        let value = &output[output.len() - length..];
+        let literal = |t: &Token| {
+            matches!(
+                t.token_type,
+                TokenType::Number(_) | TokenType::DoubleNumber(_)
+            )
+        };
+        if value.iter().all(literal) {
+            // The parser takes numbers as they are
+            return Ok(());
+        }
+
+        let value: Vec<Token> = output.drain(output.len() - length..).collect();
+        self.source.truncate(self.source.len() - length);
+        let depth = self.interpreter.stack().len();
+        self.interpret(&value, &token.position, word)?;
+        let left = self.interpreter.stack().len().saturating_sub(depth);
+        let mut numbers: Vec<Token> = (0..left)
+            .filter_map(|_| self.interpreter.pop())
+            .map(|n| Token {
+                token_type: TokenType::Number(n),
+                position: value[0].position.clone(),
+                raw: n.to_string(),
+            })
+            .collect();
+        numbers.reverse();
+        self.source.extend(numbers.iter().cloned());
+        output.extend(numbers);
+        Ok(())
+    }
+
+    /// Run `condition`, the words before the `[IF]` at `position`, and take
+    /// the flag it leaves
+    fn condition(&mut self, condition: &[Token], position: &Position) -> Result<bool, ParseErrors> {
+        let depth = self.interpreter.stack().len();
+        if !condition.is_empty() {
+            self.interpret(condition, position, "[IF]")?;
+            let left = self.interpreter.stack().len() as isize - depth as isize;
+            if left != 1 {
+                return Err(ParseError::new(
+                    format!(
+                        "The words before [IF] must leave one flag, but leave {} values",
+                        left
+                    ),
+                    condition[0].position.clone(),
+                )
+                .with_note("[IF] is here", position.clone())
+                .with_help("leave only the flag before [IF]")
+                .into());
+            }
+        }
+        let flag = self.interpreter.pop().ok_or_else(|| {
+            ParseError::new("[IF] needs a flag".to_string(), position.clone())
+                .with_help("put the condition before [IF], as in [DEFINED] WORD [IF]")
+        })?;
+        Ok(flag != 0)
+    }
+
+    /// Skip the code after the `[IF]` or `[ELSE]` at `position`, up to its
+    /// `[THEN]`, or up to its `[ELSE]` for a false `[IF]`. Returns the position
+    /// after it.
+    fn skip_conditional(
+        &mut self,
+        tokens: &[Token],
+        position: usize,
+        to_else: bool,
+    ) -> Result<usize, ParseErrors> {
+        let mut depth = 0;
+        for (i, token) in tokens.iter().enumerate().skip(position + 1) {
+            let TokenType::Word(word) = &token.token_type else {
+                continue;
+            };
+            match word.as_str() {
+                "[IF]" => depth += 1,
+                "[ELSE]" if depth == 0 && to_else => return Ok(i + 1),
+                "[THEN]" if depth == 0 => {
+                    self.open_conditionals.pop();
+                    return Ok(i + 1);
+                }
+                "[THEN]" => depth -= 1,
+                _ => {}
+            }
+        }
+        let open = self
+            .open_conditionals
+            .pop()
+            .expect("skipping code after an open [IF]");
+        Err(Self::unterminated_conditional(open).into())
+    }
+
+    fn unterminated_conditional(position: Position) -> ParseError {
+        ParseError::new("[IF] without matching [THEN]".to_string(), position)
+            .with_help("end the conditional code with [THEN]")
+    }
+
+    /// Whether `name` is a word, given everything expanded so far
+    fn is_defined(&self, name: &str) -> Result<bool, ParseErrors> {
+        if self.immediate_words.contains(name) {
+            return Ok(true);
+        }
+        let (_, analyzer) = self.analyze(self.source.clone())?;
+        Ok(analyzer.is_defined(name))
+    }
+
+    /// Run IMMEDIATE word `word`, used at `position`
+    fn run(&mut self, word: &str, position: &Position) -> Result<Vec<Compiled>, ParseErrors> {
+        self.lower_source()?;
+        let (_, program) = self
+            .program
+            .as_ref()
+            .expect("lower_source sets the program");
+        self.interpreter.run(program, word).map_err(|message| {
+            ParseError::new(
+                format!("{} failed at compile time: {}", word, message),
+                position.clone(),
+            )
+            .into()
+        })
+    }
+
+    /// Run the words between `[` and `]`, or before `[IF]`, with the `[` or
+    /// `[IF]` at `position`. `what` names them in errors.
+    fn interpret(
+        &mut self,
+        segment: &[Token],
+        position: &Position,
+        what: &str,
+    ) -> Result<Vec<Compiled>, ParseErrors> {
+        let token = |token_type, raw: &str| Token {
+            token_type,
+            position: position.clone(),
+            raw: raw.to_string(),
+        };
+        let mut tokens = self.source.clone();
+        tokens.push(token(TokenType::StartDefinition, ":"));
+        tokens.push(token(
+            TokenType::Word(INTERPRET_FUNCTION.to_string()),
+            INTERPRET_FUNCTION,
+        ));
+        tokens.extend(segment.iter().cloned());
+        tokens.push(token(TokenType::EndDefinition, ";"));
+
+        let program = self.lower(tokens)?;
+        self.interpreter
+            .run(&program, INTERPRET_FUNCTION)
+            .map_err(|message| {
+                ParseError::new(
+                    format!("{} failed at compile time: {}", what, message),
+                    position.clone(),
+                )
+                .into()
+            })
+    }
+
+    /// Bring the lowered program up to date with everything expanded so far
+    fn lower_source(&mut self) -> Result<(), ParseErrors> {
+        let up_to_date = matches!(&self.program, Some((length, _)) if *length == self.source.len());
+        if !up_to_date {
+            let program = self.lower(self.source.clone())?;
+            self.program = Some((self.source.len(), program));
+        }
+        Ok(())
+    }
+
+    fn lower(&self, tokens: Vec<Token>) -> Result<IRProgram, ParseErrors> {
+        let (ast, _) = self.analyze(tokens)?;
+        Ok(IRLowering::new()
+            .with_cell_width(self.cell_width)
+            .lower(&ast))
+    }
+
+    fn analyze(&self, tokens: Vec<Token>) -> Result<(AstNode, SemanticAnalyzer), ParseErrors> {
+        let ast = Parser::new(tokens).parse()?;
+        let mut analyzer = SemanticAnalyzer::new();
+        for word in &self.immediate_words {
+            analyzer.add_immediate_word(word.clone());
+        }
+        analyzer.analyze(&ast)?;
+        Ok((ast, analyzer))
+    }
+}
+
+impl Default for Expander {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The define set for code compiled with backend `name`, such as
+/// `BACKEND-C-IR`, so that `[DEFINED] BACKEND-C-IR [IF]` can test for it
+pub fn backend_define(name: &str) -> (String, Cell) {
+    (format!("BACKEND-{}", name.to_uppercase()), -1)
+}
+
+/// The word `token` is; conditional compilation and defining words are
+/// always words
+fn token_word(token: &Token) -> &str {
+    match &token.token_type {
+        TokenType::Word(word) => word,
+        _ => &token.raw,
+    }
+}
+
+fn is_word(token: &Token, word: &str) -> bool {
+    matches!(&token.token_type, TokenType::Word(w) if w == word)
+}
+
+/// Tokens for what a compile-time run compiled, placed where it ran
+fn compiled_tokens(compiled: Vec<Compiled>, position: &Position) -> impl Iterator<Item = Token> {
+    let position = position.clone();
+    compiled.into_iter().map(move |item| {
+        let (token_type, raw) = match item {
+            Compiled::Word(name) => (TokenType::Word(name.clone()), name),
+            Compiled::Literal(value) => (TokenType::Number(value), value.to_string()),
+        };
+        Token {
+            token_type,
+            position: position.clone(),
+            raw,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    fn expand(source: &str) -> Result<Vec<String>, ParseErrors> {
+        let tokens = Lexer::new(source.to_string()).tokenize()?;
+        let expanded = Expander::new().expand(&tokens)?;
+        Ok(expanded.into_iter().map(|token| token.raw).collect())
+    }
+
+    #[test]
+    fn test_brackets_and_literal_compile_a_number() {
+        assert_eq!(
+            expand(": TEN [ 2 3 + 2 * ] LITERAL ;").unwrap(),
+            vec![":", "TEN", "10", ";"]
+        );
+    }
+
+    #[test]
+    fn test_immediate_word_postpones_into_caller() {
+        let expanded =
+            expand(": TWICE 2 POSTPONE LITERAL POSTPONE * ; IMMEDIATE\n: DOUBLE TWICE ;").unwrap();
+        assert_eq!(expanded, vec![":", "DOUBLE", "2", "*", ";"]);
+    }
+
+    #[test]
+    fn test_immediate_word_outside_definition_is_an_error() {
+        let err = expand(": NOTHING ; IMMEDIATE NOTHING").unwrap_err();
+        assert!(err[0].message.contains("NOTHING is IMMEDIATE"));
+        let err = expand("5 LITERAL").unwrap_err();
+        assert_eq!(err[0].message, "LITERAL outside of a definition");
+    }
+
+    #[test]
+    fn test_conditionals_keep_only_the_chosen_branch() {
+        let expanded = expand(
+            ": A ;\n[DEFINED] A [IF] 1\n[UNDEFINED] B [IF] 2 [ELSE] 3 [THEN] [ELSE] 4 [THEN]\n\
+             : F [ 1 2 = ] [IF] 5 [ELSE] 6 [THEN] ;",
+        )
+        .unwrap();
+        assert_eq!(expanded, vec![":", "A", ";", "1", "2", ":", "F", "6", ";"]);
+    }
+
+    #[test]
+    fn test_constant_values_are_worked_out_at_compile_time() {
+        let expanded = expand(
+            "10 CONSTANT WIDTH\nWIDTH 3 * 1 + CONSTANT AREA\n7 DUP AREA WIDTH 2CONSTANT SIZE\n\
+             -1. 2CONSTANT DMINUS",
+        )
+        .unwrap();
+        assert_eq!(
+            expanded,
+            vec![
+                "10",
+                "CONSTANT",
+                "WIDTH",
+                "31",
+                "CONSTANT",
+                "AREA",
+                "7",
+                "DUP",
+                "31",
+                "10",
+                "2CONSTANT",
+                "SIZE",
+                "-1.",
+                "2CONSTANT",
+                "DMINUS"
+            ]
+        );
+
+        // Other values are taken from the stack when the program runs, and
+        // can't be worked out from later
+        for source in [
+            "5 DUP CONSTANT X",
+            "1\n2 + 2CONSTANT X",
+            "-1. CONSTANT X",
+            "HERE CONSTANT START START 1 + CONSTANT NEXT",
+        ] {
+            let tokens = Lexer::new(source.to_string()).tokenize().unwrap();
+            let unchanged: Vec<String> = tokens.into_iter().map(|token| token.raw).collect();
+            assert_eq!(expand(source).unwrap(), unchanged);
+        }
+
+        // A name defined again is no longer the constant
+        let expanded = expand(
+            "2 CONSTANT N : N 1 ; N 1 + CONSTANT M\nVARIABLE W 3 CONSTANT W W 1 + CONSTANT V",
+        )
+        .unwrap();
+        assert_eq!(
+            expanded,
+            vec![
+                "2", "CONSTANT", "N", ":", "N", "1", ";", "N", "1", "+", "CONSTANT", "M",
+                "VARIABLE", "W", "3", "CONSTANT", "W", "4", "CONSTANT", "V"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_numbers_spelled_as_names_are_words() {
+        let tokens = Lexer::new(
+            ": ADD + ; HEX 1 2 ADD . 1. D. FF VARIABLE BEEF BEEF [DEFINED] DEAD".to_string(),
+        )
+        .tokenize()
+        .unwrap();
+        let types: Vec<TokenType> = Expander::new()
+            .words_for_names(&tokens)
+            .into_iter()
+            .map(|token| token.token_type)
+            .collect();
+        let word = |name: &str| TokenType::Word(name.to_string());
+        assert_eq!(
+            types[4..],
+            [
+                word("HEX"),
+                TokenType::Number(1),
+                TokenType::Number(2),
+                word("ADD"),
+                word("."),
+                TokenType::DoubleNumber(1),
+                word("D."),
+                TokenType::Number(0xFF),
+                word("VARIABLE"),
+                word("BEEF"),
+                word("BEEF"),
+                word("[DEFINED]"),
+                word("DEAD"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_condition_comes_from_constants_and_defines() {
+        let tokens = Lexer::new("1 LEVEL\n2 > [IF] 5 [THEN]".to_string())
+            .tokenize()
+            .unwrap();
+        let mut expander = Expander::new().with_defines(vec![("LEVEL".to_string(), 3)]);
+        let expanded: Vec<String> = expander
+            .expand(&tokens)
+            .unwrap()
+            .into_iter()
+            .map(|token| token.raw)
+            .collect();
+        assert_eq!(expanded, vec!["1", "5"]);
+
+        // Only the words that make the flag are taken
+        let expanded = expand("7 DUP 1 2 < [IF] 8 [THEN]").unwrap();
+        assert_eq!(expanded, vec!["7", "DUP", "8"]);
+        let expanded = expand("3 CONSTANT C\nC\n2 > [IF] 4 [THEN]").unwrap();
+        assert_eq!(expanded, vec!["3", "CONSTANT", "C", "4"]);
+        let err = expand("VARIABLE V\nV [IF] [THEN]").unwrap_err();
+        assert_eq!(err[0].message, "V cannot be part of the condition for [IF]");
+        let err = expand("5 DUP 2 > [IF] [THEN]").unwrap_err();
+        assert_eq!(
+            err[0].message,
+            "DUP cannot be part of the condition for [IF]"
+        );
+        let err = expand(": F ;\n2 = [IF] [THEN]").unwrap_err();
+        assert_eq!(err[0].message, "The condition for [IF] is missing values");
+
+        let err = expand("1 [IF] 2").unwrap_err();
+        assert_eq!(err[0].message, "[IF] without matching [THEN]");
+        let err = expand("[THEN]").unwrap_err();
+        assert_eq!(err[0].message, "[THEN] without matching [IF]");
+    }
+}
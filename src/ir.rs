@@ -1,3 +1,4 @@
+use crate::types::{Cell, CellWidth};
 use roth_derive::StackEffect;
 use std::collections::HashMap;
 use std::fmt;
@@ -9,6 +10,10 @@ use std::fmt;
 pub struct IRProgram {
     pub functions: HashMap<String, IRFunction>,
     pub main: IRFunction,
+    // Functions running the builtins, variables and other words that aren't
+    // definitions, for the execution tokens taken of them
+    pub wrappers: HashMap<String, IRFunction>,
+    pub cell_width: CellWidth, // Width every backend must wrap arithmetic to
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -18,10 +23,26 @@ pub struct IRFunction {
     pub stack_effect: StackEffect, // How many items consumed/produced
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// Number of local slots a function's frame needs, from its StackAlloc
+pub fn frame_size(instructions: &[IRInstruction]) -> usize {
+    instructions
+        .iter()
+        .filter_map(|instruction| match instruction {
+            IRInstruction::StackAlloc(size) => Some(*size),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Items an instruction or function takes and leaves, on the data stack and
+/// on the float stack
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct StackEffect {
     pub consumes: usize,
     pub produces: usize,
+    pub float_consumes: usize,
+    pub float_produces: usize,
 }
 
 #[derive(Debug, Clone, PartialEq, StackEffect)]
@@ -37,10 +58,15 @@ pub enum IRInstruction {
     Drop,
     #[stack_effect(consumes = 2, produces = 2)]
     Swap,
-    #[stack_effect(consumes = 2, produces = 2)]
+    #[stack_effect(consumes = 2, produces = 3)]
     Over,
     #[stack_effect(consumes = 3, produces = 3)]
     Rot, // ( a b c -- b c a )
+    #[stack_effect(consumes = 1, produces = 1)]
+    Pick, // PICK: ( xu ... x0 u -- xu ... x0 xu ), the items below u unchecked
+    #[stack_effect(consumes = 1, produces = 0)]
+    Roll, // ROLL: ( xu xu-1 ... x0 u -- xu-1 ... x0 xu ), the items below u unchecked
+    DupIfNonZero, // ?DUP: ( x -- 0 | x x ), so its effect depends on x
 
     // Arithmetic operations
     #[stack_effect(consumes = 2, produces = 1)]
@@ -56,6 +82,24 @@ pub enum IRInstruction {
     #[stack_effect(consumes = 1, produces = 1)]
     Neg,
 
+    // Double-cell arithmetic; a double is two cells with the high cell on top
+    #[stack_effect(consumes = 4, produces = 2)]
+    DAdd, // D+
+    #[stack_effect(consumes = 4, produces = 2)]
+    DSub, // D-
+    #[stack_effect(consumes = 2, produces = 2)]
+    DNeg, // DNEGATE
+    #[stack_effect(consumes = 2, produces = 2)]
+    MulMixed, // M*: ( n1 n2 -- d ) signed product
+    #[stack_effect(consumes = 2, produces = 2)]
+    MulUnsigned, // UM*: ( u1 u2 -- ud ) unsigned product
+    #[stack_effect(consumes = 3, produces = 2)]
+    DivModUnsigned, // UM/MOD: ( ud u -- rem quot )
+    #[stack_effect(consumes = 3, produces = 2)]
+    DivRemSymmetric, // SM/REM: ( d n -- rem quot ) quotient truncated towards zero
+    #[stack_effect(consumes = 3, produces = 2)]
+    DivModFloored, // FM/MOD: ( d n -- rem quot ) quotient rounded towards negative infinity
+
     // Comparison operations
     #[stack_effect(consumes = 2, produces = 1)]
     Equal,
@@ -75,14 +119,50 @@ pub enum IRInstruction {
     And,
     #[stack_effect(consumes = 2, produces = 1)]
     Or,
+    #[stack_effect(consumes = 2, produces = 1)]
+    Xor, // XOR: bitwise
     #[stack_effect(consumes = 1, produces = 1)]
     Not,
 
-    // Memory operations
+    // Memory operations; addresses are byte offsets into the data space
+    #[stack_effect(consumes = 1, produces = 1)]
+    Load(IRValue), // @: fetch a cell from address
+    #[stack_effect(consumes = 2, produces = 0)]
+    Store(IRValue), // !: store a cell to address
+    #[stack_effect(consumes = 1, produces = 1)]
+    LoadChar, // C@: fetch a byte
+    #[stack_effect(consumes = 2, produces = 0)]
+    StoreChar, // C!: store the low byte of a cell
     #[stack_effect(consumes = 0, produces = 1)]
-    Load(IRValue), // Load from address
+    Here, // HERE: push the address of the next free byte
+    #[stack_effect(consumes = 1, produces = 0)]
+    Allot, // ALLOT: reserve (or release, if negative) n bytes
     #[stack_effect(consumes = 1, produces = 0)]
-    Store(IRValue), // Store to address
+    Comma, // ,: append a cell
+    #[stack_effect(consumes = 1, produces = 0)]
+    CharComma, // C,: append a byte
+    Align,              // ALIGN: pad HERE to a cell boundary
+    Create(String),     // CREATE: align and give the name the current HERE
+    SupplyName(String), // Name the next CreateSupplied, before calling a defining word
+    CreateSupplied,     // CREATE inside a defining word, using the supplied name
+    #[stack_effect(consumes = 0, produces = 1)]
+    PushDataField(String), // Push the address a CREATEd name was given
+
+    // Strings; each literal is laid down in data space after a count byte
+    #[stack_effect(consumes = 0, produces = 2)]
+    PushString(String), // S": push the text's address and length
+    #[stack_effect(consumes = 0, produces = 1)]
+    PushCountedString(String), // C": push the address of the count byte
+    #[stack_effect(consumes = 4, produces = 1)]
+    Compare, // COMPARE: ( c-addr1 u1 c-addr2 u2 -- n ) -1, 0 or 1
+    #[stack_effect(consumes = 4, produces = 3)]
+    Search, // SEARCH: ( c-addr1 u1 c-addr2 u2 -- c-addr3 u3 flag ) the rest from the first match
+    #[stack_effect(consumes = 2, produces = 2)]
+    TrimTrailing, // -TRAILING: ( c-addr u1 -- c-addr u2 ) drop trailing spaces
+    #[stack_effect(consumes = 3, produces = 0)]
+    CMove, // CMOVE: ( c-addr1 c-addr2 u -- ) copy byte by byte from the lowest address up
+    #[stack_effect(consumes = 3, produces = 0)]
+    Move, // MOVE: ( addr1 addr2 u -- ) copy as if through a buffer
 
     // Control flow
     Jump(IRLabel),
@@ -93,44 +173,123 @@ pub enum IRInstruction {
     Call(String), // Call function
     Return,
 
+    // Execution tokens: cells numbering the words they run, given out by
+    // each backend
+    #[stack_effect(consumes = 0, produces = 1)]
+    PushXt(String), // ' name: push the execution token of name
+    Execute, // EXECUTE: ( xt -- ) run the word xt names
+
     // Loop control
     #[stack_effect(consumes = 2, produces = 0)]
-    DoLoop(IRLabel, IRLabel), // ?DO: (limit start -- ) jump to end_label if start >= limit, otherwise continue to loop_label
-    Loop(IRLabel), // LOOP: increment index, jump to loop_label if index < limit
+    DoLoop(IRLabel, IRLabel), // DO/?DO: (limit start -- ) jump to end_label if start == limit, otherwise continue to loop_label
+    Loop(IRLabel), // LOOP: increment index, jump to loop_label unless it reached the limit
+    #[stack_effect(consumes = 1, produces = 0)]
+    PlusLoop(IRLabel), // +LOOP: (n -- ) add n to index, jump to loop_label unless it crossed the limit-1/limit boundary
+    Leave(IRLabel), // LEAVE: discard the loop frame and jump to end_label
+    Unloop,         // UNLOOP: discard the loop frame
     #[stack_effect(consumes = 0, produces = 1)]
     PushLoopIndex, // I: push current loop index
     #[stack_effect(consumes = 0, produces = 1)]
+    PushOuterLoopIndex(usize), // J/K: push index of an enclosing loop (1 = J, 2 = K)
+    #[stack_effect(consumes = 0, produces = 1)]
     PushLoopLimit, // push current loop limit
 
-    // I/O operations
+    // Exceptions, with the standard THROW codes of roth_runtime::throw_code
+    Catch, // CATCH: ( xt -- ) run xt, then push 0, or what it threw with the depths restored
+    #[stack_effect(consumes = 1, produces = 0)]
+    Throw, // THROW: ( n -- ) unwind to the innermost CATCH unless n is 0
+    Abort, // ABORT: THROW -1
+    #[stack_effect(consumes = 1, produces = 0)]
+    AbortQuote(String), // ABORT" text": ( flag -- ) THROW -2, reporting the text, if flag is true
+
+    // Compile time: append to the definition an IMMEDIATE word is compiling into
+    CompileWord(String), // POSTPONE name
+    #[stack_effect(consumes = 1, produces = 0)]
+    CompileLiteral, // POSTPONE LITERAL: compile the top of stack as a number
+
+    // I/O operations; numbers are printed in BASE
     #[stack_effect(consumes = 1, produces = 0)]
     Print,
+    #[stack_effect(consumes = 1, produces = 0)]
+    PrintUnsigned, // U.
+    #[stack_effect(consumes = 2, produces = 0)]
+    PrintAligned, // .R: ( n width -- )
+    #[stack_effect(consumes = 2, produces = 0)]
+    PrintUnsignedAligned, // U.R: ( u width -- )
     PrintStack,
     #[stack_effect(consumes = 1, produces = 0)]
     PrintChar,
     #[stack_effect(consumes = 2, produces = 0)]
-    PrintString,
+    PrintString, // TYPE: ( c-addr u -- )
+    #[stack_effect(consumes = 2, produces = 0)]
+    PrintDouble, // D.
     #[stack_effect(consumes = 0, produces = 1)]
     ReadChar,
 
+    // Pictured numeric output, built from the end of a buffer below HERE
+    HoldStart, // <#
+    #[stack_effect(consumes = 2, produces = 2)]
+    HoldDigit, // #: ( ud1 -- ud2 )
+    #[stack_effect(consumes = 2, produces = 2)]
+    HoldDigits, // #S: ( ud -- 0 0 )
+    #[stack_effect(consumes = 1, produces = 0)]
+    Hold, // HOLD: ( char -- )
+    #[stack_effect(consumes = 1, produces = 0)]
+    HoldSign, // SIGN: ( n -- )
+    #[stack_effect(consumes = 2, produces = 2)]
+    HoldEnd, // #>: ( xd -- c-addr u )
+
+    // Floating point, on a stack of its own
+    #[stack_effect(float_produces = 1)]
+    PushFloat(f64),
+    #[stack_effect(float_consumes = 2, float_produces = 1)]
+    FAdd,
+    #[stack_effect(float_consumes = 2, float_produces = 1)]
+    FSub,
+    #[stack_effect(float_consumes = 2, float_produces = 1)]
+    FMul,
+    #[stack_effect(float_consumes = 2, float_produces = 1)]
+    FDiv,
+    #[stack_effect(float_consumes = 1, float_produces = 2)]
+    FDup,
+    #[stack_effect(float_consumes = 2, float_produces = 2)]
+    FSwap,
+    #[stack_effect(float_consumes = 1)]
+    FDrop,
+    #[stack_effect(float_consumes = 2, produces = 1)]
+    FLess, // F<: push a flag
+    #[stack_effect(float_consumes = 1, produces = 1)]
+    FZeroEqual, // F0=: push a flag
+    #[stack_effect(consumes = 1, float_produces = 1)]
+    IntToFloat, // S>F
+    #[stack_effect(float_consumes = 1, produces = 1)]
+    FloatToInt, // F>S: truncate towards zero
+    #[stack_effect(consumes = 1, float_produces = 1)]
+    LoadFloat, // F@: fetch a float from address
+    #[stack_effect(consumes = 1, float_consumes = 1)]
+    StoreFloat, // F!: store a float to address
+    #[stack_effect(float_consumes = 1)]
+    PrintFloat, // F.
+
     // Labels and metadata
     Label(IRLabel),
     Comment(String),
 
     // Advanced operations for optimization
     #[stack_effect(consumes = 0, produces = 1)]
-    LoadConst(i32), // Optimized constant loading
+    LoadConst(Cell), // Optimized constant loading
     #[stack_effect(consumes = 0, produces = 1)]
     BinaryOp(BinaryOpKind, IRValue, IRValue), // Optimized binary operations
     #[stack_effect(consumes = 0, produces = 1)]
     UnaryOp(UnaryOpKind, IRValue), // Optimized unary operations
 
-    // Stack manipulation with known depths
+    // Locals: slots in a frame each call of a function gets
     #[stack_effect(consumes = 0, produces = 1)]
-    StackGet(usize), // Get item at stack position (0 = top)
-    StackSet(usize, IRValue), // Set item at stack position
-    StackAlloc(usize),        // Allocate stack space
-    StackFree(usize),         // Free stack space
+    StackGet(usize), // Push the local in a slot
+    #[stack_effect(consumes = 1, produces = 0)]
+    StackSet(usize), // Pop the top of stack into a slot
+    StackAlloc(usize), // Give the function a frame of this many slots, all zero
+    StackFree(usize),  // Free stack space
 
     // No-op for optimization passes
     Nop,
@@ -138,7 +297,7 @@ pub enum IRInstruction {
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum IRValue {
-    Constant(i32),
+    Constant(Cell),
     StackTop,         // Top of stack
     StackPos(usize),  // Position on stack (0 = top)
     Variable(String), // Named variable
@@ -199,12 +358,23 @@ impl fmt::Display for IRInstruction {
             IRInstruction::Swap => write!(f, "swap"),
             IRInstruction::Over => write!(f, "over"),
             IRInstruction::Rot => write!(f, "rot"),
+            IRInstruction::Pick => write!(f, "pick"),
+            IRInstruction::Roll => write!(f, "roll"),
+            IRInstruction::DupIfNonZero => write!(f, "dup_if_nonzero"),
             IRInstruction::Add => write!(f, "add"),
             IRInstruction::Sub => write!(f, "sub"),
             IRInstruction::Mul => write!(f, "mul"),
             IRInstruction::Div => write!(f, "div"),
             IRInstruction::Mod => write!(f, "mod"),
             IRInstruction::Neg => write!(f, "neg"),
+            IRInstruction::DAdd => write!(f, "dadd"),
+            IRInstruction::DSub => write!(f, "dsub"),
+            IRInstruction::DNeg => write!(f, "dneg"),
+            IRInstruction::MulMixed => write!(f, "mul_mixed"),
+            IRInstruction::MulUnsigned => write!(f, "mul_unsigned"),
+            IRInstruction::DivModUnsigned => write!(f, "divmod_unsigned"),
+            IRInstruction::DivRemSymmetric => write!(f, "divrem_symmetric"),
+            IRInstruction::DivModFloored => write!(f, "divmod_floored"),
             IRInstruction::Equal => write!(f, "eq"),
             IRInstruction::NotEqual => write!(f, "ne"),
             IRInstruction::Less => write!(f, "lt"),
@@ -213,25 +383,85 @@ impl fmt::Display for IRInstruction {
             IRInstruction::GreaterEqual => write!(f, "ge"),
             IRInstruction::And => write!(f, "and"),
             IRInstruction::Or => write!(f, "or"),
+            IRInstruction::Xor => write!(f, "xor"),
             IRInstruction::Not => write!(f, "not"),
             IRInstruction::Load(addr) => write!(f, "load {}", format_value(addr)),
             IRInstruction::Store(addr) => write!(f, "store {}", format_value(addr)),
+            IRInstruction::LoadChar => write!(f, "load_char"),
+            IRInstruction::StoreChar => write!(f, "store_char"),
+            IRInstruction::Here => write!(f, "here"),
+            IRInstruction::Allot => write!(f, "allot"),
+            IRInstruction::Comma => write!(f, "comma"),
+            IRInstruction::CharComma => write!(f, "char_comma"),
+            IRInstruction::Align => write!(f, "align"),
+            IRInstruction::Create(name) => write!(f, "create {}", name),
+            IRInstruction::SupplyName(name) => write!(f, "supply_name {}", name),
+            IRInstruction::CreateSupplied => write!(f, "create_supplied"),
+            IRInstruction::PushDataField(name) => write!(f, "push_data_field {}", name),
             IRInstruction::Jump(label) => write!(f, "jump {}", label),
             IRInstruction::JumpIf(label) => write!(f, "jump_if {}", label),
             IRInstruction::JumpIfNot(label) => write!(f, "jump_if_not {}", label),
             IRInstruction::Call(name) => write!(f, "call {}", name),
             IRInstruction::Return => write!(f, "return"),
+            IRInstruction::PushXt(name) => write!(f, "push_xt {}", name),
+            IRInstruction::Execute => write!(f, "execute"),
+            IRInstruction::Catch => write!(f, "catch"),
+            IRInstruction::Throw => write!(f, "throw"),
+            IRInstruction::Abort => write!(f, "abort"),
+            IRInstruction::AbortQuote(text) => write!(f, "abort {:?}", text),
             IRInstruction::DoLoop(loop_label, end_label) => {
                 write!(f, "do_loop {} {}", loop_label, end_label)
             }
             IRInstruction::Loop(loop_label) => write!(f, "loop {}", loop_label),
+            IRInstruction::PlusLoop(loop_label) => write!(f, "plus_loop {}", loop_label),
+            IRInstruction::Leave(end_label) => write!(f, "leave {}", end_label),
+            IRInstruction::Unloop => write!(f, "unloop"),
             IRInstruction::PushLoopIndex => write!(f, "push_loop_index"),
+            IRInstruction::PushOuterLoopIndex(depth) => {
+                write!(f, "push_outer_loop_index {}", depth)
+            }
             IRInstruction::PushLoopLimit => write!(f, "push_loop_limit"),
+            IRInstruction::CompileWord(name) => write!(f, "compile_word {}", name),
+            IRInstruction::CompileLiteral => write!(f, "compile_literal"),
             IRInstruction::Print => write!(f, "print"),
+            IRInstruction::PrintUnsigned => write!(f, "print_unsigned"),
+            IRInstruction::PrintAligned => write!(f, "print_aligned"),
+            IRInstruction::PrintUnsignedAligned => write!(f, "print_unsigned_aligned"),
             IRInstruction::PrintStack => write!(f, "print_stack"),
             IRInstruction::PrintChar => write!(f, "print_char"),
             IRInstruction::PrintString => write!(f, "print_string"),
+            IRInstruction::PushString(text) => write!(f, "push_string {:?}", text),
+            IRInstruction::PushCountedString(text) => {
+                write!(f, "push_counted_string {:?}", text)
+            }
+            IRInstruction::Compare => write!(f, "compare"),
+            IRInstruction::Search => write!(f, "search"),
+            IRInstruction::TrimTrailing => write!(f, "trim_trailing"),
+            IRInstruction::CMove => write!(f, "cmove"),
+            IRInstruction::Move => write!(f, "move"),
             IRInstruction::ReadChar => write!(f, "read_char"),
+            IRInstruction::PushFloat(r) => write!(f, "push_float {:?}", r),
+            IRInstruction::FAdd => write!(f, "fadd"),
+            IRInstruction::FSub => write!(f, "fsub"),
+            IRInstruction::FMul => write!(f, "fmul"),
+            IRInstruction::FDiv => write!(f, "fdiv"),
+            IRInstruction::FDup => write!(f, "fdup"),
+            IRInstruction::FSwap => write!(f, "fswap"),
+            IRInstruction::FDrop => write!(f, "fdrop"),
+            IRInstruction::FLess => write!(f, "flt"),
+            IRInstruction::FZeroEqual => write!(f, "fzero_eq"),
+            IRInstruction::IntToFloat => write!(f, "int_to_float"),
+            IRInstruction::FloatToInt => write!(f, "float_to_int"),
+            IRInstruction::LoadFloat => write!(f, "load_float"),
+            IRInstruction::StoreFloat => write!(f, "store_float"),
+            IRInstruction::PrintFloat => write!(f, "print_float"),
+            IRInstruction::PrintDouble => write!(f, "print_double"),
+            IRInstruction::HoldStart => write!(f, "hold_start"),
+            IRInstruction::HoldDigit => write!(f, "hold_digit"),
+            IRInstruction::HoldDigits => write!(f, "hold_digits"),
+            IRInstruction::Hold => write!(f, "hold"),
+            IRInstruction::HoldSign => write!(f, "hold_sign"),
+            IRInstruction::HoldEnd => write!(f, "hold_end"),
             IRInstruction::Label(label) => write!(f, "{}:", label),
             IRInstruction::Comment(text) => write!(f, "; {}", text),
             IRInstruction::LoadConst(val) => write!(f, "load_const {}", val),
@@ -239,10 +469,8 @@ impl fmt::Display for IRInstruction {
                 write!(f, "{:?} {}, {}", op, format_value(a), format_value(b))
             }
             IRInstruction::UnaryOp(op, a) => write!(f, "{:?} {}", op, format_value(a)),
-            IRInstruction::StackGet(pos) => write!(f, "stack_get {}", pos),
-            IRInstruction::StackSet(pos, val) => {
-                write!(f, "stack_set {}, {}", pos, format_value(val))
-            }
+            IRInstruction::StackGet(slot) => write!(f, "stack_get {}", slot),
+            IRInstruction::StackSet(slot) => write!(f, "stack_set {}", slot),
             IRInstruction::StackAlloc(size) => write!(f, "stack_alloc {}", size),
             IRInstruction::StackFree(size) => write!(f, "stack_free {}", size),
             IRInstruction::Nop => write!(f, "nop"),
@@ -283,6 +511,9 @@ impl fmt::Display for IRProgram {
                 writeln!(f, "{}", func)?;
             }
         }
+        for func in self.wrappers.values() {
+            writeln!(f, "{}", func)?;
+        }
         Ok(())
     }
 }
@@ -301,10 +532,7 @@ impl IRBuilder {
             current_function: IRFunction {
                 name: function_name.to_string(),
                 instructions: Vec::new(),
-                stack_effect: StackEffect {
-                    consumes: 0,
-                    produces: 0,
-                },
+                stack_effect: StackEffect::default(),
             },
             functions: HashMap::new(),
             label_counter: 0,
@@ -316,6 +544,11 @@ impl IRBuilder {
         self.current_function.instructions.push(instruction);
     }
 
+    /// Index the next instruction emitted will have in the current function
+    pub fn next_index(&self) -> usize {
+        self.current_function.instructions.len()
+    }
+
     pub fn emit_comment(&mut self, text: &str) {
         self.emit(IRInstruction::Comment(text.to_string()));
     }
@@ -340,10 +573,7 @@ impl IRBuilder {
         let mut func = IRFunction {
             name: "temp".to_string(),
             instructions: Vec::new(),
-            stack_effect: StackEffect {
-                consumes: 0,
-                produces: 0,
-            },
+            stack_effect: StackEffect::default(),
         };
         std::mem::swap(&mut func, &mut self.current_function);
         func
@@ -358,10 +588,7 @@ impl IRBuilder {
         self.current_function = IRFunction {
             name: name.to_string(),
             instructions: Vec::new(),
-            stack_effect: StackEffect {
-                consumes: 0,
-                produces: 0,
-            },
+            stack_effect: StackEffect::default(),
         };
     }
 
@@ -370,6 +597,8 @@ impl IRBuilder {
         IRProgram {
             main,
             functions: self.functions,
+            wrappers: HashMap::new(),
+            cell_width: CellWidth::default(),
         }
     }
 }
@@ -403,21 +632,32 @@ mod tests {
             IRInstruction::Push(IRValue::Constant(42)).stack_effect(),
             StackEffect {
                 consumes: 0,
-                produces: 1
+                produces: 1,
+                ..StackEffect::default()
             }
         );
         assert_eq!(
             IRInstruction::Add.stack_effect(),
             StackEffect {
                 consumes: 2,
-                produces: 1
+                produces: 1,
+                ..StackEffect::default()
             }
         );
         assert_eq!(
             IRInstruction::Dup.stack_effect(),
             StackEffect {
                 consumes: 1,
-                produces: 2
+                produces: 2,
+                ..StackEffect::default()
+            }
+        );
+        assert_eq!(
+            IRInstruction::IntToFloat.stack_effect(),
+            StackEffect {
+                consumes: 1,
+                float_produces: 1,
+                ..StackEffect::default()
             }
         );
     }
@@ -1,11 +1,135 @@
 use crate::codegen::CodeGenerator;
-use crate::ir::{BinaryOpKind, IRFunction, IRInstruction, IRProgram, IRValue, UnaryOpKind};
+use crate::ir::{
+    BinaryOpKind, IRFunction, IRInstruction, IRProgram, IRValue, UnaryOpKind, frame_size,
+};
+use crate::types::{Cell, CellWidth, Float};
+use roth_runtime::{BASE_ADDRESS, HOLD_SIZE, SYSTEM_SPACE_SIZE, throw_code};
 use std::collections::HashMap;
 
+/// String literals laid down in data space after BASE and the pictured
+/// numeric output buffer, each after its count byte, and padded so that
+/// HERE starts aligned
+#[derive(Default)]
+struct StringSpace {
+    bytes: Vec<u8>,
+    addresses: HashMap<String, Cell>, // Where each literal's count byte is
+}
+
+impl StringSpace {
+    fn new(program: &IRProgram) -> Self {
+        let mut texts: Vec<&String> = std::iter::once(&program.main)
+            .chain(program.functions.values())
+            .flat_map(|function| &function.instructions)
+            .filter_map(|instruction| match instruction {
+                IRInstruction::PushString(text) | IRInstruction::PushCountedString(text) => {
+                    Some(text)
+                }
+                _ => None,
+            })
+            .collect();
+        texts.sort();
+        texts.dedup();
+
+        let mut space = Self::default();
+        for text in texts {
+            space.addresses.insert(
+                text.clone(),
+                (SYSTEM_SPACE_SIZE + space.bytes.len()) as Cell,
+            );
+            space.bytes.push(text.len() as u8);
+            space.bytes.extend_from_slice(text.as_bytes());
+        }
+        let cell_bytes = program.cell_width.bytes() as usize;
+        space
+            .bytes
+            .resize(space.bytes.len().next_multiple_of(cell_bytes), 0);
+        space
+    }
+
+    fn address(&self, text: &str) -> Cell {
+        self.addresses[text]
+    }
+}
+
+/// Execution tokens of the words a program takes them of, numbered from 1
+/// in name order so that 0 is none, with the function each one runs
+#[derive(Default)]
+struct ExecutionTokens {
+    words: Vec<(String, String)>, // Name and IR function of token index + 1
+}
+
+impl ExecutionTokens {
+    fn new(program: &IRProgram) -> Self {
+        let mut names: Vec<&String> = std::iter::once(&program.main)
+            .chain(program.functions.values())
+            .chain(program.wrappers.values())
+            .flat_map(|function| &function.instructions)
+            .filter_map(|instruction| match instruction {
+                IRInstruction::PushXt(name) => Some(name),
+                _ => None,
+            })
+            .collect();
+        names.sort();
+        names.dedup();
+
+        let words = names
+            .into_iter()
+            .map(|name| {
+                let function = match program.wrappers.get(name) {
+                    Some(wrapper) => wrapper.name.clone(),
+                    None => name.clone(),
+                };
+                (name.clone(), function)
+            })
+            .collect();
+        Self { words }
+    }
+
+    fn token(&self, name: &str) -> Cell {
+        let index = self
+            .words
+            .binary_search_by(|(word, _)| word.as_str().cmp(name))
+            .expect("execution tokens are collected before code is generated");
+        index as Cell + 1
+    }
+
+    /// Each token with the name and function of its word
+    fn iter(&self) -> impl Iterator<Item = (Cell, &str, &str)> {
+        self.words
+            .iter()
+            .enumerate()
+            .map(|(index, (name, function))| (index as Cell + 1, name.as_str(), function.as_str()))
+    }
+}
+
+/// The word or IR function `name` as an identifier in Rust and C. Letters
+/// are lowercased, `-` becomes `_` and any other punctuation its code in hex,
+/// so `?DUP` and `+` get names of their own.
+fn identifier(name: &str) -> String {
+    let mut identifier = String::new();
+    for c in name.to_lowercase().chars() {
+        match c {
+            c if c.is_ascii_alphanumeric() || c == '_' => identifier.push(c),
+            '-' => identifier.push('_'),
+            c => identifier.push_str(&format!("_{:x}", c as u32)),
+        }
+    }
+    identifier
+}
+
+/// Name of the function running the word or IR function `name`, kept apart
+/// from keywords and runtime functions by its prefix, and from a leading digit
+/// as in `2PICK`
+pub(crate) fn function_name(name: &str) -> String {
+    format!("word_{}", identifier(name))
+}
+
 /// Generates Rust code from IR
 pub struct IRRustGenerator {
     indent_level: usize,
     loop_counter: usize,
+    strings: StringSpace,
+    xts: ExecutionTokens,
 }
 
 impl IRRustGenerator {
@@ -13,6 +137,8 @@ impl IRRustGenerator {
         Self {
             indent_level: 0,
             loop_counter: 0,
+            strings: StringSpace::default(),
+            xts: ExecutionTokens::default(),
         }
     }
 
@@ -20,17 +146,62 @@ impl IRRustGenerator {
         "    ".repeat(self.indent_level)
     }
 
+    fn cell_type(width: CellWidth) -> &'static str {
+        match width {
+            CellWidth::Bits32 => "i32",
+            CellWidth::Bits64 => "i64",
+        }
+    }
+
     pub fn generate_program(&mut self, program: &IRProgram) -> String {
         let mut output = String::new();
 
         // Generate header
         output.push_str("// Generated from optimized IR\n");
         output.push_str("use std::collections::HashMap;\n\n");
+        output.push_str(&format!(
+            "type Cell = {};\n",
+            Self::cell_type(program.cell_width)
+        ));
+        // Unsigned and double-width companions of Cell
+        let (unsigned, double, unsigned_double) = match program.cell_width {
+            CellWidth::Bits32 => ("u32", "i64", "u64"),
+            CellWidth::Bits64 => ("u64", "i128", "u128"),
+        };
+        output.push_str(&format!("type UCell = {};\n", unsigned));
+        output.push_str(&format!("type DCell = {};\n", double));
+        output.push_str(&format!("type UDCell = {};\n\n", unsigned_double));
+        output.push_str("const CELL_SIZE: usize = std::mem::size_of::<Cell>();\n");
+        output.push_str("const DATA_SPACE_SIZE: usize = 1 << 20;\n");
+        output.push_str(&format!("const BASE_ADDRESS: Cell = {};\n", BASE_ADDRESS));
+        output.push_str(&format!("const HOLD_SIZE: usize = {};\n", HOLD_SIZE));
+        output.push_str(&format!(
+            "const HOLD_END: usize = {}; // Pictured numeric output is built down from here\n",
+            SYSTEM_SPACE_SIZE
+        ));
+        output.push_str("const DIGITS: &[u8] = b\"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ\";\n");
+        self.strings = StringSpace::new(program);
+        output.push_str(&format!(
+            "const STRING_SPACE: &[u8] = b\"{}\"; // String literals, laid down from HOLD_END\n\n",
+            self.strings.bytes.escape_ascii()
+        ));
+        for (name, code) in throw_code::ALL {
+            output.push_str(&format!("const {}: Cell = {};\n", name, code));
+        }
+        output.push_str(RUST_EXCEPTION);
         output.push_str("pub struct OptimizedForth {\n");
-        output.push_str("    stack: Vec<i32>,\n");
+        output.push_str("    stack: Vec<Cell>,\n");
+        output.push_str("    fstack: Vec<f64>, // Floats, apart from the data stack\n");
         output.push_str("    words: HashMap<String, Vec<String>>,\n");
-        output.push_str("    loop_stack: Vec<(i32, i32)>, // (index, limit) pairs\n");
-        output.push_str("    memory: HashMap<i32, i32>, // Memory for variables\n");
+        output.push_str("    loop_stack: Vec<(Cell, Cell)>, // (index, limit) pairs\n");
+        output.push_str("    memory: Vec<u8>, // Data space; HERE is its length\n");
+        output.push_str(
+            "    data_fields: HashMap<&'static str, Cell>, // Addresses given by CREATE\n",
+        );
+        output.push_str(
+            "    supplied_name: &'static str, // Name for CREATE inside a defining word\n",
+        );
+        output.push_str("    held: usize, // Start of the pictured numeric output so far\n");
         output.push_str("}\n\n");
 
         output.push_str("impl OptimizedForth {\n");
@@ -42,25 +213,48 @@ impl IRRustGenerator {
         output.push_str(&format!("{}Self {{\n", self.emit_indent()));
         self.indent_level += 1;
         output.push_str(&format!("{}stack: Vec::new(),\n", self.emit_indent()));
+        output.push_str(&format!("{}fstack: Vec::new(),\n", self.emit_indent()));
         output.push_str(&format!("{}words: HashMap::new(),\n", self.emit_indent()));
         output.push_str(&format!("{}loop_stack: Vec::new(),\n", self.emit_indent()));
-        output.push_str(&format!("{}memory: HashMap::new(),\n", self.emit_indent()));
+        output.push_str(&format!(
+            "{}memory: [&(10 as Cell).to_ne_bytes()[..], &[0; HOLD_END - CELL_SIZE][..], STRING_SPACE].concat(),\n",
+            self.emit_indent()
+        ));
+        output.push_str(&format!(
+            "{}data_fields: HashMap::new(),\n",
+            self.emit_indent()
+        ));
+        output.push_str(&format!("{}supplied_name: \"\",\n", self.emit_indent()));
+        output.push_str(&format!("{}held: HOLD_END,\n", self.emit_indent()));
         self.indent_level -= 1;
         output.push_str(&format!("{}}}\n", self.emit_indent()));
         self.indent_level -= 1;
         output.push_str(&format!("{}}}\n\n", self.emit_indent()));
 
-        // Generate user-defined functions
+        output.push_str(RUST_STACK_HELPERS);
+        output.push_str(RUST_DATA_SPACE_HELPERS);
+        output.push_str(RUST_DOUBLE_HELPERS);
+        output.push_str(RUST_STRING_HELPERS);
+        output.push_str(RUST_NUMBER_OUTPUT_HELPERS);
+
+        // Generate user-defined functions, then those run only by execution
+        // tokens
+        self.xts = ExecutionTokens::new(program);
         for (name, function) in &program.functions {
             if name != "main" {
                 output.push_str(&self.generate_function(function));
                 output.push('\n');
             }
         }
+        for wrapper in program.wrappers.values() {
+            output.push_str(&self.generate_function(wrapper));
+            output.push('\n');
+        }
+        output.push_str(&self.generate_execute());
 
         // Generate main execution function
         output.push_str(&format!(
-            "{}pub fn execute(&mut self) -> Result<(), String> {{\n",
+            "{}pub fn execute(&mut self) -> Result<(), Exception> {{\n",
             self.emit_indent()
         ));
         self.indent_level += 1;
@@ -72,18 +266,46 @@ impl IRRustGenerator {
         self.indent_level -= 1;
         output.push_str("}\n\n");
 
+        output.push_str(RUST_FORMAT_FLOAT);
+
         // Add main function for execution
         output.push_str("fn main() {\n");
         output.push_str("    let mut forth = OptimizedForth::new();\n");
         output.push_str("    match forth.execute() {\n");
         output.push_str("        Ok(()) => {},\n");
-        output.push_str("        Err(e) => eprintln!(\"Error: {}\", e),\n");
+        output.push_str("        Err(e) => eprintln!(\"Error: {}\", e.message),\n");
         output.push_str("    }\n");
         output.push_str("}\n");
 
         output
     }
 
+    /// EXECUTE's dispatch from execution token to function
+    fn generate_execute(&self) -> String {
+        let mut output = String::new();
+        output.push_str(&format!(
+            "{}fn __execute(&mut self, xt: Cell) -> Result<(), Exception> {{\n",
+            self.emit_indent()
+        ));
+        output.push_str(&format!("{}    match xt {{\n", self.emit_indent()));
+        for (xt, name, function) in self.xts.iter() {
+            output.push_str(&format!(
+                "{}        {} => self.{}(), // {}\n",
+                self.emit_indent(),
+                xt,
+                function_name(function),
+                name
+            ));
+        }
+        output.push_str(&format!(
+            "{}        _ => __throw(UNDEFINED_WORD, format!(\"Invalid execution token: {{}}\", xt)),\n",
+            self.emit_indent()
+        ));
+        output.push_str(&format!("{}    }}\n", self.emit_indent()));
+        output.push_str(&format!("{}}}\n\n", self.emit_indent()));
+        output
+    }
+
     fn generate_function(&mut self, function: &IRFunction) -> String {
         let mut output = String::new();
 
@@ -95,12 +317,20 @@ impl IRRustGenerator {
             function.stack_effect.produces
         ));
         output.push_str(&format!(
-            "{}fn {}(&mut self) -> Result<(), String> {{\n",
+            "{}fn {}(&mut self) -> Result<(), Exception> {{\n",
             self.emit_indent(),
-            function.name.to_lowercase().replace("-", "_")
+            function_name(&function.name)
         ));
         self.indent_level += 1;
 
+        // Locals are native variables, so every call has its own
+        for slot in 0..frame_size(&function.instructions) {
+            output.push_str(&format!(
+                "{}let mut local_{}: Cell = 0;\n",
+                self.emit_indent(),
+                slot
+            ));
+        }
         output.push_str(&self.generate_function_body(function));
 
         // Only add Ok(()) if the function doesn't end with a return
@@ -118,13 +348,7 @@ impl IRRustGenerator {
     }
 
     fn generate_function_body(&mut self, function: &IRFunction) -> String {
-        // Check if this function has control flow (jumps/labels) but NOT loops
-        // Loops use a different code generation pattern that doesn't work with state machine
-        let has_loops = function
-            .instructions
-            .iter()
-            .any(|instr| matches!(instr, IRInstruction::DoLoop(_, _) | IRInstruction::Loop(_)));
-
+        // Any jump or loop needs the state machine so branches can target arbitrary PCs
         let has_control_flow = function.instructions.iter().any(|instr| {
             matches!(
                 instr,
@@ -132,10 +356,14 @@ impl IRRustGenerator {
                     | IRInstruction::JumpIf(_)
                     | IRInstruction::JumpIfNot(_)
                     | IRInstruction::Label(_)
+                    | IRInstruction::DoLoop(_, _)
+                    | IRInstruction::Loop(_)
+                    | IRInstruction::PlusLoop(_)
+                    | IRInstruction::Leave(_)
             )
         });
 
-        if has_control_flow && !has_loops {
+        if has_control_flow {
             self.generate_function_body_with_control_flow(function)
         } else {
             let mut output = String::new();
@@ -189,7 +417,7 @@ impl IRRustGenerator {
                 IRInstruction::JumpIf(label) => {
                     let label_key = format!("{}_{}", label.name, label.id);
                     output.push_str(&format!(
-                        "{}let __cond = self.stack.pop().unwrap();\n",
+                        "{}let __cond = self.__pop()?;\n",
                         self.emit_indent()
                     ));
                     if let Some(&target_pc) = label_to_pc.get(&label_key) {
@@ -206,7 +434,7 @@ impl IRRustGenerator {
                 IRInstruction::JumpIfNot(label) => {
                     let label_key = format!("{}_{}", label.name, label.id);
                     output.push_str(&format!(
-                        "{}let __cond = self.stack.pop().unwrap();\n",
+                        "{}let __cond = self.__pop()?;\n",
                         self.emit_indent()
                     ));
                     if let Some(&target_pc) = label_to_pc.get(&label_key) {
@@ -220,6 +448,63 @@ impl IRRustGenerator {
                         output.push_str(&format!("{}__pc = {};\n", self.emit_indent(), pc + 1));
                     }
                 }
+                IRInstruction::DoLoop(_, end_label) => {
+                    let end_key = format!("{}_{}", end_label.name, end_label.id);
+                    let end_pc = label_to_pc.get(&end_key).copied().unwrap_or(pc + 1);
+                    output.push_str(&format!(
+                        "{}let __start = self.__pop()?; let __limit = self.__pop()?;\n",
+                        self.emit_indent()
+                    ));
+                    output.push_str(&format!(
+                        "{}if __start != __limit {{ self.loop_stack.push((__start, __limit)); __pc = {}; }} else {{ __pc = {}; }}\n",
+                        self.emit_indent(),
+                        pc + 1,
+                        end_pc
+                    ));
+                }
+                IRInstruction::Loop(loop_label) => {
+                    let loop_key = format!("{}_{}", loop_label.name, loop_label.id);
+                    let loop_pc = label_to_pc.get(&loop_key).copied().unwrap_or(pc + 1);
+                    output.push_str(&format!(
+                        "{}let __done = {{ let __frame = self.loop_stack.last_mut().unwrap(); __frame.0 = __frame.0.wrapping_add(1); __frame.0 == __frame.1 }};\n",
+                        self.emit_indent()
+                    ));
+                    output.push_str(&format!(
+                        "{}if __done {{ self.loop_stack.pop(); __pc = {}; }} else {{ __pc = {}; }}\n",
+                        self.emit_indent(),
+                        pc + 1,
+                        loop_pc
+                    ));
+                }
+                IRInstruction::PlusLoop(loop_label) => {
+                    let loop_key = format!("{}_{}", loop_label.name, loop_label.id);
+                    let loop_pc = label_to_pc.get(&loop_key).copied().unwrap_or(pc + 1);
+                    // Done once index - limit changes sign while moving towards the limit
+                    output.push_str(&format!(
+                        "{}let __step = self.__pop()?;\n",
+                        self.emit_indent()
+                    ));
+                    output.push_str(&format!(
+                        "{}let __done = {{ let __frame = self.loop_stack.last_mut().unwrap(); let __diff = __frame.0.wrapping_sub(__frame.1); __frame.0 = __frame.0.wrapping_add(__step); ((__diff ^ __diff.wrapping_add(__step)) & (__diff ^ __step)) < 0 }};\n",
+                        self.emit_indent()
+                    ));
+                    output.push_str(&format!(
+                        "{}if __done {{ self.loop_stack.pop(); __pc = {}; }} else {{ __pc = {}; }}\n",
+                        self.emit_indent(),
+                        pc + 1,
+                        loop_pc
+                    ));
+                }
+                IRInstruction::Leave(end_label) => {
+                    let end_key = format!("{}_{}", end_label.name, end_label.id);
+                    let end_pc = label_to_pc.get(&end_key).copied().unwrap_or(pc + 1);
+                    output.push_str(&format!(
+                        "{}self.loop_stack.pop();\n{}__pc = {};\n",
+                        self.emit_indent(),
+                        self.emit_indent(),
+                        end_pc
+                    ));
+                }
                 IRInstruction::Return => {
                     output.push_str(&format!("{}return Ok(());\n", self.emit_indent()));
                 }
@@ -264,151 +549,363 @@ impl IRRustGenerator {
                 format!("{}self.stack.push({});\n", self.emit_indent(), n)
             }
             IRInstruction::Pop => {
-                format!("{}self.stack.pop();\n", self.emit_indent())
+                format!("{}self.__pop()?;\n", self.emit_indent())
             }
             IRInstruction::Drop => {
-                format!("{}self.stack.pop();\n", self.emit_indent())
+                format!("{}self.__pop()?;\n", self.emit_indent())
             }
             IRInstruction::Dup => {
                 format!(
-                    "{}{{ let top = *self.stack.last().unwrap(); self.stack.push(top); }}\n",
+                    "{}{{ let top = self.__pop()?; self.stack.push(top); self.stack.push(top); }}\n",
                     self.emit_indent()
                 )
             }
             IRInstruction::Swap => {
                 format!(
-                    "{}{{ let len = self.stack.len(); self.stack.swap(len-1, len-2); }}\n",
+                    "{}{{ let b = self.__pop()?; let a = self.__pop()?; self.stack.push(b); self.stack.push(a); }}\n",
                     self.emit_indent()
                 )
             }
             IRInstruction::Over => {
                 format!(
-                    "{}{{ let val = self.stack[self.stack.len()-2]; self.stack.push(val); }}\n",
+                    "{}{{ let b = self.__pop()?; let a = self.__pop()?; self.stack.push(a); self.stack.push(b); self.stack.push(a); }}\n",
                     self.emit_indent()
                 )
             }
             IRInstruction::Rot => {
                 format!(
-                    "{}{{ let c = self.stack.pop().unwrap(); let b = self.stack.pop().unwrap(); let a = self.stack.pop().unwrap(); self.stack.push(b); self.stack.push(c); self.stack.push(a); }}\n",
+                    "{}{{ let c = self.__pop()?; let b = self.__pop()?; let a = self.__pop()?; self.stack.push(b); self.stack.push(c); self.stack.push(a); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::Pick => {
+                format!(
+                    "{}{{ let n = self.__pop()? as usize; let len = self.stack.len(); if n >= len {{ return __throw(STACK_UNDERFLOW, \"Stack underflow\"); }} self.stack.push(self.stack[len - 1 - n]); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::Roll => {
+                format!(
+                    "{}{{ let n = self.__pop()? as usize; let len = self.stack.len(); if n >= len {{ return __throw(STACK_UNDERFLOW, \"Stack underflow\"); }} let x = self.stack.remove(len - 1 - n); self.stack.push(x); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::DupIfNonZero => {
+                format!(
+                    "{}{{ let top = self.__pop()?; self.stack.push(top); if top != 0 {{ self.stack.push(top); }} }}\n",
                     self.emit_indent()
                 )
             }
             IRInstruction::Add => {
                 format!(
-                    "{}{{ let b = self.stack.pop().unwrap(); let a = self.stack.pop().unwrap(); self.stack.push(a + b); }}\n",
+                    "{}{{ let b = self.__pop()?; let a = self.__pop()?; self.stack.push(a.wrapping_add(b)); }}\n",
                     self.emit_indent()
                 )
             }
             IRInstruction::Sub => {
                 format!(
-                    "{}{{ let b = self.stack.pop().unwrap(); let a = self.stack.pop().unwrap(); self.stack.push(a - b); }}\n",
+                    "{}{{ let b = self.__pop()?; let a = self.__pop()?; self.stack.push(a.wrapping_sub(b)); }}\n",
                     self.emit_indent()
                 )
             }
             IRInstruction::Mul => {
                 format!(
-                    "{}{{ let b = self.stack.pop().unwrap(); let a = self.stack.pop().unwrap(); self.stack.push(a * b); }}\n",
+                    "{}{{ let b = self.__pop()?; let a = self.__pop()?; self.stack.push(a.wrapping_mul(b)); }}\n",
                     self.emit_indent()
                 )
             }
             IRInstruction::Div => {
                 format!(
-                    "{}{{ let b = self.stack.pop().unwrap(); let a = self.stack.pop().unwrap(); self.stack.push(a / b); }}\n",
+                    "{}{{ let b = self.__pop()?; let a = self.__pop()?; if b == 0 {{ return __throw(DIVISION_BY_ZERO, \"Division by zero\"); }} self.stack.push(a.wrapping_div(b)); }}\n",
                     self.emit_indent()
                 )
             }
             IRInstruction::Mod => {
                 format!(
-                    "{}{{ let b = self.stack.pop().unwrap(); let a = self.stack.pop().unwrap(); self.stack.push(a % b); }}\n",
+                    "{}{{ let b = self.__pop()?; let a = self.__pop()?; if b == 0 {{ return __throw(DIVISION_BY_ZERO, \"Division by zero\"); }} self.stack.push(a.wrapping_rem(b)); }}\n",
                     self.emit_indent()
                 )
             }
             IRInstruction::Neg => {
                 format!(
-                    "{}{{ let a = self.stack.pop().unwrap(); self.stack.push(-a); }}\n",
+                    "{}{{ let a = self.__pop()?; self.stack.push(a.wrapping_neg()); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::DAdd => self.generate_double_binary_op("a.wrapping_add(b)"),
+            IRInstruction::DSub => self.generate_double_binary_op("a.wrapping_sub(b)"),
+            IRInstruction::DNeg => {
+                format!(
+                    "{}{{ let a = self.__pop_double()?; self.__push_double(a.wrapping_neg()); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::MulMixed => {
+                format!(
+                    "{}{{ let b = self.__pop()? as DCell; let a = self.__pop()? as DCell; self.__push_double(a * b); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::MulUnsigned => {
+                format!(
+                    "{}{{ let b = self.__pop()? as UCell as UDCell; let a = self.__pop()? as UCell as UDCell; self.__push_double((a * b) as DCell); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::DivModUnsigned => {
+                format!(
+                    "{}{{ let divisor = self.__pop()? as UCell as UDCell; let dividend = self.__pop_double()? as UDCell; if divisor == 0 {{ return __throw(DIVISION_BY_ZERO, \"Division by zero\"); }} self.stack.push((dividend % divisor) as Cell); self.stack.push((dividend / divisor) as Cell); }}\n",
                     self.emit_indent()
                 )
             }
+            IRInstruction::DivRemSymmetric => {
+                format!("{}self.__divide_double(false)?;\n", self.emit_indent())
+            }
+            IRInstruction::DivModFloored => {
+                format!("{}self.__divide_double(true)?;\n", self.emit_indent())
+            }
             IRInstruction::Equal => {
                 format!(
-                    "{}{{ let b = self.stack.pop().unwrap(); let a = self.stack.pop().unwrap(); self.stack.push(if a == b {{ -1 }} else {{ 0 }}); }}\n",
+                    "{}{{ let b = self.__pop()?; let a = self.__pop()?; self.stack.push(if a == b {{ -1 }} else {{ 0 }}); }}\n",
                     self.emit_indent()
                 )
             }
             IRInstruction::NotEqual => {
                 format!(
-                    "{}{{ let b = self.stack.pop().unwrap(); let a = self.stack.pop().unwrap(); self.stack.push(if a != b {{ -1 }} else {{ 0 }}); }}\n",
+                    "{}{{ let b = self.__pop()?; let a = self.__pop()?; self.stack.push(if a != b {{ -1 }} else {{ 0 }}); }}\n",
                     self.emit_indent()
                 )
             }
             IRInstruction::Less => {
                 format!(
-                    "{}{{ let b = self.stack.pop().unwrap(); let a = self.stack.pop().unwrap(); self.stack.push(if a < b {{ -1 }} else {{ 0 }}); }}\n",
+                    "{}{{ let b = self.__pop()?; let a = self.__pop()?; self.stack.push(if a < b {{ -1 }} else {{ 0 }}); }}\n",
                     self.emit_indent()
                 )
             }
             IRInstruction::Greater => {
                 format!(
-                    "{}{{ let b = self.stack.pop().unwrap(); let a = self.stack.pop().unwrap(); self.stack.push(if a > b {{ -1 }} else {{ 0 }}); }}\n",
+                    "{}{{ let b = self.__pop()?; let a = self.__pop()?; self.stack.push(if a > b {{ -1 }} else {{ 0 }}); }}\n",
                     self.emit_indent()
                 )
             }
             IRInstruction::LessEqual => {
                 format!(
-                    "{}{{ let b = self.stack.pop().unwrap(); let a = self.stack.pop().unwrap(); self.stack.push(if a <= b {{ -1 }} else {{ 0 }}); }}\n",
+                    "{}{{ let b = self.__pop()?; let a = self.__pop()?; self.stack.push(if a <= b {{ -1 }} else {{ 0 }}); }}\n",
                     self.emit_indent()
                 )
             }
             IRInstruction::GreaterEqual => {
                 format!(
-                    "{}{{ let b = self.stack.pop().unwrap(); let a = self.stack.pop().unwrap(); self.stack.push(if a >= b {{ -1 }} else {{ 0 }}); }}\n",
+                    "{}{{ let b = self.__pop()?; let a = self.__pop()?; self.stack.push(if a >= b {{ -1 }} else {{ 0 }}); }}\n",
                     self.emit_indent()
                 )
             }
             IRInstruction::And => {
                 format!(
-                    "{}{{ let b = self.stack.pop().unwrap(); let a = self.stack.pop().unwrap(); self.stack.push(if a != 0 && b != 0 {{ -1 }} else {{ 0 }}); }}\n",
+                    "{}{{ let b = self.__pop()?; let a = self.__pop()?; self.stack.push(if a != 0 && b != 0 {{ -1 }} else {{ 0 }}); }}\n",
                     self.emit_indent()
                 )
             }
             IRInstruction::Or => {
                 format!(
-                    "{}{{ let b = self.stack.pop().unwrap(); let a = self.stack.pop().unwrap(); self.stack.push(if a != 0 || b != 0 {{ -1 }} else {{ 0 }}); }}\n",
+                    "{}{{ let b = self.__pop()?; let a = self.__pop()?; self.stack.push(if a != 0 || b != 0 {{ -1 }} else {{ 0 }}); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::Xor => {
+                format!(
+                    "{}{{ let b = self.__pop()?; let a = self.__pop()?; self.stack.push(a ^ b); }}\n",
                     self.emit_indent()
                 )
             }
             IRInstruction::Not => {
                 format!(
-                    "{}{{ let a = self.stack.pop().unwrap(); self.stack.push(if a == 0 {{ -1 }} else {{ 0 }}); }}\n",
+                    "{}{{ let a = self.__pop()?; self.stack.push(if a == 0 {{ -1 }} else {{ 0 }}); }}\n",
                     self.emit_indent()
                 )
             }
             IRInstruction::Print => {
                 format!(
-                    "{}print!(\"{{}}\", self.stack.pop().unwrap());\n{}print!(\" \");\n",
-                    self.emit_indent(),
+                    "{}{{ let n = self.__pop()?; print!(\"{{}} \", self.__format_signed(n as DCell)?); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::PrintUnsigned => {
+                format!(
+                    "{}{{ let u = self.__pop()?; print!(\"{{}} \", self.__format_number(u as UCell as UDCell, false)?); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::PrintAligned => {
+                format!(
+                    "{}{{ let width = self.__pop()?.max(0) as usize; let n = self.__pop()?; print!(\"{{:>1$}}\", self.__format_signed(n as DCell)?, width); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::PrintUnsignedAligned => {
+                format!(
+                    "{}{{ let width = self.__pop()?.max(0) as usize; let u = self.__pop()?; print!(\"{{:>1$}}\", self.__format_number(u as UCell as UDCell, false)?, width); }}\n",
                     self.emit_indent()
                 )
             }
             IRInstruction::PrintStack => {
                 format!(
-                    "{}{{ print!(\"<{{}}> \", self.stack.len()); for v in &self.stack {{ print!(\"{{}} \", v); }} }}\n",
+                    "{}{{ let mut text = format!(\"<{{}}> \", self.stack.len()); for i in 0..self.stack.len() {{ text += &self.__format_signed(self.stack[i] as DCell)?; text.push(' '); }} print!(\"{{}}\", text); }}\n",
                     self.emit_indent()
                 )
             }
             IRInstruction::PrintChar => {
                 format!(
-                    "{}print!(\"{{}}\", char::from(self.stack.pop().unwrap() as u8));\n",
+                    "{}print!(\"{{}}\", char::from(self.__pop()? as u8));\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::PrintDouble => {
+                format!(
+                    "{}{{ let d = self.__pop_double()?; print!(\"{{}} \", self.__format_signed(d)?); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::HoldStart => format!("{}self.held = HOLD_END;\n", self.emit_indent()),
+            IRInstruction::HoldDigit => {
+                format!(
+                    "{}{{ let ud = self.__pop_double()? as UDCell; let rest = self.__hold_digit(ud)?; self.__push_double(rest as DCell); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::HoldDigits => {
+                format!(
+                    "{}{{ let mut ud = self.__pop_double()? as UDCell; loop {{ ud = self.__hold_digit(ud)?; if ud == 0 {{ break; }} }} self.__push_double(0); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::Hold => {
+                format!(
+                    "{}{{ let c = self.__pop()?; self.__hold(c as u8)?; }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::HoldSign => {
+                format!(
+                    "{}if self.__pop()? < 0 {{ self.__hold(b'-')?; }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::HoldEnd => {
+                format!(
+                    "{}{{ self.__pop_double()?; self.stack.push(self.held as Cell); self.stack.push((HOLD_END - self.held) as Cell); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::PrintString => format!("{}self.__type()?;\n", self.emit_indent()),
+            IRInstruction::PushString(text) => {
+                format!(
+                    "{}self.stack.push({}); self.stack.push({});\n",
+                    self.emit_indent(),
+                    self.strings.address(text) + 1,
+                    text.len()
+                )
+            }
+            IRInstruction::PushCountedString(text) => {
+                format!(
+                    "{}self.stack.push({});\n",
+                    self.emit_indent(),
+                    self.strings.address(text)
+                )
+            }
+            IRInstruction::Compare => format!("{}self.__compare()?;\n", self.emit_indent()),
+            IRInstruction::Search => format!("{}self.__search()?;\n", self.emit_indent()),
+            IRInstruction::TrimTrailing => {
+                format!("{}self.__trim_trailing()?;\n", self.emit_indent())
+            }
+            IRInstruction::CMove => {
+                format!(
+                    "{}{{ let (from, to) = self.__pop_move()?; for i in 0..from.len() {{ self.memory[to.start + i] = self.memory[from.start + i]; }} }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::Move => {
+                format!(
+                    "{}{{ let (from, to) = self.__pop_move()?; self.memory.copy_within(from, to.start); }}\n",
                     self.emit_indent()
                 )
             }
-            IRInstruction::PrintString => {
+            IRInstruction::PushFloat(r) => {
                 format!(
-                    "{}// PrintString: print characters from stack\n{}{{ let count = self.stack.pop().unwrap(); let mut chars: Vec<char> = Vec::new(); for _ in 0..count {{ chars.push(char::from(self.stack.pop().unwrap() as u8)); }} for c in chars.iter().rev() {{ print!(\"{{}}\", c); }} }}\n",
+                    "{}self.fstack.push({});\n",
                     self.emit_indent(),
+                    Self::float_literal(*r)
+                )
+            }
+            IRInstruction::FAdd => self.generate_float_binary_op("a + b"),
+            IRInstruction::FSub => self.generate_float_binary_op("a - b"),
+            IRInstruction::FMul => self.generate_float_binary_op("a * b"),
+            IRInstruction::FDiv => self.generate_float_binary_op("a / b"),
+            IRInstruction::FDup => {
+                format!(
+                    "{}{{ let r = self.__fpop()?; self.fstack.push(r); self.fstack.push(r); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::FSwap => {
+                format!(
+                    "{}{{ let b = self.__fpop()?; let a = self.__fpop()?; self.fstack.push(b); self.fstack.push(a); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::FDrop => {
+                format!("{}self.__fpop()?;\n", self.emit_indent())
+            }
+            IRInstruction::FLess => {
+                format!(
+                    "{}{{ let b = self.__fpop()?; let a = self.__fpop()?; self.stack.push(if a < b {{ -1 }} else {{ 0 }}); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::FZeroEqual => {
+                format!(
+                    "{}{{ let r = self.__fpop()?; self.stack.push(if r == 0.0 {{ -1 }} else {{ 0 }}); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::IntToFloat => {
+                format!(
+                    "{}{{ let n = self.__pop()?; self.fstack.push(n as f64); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::FloatToInt => {
+                format!(
+                    "{}{{ let r = self.__fpop()?; self.stack.push(r as Cell); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::LoadFloat => {
+                format!(
+                    "{}{{ let addr = self.__pop()?; let r = self.__fetch_float(addr)?; self.fstack.push(r); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::StoreFloat => {
+                format!(
+                    "{}{{ let addr = self.__pop()?; let r = self.__fpop()?; self.__store_float(addr, r)?; }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::PrintFloat => {
+                format!(
+                    "{}print!(\"{{}} \", __format_float(self.__fpop()?));\n",
                     self.emit_indent()
                 )
             }
+            IRInstruction::CompileWord(_) | IRInstruction::CompileLiteral => {
+                // IMMEDIATE words run inside the compiler and never get here
+                format!(
+                    "{}// {} only runs at compile time\n",
+                    self.emit_indent(),
+                    instruction
+                )
+            }
             IRInstruction::ReadChar => {
                 format!(
                     "{}// ReadChar not implemented in this generator\n",
@@ -416,51 +913,55 @@ impl IRRustGenerator {
                 )
             }
             IRInstruction::Call(name) => {
+                format!("{}self.{}()?;\n", self.emit_indent(), function_name(name))
+            }
+            IRInstruction::Return => {
+                format!("{}return Ok(());\n", self.emit_indent())
+            }
+            IRInstruction::PushXt(name) => {
                 format!(
-                    "{}self.{}()?;\n",
+                    "{}self.stack.push({}); // ' {}\n",
                     self.emit_indent(),
-                    name.to_lowercase().replace("-", "_")
+                    self.xts.token(name),
+                    name
                 )
             }
-            IRInstruction::Return => {
-                format!("{}return Ok(());\n", self.emit_indent())
+            IRInstruction::Execute => {
+                format!(
+                    "{}{{ let xt = self.__pop()?; self.__execute(xt)?; }}\n",
+                    self.emit_indent()
+                )
             }
-            IRInstruction::DoLoop(_loop_label, _end_label) => {
-                let mut output = String::new();
-                output.push_str(&format!("{}// ?DO: setup loop\n", self.emit_indent()));
-                output.push_str(&format!("{}{{\n", self.emit_indent()));
-                self.indent_level += 1;
-                output.push_str(&format!(
-                    "{}let start = self.stack.pop().unwrap();\n",
-                    self.emit_indent()
-                ));
-                output.push_str(&format!(
-                    "{}let limit = self.stack.pop().unwrap();\n",
-                    self.emit_indent()
-                ));
-                output.push_str(&format!("{}if start < limit {{\n", self.emit_indent()));
-                self.indent_level += 1;
-                output.push_str(&format!(
-                    "{}for loop_index in start..limit {{\n",
-                    self.emit_indent()
-                ));
-                self.indent_level += 1;
-                output.push_str(&format!(
-                    "{}self.loop_stack.push((loop_index, limit));\n",
-                    self.emit_indent()
-                ));
-                output
-            }
-            IRInstruction::Loop(_loop_label) => {
-                let mut output = String::new();
-                output.push_str(&format!("{}self.loop_stack.pop();\n", self.emit_indent()));
-                self.indent_level -= 1;
-                output.push_str(&format!("{}}}\n", self.emit_indent()));
-                self.indent_level -= 1;
-                output.push_str(&format!("{}}}\n", self.emit_indent()));
-                self.indent_level -= 1;
-                output.push_str(&format!("{}}}\n", self.emit_indent()));
-                output
+            IRInstruction::Catch => {
+                format!("{}self.__catch()?;\n", self.emit_indent())
+            }
+            IRInstruction::Throw => {
+                format!("{}self.__throw_top()?;\n", self.emit_indent())
+            }
+            IRInstruction::Abort => {
+                format!(
+                    "{}__throw::<()>(ABORT, \"Aborted\")?;\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::AbortQuote(text) => {
+                format!(
+                    "{}if self.__pop()? != 0 {{ return __throw(ABORT_QUOTE, {:?}); }}\n",
+                    self.emit_indent(),
+                    text
+                )
+            }
+            IRInstruction::DoLoop(_, _)
+            | IRInstruction::Loop(_)
+            | IRInstruction::PlusLoop(_)
+            | IRInstruction::Leave(_) => {
+                format!(
+                    "{}// Loop instructions are handled by the state machine\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::Unloop => {
+                format!("{}self.loop_stack.pop();\n", self.emit_indent())
             }
             IRInstruction::PushLoopIndex => {
                 format!(
@@ -469,6 +970,14 @@ impl IRRustGenerator {
                     self.emit_indent()
                 )
             }
+            IRInstruction::PushOuterLoopIndex(depth) => {
+                format!(
+                    "{}// J/K: push outer loop index\n{}if let Some((index, _)) = self.loop_stack.iter().rev().nth({}) {{ self.stack.push(*index); }} else {{ self.stack.push(0); }}\n",
+                    self.emit_indent(),
+                    self.emit_indent(),
+                    depth
+                )
+            }
             IRInstruction::PushLoopLimit => {
                 format!(
                     "{}// push loop limit\n{}if let Some((_, limit)) = self.loop_stack.last() {{ self.stack.push(*limit); }} else {{ self.stack.push(0); }}\n",
@@ -477,40 +986,31 @@ impl IRRustGenerator {
                 )
             }
             IRInstruction::BinaryOp(op, a, b) => {
-                let op_str = match op {
-                    BinaryOpKind::Add => "+",
-                    BinaryOpKind::Sub => "-",
-                    BinaryOpKind::Mul => "*",
-                    BinaryOpKind::Div => "/",
-                    BinaryOpKind::Mod => "%",
-                    BinaryOpKind::Equal => "==",
-                    BinaryOpKind::NotEqual => "!=",
-                    BinaryOpKind::Less => "<",
-                    BinaryOpKind::Greater => ">",
-                    BinaryOpKind::LessEqual => "<=",
-                    BinaryOpKind::GreaterEqual => ">=",
-                    BinaryOpKind::And => "&&",
-                    BinaryOpKind::Or => "||",
+                let (a, b) = (self.generate_value(a), self.generate_value(b));
+                let expr = match op {
+                    BinaryOpKind::Add => format!("Cell::wrapping_add({}, {})", a, b),
+                    BinaryOpKind::Sub => format!("Cell::wrapping_sub({}, {})", a, b),
+                    BinaryOpKind::Mul => format!("Cell::wrapping_mul({}, {})", a, b),
+                    BinaryOpKind::Div => format!("Cell::wrapping_div({}, {})", a, b),
+                    BinaryOpKind::Mod => format!("Cell::wrapping_rem({}, {})", a, b),
+                    BinaryOpKind::Equal => format!("{} == {}", a, b),
+                    BinaryOpKind::NotEqual => format!("{} != {}", a, b),
+                    BinaryOpKind::Less => format!("{} < {}", a, b),
+                    BinaryOpKind::Greater => format!("{} > {}", a, b),
+                    BinaryOpKind::LessEqual => format!("{} <= {}", a, b),
+                    BinaryOpKind::GreaterEqual => format!("{} >= {}", a, b),
+                    BinaryOpKind::And => format!("{} && {}", a, b),
+                    BinaryOpKind::Or => format!("{} || {}", a, b),
                 };
-                format!(
-                    "{}self.stack.push({} {} {});\n",
-                    self.emit_indent(),
-                    self.generate_value(a),
-                    op_str,
-                    self.generate_value(b)
-                )
+                format!("{}self.stack.push({});\n", self.emit_indent(), expr)
             }
             IRInstruction::UnaryOp(op, a) => {
-                let op_str = match op {
-                    UnaryOpKind::Neg => "-",
-                    UnaryOpKind::Not => "!",
+                let a = self.generate_value(a);
+                let expr = match op {
+                    UnaryOpKind::Neg => format!("Cell::wrapping_neg({})", a),
+                    UnaryOpKind::Not => format!("!{}", a),
                 };
-                format!(
-                    "{}self.stack.push({}{});\n",
-                    self.emit_indent(),
-                    op_str,
-                    self.generate_value(a)
-                )
+                format!("{}self.stack.push({});\n", self.emit_indent(), expr)
             }
             IRInstruction::Comment(text) => {
                 format!("{}// {}\n", self.emit_indent(), text)
@@ -527,35 +1027,89 @@ impl IRRustGenerator {
             }
             IRInstruction::Load(_) => {
                 format!(
-                    "{}{{ let addr = self.stack.pop().unwrap(); let val = *self.memory.get(&addr).unwrap_or(&0); self.stack.push(val); }}\n",
+                    "{}{{ let addr = self.__pop()?; let val = self.__fetch(addr)?; self.stack.push(val); }}\n",
                     self.emit_indent()
                 )
             }
             IRInstruction::Store(_) => {
                 format!(
-                    "{}{{ let addr = self.stack.pop().unwrap(); let val = self.stack.pop().unwrap(); self.memory.insert(addr, val); }}\n",
+                    "{}{{ let addr = self.__pop()?; let val = self.__pop()?; self.__store(addr, val)?; }}\n",
                     self.emit_indent()
                 )
             }
-            IRInstruction::StackGet(pos) => {
+            IRInstruction::LoadChar => {
                 format!(
-                    "{}self.stack.push(self.stack[self.stack.len() - 1 - {}]);\n",
-                    self.emit_indent(),
-                    pos
+                    "{}{{ let addr = self.__pop()?; let range = self.__data_range(addr, 1)?; self.stack.push(self.memory[range.start] as Cell); }}\n",
+                    self.emit_indent()
                 )
             }
-            IRInstruction::StackSet(pos, value) => {
+            IRInstruction::StoreChar => {
                 format!(
-                    "{}self.stack[self.stack.len() - 1 - {}] = {};\n",
-                    self.emit_indent(),
-                    pos,
-                    self.generate_value(value)
+                    "{}{{ let addr = self.__pop()?; let val = self.__pop()?; let range = self.__data_range(addr, 1)?; self.memory[range.start] = val as u8; }}\n",
+                    self.emit_indent()
                 )
             }
-            IRInstruction::StackAlloc(size) => {
-                format!("{}self.stack.reserve({});\n", self.emit_indent(), size)
-            }
-            IRInstruction::StackFree(_) => {
+            IRInstruction::Here => {
+                format!(
+                    "{}self.stack.push(self.memory.len() as Cell);\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::Allot => {
+                format!(
+                    "{}{{ let n = self.__pop()?; self.__allot(n)?; }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::Comma => {
+                format!(
+                    "{}{{ let val = self.__pop()?; let addr = self.memory.len() as Cell; self.__allot(CELL_SIZE as Cell)?; self.__store(addr, val)?; }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::CharComma => {
+                format!(
+                    "{}{{ let val = self.__pop()?; self.__allot(1)?; *self.memory.last_mut().unwrap() = val as u8; }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::Align => {
+                format!("{}self.__align()?;\n", self.emit_indent())
+            }
+            IRInstruction::Create(name) => {
+                format!(
+                    "{}self.__align()?; self.data_fields.insert({:?}, self.memory.len() as Cell);\n",
+                    self.emit_indent(),
+                    name
+                )
+            }
+            IRInstruction::SupplyName(name) => {
+                format!("{}self.supplied_name = {:?};\n", self.emit_indent(), name)
+            }
+            IRInstruction::CreateSupplied => {
+                format!(
+                    "{}self.__align()?; self.data_fields.insert(self.supplied_name, self.memory.len() as Cell);\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::PushDataField(name) => {
+                format!(
+                    "{}self.stack.push(self.data_fields[{:?}]);\n",
+                    self.emit_indent(),
+                    name
+                )
+            }
+            IRInstruction::StackGet(slot) => {
+                format!("{}self.stack.push(local_{});\n", self.emit_indent(), slot)
+            }
+            IRInstruction::StackSet(slot) => {
+                format!("{}local_{} = self.__pop()?;\n", self.emit_indent(), slot)
+            }
+            IRInstruction::StackAlloc(_) => {
+                // The locals are declared at the top of the function
+                String::new()
+            }
+            IRInstruction::StackFree(_) => {
                 // No explicit stack freeing needed in Rust
                 String::new()
             }
@@ -565,6 +1119,33 @@ impl IRRustGenerator {
         }
     }
 
+    fn generate_double_binary_op(&self, expr: &str) -> String {
+        format!(
+            "{}{{ let b = self.__pop_double()?; let a = self.__pop_double()?; self.__push_double({}); }}\n",
+            self.emit_indent(),
+            expr
+        )
+    }
+
+    fn generate_float_binary_op(&self, expr: &str) -> String {
+        format!(
+            "{}{{ let b = self.__fpop()?; let a = self.__fpop()?; self.fstack.push({}); }}\n",
+            self.emit_indent(),
+            expr
+        )
+    }
+
+    /// Rust spelling of a float; infinities and NaN have no literal form
+    pub(crate) fn float_literal(r: Float) -> String {
+        if r.is_nan() {
+            "f64::NAN".to_string()
+        } else if r.is_infinite() {
+            format!("f64::{}INFINITY", if r < 0.0 { "NEG_" } else { "" })
+        } else {
+            format!("{:?}", r)
+        }
+    }
+
     fn generate_value(&self, value: &IRValue) -> String {
         match value {
             IRValue::Constant(n) => n.to_string(),
@@ -597,14 +1178,533 @@ impl CodeGenerator for IRRustGenerator {
     }
 }
 
+/// Methods of the generated Rust program for printing numbers in BASE and
+/// pictured numeric output, matching the REPL runtime's
+const RUST_NUMBER_OUTPUT_HELPERS: &str = r#"    fn __base(&self) -> Result<UDCell, Exception> {
+        let base = self.__fetch(BASE_ADDRESS)?;
+        if (2..=36).contains(&base) {
+            Ok(base as UDCell)
+        } else {
+            __throw(INVALID_NUMERIC_ARGUMENT, format!("Invalid BASE: {}", base))
+        }
+    }
+
+    fn __format_number(&self, magnitude: UDCell, negative: bool) -> Result<String, Exception> {
+        let base = self.__base()?;
+        let mut digits = Vec::new();
+        let mut rest = magnitude;
+        loop {
+            digits.push(DIGITS[(rest % base) as usize]);
+            rest /= base;
+            if rest == 0 {
+                break;
+            }
+        }
+        if negative {
+            digits.push(b'-');
+        }
+        Ok(digits.iter().rev().map(|&c| c as char).collect())
+    }
+
+    fn __format_signed(&self, n: DCell) -> Result<String, Exception> {
+        self.__format_number(n.unsigned_abs(), n < 0)
+    }
+
+    fn __hold(&mut self, c: u8) -> Result<(), Exception> {
+        if self.held <= HOLD_END - HOLD_SIZE {
+            return __throw(PICTURED_OUTPUT_OVERFLOW, "Pictured numeric output overflow");
+        }
+        self.held -= 1;
+        self.memory[self.held] = c;
+        Ok(())
+    }
+
+    // Hold the last digit of ud, returning the digits before it
+    fn __hold_digit(&mut self, ud: UDCell) -> Result<UDCell, Exception> {
+        let base = self.__base()?;
+        self.__hold(DIGITS[(ud % base) as usize])?;
+        Ok(ud / base)
+    }
+
+"#;
+
+/// What a generated Rust program throws, with its THROW code and the message
+/// shown when nothing catches it
+const RUST_EXCEPTION: &str = r#"
+#[derive(Debug)]
+struct Exception {
+    code: Cell,
+    message: String,
+}
+
+fn __throw<T>(code: Cell, message: impl Into<String>) -> Result<T, Exception> {
+    Err(Exception { code, message: message.into() })
+}
+
+"#;
+
+/// Methods of the generated Rust program for checked pops and for CATCH
+/// and THROW, which restore the stack depths like the REPL runtime's
+const RUST_STACK_HELPERS: &str = r#"    fn __pop(&mut self) -> Result<Cell, Exception> {
+        match self.stack.pop() {
+            Some(n) => Ok(n),
+            None => __throw(STACK_UNDERFLOW, "Stack underflow"),
+        }
+    }
+
+    fn __fpop(&mut self) -> Result<f64, Exception> {
+        match self.fstack.pop() {
+            Some(r) => Ok(r),
+            None => __throw(FLOAT_STACK_UNDERFLOW, "Float stack underflow"),
+        }
+    }
+
+    fn __catch(&mut self) -> Result<(), Exception> {
+        let xt = self.__pop()?;
+        let depths = (self.stack.len(), self.fstack.len(), self.loop_stack.len());
+        match self.__execute(xt) {
+            Ok(()) => self.stack.push(0),
+            Err(exception) => {
+                self.stack.resize(depths.0, 0);
+                self.fstack.resize(depths.1, 0.0);
+                self.loop_stack.truncate(depths.2);
+                self.stack.push(exception.code);
+            }
+        }
+        Ok(())
+    }
+
+    fn __throw_top(&mut self) -> Result<(), Exception> {
+        match self.__pop()? {
+            0 => Ok(()),
+            ABORT => __throw(ABORT, "Aborted"),
+            code => __throw(code, format!("Uncaught exception {}", code)),
+        }
+    }
+
+"#;
+
+/// Methods of the generated Rust program for ( c-addr u ) strings in data
+/// space, matching the REPL runtime's
+const RUST_STRING_HELPERS: &str = r#"    fn __pop_string(&mut self) -> Result<std::ops::Range<usize>, Exception> {
+        let len = self.__pop()?;
+        let addr = self.__pop()?;
+        self.__data_range(addr, len as usize)
+    }
+
+    // The source and destination of CMOVE and MOVE
+    fn __pop_move(&mut self) -> Result<(std::ops::Range<usize>, std::ops::Range<usize>), Exception> {
+        let len = self.__pop()? as usize;
+        let to = self.__pop()?;
+        let from = self.__pop()?;
+        Ok((self.__data_range(from, len)?, self.__data_range(to, len)?))
+    }
+
+    fn __type(&mut self) -> Result<(), Exception> {
+        let range = self.__pop_string()?;
+        std::io::Write::write_all(&mut std::io::stdout(), &self.memory[range]).map_err(|e| Exception { code: FILE_IO, message: e.to_string() })
+    }
+
+    fn __compare(&mut self) -> Result<(), Exception> {
+        let second = self.__pop_string()?;
+        let first = self.__pop_string()?;
+        let order = self.memory[first].cmp(&self.memory[second]);
+        self.stack.push(order as Cell);
+        Ok(())
+    }
+
+    fn __search(&mut self) -> Result<(), Exception> {
+        let needle = self.__pop_string()?;
+        let haystack = self.__pop_string()?;
+        let found = if needle.is_empty() {
+            Some(0)
+        } else {
+            self.memory[haystack.clone()]
+                .windows(needle.len())
+                .position(|window| window == &self.memory[needle.clone()])
+        };
+        let offset = found.unwrap_or(0);
+        self.stack.push((haystack.start + offset) as Cell);
+        self.stack.push((haystack.len() - offset) as Cell);
+        self.stack.push(if found.is_some() { -1 } else { 0 });
+        Ok(())
+    }
+
+    fn __trim_trailing(&mut self) -> Result<(), Exception> {
+        let range = self.__pop_string()?;
+        let len = self.memory[range.clone()]
+            .iter()
+            .rposition(|&c| c != b' ')
+            .map_or(0, |i| i + 1);
+        self.stack.push(range.start as Cell);
+        self.stack.push(len as Cell);
+        Ok(())
+    }
+
+"#;
+
+/// Methods of the generated Rust program for double-cell numbers, which are
+/// kept as two cells with the high cell on top
+const RUST_DOUBLE_HELPERS: &str = r#"    fn __pop_double(&mut self) -> Result<DCell, Exception> {
+        let high = self.__pop()?;
+        let low = self.__pop()?;
+        Ok(((high as DCell) << Cell::BITS) | (low as UCell as DCell))
+    }
+
+    fn __push_double(&mut self, value: DCell) {
+        self.stack.push(value as Cell);
+        self.stack.push((value >> Cell::BITS) as Cell);
+    }
+
+    // SM/REM, or FM/MOD when floored: ( d n -- rem quot )
+    fn __divide_double(&mut self, floored: bool) -> Result<(), Exception> {
+        let divisor = self.__pop()? as DCell;
+        let dividend = self.__pop_double()?;
+        if divisor == 0 {
+            return __throw(DIVISION_BY_ZERO, "Division by zero");
+        }
+        let mut quotient = dividend.wrapping_div(divisor);
+        let mut remainder = dividend.wrapping_rem(divisor);
+        if floored && remainder != 0 && (remainder < 0) != (divisor < 0) {
+            quotient -= 1;
+            remainder += divisor;
+        }
+        self.stack.push(remainder as Cell);
+        self.stack.push(quotient as Cell);
+        Ok(())
+    }
+
+"#;
+
+/// Data-space accessors shared by every generated Rust program; they report
+/// errors with the same wording as the runtime crate
+const RUST_DATA_SPACE_HELPERS: &str = r#"    fn __data_range(&self, addr: Cell, len: usize) -> Result<std::ops::Range<usize>, Exception> {
+        let start = addr as usize;
+        if addr < 0 || start.saturating_add(len) > self.memory.len() {
+            return __throw(INVALID_MEMORY_ADDRESS, format!("Invalid memory address: {}", addr));
+        }
+        Ok(start..start + len)
+    }
+
+    fn __fetch(&self, addr: Cell) -> Result<Cell, Exception> {
+        let range = self.__data_range(addr, CELL_SIZE)?;
+        let mut bytes = [0u8; CELL_SIZE];
+        bytes.copy_from_slice(&self.memory[range]);
+        Ok(Cell::from_ne_bytes(bytes))
+    }
+
+    fn __store(&mut self, addr: Cell, value: Cell) -> Result<(), Exception> {
+        let range = self.__data_range(addr, CELL_SIZE)?;
+        self.memory[range].copy_from_slice(&value.to_ne_bytes());
+        Ok(())
+    }
+
+    fn __allot(&mut self, n: Cell) -> Result<(), Exception> {
+        let new_len = self.memory.len() as i128 + n as i128;
+        if new_len < 0 || new_len > DATA_SPACE_SIZE as i128 {
+            return __throw(DATA_SPACE_OVERFLOW, "Data space overflow");
+        }
+        self.memory.resize(new_len as usize, 0);
+        Ok(())
+    }
+
+    fn __align(&mut self) -> Result<(), Exception> {
+        let padding = self.memory.len().next_multiple_of(CELL_SIZE) - self.memory.len();
+        self.__allot(padding as Cell)
+    }
+
+    fn __fetch_float(&self, addr: Cell) -> Result<f64, Exception> {
+        let range = self.__data_range(addr, 8)?;
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&self.memory[range]);
+        Ok(f64::from_ne_bytes(bytes))
+    }
+
+    fn __store_float(&mut self, addr: Cell, r: f64) -> Result<(), Exception> {
+        let range = self.__data_range(addr, 8)?;
+        self.memory[range].copy_from_slice(&r.to_ne_bytes());
+        Ok(())
+    }
+
+"#;
+
+/// THROW in C: a longjmp to the innermost CATCH, or leaving the program
+/// with the message when there is none
+const C_THROW_FUNCTION: &str = r#"jmp_buf *catch_frame = NULL;
+cell thrown; /* The code of the exception being caught */
+
+_Noreturn void throw_exception(cell code, const char *message) {
+    if (catch_frame == NULL) {
+        printf("%s\n", message);
+        exit(1);
+    }
+    thrown = code;
+    longjmp(*catch_frame, 1);
+}
+
+"#;
+
+/// CATCH in C, restoring the stack depths like the REPL runtime's
+const C_CATCH_FUNCTION: &str = r#"void execute(cell xt); /* Generated after the words */
+
+void catch_word(void) {
+    cell xt = pop();
+    jmp_buf frame;
+    jmp_buf *outer = catch_frame;
+    int depth = stack.top, float_depth = fstack_top, loop_depth = loop_sp;
+    catch_frame = &frame;
+    if (setjmp(frame) == 0) {
+        execute(xt);
+        catch_frame = outer;
+        push(0);
+        return;
+    }
+    catch_frame = outer;
+    while (stack.top < depth) stack.data[stack.top++] = 0;
+    while (fstack_top < float_depth) fstack[fstack_top++] = 0.0;
+    stack.top = depth;
+    fstack_top = float_depth;
+    loop_sp = loop_depth;
+    push(thrown);
+}
+
+void throw_top(void) {
+    cell code = pop();
+    char message[64];
+    if (code == 0) return;
+    if (code == ABORT) throw_exception(ABORT, "Aborted");
+    snprintf(message, sizeof message, "Uncaught exception %" CELL_FMT, code);
+    throw_exception(code, message);
+}
+
+"#;
+
+/// Printing numbers in BASE and pictured numeric output in C, matching the
+/// REPL runtime's. printf has no conversion for other radixes, nor for
+/// __int128
+const C_NUMBER_OUTPUT_FUNCTIONS: &str = r#"const char digit_chars[] = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+udcell number_base(void) {
+    cell base = fetch(BASE_ADDRESS);
+    if (base < 2 || base > 36) {
+        char message[64];
+        snprintf(message, sizeof message, "Invalid BASE: %" CELL_FMT, base);
+        throw_exception(INVALID_NUMERIC_ARGUMENT, message);
+    }
+    return (udcell)base;
+}
+
+/* Right-aligned in width characters, with a space after it if asked */
+void print_number(udcell magnitude, int negative, cell width, int space) {
+    char digits[2 * CELL_BITS + 1];
+    udcell base = number_base();
+    int count = 0;
+    do {
+        digits[count++] = digit_chars[magnitude % base];
+        magnitude /= base;
+    } while (magnitude != 0);
+    if (negative) digits[count++] = '-';
+    for (; width > count; width--) putchar(' ');
+    while (count > 0) putchar(digits[--count]);
+    if (space) putchar(' ');
+}
+
+void print_signed(dcell n, cell width, int space) {
+    print_number(n < 0 ? 0 - (udcell)n : (udcell)n, n < 0, width, space);
+}
+
+void hold(cell c) {
+    if (held <= HOLD_END - HOLD_SIZE) {
+        throw_exception(PICTURED_OUTPUT_OVERFLOW, "Pictured numeric output overflow");
+    }
+    data_space[--held] = (unsigned char)c;
+}
+
+/* Hold the last digit of ud, returning the digits before it */
+udcell hold_digit(udcell ud) {
+    udcell base = number_base();
+    hold(digit_chars[ud % base]);
+    return ud / base;
+}
+
+"#;
+
+/// ( c-addr u ) strings in C, matching the REPL runtime's
+const C_STRING_FUNCTIONS: &str = r#"void check_string(cell addr, cell len) {
+    if (len < 0) {
+        char message[64];
+        snprintf(message, sizeof message, "Invalid memory address: %" CELL_FMT, addr);
+        throw_exception(INVALID_MEMORY_ADDRESS, message);
+    }
+    check_address(addr, len);
+}
+
+void type_string(void) {
+    cell len = pop();
+    cell addr = pop();
+    check_string(addr, len);
+    fwrite(data_space + addr, 1, (size_t)len, stdout);
+}
+
+void compare_strings(void) {
+    cell len2 = pop();
+    cell addr2 = pop();
+    cell len1 = pop();
+    cell addr1 = pop();
+    int order;
+    check_string(addr1, len1);
+    check_string(addr2, len2);
+    order = memcmp(data_space + addr1, data_space + addr2, (size_t)(len1 < len2 ? len1 : len2));
+    if (order == 0) order = (len1 > len2) - (len1 < len2);
+    push(order < 0 ? -1 : order > 0 ? 1 : 0);
+}
+
+void search_string(void) {
+    cell len2 = pop();
+    cell addr2 = pop();
+    cell len1 = pop();
+    cell addr1 = pop();
+    cell offset;
+    check_string(addr1, len1);
+    check_string(addr2, len2);
+    for (offset = 0; offset + len2 <= len1; offset++) {
+        if (memcmp(data_space + addr1 + offset, data_space + addr2, (size_t)len2) == 0) {
+            push(addr1 + offset);
+            push(len1 - offset);
+            push(-1);
+            return;
+        }
+    }
+    push(addr1);
+    push(len1);
+    push(0);
+}
+
+void trim_trailing(void) {
+    cell len = pop();
+    cell addr = pop();
+    check_string(addr, len);
+    while (len > 0 && data_space[addr + len - 1] == ' ') len--;
+    push(addr);
+    push(len);
+}
+
+void cmove(void) {
+    cell len = pop();
+    cell to = pop();
+    cell from = pop();
+    cell i;
+    check_string(from, len);
+    check_string(to, len);
+    for (i = 0; i < len; i++) data_space[to + i] = data_space[from + i];
+}
+
+void move_bytes(void) {
+    cell len = pop();
+    cell to = pop();
+    cell from = pop();
+    check_string(from, len);
+    check_string(to, len);
+    memmove(data_space + to, data_space + from, (size_t)len);
+}
+
+"#;
+
+/// Double-cell numbers in C: two cells on the stack with the high cell on top
+const C_DOUBLE_FUNCTIONS: &str = r#"dcell dpop(void) {
+    ucell high = (ucell)pop();
+    ucell low = (ucell)pop();
+    return (dcell)(((udcell)high << CELL_BITS) | low);
+}
+
+void dpush(dcell value) {
+    push((cell)(ucell)value);
+    push((cell)(ucell)((udcell)value >> CELL_BITS));
+}
+
+/* SM/REM, or FM/MOD when floored: ( d n -- rem quot ) */
+void divide_double(int floored) {
+    cell divisor = pop();
+    dcell dividend = dpop();
+    dcell quotient, remainder;
+    if (divisor == 0) throw_exception(DIVISION_BY_ZERO, "Division by zero");
+    if (divisor == -1) {
+        quotient = (dcell)(0 - (udcell)dividend);
+        remainder = 0;
+    } else {
+        quotient = dividend / divisor;
+        remainder = dividend % divisor;
+    }
+    if (floored && remainder != 0 && (remainder < 0) != (divisor < 0)) {
+        quotient--;
+        remainder += divisor;
+    }
+    push((cell)(ucell)remainder);
+    push((cell)(ucell)quotient);
+}
+
+"#;
+
+/// F. output in C, matching the runtime crate's `format_float`: the fewest
+/// digits that read back as the same value, written out without an exponent
+const C_PRINT_FLOAT: &str = r#"void print_float(double r) {
+    char text[32];
+    char digits[24];
+    int precision, exponent, count = 0, i;
+    char *p = text;
+    if (isnan(r)) { printf("NaN "); return; }
+    if (isinf(r)) { printf(r < 0 ? "-inf " : "inf "); return; }
+    for (precision = 0; precision < 17; precision++) {
+        snprintf(text, sizeof text, "%.*e", precision, r);
+        if (strtod(text, NULL) == r) break;
+    }
+    if (*p == '-') { putchar('-'); p++; }
+    for (; *p != 'e'; p++) {
+        if (*p != '.') digits[count++] = *p;
+    }
+    exponent = atoi(p + 1);
+    if (exponent < 0) {
+        printf("0.");
+        for (i = -1; i > exponent; i--) putchar('0');
+        printf("%.*s", count, digits);
+    } else if (exponent + 1 >= count) {
+        printf("%.*s", count, digits);
+        for (i = count; i <= exponent; i++) putchar('0');
+        putchar('.');
+    } else {
+        printf("%.*s.%.*s", exponent + 1, digits, count - exponent - 1, digits + exponent + 1);
+    }
+    putchar(' ');
+}
+
+"#;
+
+/// F. output, the same as the runtime crate's `format_float`
+const RUST_FORMAT_FLOAT: &str = r#"fn __format_float(r: f64) -> String {
+    let text = r.to_string();
+    if r.is_finite() && !text.contains('.') { text + "." } else { text }
+}
+
+"#;
+
 /// Generates C code from IR
 pub struct IRCGenerator {
     indent_level: usize,
+    cell_width: CellWidth,
+    data_fields: Vec<String>, // CREATEd names, indexing the data_fields array
+    strings: StringSpace,
+    xts: ExecutionTokens,
 }
 
 impl IRCGenerator {
     pub fn new() -> Self {
-        Self { indent_level: 0 }
+        Self {
+            indent_level: 0,
+            cell_width: CellWidth::default(),
+            data_fields: Vec::new(),
+            strings: StringSpace::default(),
+            xts: ExecutionTokens::default(),
+        }
     }
 
     fn emit_indent(&self) -> String {
@@ -613,35 +1713,111 @@ impl IRCGenerator {
 
     pub fn generate_program(&mut self, program: &IRProgram) -> String {
         let mut output = String::new();
+        self.cell_width = program.cell_width;
 
         // Generate header
         output.push_str("// Generated from optimized IR\n");
+        output.push_str("#include <inttypes.h>\n");
+        output.push_str("#include <math.h>\n");
+        output.push_str("#include <setjmp.h>\n");
+        output.push_str("#include <stdint.h>\n");
         output.push_str("#include <stdio.h>\n");
         output.push_str("#include <stdlib.h>\n");
         output.push_str("#include <string.h>\n\n");
         output.push_str("#define STACK_SIZE 1000\n\n");
 
+        // Arithmetic goes through ucell so overflow wraps instead of being undefined
+        let bits = self.cell_width.bits();
+        output.push_str(&format!("typedef int{}_t cell;\n", bits));
+        output.push_str(&format!("typedef uint{}_t ucell;\n", bits));
+        output.push_str(&format!("#define CELL_FMT PRId{}\n", bits));
+        output.push_str(&format!("#define CELL_BITS {}\n", bits));
+        match self.cell_width {
+            CellWidth::Bits32 => {
+                output.push_str("typedef int64_t dcell;\n");
+                output.push_str("typedef uint64_t udcell;\n\n");
+            }
+            CellWidth::Bits64 => {
+                output.push_str("__extension__ typedef __int128 dcell;\n");
+                output.push_str("__extension__ typedef unsigned __int128 udcell;\n\n");
+            }
+        }
+
         // Generate stack structure
         output.push_str("typedef struct {\n");
-        output.push_str("    int data[STACK_SIZE];\n");
+        output.push_str("    cell data[STACK_SIZE];\n");
         output.push_str("    int top;\n");
         output.push_str("} Stack;\n\n");
         output.push_str("Stack stack = {0};\n\n");
 
+        // Generate loop control stack (index, limit) pairs for DO loops
+        output.push_str("#define LOOP_STACK_SIZE 100\n\n");
+        output.push_str("typedef struct {\n");
+        output.push_str("    cell index;\n");
+        output.push_str("    cell limit;\n");
+        output.push_str("} LoopFrame;\n\n");
+        output.push_str("LoopFrame loop_stack[LOOP_STACK_SIZE];\n");
+        output.push_str("int loop_sp = 0;\n\n");
+
+        for (name, code) in throw_code::ALL {
+            output.push_str(&format!("#define {} ({})\n", name, code));
+        }
+        output.push_str(C_THROW_FUNCTION);
+
         // Generate stack functions
         self.generate_stack_functions(&mut output);
+        output.push_str(C_DOUBLE_FUNCTIONS);
+        self.generate_float_functions(&mut output);
+        output.push_str(C_CATCH_FUNCTION);
+
+        // Data space, with one address slot per CREATEd name
+        self.data_fields = program
+            .functions
+            .values()
+            .chain(std::iter::once(&program.main))
+            .flat_map(|function| &function.instructions)
+            .filter_map(|instr| match instr {
+                IRInstruction::Create(name) | IRInstruction::SupplyName(name) => Some(name.clone()),
+                _ => None,
+            })
+            .collect();
+        self.data_fields.sort();
+        self.data_fields.dedup();
+        self.strings = StringSpace::new(program);
+        self.generate_data_space_functions(&mut output);
+        output.push_str(C_STRING_FUNCTIONS);
+        output.push_str(C_NUMBER_OUTPUT_FUNCTIONS);
+
+        // Forward declarations so words can call each other in any order
+        for (name, function) in &program.functions {
+            if name != "main" {
+                output.push_str(&format!("void {}(void);\n", function_name(&function.name)));
+            }
+        }
+        output.push('\n');
 
-        // Generate user-defined functions
+        // Generate user-defined functions, then those run only by execution
+        // tokens
+        self.xts = ExecutionTokens::new(program);
         for (name, function) in &program.functions {
             if name != "main" {
                 output.push_str(&self.generate_function(function));
                 output.push('\n');
             }
         }
+        for wrapper in program.wrappers.values() {
+            output.push_str(&self.generate_function(wrapper));
+            output.push('\n');
+        }
+        self.generate_execute(&mut output);
 
         // Generate main function
         output.push_str("int main() {\n");
         self.indent_level += 1;
+        output.push_str("    store(BASE_ADDRESS, 10);\n");
+        output.push_str(
+            "    memcpy(data_space + HOLD_END, string_space, sizeof string_space - 1);\n",
+        );
         output.push_str(&self.generate_function_body(&program.main));
         output.push_str(&format!("{}return 0;\n", self.emit_indent()));
         self.indent_level -= 1;
@@ -651,23 +1827,160 @@ impl IRCGenerator {
     }
 
     fn generate_stack_functions(&self, output: &mut String) {
-        output.push_str("void push(int value) {\n");
+        output.push_str("void push(cell value) {\n");
         output.push_str("    if (stack.top < STACK_SIZE) {\n");
         output.push_str("        stack.data[stack.top++] = value;\n");
         output.push_str("    } else {\n");
-        output.push_str("        printf(\"Stack overflow\\n\");\n");
-        output.push_str("        exit(1);\n");
+        output.push_str("        throw_exception(STACK_OVERFLOW, \"Stack overflow\");\n");
         output.push_str("    }\n");
         output.push_str("}\n\n");
 
-        output.push_str("int pop() {\n");
+        output.push_str("cell pop() {\n");
         output.push_str("    if (stack.top > 0) {\n");
         output.push_str("        return stack.data[--stack.top];\n");
         output.push_str("    } else {\n");
-        output.push_str("        printf(\"Stack underflow\\n\");\n");
-        output.push_str("        exit(1);\n");
+        output.push_str("        throw_exception(STACK_UNDERFLOW, \"Stack underflow\");\n");
+        output.push_str("    }\n");
+        output.push_str("}\n\n");
+    }
+
+    fn generate_float_functions(&self, output: &mut String) {
+        output.push_str("double fstack[STACK_SIZE];\n");
+        output.push_str("int fstack_top = 0;\n\n");
+
+        output.push_str("void fpush(double value) {\n");
+        output.push_str("    if (fstack_top < STACK_SIZE) {\n");
+        output.push_str("        fstack[fstack_top++] = value;\n");
+        output.push_str("    } else {\n");
+        output.push_str("        throw_exception(STACK_OVERFLOW, \"Stack overflow\");\n");
         output.push_str("    }\n");
         output.push_str("}\n\n");
+
+        output.push_str("double fpop() {\n");
+        output.push_str("    if (fstack_top > 0) {\n");
+        output.push_str("        return fstack[--fstack_top];\n");
+        output.push_str("    } else {\n");
+        output.push_str(
+            "        throw_exception(FLOAT_STACK_UNDERFLOW, \"Float stack underflow\");\n",
+        );
+        output.push_str("    }\n");
+        output.push_str("}\n\n");
+
+        output.push_str(C_PRINT_FLOAT);
+    }
+
+    fn generate_data_space_functions(&self, output: &mut String) {
+        output.push_str("#define DATA_SPACE_SIZE 1048576\n\n");
+        output.push_str(&format!("#define BASE_ADDRESS {}\n", BASE_ADDRESS));
+        output.push_str(&format!("#define HOLD_SIZE {}\n", HOLD_SIZE));
+        output.push_str(&format!(
+            "#define HOLD_END {} /* Pictured numeric output is built down from here */\n\n",
+            SYSTEM_SPACE_SIZE
+        ));
+        output.push_str("unsigned char data_space[DATA_SPACE_SIZE];\n");
+        // String literals, copied in from HOLD_END when the program starts
+        output.push_str(&format!(
+            "const char string_space[] = \"{}\";\n",
+            Self::bytes_literal(&self.strings.bytes)
+        ));
+        output.push_str(&format!(
+            "cell data_here = HOLD_END + {};\n",
+            self.strings.bytes.len()
+        ));
+        output
+            .push_str("cell held = HOLD_END; /* Start of the pictured numeric output so far */\n");
+        output.push_str(&format!(
+            "cell data_fields[{}];\n",
+            self.data_fields.len().max(1)
+        ));
+        output.push_str("int supplied_field = 0; // Slot for CREATE inside a defining word\n\n");
+
+        output.push_str("void check_address(cell addr, cell len) {\n");
+        output.push_str("    if (addr < 0 || addr > data_here - len) {\n");
+        output.push_str("        char message[64];\n");
+        output.push_str(
+            "        snprintf(message, sizeof message, \"Invalid memory address: %\" CELL_FMT, addr);\n",
+        );
+        output.push_str("        throw_exception(INVALID_MEMORY_ADDRESS, message);\n");
+        output.push_str("    }\n");
+        output.push_str("}\n\n");
+
+        output.push_str("cell fetch(cell addr) {\n");
+        output.push_str("    cell value;\n");
+        output.push_str("    check_address(addr, sizeof(cell));\n");
+        output.push_str("    memcpy(&value, data_space + addr, sizeof(cell));\n");
+        output.push_str("    return value;\n");
+        output.push_str("}\n\n");
+
+        output.push_str("void store(cell addr, cell value) {\n");
+        output.push_str("    check_address(addr, sizeof(cell));\n");
+        output.push_str("    memcpy(data_space + addr, &value, sizeof(cell));\n");
+        output.push_str("}\n\n");
+
+        output.push_str("cell fetch_char(cell addr) {\n");
+        output.push_str("    check_address(addr, 1);\n");
+        output.push_str("    return data_space[addr];\n");
+        output.push_str("}\n\n");
+
+        output.push_str("void store_char(cell addr, cell value) {\n");
+        output.push_str("    check_address(addr, 1);\n");
+        output.push_str("    data_space[addr] = (unsigned char)value;\n");
+        output.push_str("}\n\n");
+
+        output.push_str("void allot(cell n) {\n");
+        output.push_str("    if (n > (cell)DATA_SPACE_SIZE - data_here || n < -data_here) {\n");
+        output.push_str("        throw_exception(DATA_SPACE_OVERFLOW, \"Data space overflow\");\n");
+        output.push_str("    }\n");
+        output.push_str("    if (n > 0) {\n");
+        output.push_str("        memset(data_space + data_here, 0, n);\n");
+        output.push_str("    }\n");
+        output.push_str("    data_here += n;\n");
+        output.push_str("}\n\n");
+
+        output.push_str("double fetch_float(cell addr) {\n");
+        output.push_str("    double value;\n");
+        output.push_str("    check_address(addr, sizeof(double));\n");
+        output.push_str("    memcpy(&value, data_space + addr, sizeof(double));\n");
+        output.push_str("    return value;\n");
+        output.push_str("}\n\n");
+
+        output.push_str("void store_float(cell addr, double value) {\n");
+        output.push_str("    check_address(addr, sizeof(double));\n");
+        output.push_str("    memcpy(data_space + addr, &value, sizeof(double));\n");
+        output.push_str("}\n\n");
+
+        output.push_str("void align_data(void) {\n");
+        output.push_str("    cell size = (cell)sizeof(cell);\n");
+        output.push_str("    allot((size - data_here % size) % size);\n");
+        output.push_str("}\n\n");
+    }
+
+    /// Slot in the generated data_fields array holding a CREATEd name's address
+    /// EXECUTE's dispatch from execution token to function
+    fn generate_execute(&self, output: &mut String) {
+        output.push_str("void execute(cell xt) {\n");
+        output.push_str("    char message[64];\n");
+        output.push_str("    switch (xt) {\n");
+        for (xt, name, function) in self.xts.iter() {
+            output.push_str(&format!(
+                "    case {}: {}(); return; // {}\n",
+                xt,
+                function_name(function),
+                name
+            ));
+        }
+        output.push_str("    }\n");
+        output.push_str(
+            "    snprintf(message, sizeof message, \"Invalid execution token: %\" CELL_FMT, xt);\n",
+        );
+        output.push_str("    throw_exception(UNDEFINED_WORD, message);\n");
+        output.push_str("}\n\n");
+    }
+
+    fn data_field_index(&self, name: &str) -> usize {
+        self.data_fields
+            .binary_search_by(|field| field.as_str().cmp(name))
+            .unwrap_or(0)
     }
 
     fn generate_function(&mut self, function: &IRFunction) -> String {
@@ -677,9 +1990,16 @@ impl IRCGenerator {
             "// Function: {} (consumes: {}, produces: {})\n",
             function.name, function.stack_effect.consumes, function.stack_effect.produces
         ));
-        output.push_str(&format!("void {}() {{\n", function.name.to_lowercase()));
+        output.push_str(&format!(
+            "void {}(void) {{\n",
+            function_name(&function.name)
+        ));
         self.indent_level += 1;
 
+        // Locals are native variables, so every call has its own
+        for slot in 0..frame_size(&function.instructions) {
+            output.push_str(&format!("{}cell local_{} = 0;\n", self.emit_indent(), slot));
+        }
         output.push_str(&self.generate_function_body(function));
 
         self.indent_level -= 1;
@@ -698,6 +2018,24 @@ impl IRCGenerator {
         output
     }
 
+    /// C spelling of a constant; the most negative cell has no literal form
+    fn cell_literal(&self, n: Cell) -> String {
+        let min = self.cell_width.wrap(1 << (self.cell_width.bits() - 1));
+        if n == min {
+            format!("({} - 1)", n + 1)
+        } else {
+            n.to_string()
+        }
+    }
+
+    fn generate_binary_op(&self, expr: &str) -> String {
+        format!(
+            "{}{{ cell b = pop(); cell a = pop(); push({}); }}\n",
+            self.emit_indent(),
+            expr
+        )
+    }
+
     fn generate_instruction(&self, instruction: &IRInstruction) -> String {
         match instruction {
             IRInstruction::Push(value) => {
@@ -708,59 +2046,229 @@ impl IRCGenerator {
                 )
             }
             IRInstruction::LoadConst(n) => {
-                format!("{}push({});\n", self.emit_indent(), n)
+                format!("{}push({});\n", self.emit_indent(), self.cell_literal(*n))
             }
             IRInstruction::Pop | IRInstruction::Drop => {
                 format!("{}pop();\n", self.emit_indent())
             }
             IRInstruction::Dup => {
                 format!(
-                    "{}if (stack.top > 0) {{ push(stack.data[stack.top - 1]); }} else {{ printf(\"Stack underflow\\n\"); exit(1); }}\n",
+                    "{}if (stack.top > 0) {{ push(stack.data[stack.top - 1]); }} else {{ throw_exception(STACK_UNDERFLOW, \"Stack underflow\"); }}\n",
                     self.emit_indent()
                 )
             }
             IRInstruction::Swap => {
                 format!(
-                    "{}{{ int b = pop(); int a = pop(); push(b); push(a); }}\n",
+                    "{}{{ cell b = pop(); cell a = pop(); push(b); push(a); }}\n",
                     self.emit_indent()
                 )
             }
             IRInstruction::Over => {
                 format!(
-                    "{}if (stack.top >= 2) {{ push(stack.data[stack.top - 2]); }} else {{ printf(\"Stack underflow\\n\"); exit(1); }}\n",
+                    "{}if (stack.top >= 2) {{ push(stack.data[stack.top - 2]); }} else {{ throw_exception(STACK_UNDERFLOW, \"Stack underflow\"); }}\n",
                     self.emit_indent()
                 )
             }
             IRInstruction::Add => {
                 format!(
-                    "{}{{ int b = pop(); int a = pop(); push(a + b); }}\n",
+                    "{}{{ cell b = pop(); cell a = pop(); push((cell)((ucell)a + (ucell)b)); }}\n",
                     self.emit_indent()
                 )
             }
             IRInstruction::Sub => {
                 format!(
-                    "{}{{ int b = pop(); int a = pop(); push(a - b); }}\n",
+                    "{}{{ cell b = pop(); cell a = pop(); push((cell)((ucell)a - (ucell)b)); }}\n",
                     self.emit_indent()
                 )
             }
             IRInstruction::Mul => {
                 format!(
-                    "{}{{ int b = pop(); int a = pop(); push(a * b); }}\n",
+                    "{}{{ cell b = pop(); cell a = pop(); push((cell)((ucell)a * (ucell)b)); }}\n",
                     self.emit_indent()
                 )
             }
             IRInstruction::Div => {
                 format!(
-                    "{}{{ int b = pop(); int a = pop(); if (b == 0) {{ printf(\"Division by zero\\n\"); exit(1); }} push(a / b); }}\n",
+                    "{}{{ cell b = pop(); cell a = pop(); if (b == 0) {{ throw_exception(DIVISION_BY_ZERO, \"Division by zero\"); }} push(b == -1 ? (cell)(0 - (ucell)a) : a / b); }}\n",
                     self.emit_indent()
                 )
             }
-            IRInstruction::Print => {
-                format!("{}printf(\"%d \", pop());\n", self.emit_indent())
+            IRInstruction::Rot => {
+                format!(
+                    "{}{{ cell c = pop(); cell b = pop(); cell a = pop(); push(b); push(c); push(a); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::Pick => {
+                format!(
+                    "{}{{ ucell n = (ucell)pop(); if (n >= (ucell)stack.top) {{ throw_exception(STACK_UNDERFLOW, \"Stack underflow\"); }} push(stack.data[stack.top - 1 - n]); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::Roll => {
+                format!(
+                    "{}{{ ucell n = (ucell)pop(); if (n >= (ucell)stack.top) {{ throw_exception(STACK_UNDERFLOW, \"Stack underflow\"); }} cell x = stack.data[stack.top - 1 - n]; memmove(&stack.data[stack.top - 1 - n], &stack.data[stack.top - n], n * sizeof(cell)); stack.data[stack.top - 1] = x; }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::DupIfNonZero => {
+                format!(
+                    "{}{{ cell a = pop(); push(a); if (a != 0) {{ push(a); }} }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::Mod => {
+                format!(
+                    "{}{{ cell b = pop(); cell a = pop(); if (b == 0) {{ throw_exception(DIVISION_BY_ZERO, \"Division by zero\"); }} push(b == -1 ? 0 : a % b); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::Neg => {
+                format!("{}push((cell)(0 - (ucell)pop()));\n", self.emit_indent())
+            }
+            IRInstruction::DAdd => self.generate_double_binary_op("(dcell)((udcell)a + (udcell)b)"),
+            IRInstruction::DSub => self.generate_double_binary_op("(dcell)((udcell)a - (udcell)b)"),
+            IRInstruction::DNeg => {
+                format!(
+                    "{}dpush((dcell)(0 - (udcell)dpop()));\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::MulMixed => {
+                format!(
+                    "{}{{ cell b = pop(); cell a = pop(); dpush((dcell)a * b); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::MulUnsigned => {
+                format!(
+                    "{}{{ ucell b = (ucell)pop(); ucell a = (ucell)pop(); dpush((dcell)((udcell)a * b)); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::DivModUnsigned => {
+                format!(
+                    "{}{{ udcell divisor = (ucell)pop(); udcell dividend = (udcell)dpop(); if (divisor == 0) {{ throw_exception(DIVISION_BY_ZERO, \"Division by zero\"); }} push((cell)(ucell)(dividend % divisor)); push((cell)(ucell)(dividend / divisor)); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::DivRemSymmetric => {
+                format!("{}divide_double(0);\n", self.emit_indent())
+            }
+            IRInstruction::DivModFloored => {
+                format!("{}divide_double(1);\n", self.emit_indent())
+            }
+            IRInstruction::PrintDouble => {
+                format!("{}print_signed(dpop(), 0, 1);\n", self.emit_indent())
+            }
+            IRInstruction::HoldStart => format!("{}held = HOLD_END;\n", self.emit_indent()),
+            IRInstruction::HoldDigit => {
+                format!(
+                    "{}dpush((dcell)hold_digit((udcell)dpop()));\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::HoldDigits => {
+                format!(
+                    "{}{{ udcell ud = (udcell)dpop(); do {{ ud = hold_digit(ud); }} while (ud != 0); dpush(0); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::Hold => format!("{}hold(pop());\n", self.emit_indent()),
+            IRInstruction::HoldSign => {
+                format!("{}if (pop() < 0) hold('-');\n", self.emit_indent())
+            }
+            IRInstruction::HoldEnd => {
+                format!(
+                    "{}dpop(); push(held); push(HOLD_END - held);\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::Equal => self.generate_binary_op("a == b ? -1 : 0"),
+            IRInstruction::NotEqual => self.generate_binary_op("a != b ? -1 : 0"),
+            IRInstruction::Less => self.generate_binary_op("a < b ? -1 : 0"),
+            IRInstruction::Greater => self.generate_binary_op("a > b ? -1 : 0"),
+            IRInstruction::LessEqual => self.generate_binary_op("a <= b ? -1 : 0"),
+            IRInstruction::GreaterEqual => self.generate_binary_op("a >= b ? -1 : 0"),
+            IRInstruction::And => self.generate_binary_op("(a != 0 && b != 0) ? -1 : 0"),
+            IRInstruction::Or => self.generate_binary_op("(a != 0 || b != 0) ? -1 : 0"),
+            IRInstruction::Xor => self.generate_binary_op("a ^ b"),
+            IRInstruction::Not => format!("{}push(pop() == 0 ? -1 : 0);\n", self.emit_indent()),
+            IRInstruction::Label(label) => format!("{}:;\n", label),
+            IRInstruction::Jump(label) => format!("{}goto {};\n", self.emit_indent(), label),
+            IRInstruction::JumpIf(label) => {
+                format!("{}if (pop() != 0) goto {};\n", self.emit_indent(), label)
+            }
+            IRInstruction::JumpIfNot(label) => {
+                format!("{}if (pop() == 0) goto {};\n", self.emit_indent(), label)
+            }
+            IRInstruction::DoLoop(_, end_label) => {
+                format!(
+                    "{}{{ cell start = pop(); cell limit = pop(); if (start == limit) goto {}; loop_stack[loop_sp].index = start; loop_stack[loop_sp].limit = limit; loop_sp++; }}\n",
+                    self.emit_indent(),
+                    end_label
+                )
+            }
+            IRInstruction::Loop(loop_label) => {
+                format!(
+                    "{}{{ LoopFrame *frame = &loop_stack[loop_sp - 1]; frame->index = (cell)((ucell)frame->index + 1); if (frame->index != frame->limit) goto {}; loop_sp--; }}\n",
+                    self.emit_indent(),
+                    loop_label
+                )
+            }
+            IRInstruction::PlusLoop(loop_label) => {
+                // Unsigned arithmetic keeps the boundary check free of signed overflow
+                format!(
+                    "{}{{ LoopFrame *frame = &loop_stack[loop_sp - 1]; cell step = pop(); cell diff = (cell)((ucell)frame->index - (ucell)frame->limit); frame->index = (cell)((ucell)frame->index + (ucell)step); if (((diff ^ (cell)((ucell)diff + (ucell)step)) & (diff ^ step)) >= 0) goto {}; loop_sp--; }}\n",
+                    self.emit_indent(),
+                    loop_label
+                )
+            }
+            IRInstruction::Leave(end_label) => {
+                format!("{}loop_sp--; goto {};\n", self.emit_indent(), end_label)
+            }
+            IRInstruction::Unloop => format!("{}loop_sp--;\n", self.emit_indent()),
+            IRInstruction::PushLoopIndex => {
+                format!(
+                    "{}push(loop_stack[loop_sp - 1].index);\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::PushOuterLoopIndex(depth) => {
+                format!(
+                    "{}push(loop_stack[loop_sp - 1 - {}].index);\n",
+                    self.emit_indent(),
+                    depth
+                )
+            }
+            IRInstruction::PushLoopLimit => {
+                format!(
+                    "{}push(loop_stack[loop_sp - 1].limit);\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::Print => format!("{}print_signed(pop(), 0, 1);\n", self.emit_indent()),
+            IRInstruction::PrintUnsigned => {
+                format!(
+                    "{}print_number((ucell)pop(), 0, 0, 1);\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::PrintAligned => {
+                format!(
+                    "{}{{ cell width = pop(); print_signed(pop(), width, 0); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::PrintUnsignedAligned => {
+                format!(
+                    "{}{{ cell width = pop(); print_number((ucell)pop(), 0, width, 0); }}\n",
+                    self.emit_indent()
+                )
             }
             IRInstruction::PrintStack => {
                 format!(
-                    "{}printf(\"<%d> \", stack.top); for (int i = 0; i < stack.top; i++) {{ printf(\"%d \", stack.data[i]); }} printf(\"\\n\");\n",
+                    "{}printf(\"<%d> \", stack.top); for (int i = 0; i < stack.top; i++) {{ print_signed(stack.data[i], 0, 1); }}\n",
                     self.emit_indent()
                 )
             }
@@ -768,14 +2276,193 @@ impl IRCGenerator {
                 format!("{}printf(\"%c\", (char)pop());\n", self.emit_indent())
             }
             IRInstruction::Call(name) => {
-                format!("{}{}();\n", self.emit_indent(), name.to_lowercase())
+                format!("{}{}();\n", self.emit_indent(), function_name(name))
             }
             IRInstruction::Return => {
                 format!("{}return;\n", self.emit_indent())
             }
+            IRInstruction::PushXt(name) => {
+                format!(
+                    "{}push({}); // ' {}\n",
+                    self.emit_indent(),
+                    self.xts.token(name),
+                    name
+                )
+            }
+            IRInstruction::Execute => {
+                format!("{}execute(pop());\n", self.emit_indent())
+            }
+            IRInstruction::Catch => {
+                format!("{}catch_word();\n", self.emit_indent())
+            }
+            IRInstruction::Throw => {
+                format!("{}throw_top();\n", self.emit_indent())
+            }
+            IRInstruction::Abort => {
+                format!(
+                    "{}throw_exception(ABORT, \"Aborted\");\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::AbortQuote(text) => {
+                format!(
+                    "{}if (pop() != 0) throw_exception(ABORT_QUOTE, \"{}\");\n",
+                    self.emit_indent(),
+                    Self::bytes_literal(text.as_bytes())
+                )
+            }
             IRInstruction::Comment(text) => {
                 format!("{}// {}\n", self.emit_indent(), text)
             }
+            IRInstruction::Load(_) => {
+                format!("{}push(fetch(pop()));\n", self.emit_indent())
+            }
+            IRInstruction::Store(_) => {
+                format!(
+                    "{}{{ cell addr = pop(); cell value = pop(); store(addr, value); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::LoadChar => {
+                format!("{}push(fetch_char(pop()));\n", self.emit_indent())
+            }
+            IRInstruction::StoreChar => {
+                format!(
+                    "{}{{ cell addr = pop(); cell value = pop(); store_char(addr, value); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::Here => {
+                format!("{}push(data_here);\n", self.emit_indent())
+            }
+            IRInstruction::Allot => {
+                format!("{}allot(pop());\n", self.emit_indent())
+            }
+            IRInstruction::Comma => {
+                format!(
+                    "{}{{ cell value = pop(); cell addr = data_here; allot(sizeof(cell)); store(addr, value); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::CharComma => {
+                format!(
+                    "{}{{ cell value = pop(); cell addr = data_here; allot(1); store_char(addr, value); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::Align => {
+                format!("{}align_data();\n", self.emit_indent())
+            }
+            IRInstruction::Create(name) => {
+                format!(
+                    "{}align_data(); data_fields[{}] = data_here; // {}\n",
+                    self.emit_indent(),
+                    self.data_field_index(name),
+                    name
+                )
+            }
+            IRInstruction::SupplyName(name) => {
+                format!(
+                    "{}supplied_field = {}; // {}\n",
+                    self.emit_indent(),
+                    self.data_field_index(name),
+                    name
+                )
+            }
+            IRInstruction::CreateSupplied => {
+                format!(
+                    "{}align_data(); data_fields[supplied_field] = data_here;\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::PushDataField(name) => {
+                format!(
+                    "{}push(data_fields[{}]); // {}\n",
+                    self.emit_indent(),
+                    self.data_field_index(name),
+                    name
+                )
+            }
+            IRInstruction::StackGet(slot) => {
+                format!("{}push(local_{});\n", self.emit_indent(), slot)
+            }
+            IRInstruction::StackSet(slot) => {
+                format!("{}local_{} = pop();\n", self.emit_indent(), slot)
+            }
+            // The locals are declared at the top of the function
+            IRInstruction::StackAlloc(_) => String::new(),
+            IRInstruction::PushFloat(r) => {
+                format!(
+                    "{}fpush({});\n",
+                    self.emit_indent(),
+                    Self::float_literal(*r)
+                )
+            }
+            IRInstruction::FAdd => self.generate_float_binary_op("a + b"),
+            IRInstruction::FSub => self.generate_float_binary_op("a - b"),
+            IRInstruction::FMul => self.generate_float_binary_op("a * b"),
+            IRInstruction::FDiv => self.generate_float_binary_op("a / b"),
+            IRInstruction::FDup => {
+                format!(
+                    "{}{{ double r = fpop(); fpush(r); fpush(r); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::FSwap => {
+                format!(
+                    "{}{{ double b = fpop(); double a = fpop(); fpush(b); fpush(a); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::FDrop => format!("{}fpop();\n", self.emit_indent()),
+            IRInstruction::FLess => {
+                format!(
+                    "{}{{ double b = fpop(); double a = fpop(); push(a < b ? -1 : 0); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::FZeroEqual => {
+                format!("{}push(fpop() == 0.0 ? -1 : 0);\n", self.emit_indent())
+            }
+            IRInstruction::IntToFloat => {
+                format!("{}fpush((double)pop());\n", self.emit_indent())
+            }
+            IRInstruction::FloatToInt => {
+                format!("{}push((cell)fpop());\n", self.emit_indent())
+            }
+            IRInstruction::LoadFloat => {
+                format!("{}fpush(fetch_float(pop()));\n", self.emit_indent())
+            }
+            IRInstruction::StoreFloat => {
+                format!(
+                    "{}{{ cell addr = pop(); store_float(addr, fpop()); }}\n",
+                    self.emit_indent()
+                )
+            }
+            IRInstruction::PrintFloat => {
+                format!("{}print_float(fpop());\n", self.emit_indent())
+            }
+            IRInstruction::PrintString => format!("{}type_string();\n", self.emit_indent()),
+            IRInstruction::PushString(text) => {
+                format!(
+                    "{}push({}); push({});\n",
+                    self.emit_indent(),
+                    self.strings.address(text) + 1,
+                    text.len()
+                )
+            }
+            IRInstruction::PushCountedString(text) => {
+                format!(
+                    "{}push({});\n",
+                    self.emit_indent(),
+                    self.strings.address(text)
+                )
+            }
+            IRInstruction::Compare => format!("{}compare_strings();\n", self.emit_indent()),
+            IRInstruction::Search => format!("{}search_string();\n", self.emit_indent()),
+            IRInstruction::TrimTrailing => format!("{}trim_trailing();\n", self.emit_indent()),
+            IRInstruction::CMove => format!("{}cmove();\n", self.emit_indent()),
+            IRInstruction::Move => format!("{}move_bytes();\n", self.emit_indent()),
             _ => {
                 format!(
                     "{}// Instruction not implemented: {:?}\n",
@@ -786,9 +2473,49 @@ impl IRCGenerator {
         }
     }
 
+    fn generate_double_binary_op(&self, expr: &str) -> String {
+        format!(
+            "{}{{ dcell b = dpop(); dcell a = dpop(); dpush({}); }}\n",
+            self.emit_indent(),
+            expr
+        )
+    }
+
+    fn generate_float_binary_op(&self, expr: &str) -> String {
+        format!(
+            "{}{{ double b = fpop(); double a = fpop(); fpush({}); }}\n",
+            self.emit_indent(),
+            expr
+        )
+    }
+
+    /// `bytes` inside a C string literal. Octal escapes always have three
+    /// digits, so unlike hex ones they cannot run into the next character
+    fn bytes_literal(bytes: &[u8]) -> String {
+        bytes
+            .iter()
+            .map(|&byte| match byte {
+                b'"' | b'\\' | b'?' => format!("\\{}", byte as char),
+                b' '..=b'~' => (byte as char).to_string(),
+                _ => format!("\\{:03o}", byte),
+            })
+            .collect()
+    }
+
+    /// C spelling of a float; infinities and NaN have no literal form
+    fn float_literal(r: Float) -> String {
+        if r.is_nan() {
+            "NAN".to_string()
+        } else if r.is_infinite() {
+            format!("{}INFINITY", if r < 0.0 { "-" } else { "" })
+        } else {
+            format!("{:?}", r)
+        }
+    }
+
     fn generate_value(&self, value: &IRValue) -> String {
         match value {
-            IRValue::Constant(n) => n.to_string(),
+            IRValue::Constant(n) => self.cell_literal(*n),
             IRValue::StackTop => "stack.data[stack.top - 1]".to_string(),
             IRValue::StackPos(pos) => format!("stack.data[stack.top - 1 - {}]", pos),
             IRValue::Variable(name) => format!("/* variable {} */", name),
@@ -0,0 +1,1010 @@
+use crate::ir::{
+    BinaryOpKind, IRFunction, IRInstruction, IRProgram, IRValue, UnaryOpKind, frame_size,
+};
+use crate::types::{Cell, CellWidth, DoubleCell, Float};
+use roth_runtime::{
+    BASE_ADDRESS, HOLD_SIZE, SYSTEM_SPACE_SIZE, compare_bytes, digit_char, format_float,
+    format_number, search_bytes, throw_code, trimmed_len,
+};
+use std::collections::HashMap;
+use std::io::Write;
+
+/// Calls nested deeper than this are reported instead of overflowing the
+/// compiler's own stack
+const MAX_CALL_DEPTH: usize = 256;
+
+/// Largest data space words can build at compile time, as at run time
+const DATA_SPACE_SIZE: usize = 1024 * 1024;
+
+/// What a word run at compile time appends to the definition being compiled
+#[derive(Debug, Clone, PartialEq)]
+pub enum Compiled {
+    /// `POSTPONE name`
+    Word(String),
+    /// `LITERAL`
+    Literal(Cell),
+}
+
+/// Runs IR inside the compiler, for IMMEDIATE words and `[ ... ]`.
+///
+/// The stack and data space live as long as the interpreter, so values left
+/// by one run are there for the next, as in an interactive Forth.
+#[derive(Debug, Clone)]
+pub struct IRInterpreter {
+    stack: Vec<Cell>,
+    float_stack: Vec<Float>,
+    loop_stack: Vec<(Cell, Cell)>, // (index, limit) of each open DO loop
+    memory: Vec<u8>,
+    data_fields: HashMap<String, Cell>,
+    strings: HashMap<String, Cell>, // Address of each string literal's count byte
+    held: usize,                    // Start of the pictured numeric output so far
+    supplied_name: Option<String>,
+    compiled: Vec<Compiled>,
+    cell_width: CellWidth,
+}
+
+impl Default for IRInterpreter {
+    fn default() -> Self {
+        Self::new(CellWidth::default())
+    }
+}
+
+impl IRInterpreter {
+    pub fn new(cell_width: CellWidth) -> Self {
+        let mut interpreter = Self {
+            stack: Vec::new(),
+            float_stack: Vec::new(),
+            loop_stack: Vec::new(),
+            memory: vec![0; SYSTEM_SPACE_SIZE],
+            data_fields: HashMap::new(),
+            strings: HashMap::new(),
+            held: SYSTEM_SPACE_SIZE,
+            supplied_name: None,
+            compiled: Vec::new(),
+            cell_width,
+        };
+        interpreter
+            .store(BASE_ADDRESS, 10)
+            .expect("BASE lies below the first HERE");
+        interpreter
+    }
+
+    /// The compile-time data stack, bottom first
+    pub fn stack(&self) -> &[Cell] {
+        &self.stack
+    }
+
+    pub fn push(&mut self, value: Cell) {
+        self.stack.push(self.cell_width.wrap(value));
+    }
+
+    pub fn pop(&mut self) -> Option<Cell> {
+        self.stack.pop()
+    }
+
+    /// Run `function` from `program`, returning what it compiled
+    pub fn run(&mut self, program: &IRProgram, function: &str) -> Result<Vec<Compiled>, String> {
+        self.compiled.clear();
+        let result = self.call(program, function, 0);
+        // A failed run can leave loop frames behind
+        self.loop_stack.clear();
+        let compiled = std::mem::take(&mut self.compiled);
+        result.map(|()| compiled)
+    }
+
+    fn call(&mut self, program: &IRProgram, name: &str, depth: usize) -> Result<(), String> {
+        if depth >= MAX_CALL_DEPTH {
+            return Err(format!("Calls nested too deeply in {}", name));
+        }
+        let function = match program.functions.get(name) {
+            Some(function) => function,
+            None if name == "main" => &program.main,
+            None => return Err(format!("Undefined word: {}", name)),
+        };
+        self.execute(program, function, depth)
+    }
+
+    fn execute(
+        &mut self,
+        program: &IRProgram,
+        function: &IRFunction,
+        depth: usize,
+    ) -> Result<(), String> {
+        let labels: HashMap<_, _> = function
+            .instructions
+            .iter()
+            .enumerate()
+            .filter_map(|(pc, instruction)| match instruction {
+                IRInstruction::Label(label) => Some((label.clone(), pc)),
+                _ => None,
+            })
+            .collect();
+        let target = |label| {
+            labels
+                .get(label)
+                .copied()
+                .ok_or_else(|| format!("Label {} not found", label))
+        };
+
+        // Each call has its own locals
+        let mut locals = vec![0; frame_size(&function.instructions)];
+
+        let mut pc = 0;
+        while let Some(instruction) = function.instructions.get(pc) {
+            pc += 1;
+            match instruction {
+                IRInstruction::Push(value) => {
+                    let value = self.value(value)?;
+                    self.push(value);
+                }
+                IRInstruction::LoadConst(n) => self.push(*n),
+                IRInstruction::Pop | IRInstruction::Drop => {
+                    self.pop_cell()?;
+                }
+                IRInstruction::Dup => {
+                    let a = self.peek(0)?;
+                    self.push(a);
+                }
+                IRInstruction::Swap => {
+                    let b = self.pop_cell()?;
+                    let a = self.pop_cell()?;
+                    self.push(b);
+                    self.push(a);
+                }
+                IRInstruction::Over => {
+                    let a = self.peek(1)?;
+                    self.push(a);
+                }
+                IRInstruction::Rot => {
+                    let c = self.pop_cell()?;
+                    let b = self.pop_cell()?;
+                    let a = self.pop_cell()?;
+                    self.push(b);
+                    self.push(c);
+                    self.push(a);
+                }
+                IRInstruction::Pick => {
+                    let depth = self.pop_depth()?;
+                    let x = self.peek(depth)?;
+                    self.push(x);
+                }
+                IRInstruction::Roll => {
+                    let depth = self.pop_depth()?;
+                    self.peek(depth)?;
+                    let x = self.stack.remove(self.stack.len() - 1 - depth);
+                    self.push(x);
+                }
+                IRInstruction::DupIfNonZero => {
+                    let a = self.peek(0)?;
+                    if a != 0 {
+                        self.push(a);
+                    }
+                }
+                IRInstruction::Add => self.binary(BinaryOpKind::Add)?,
+                IRInstruction::Sub => self.binary(BinaryOpKind::Sub)?,
+                IRInstruction::Mul => self.binary(BinaryOpKind::Mul)?,
+                IRInstruction::Div => self.binary(BinaryOpKind::Div)?,
+                IRInstruction::Mod => self.binary(BinaryOpKind::Mod)?,
+                IRInstruction::Equal => self.binary(BinaryOpKind::Equal)?,
+                IRInstruction::NotEqual => self.binary(BinaryOpKind::NotEqual)?,
+                IRInstruction::Less => self.binary(BinaryOpKind::Less)?,
+                IRInstruction::Greater => self.binary(BinaryOpKind::Greater)?,
+                IRInstruction::LessEqual => self.binary(BinaryOpKind::LessEqual)?,
+                IRInstruction::GreaterEqual => self.binary(BinaryOpKind::GreaterEqual)?,
+                IRInstruction::And => self.binary(BinaryOpKind::And)?,
+                IRInstruction::Or => self.binary(BinaryOpKind::Or)?,
+                IRInstruction::Xor => {
+                    let b = self.pop_cell()?;
+                    let a = self.pop_cell()?;
+                    self.push(a ^ b);
+                }
+                IRInstruction::Neg => {
+                    let a = self.pop_cell()?;
+                    self.push(a.wrapping_neg());
+                }
+                IRInstruction::Not => {
+                    let a = self.pop_cell()?;
+                    self.push(flag(a == 0));
+                }
+
+                // Double-cell arithmetic
+                IRInstruction::DAdd => {
+                    let b = self.pop_double()?;
+                    let a = self.pop_double()?;
+                    self.push_double(a.wrapping_add(b));
+                }
+                IRInstruction::DSub => {
+                    let b = self.pop_double()?;
+                    let a = self.pop_double()?;
+                    self.push_double(a.wrapping_sub(b));
+                }
+                IRInstruction::DNeg => {
+                    let a = self.pop_double()?;
+                    self.push_double(a.wrapping_neg());
+                }
+                IRInstruction::MulMixed => {
+                    let b = self.pop_cell()?;
+                    let a = self.pop_cell()?;
+                    self.push_double(a as DoubleCell * b as DoubleCell);
+                }
+                IRInstruction::MulUnsigned => {
+                    let width = self.cell_width;
+                    let b = width.unsigned(self.pop_cell()?);
+                    let a = width.unsigned(self.pop_cell()?);
+                    self.push_double((a * b) as DoubleCell);
+                }
+                IRInstruction::DivModUnsigned => {
+                    let width = self.cell_width;
+                    let divisor = width.unsigned(self.pop_cell()?);
+                    let dividend = width.unsigned_double(self.pop_double()?);
+                    if divisor == 0 {
+                        return Err("Division by zero".to_string());
+                    }
+                    self.push((dividend % divisor) as Cell);
+                    self.push((dividend / divisor) as Cell);
+                }
+                IRInstruction::DivRemSymmetric | IRInstruction::DivModFloored => {
+                    let divisor = self.pop_cell()? as DoubleCell;
+                    let dividend = self.pop_double()?;
+                    if divisor == 0 {
+                        return Err("Division by zero".to_string());
+                    }
+                    let mut quotient = dividend.wrapping_div(divisor);
+                    let mut remainder = dividend.wrapping_rem(divisor);
+                    if matches!(instruction, IRInstruction::DivModFloored)
+                        && remainder != 0
+                        && (remainder < 0) != (divisor < 0)
+                    {
+                        quotient -= 1;
+                        remainder += divisor;
+                    }
+                    self.push(remainder as Cell);
+                    self.push(quotient as Cell);
+                }
+                IRInstruction::BinaryOp(op, a, b) => {
+                    let b = self.value(b)?;
+                    let a = self.value(a)?;
+                    let result = self.apply(op, a, b)?;
+                    self.push(result);
+                }
+                IRInstruction::UnaryOp(op, a) => {
+                    let a = self.value(a)?;
+                    self.push(match op {
+                        UnaryOpKind::Neg => a.wrapping_neg(),
+                        UnaryOpKind::Not => !a,
+                    });
+                }
+
+                // Data space
+                IRInstruction::Load(_) => {
+                    let addr = self.pop_cell()?;
+                    let value = self.fetch(addr)?;
+                    self.push(value);
+                }
+                IRInstruction::Store(_) => {
+                    let addr = self.pop_cell()?;
+                    let value = self.pop_cell()?;
+                    self.store(addr, value)?;
+                }
+                IRInstruction::LoadChar => {
+                    let addr = self.pop_cell()?;
+                    let start = self.data_range(addr, 1)?.start;
+                    self.push(self.memory[start] as Cell);
+                }
+                IRInstruction::StoreChar => {
+                    let addr = self.pop_cell()?;
+                    let value = self.pop_cell()?;
+                    let start = self.data_range(addr, 1)?.start;
+                    self.memory[start] = value as u8;
+                }
+                IRInstruction::Here => self.push(self.memory.len() as Cell),
+                IRInstruction::Allot => {
+                    let n = self.pop_cell()?;
+                    self.allot(n)?;
+                }
+                IRInstruction::Comma => {
+                    let value = self.pop_cell()?;
+                    let addr = self.memory.len() as Cell;
+                    self.allot(self.cell_width.bytes())?;
+                    self.store(addr, value)?;
+                }
+                IRInstruction::CharComma => {
+                    let value = self.pop_cell()?;
+                    self.allot(1)?;
+                    if let Some(byte) = self.memory.last_mut() {
+                        *byte = value as u8;
+                    }
+                }
+                IRInstruction::Align => self.align()?,
+                IRInstruction::Create(name) => {
+                    self.align()?;
+                    self.data_fields
+                        .insert(name.clone(), self.memory.len() as Cell);
+                }
+                IRInstruction::SupplyName(name) => self.supplied_name = Some(name.clone()),
+                IRInstruction::CreateSupplied => {
+                    let name = self
+                        .supplied_name
+                        .take()
+                        .ok_or_else(|| "CREATE has no name to define".to_string())?;
+                    self.align()?;
+                    self.data_fields.insert(name, self.memory.len() as Cell);
+                }
+                IRInstruction::PushDataField(name) => {
+                    let addr = *self
+                        .data_fields
+                        .get(name)
+                        .ok_or_else(|| format!("{} has no data space at compile time", name))?;
+                    self.push(addr);
+                }
+
+                // Strings
+                IRInstruction::PushString(text) => {
+                    let addr = self.intern_string(text)?;
+                    self.push(addr + 1);
+                    self.push(text.len() as Cell);
+                }
+                IRInstruction::PushCountedString(text) => {
+                    let addr = self.intern_string(text)?;
+                    self.push(addr);
+                }
+                IRInstruction::Compare => {
+                    let second = self.pop_string()?;
+                    let first = self.pop_string()?;
+                    self.push(compare_bytes(&self.memory[first], &self.memory[second]));
+                }
+                IRInstruction::Search => {
+                    let needle = self.pop_string()?;
+                    let haystack = self.pop_string()?;
+                    let found = search_bytes(&self.memory[haystack.clone()], &self.memory[needle]);
+                    let offset = found.unwrap_or(0);
+                    self.push((haystack.start + offset) as Cell);
+                    self.push((haystack.len() - offset) as Cell);
+                    self.push(flag(found.is_some()));
+                }
+                IRInstruction::TrimTrailing => {
+                    let range = self.pop_string()?;
+                    self.push(range.start as Cell);
+                    self.push(trimmed_len(&self.memory[range]) as Cell);
+                }
+                IRInstruction::CMove => {
+                    let (from, to) = self.pop_move()?;
+                    for i in 0..from.len() {
+                        self.memory[to.start + i] = self.memory[from.start + i];
+                    }
+                }
+                IRInstruction::Move => {
+                    let (from, to) = self.pop_move()?;
+                    self.memory.copy_within(from, to.start);
+                }
+
+                // Floating point
+                IRInstruction::PushFloat(r) => self.float_stack.push(*r),
+                IRInstruction::FAdd => self.float_binary(|a, b| a + b)?,
+                IRInstruction::FSub => self.float_binary(|a, b| a - b)?,
+                IRInstruction::FMul => self.float_binary(|a, b| a * b)?,
+                IRInstruction::FDiv => self.float_binary(|a, b| a / b)?,
+                IRInstruction::FDup => {
+                    let r = self.pop_float()?;
+                    self.float_stack.extend([r, r]);
+                }
+                IRInstruction::FSwap => {
+                    let b = self.pop_float()?;
+                    let a = self.pop_float()?;
+                    self.float_stack.extend([b, a]);
+                }
+                IRInstruction::FDrop => {
+                    self.pop_float()?;
+                }
+                IRInstruction::FLess => {
+                    let b = self.pop_float()?;
+                    let a = self.pop_float()?;
+                    self.push(flag(a < b));
+                }
+                IRInstruction::FZeroEqual => {
+                    let r = self.pop_float()?;
+                    self.push(flag(r == 0.0));
+                }
+                IRInstruction::IntToFloat => {
+                    let n = self.pop_cell()?;
+                    self.float_stack.push(n as Float);
+                }
+                IRInstruction::FloatToInt => {
+                    let r = self.pop_float()?;
+                    self.push(r as Cell);
+                }
+                IRInstruction::LoadFloat => {
+                    let addr = self.pop_cell()?;
+                    let range = self.data_range(addr, size_of::<Float>())?;
+                    let mut bytes = [0u8; size_of::<Float>()];
+                    bytes.copy_from_slice(&self.memory[range]);
+                    self.float_stack.push(Float::from_ne_bytes(bytes));
+                }
+                IRInstruction::StoreFloat => {
+                    let addr = self.pop_cell()?;
+                    let r = self.pop_float()?;
+                    let range = self.data_range(addr, size_of::<Float>())?;
+                    self.memory[range].copy_from_slice(&r.to_ne_bytes());
+                }
+
+                // Control flow
+                IRInstruction::Jump(label) => pc = target(label)?,
+                IRInstruction::JumpIf(label) => {
+                    if self.pop_cell()? != 0 {
+                        pc = target(label)?;
+                    }
+                }
+                IRInstruction::JumpIfNot(label) => {
+                    if self.pop_cell()? == 0 {
+                        pc = target(label)?;
+                    }
+                }
+                IRInstruction::Call(name) => self.call(program, name, depth + 1)?,
+                IRInstruction::Return => return Ok(()),
+
+                // Loops, with the same entry check and termination as the backends
+                IRInstruction::DoLoop(_, end_label) => {
+                    let start = self.pop_cell()?;
+                    let limit = self.pop_cell()?;
+                    if start == limit {
+                        pc = target(end_label)?;
+                    } else {
+                        self.loop_stack.push((start, limit));
+                    }
+                }
+                IRInstruction::Loop(loop_label) => {
+                    let width = self.cell_width;
+                    let (index, limit) = self.loop_frame(0)?;
+                    let next = width.wrap(index.wrapping_add(1));
+                    if next == limit {
+                        self.loop_stack.pop();
+                    } else {
+                        self.set_loop_index(next);
+                        pc = target(loop_label)?;
+                    }
+                }
+                IRInstruction::PlusLoop(loop_label) => {
+                    let width = self.cell_width;
+                    let step = self.pop_cell()?;
+                    let (index, limit) = self.loop_frame(0)?;
+                    let diff = width.wrap(index.wrapping_sub(limit));
+                    let crossed =
+                        ((diff ^ width.wrap(diff.wrapping_add(step))) & (diff ^ step)) < 0;
+                    if crossed {
+                        self.loop_stack.pop();
+                    } else {
+                        self.set_loop_index(width.wrap(index.wrapping_add(step)));
+                        pc = target(loop_label)?;
+                    }
+                }
+                IRInstruction::Leave(end_label) => {
+                    self.loop_stack.pop();
+                    pc = target(end_label)?;
+                }
+                IRInstruction::Unloop => {
+                    self.loop_stack.pop();
+                }
+                IRInstruction::PushLoopIndex => {
+                    let (index, _) = self.loop_frame(0)?;
+                    self.push(index);
+                }
+                IRInstruction::PushOuterLoopIndex(depth) => {
+                    let (index, _) = self.loop_frame(*depth)?;
+                    self.push(index);
+                }
+                IRInstruction::PushLoopLimit => {
+                    let (_, limit) = self.loop_frame(0)?;
+                    self.push(limit);
+                }
+
+                // Compile time
+                IRInstruction::CompileWord(name) => {
+                    self.compiled.push(Compiled::Word(name.clone()))
+                }
+                IRInstruction::CompileLiteral => {
+                    let value = self.pop_cell()?;
+                    self.compiled.push(Compiled::Literal(value));
+                }
+
+                // Output goes wherever the compiler's does
+                IRInstruction::Print => {
+                    let n = self.pop_cell()?;
+                    print!("{} ", self.format_signed(n as DoubleCell)?);
+                }
+                IRInstruction::PrintUnsigned => {
+                    let u = self.pop_cell()?;
+                    print!("{} ", self.format_unsigned(u)?);
+                }
+                IRInstruction::PrintAligned => {
+                    let width = self.pop_cell()?.max(0) as usize;
+                    let n = self.pop_cell()?;
+                    print!("{:>1$}", self.format_signed(n as DoubleCell)?, width);
+                }
+                IRInstruction::PrintUnsignedAligned => {
+                    let width = self.pop_cell()?.max(0) as usize;
+                    let u = self.pop_cell()?;
+                    print!("{:>1$}", self.format_unsigned(u)?, width);
+                }
+                IRInstruction::PrintStack => {
+                    let mut text = format!("<{}> ", self.stack.len());
+                    for &value in &self.stack {
+                        text.push_str(&self.format_signed(value as DoubleCell)?);
+                        text.push(' ');
+                    }
+                    print!("{}", text);
+                }
+                IRInstruction::PrintChar => print!("{}", char::from(self.pop_cell()? as u8)),
+                IRInstruction::PrintFloat => print!("{} ", format_float(self.pop_float()?)),
+                IRInstruction::PrintDouble => {
+                    let d = self.pop_double()?;
+                    print!("{} ", self.format_signed(d)?);
+                }
+                IRInstruction::HoldStart => self.held = SYSTEM_SPACE_SIZE,
+                IRInstruction::HoldDigit => {
+                    let ud = self.cell_width.unsigned_double(self.pop_double()?);
+                    let rest = self.hold_digit(ud)?;
+                    self.push_double(rest as DoubleCell);
+                }
+                IRInstruction::HoldDigits => {
+                    let mut ud = self.cell_width.unsigned_double(self.pop_double()?);
+                    loop {
+                        ud = self.hold_digit(ud)?;
+                        if ud == 0 {
+                            break;
+                        }
+                    }
+                    self.push_double(0);
+                }
+                IRInstruction::Hold => {
+                    let c = self.pop_cell()?;
+                    self.hold(c as u8)?;
+                }
+                IRInstruction::HoldSign => {
+                    if self.pop_cell()? < 0 {
+                        self.hold(b'-')?;
+                    }
+                }
+                IRInstruction::HoldEnd => {
+                    self.pop_double()?;
+                    self.push(self.held as Cell);
+                    self.push((SYSTEM_SPACE_SIZE - self.held) as Cell);
+                }
+                IRInstruction::PrintString => {
+                    let range = self.pop_string()?;
+                    let mut stdout = std::io::stdout();
+                    stdout
+                        .write_all(&self.memory[range])
+                        .map_err(|e| e.to_string())?;
+                }
+                IRInstruction::ReadChar => {
+                    return Err("KEY is not available at compile time".to_string());
+                }
+
+                // Execution tokens are only given out by the backends
+                IRInstruction::PushXt(_) | IRInstruction::Execute => {
+                    return Err("Execution tokens are not available at compile time".to_string());
+                }
+                // Nothing inside the compiler can catch, so a THROW ends it
+                IRInstruction::Catch => {
+                    return Err("CATCH is not available at compile time".to_string());
+                }
+                IRInstruction::Throw => match self.pop_cell()? {
+                    0 => {}
+                    throw_code::ABORT => return Err("Aborted".to_string()),
+                    code => return Err(format!("Uncaught exception {}", code)),
+                },
+                IRInstruction::Abort => return Err("Aborted".to_string()),
+                IRInstruction::AbortQuote(text) => {
+                    if self.pop_cell()? != 0 {
+                        return Err(text.clone());
+                    }
+                }
+
+                IRInstruction::StackGet(slot) => self.push(locals[*slot]),
+                IRInstruction::StackSet(slot) => locals[*slot] = self.pop_cell()?,
+                IRInstruction::StackAlloc(_) => {}
+                IRInstruction::StackFree(_) => {
+                    return Err(format!("Cannot run {} at compile time", instruction));
+                }
+                IRInstruction::Label(_) | IRInstruction::Comment(_) | IRInstruction::Nop => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn pop_cell(&mut self) -> Result<Cell, String> {
+        self.stack
+            .pop()
+            .ok_or_else(|| "Stack underflow".to_string())
+    }
+
+    /// How deep PICK or ROLL reaches, below the count itself
+    fn pop_depth(&mut self) -> Result<usize, String> {
+        let depth = self.pop_cell()?;
+        usize::try_from(depth).map_err(|_| "Stack underflow".to_string())
+    }
+
+    /// A double-cell number: the high cell on top, the low cell below it
+    fn pop_double(&mut self) -> Result<DoubleCell, String> {
+        let high = self.pop_cell()?;
+        let low = self.pop_cell()?;
+        Ok(self.cell_width.join(low, high))
+    }
+
+    fn push_double(&mut self, value: DoubleCell) {
+        for cell in self.cell_width.split(value) {
+            self.push(cell);
+        }
+    }
+
+    /// The radix in BASE
+    fn base(&self) -> Result<u32, String> {
+        let base = self.fetch(BASE_ADDRESS)?;
+        if (2..=36).contains(&base) {
+            Ok(base as u32)
+        } else {
+            Err(format!("Invalid BASE: {}", base))
+        }
+    }
+
+    fn format_signed(&self, n: DoubleCell) -> Result<String, String> {
+        Ok(format_number(n.unsigned_abs(), n < 0, self.base()?))
+    }
+
+    fn format_unsigned(&self, u: Cell) -> Result<String, String> {
+        Ok(format_number(
+            self.cell_width.unsigned(u),
+            false,
+            self.base()?,
+        ))
+    }
+
+    /// Lay `c` down in front of the pictured numeric output
+    fn hold(&mut self, c: u8) -> Result<(), String> {
+        if self.held <= SYSTEM_SPACE_SIZE - HOLD_SIZE {
+            return Err("Pictured numeric output overflow".to_string());
+        }
+        self.held -= 1;
+        self.memory[self.held] = c;
+        Ok(())
+    }
+
+    /// Hold the last digit of `ud` in BASE, returning the digits before it
+    fn hold_digit(&mut self, ud: u128) -> Result<u128, String> {
+        let base = self.base()? as u128;
+        self.hold(digit_char((ud % base) as u32))?;
+        Ok(ud / base)
+    }
+
+    /// The bytes of the ( c-addr u ) string on top of the stack
+    fn pop_string(&mut self) -> Result<std::ops::Range<usize>, String> {
+        let len = self.pop_cell()?;
+        let addr = self.pop_cell()?;
+        self.data_range(addr, len as usize)
+    }
+
+    /// The source and destination of ( addr1 addr2 u ), for CMOVE and MOVE
+    fn pop_move(&mut self) -> Result<(std::ops::Range<usize>, std::ops::Range<usize>), String> {
+        let len = self.pop_cell()? as usize;
+        let to = self.pop_cell()?;
+        let from = self.pop_cell()?;
+        Ok((self.data_range(from, len)?, self.data_range(to, len)?))
+    }
+
+    /// Lay `text` down after a count byte the first time it is used,
+    /// returning the address of the count byte
+    fn intern_string(&mut self, text: &str) -> Result<Cell, String> {
+        if let Some(&addr) = self.strings.get(text) {
+            return Ok(addr);
+        }
+        let addr = self.memory.len() as Cell;
+        self.allot(1 + text.len() as Cell)?;
+        let start = addr as usize;
+        self.memory[start] = text.len() as u8;
+        self.memory[start + 1..].copy_from_slice(text.as_bytes());
+        self.strings.insert(text.to_string(), addr);
+        Ok(addr)
+    }
+
+    fn pop_float(&mut self) -> Result<Float, String> {
+        self.float_stack
+            .pop()
+            .ok_or_else(|| "Float stack underflow".to_string())
+    }
+
+    fn float_binary(&mut self, op: fn(Float, Float) -> Float) -> Result<(), String> {
+        let b = self.pop_float()?;
+        let a = self.pop_float()?;
+        self.float_stack.push(op(a, b));
+        Ok(())
+    }
+
+    /// The item `depth` places below the top of the stack
+    fn peek(&self, depth: usize) -> Result<Cell, String> {
+        self.stack
+            .len()
+            .checked_sub(depth + 1)
+            .map(|i| self.stack[i])
+            .ok_or_else(|| "Stack underflow".to_string())
+    }
+
+    fn value(&self, value: &IRValue) -> Result<Cell, String> {
+        match value {
+            IRValue::Constant(n) => Ok(*n),
+            IRValue::StackTop => self.peek(0),
+            IRValue::StackPos(pos) => self.peek(*pos),
+            IRValue::Variable(_) | IRValue::Temporary(_) => {
+                Err(format!("Cannot read {:?} at compile time", value))
+            }
+        }
+    }
+
+    fn binary(&mut self, op: BinaryOpKind) -> Result<(), String> {
+        let b = self.pop_cell()?;
+        let a = self.pop_cell()?;
+        let result = self.apply(&op, a, b)?;
+        self.push(result);
+        Ok(())
+    }
+
+    fn apply(&self, op: &BinaryOpKind, a: Cell, b: Cell) -> Result<Cell, String> {
+        Ok(match op {
+            BinaryOpKind::Add => a.wrapping_add(b),
+            BinaryOpKind::Sub => a.wrapping_sub(b),
+            BinaryOpKind::Mul => a.wrapping_mul(b),
+            BinaryOpKind::Div | BinaryOpKind::Mod if b == 0 => {
+                return Err("Division by zero".to_string());
+            }
+            BinaryOpKind::Div => a.wrapping_div(b),
+            BinaryOpKind::Mod => a.wrapping_rem(b),
+            BinaryOpKind::Equal => flag(a == b),
+            BinaryOpKind::NotEqual => flag(a != b),
+            BinaryOpKind::Less => flag(a < b),
+            BinaryOpKind::Greater => flag(a > b),
+            BinaryOpKind::LessEqual => flag(a <= b),
+            BinaryOpKind::GreaterEqual => flag(a >= b),
+            BinaryOpKind::And => flag(a != 0 && b != 0),
+            BinaryOpKind::Or => flag(a != 0 || b != 0),
+        })
+    }
+
+    /// The DO loop frame `depth` loops out from the innermost
+    fn loop_frame(&self, depth: usize) -> Result<(Cell, Cell), String> {
+        self.loop_stack
+            .len()
+            .checked_sub(depth + 1)
+            .map(|i| self.loop_stack[i])
+            .ok_or_else(|| "Loop index used outside of a DO loop".to_string())
+    }
+
+    fn set_loop_index(&mut self, index: Cell) {
+        if let Some(frame) = self.loop_stack.last_mut() {
+            frame.0 = index;
+        }
+    }
+
+    fn data_range(&self, addr: Cell, len: usize) -> Result<std::ops::Range<usize>, String> {
+        let start = addr as usize;
+        if addr < 0 || start.saturating_add(len) > self.memory.len() {
+            return Err(format!("Invalid memory address: {}", addr));
+        }
+        Ok(start..start + len)
+    }
+
+    fn fetch(&self, addr: Cell) -> Result<Cell, String> {
+        let range = self.data_range(addr, self.cell_width.bytes() as usize)?;
+        let value = match self.cell_width {
+            CellWidth::Bits32 => {
+                let mut bytes = [0u8; 4];
+                bytes.copy_from_slice(&self.memory[range]);
+                i32::from_ne_bytes(bytes) as Cell
+            }
+            CellWidth::Bits64 => {
+                let mut bytes = [0u8; 8];
+                bytes.copy_from_slice(&self.memory[range]);
+                i64::from_ne_bytes(bytes)
+            }
+        };
+        Ok(value)
+    }
+
+    fn store(&mut self, addr: Cell, value: Cell) -> Result<(), String> {
+        let range = self.data_range(addr, self.cell_width.bytes() as usize)?;
+        match self.cell_width {
+            CellWidth::Bits32 => self.memory[range].copy_from_slice(&(value as i32).to_ne_bytes()),
+            CellWidth::Bits64 => self.memory[range].copy_from_slice(&value.to_ne_bytes()),
+        }
+        Ok(())
+    }
+
+    fn allot(&mut self, n: Cell) -> Result<(), String> {
+        let new_len = self.memory.len() as i128 + n as i128;
+        if new_len < 0 || new_len > DATA_SPACE_SIZE as i128 {
+            return Err("Data space overflow".to_string());
+        }
+        self.memory.resize(new_len as usize, 0);
+        Ok(())
+    }
+
+    fn align(&mut self) -> Result<(), String> {
+        let cell_size = self.cell_width.bytes() as usize;
+        let padding = self.memory.len().next_multiple_of(cell_size) - self.memory.len();
+        self.allot(padding as Cell)
+    }
+}
+
+/// A Forth flag: all bits set for true
+fn flag(condition: bool) -> Cell {
+    if condition { -1 } else { 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::{IRBuilder, IRLabel};
+
+    fn program_with(name: &str, instructions: Vec<IRInstruction>) -> IRProgram {
+        let mut builder = IRBuilder::new("main");
+        builder.start_function(name);
+        for instruction in instructions {
+            builder.emit(instruction);
+        }
+        builder.start_function("main");
+        builder.build()
+    }
+
+    #[test]
+    fn test_interpreter_runs_loops() {
+        // 0 5 0 DO I + LOOP
+        let start = IRLabel::new("loop_start", 0);
+        let end = IRLabel::new("loop_end", 1);
+        let program = program_with(
+            "SUM",
+            vec![
+                IRInstruction::Push(IRValue::Constant(0)),
+                IRInstruction::Push(IRValue::Constant(5)),
+                IRInstruction::Push(IRValue::Constant(0)),
+                IRInstruction::DoLoop(start.clone(), end.clone()),
+                IRInstruction::Label(start.clone()),
+                IRInstruction::PushLoopIndex,
+                IRInstruction::Add,
+                IRInstruction::Loop(start),
+                IRInstruction::Label(end),
+                IRInstruction::Return,
+            ],
+        );
+
+        let mut interpreter = IRInterpreter::new(CellWidth::Bits64);
+        interpreter.run(&program, "SUM").unwrap();
+        assert_eq!(interpreter.stack(), &[10]);
+    }
+
+    #[test]
+    fn test_interpreter_gives_each_call_its_own_locals() {
+        // : COUNTDOWN {: n :} n IF n 1 - RECURSE n THEN ;
+        let end = IRLabel::new("endif", 0);
+        let program = program_with(
+            "COUNTDOWN",
+            vec![
+                IRInstruction::StackAlloc(1),
+                IRInstruction::StackSet(0),
+                IRInstruction::StackGet(0),
+                IRInstruction::JumpIfNot(end.clone()),
+                IRInstruction::StackGet(0),
+                IRInstruction::Push(IRValue::Constant(1)),
+                IRInstruction::Sub,
+                IRInstruction::Call("COUNTDOWN".to_string()),
+                IRInstruction::StackGet(0),
+                IRInstruction::Label(end),
+                IRInstruction::Return,
+            ],
+        );
+
+        let mut interpreter = IRInterpreter::new(CellWidth::Bits64);
+        interpreter.push(3);
+        interpreter.run(&program, "COUNTDOWN").unwrap();
+        assert_eq!(interpreter.stack(), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn test_interpreter_reaches_into_the_stack() {
+        // 1 2 3 2 PICK 3 ROLL 0 ?DUP 6 ?DUP 6 3 XOR
+        let program = program_with(
+            "REACH",
+            vec![
+                IRInstruction::Push(IRValue::Constant(1)),
+                IRInstruction::Push(IRValue::Constant(2)),
+                IRInstruction::Push(IRValue::Constant(3)),
+                IRInstruction::Push(IRValue::Constant(2)),
+                IRInstruction::Pick,
+                IRInstruction::Push(IRValue::Constant(3)),
+                IRInstruction::Roll,
+                IRInstruction::Push(IRValue::Constant(0)),
+                IRInstruction::DupIfNonZero,
+                IRInstruction::Push(IRValue::Constant(6)),
+                IRInstruction::DupIfNonZero,
+                IRInstruction::Push(IRValue::Constant(6)),
+                IRInstruction::Push(IRValue::Constant(3)),
+                IRInstruction::Xor,
+                IRInstruction::Return,
+            ],
+        );
+
+        let mut interpreter = IRInterpreter::new(CellWidth::Bits64);
+        interpreter.run(&program, "REACH").unwrap();
+        assert_eq!(interpreter.stack(), &[2, 3, 1, 1, 0, 6, 6, 5]);
+
+        let program = program_with(
+            "TOO-DEEP",
+            vec![
+                IRInstruction::Push(IRValue::Constant(1)),
+                IRInstruction::Push(IRValue::Constant(1)),
+                IRInstruction::Roll,
+                IRInstruction::Return,
+            ],
+        );
+        let mut interpreter = IRInterpreter::new(CellWidth::Bits64);
+        assert_eq!(
+            interpreter.run(&program, "TOO-DEEP").unwrap_err(),
+            "Stack underflow"
+        );
+    }
+
+    #[test]
+    fn test_interpreter_divides_doubles() {
+        // -7. 2 FM/MOD  -7. 2 SM/REM  with -7. taking a cell pair at each width
+        let program = program_with(
+            "DIVIDE",
+            vec![
+                IRInstruction::Push(IRValue::Constant(-7)),
+                IRInstruction::Push(IRValue::Constant(-1)),
+                IRInstruction::Push(IRValue::Constant(2)),
+                IRInstruction::DivModFloored,
+                IRInstruction::Push(IRValue::Constant(-7)),
+                IRInstruction::Push(IRValue::Constant(-1)),
+                IRInstruction::Push(IRValue::Constant(2)),
+                IRInstruction::DivRemSymmetric,
+            ],
+        );
+
+        for width in [CellWidth::Bits32, CellWidth::Bits64] {
+            let mut interpreter = IRInterpreter::new(width);
+            interpreter.run(&program, "DIVIDE").unwrap();
+            assert_eq!(interpreter.stack(), &[1, -4, -1, -3]);
+        }
+    }
+
+    #[test]
+    fn test_interpreter_collects_compiled_words() {
+        let program = program_with(
+            "MACRO",
+            vec![
+                IRInstruction::Push(IRValue::Constant(7)),
+                IRInstruction::CompileLiteral,
+                IRInstruction::CompileWord("DUP".to_string()),
+            ],
+        );
+
+        let mut interpreter = IRInterpreter::new(CellWidth::Bits64);
+        let compiled = interpreter.run(&program, "MACRO").unwrap();
+        assert_eq!(
+            compiled,
+            vec![Compiled::Literal(7), Compiled::Word("DUP".to_string())]
+        );
+        assert!(interpreter.stack().is_empty());
+    }
+
+    #[test]
+    fn test_interpreter_reports_errors() {
+        let program = program_with("BAD", vec![IRInstruction::Drop]);
+        let mut interpreter = IRInterpreter::new(CellWidth::Bits64);
+        assert_eq!(
+            interpreter.run(&program, "BAD"),
+            Err("Stack underflow".to_string())
+        );
+        assert_eq!(
+            interpreter.run(&program, "MISSING"),
+            Err("Undefined word: MISSING".to_string())
+        );
+    }
+}
@@ -1,5 +1,9 @@
+use crate::diagnostics::{Warning, WarningKind};
 use crate::ir::{IRBuilder, IRFunction, IRInstruction, IRLabel, IRProgram, IRValue, StackEffect};
-use crate::types::AstNode;
+use crate::types::{
+    AstNode, BeginKind, Cell, CellWidth, Float, ParseError, Position, StackSignature, VariableKind,
+};
+use roth_runtime::{BASE_ADDRESS, throw_code};
 use std::collections::{HashMap, HashSet};
 
 /// Lowers AST to IR
@@ -9,12 +13,20 @@ pub struct IRLowering {
     /// Words known to exist externally (from previous REPL sessions).
     /// These generate Call instructions but don't create IR functions.
     external_words: HashSet<String>,
-    loop_stack: Vec<(IRLabel, IRLabel)>, // (loop_start_label, loop_end_label)
-    conditional_stack: Vec<(Option<IRLabel>, IRLabel)>, // (else_label, endif_label)
-    in_definition: bool,                 // Track if we're inside a colon definition (compile mode)
+    loop_ends: Vec<IRLabel>, // End labels of enclosing DO loops, innermost last
+    in_definition: bool,     // Track if we're inside a colon definition (compile mode)
     current_definition_name: Option<String>, // Track current definition name for RECURSE
-    variables: HashMap<String, i32>,     // Map variable names to addresses
-    next_variable_address: i32,
+    locals: HashMap<String, usize>, // Frame slot of each local of the current definition
+    return_depth: usize,     // Cells the current definition has moved to the return stack with >R
+    data_fields: HashSet<String>, // Names given a data space address by CREATE or VARIABLE
+    values: HashSet<String>, // Data fields that push their contents rather than their address
+    constants: HashMap<String, Vec<Cell>>, // CONSTANT and 2CONSTANT cells, lowest first
+    stored_constants: HashMap<String, usize>, // Constants set as the program runs -> their cells
+    does_behaviors: HashMap<String, String>, // Defining word -> function holding its DOES> body
+    does_instances: HashMap<String, String>, // Word created by a defining word -> its DOES> function
+    wrappers: HashMap<String, IRFunction>, // Functions running words ' takes that aren't definitions
+    statement_starts: Vec<(usize, Position)>, // Main function index of each top-level statement
+    cell_width: CellWidth,
 }
 
 impl IRLowering {
@@ -23,15 +35,29 @@ impl IRLowering {
             builder: IRBuilder::new("main"),
             word_definitions: HashMap::new(),
             external_words: HashSet::new(),
-            loop_stack: Vec::new(),
-            conditional_stack: Vec::new(),
+            loop_ends: Vec::new(),
             in_definition: false,
             current_definition_name: None,
-            variables: HashMap::new(),
-            next_variable_address: 0,
+            locals: HashMap::new(),
+            return_depth: 0,
+            data_fields: HashSet::new(),
+            values: HashSet::new(),
+            constants: HashMap::new(),
+            stored_constants: HashMap::new(),
+            does_behaviors: HashMap::new(),
+            does_instances: HashMap::new(),
+            wrappers: HashMap::new(),
+            statement_starts: Vec::new(),
+            cell_width: CellWidth::default(),
         }
     }
 
+    /// Lower for a target whose cells are `cell_width` wide
+    pub fn with_cell_width(mut self, cell_width: CellWidth) -> Self {
+        self.cell_width = cell_width;
+        self
+    }
+
     /// Register a known user-defined word from a previous REPL session.
     /// This allows the IR lowering to generate Call instructions for these words,
     /// but does NOT create IR functions for them (they exist externally).
@@ -39,22 +65,90 @@ impl IRLowering {
         self.external_words.insert(name.into());
     }
 
-    /// Register a known variable.
-    /// This allows the IR lowering to handle variables defined in previous
-    /// REPL sessions.
-    pub fn add_known_variable(&mut self, name: impl Into<String>) {
+    /// Register a word created by CREATE or VARIABLE in a previous REPL
+    /// session, whose address lives in the runtime's data space.
+    pub fn add_known_data_field(&mut self, name: impl Into<String>) {
+        self.data_fields.insert(name.into());
+    }
+
+    /// Register a VALUE from a previous REPL session.
+    pub fn add_known_value(&mut self, name: impl Into<String>) {
         let name = name.into();
-        if !self.variables.contains_key(&name) {
-            let addr = self.next_variable_address;
-            self.next_variable_address += 1;
-            self.variables.insert(name, addr);
-        }
+        self.data_fields.insert(name.clone());
+        self.values.insert(name);
+    }
+
+    /// Register a CONSTANT or 2CONSTANT from a previous REPL session.
+    pub fn add_known_constant(&mut self, name: impl Into<String>, cells: Vec<Cell>) {
+        self.constants.insert(name.into(), cells);
+    }
+
+    /// Register a CONSTANT or 2CONSTANT from a previous REPL session whose
+    /// `cells` cells were taken from the stack and kept in data space.
+    pub fn add_known_stored_constant(&mut self, name: impl Into<String>, cells: usize) {
+        let name = name.into();
+        self.data_fields.insert(name.clone());
+        self.stored_constants.insert(name, cells);
+    }
+
+    /// Register a defining word with a DOES> part from a previous REPL
+    /// session, so words it creates later run that part.
+    pub fn add_known_does_behavior(
+        &mut self,
+        word: impl Into<String>,
+        function: impl Into<String>,
+    ) {
+        self.does_behaviors.insert(word.into(), function.into());
+    }
+
+    /// Register a word created by a defining word with a DOES> part in a
+    /// previous REPL session.
+    pub fn add_known_does_instance(
+        &mut self,
+        name: impl Into<String>,
+        function: impl Into<String>,
+    ) {
+        let name = name.into();
+        self.data_fields.insert(name.clone());
+        self.does_instances.insert(name, function.into());
+    }
+
+    /// Defining words seen so far that have a DOES> part, with the function
+    /// holding it.
+    pub fn does_behaviors(&self) -> &HashMap<String, String> {
+        &self.does_behaviors
+    }
+
+    /// Words created by defining words with a DOES> part, with the function
+    /// each one runs.
+    pub fn does_instances(&self) -> &HashMap<String, String> {
+        &self.does_instances
+    }
+
+    /// Constants seen so far, with the cells each one pushes.
+    pub fn constants(&self) -> &HashMap<String, Vec<Cell>> {
+        &self.constants
+    }
+
+    /// Constants seen so far whose value was taken from the stack, with the
+    /// cells each one holds.
+    pub fn stored_constants(&self) -> &HashMap<String, usize> {
+        &self.stored_constants
+    }
+
+    /// Where each top-level statement starts in the main function, by
+    /// instruction index, with its position in the source.
+    pub fn statement_starts(&self) -> &[(usize, Position)] {
+        &self.statement_starts
     }
 
     pub fn lower(&mut self, ast: &AstNode) -> IRProgram {
         self.lower_node(ast);
         let builder = std::mem::replace(&mut self.builder, IRBuilder::new("temp"));
-        builder.build()
+        let mut program = builder.build();
+        program.cell_width = self.cell_width;
+        program.wrappers = std::mem::take(&mut self.wrappers);
+        program
     }
 
     fn lower_node(&mut self, node: &AstNode) {
@@ -62,10 +156,49 @@ impl IRLowering {
             AstNode::Program(nodes) => {
                 self.builder.emit_comment("Generated from Forth AST");
 
-                // First pass: collect definitions
+                // First pass: collect definitions, data-field names and
+                // constants, so words can refer to names defined at the top
+                // level. The last definition of a name is the one they see.
                 for node in nodes {
-                    if let AstNode::Definition { name, body, .. } = node {
-                        self.word_definitions.insert(name.clone(), body.clone());
+                    if let Some(name) = node.defined_name() {
+                        self.forget(name);
+                    }
+                    match node {
+                        AstNode::Definition { name, body, .. } => {
+                            self.word_definitions.insert(name.clone(), body.clone());
+                            if matches!(body.last(), Some(AstNode::Does { .. })) {
+                                self.does_behaviors
+                                    .insert(name.clone(), Self::does_function_name(name));
+                            } else {
+                                self.does_behaviors.remove(name);
+                            }
+                        }
+                        AstNode::DefinedBy { word, name, .. } => {
+                            self.register_defined_by(word, name);
+                        }
+                        AstNode::VariableDeclaration { name, .. }
+                        | AstNode::Create { name, .. } => {
+                            self.data_fields.insert(name.clone());
+                        }
+                        AstNode::Value { name, .. } => {
+                            self.add_known_value(name.clone());
+                        }
+                        AstNode::Defer { name, position } => {
+                            // Same as : name ACTION-OF name EXECUTE ;
+                            let body = vec![
+                                AstNode::ActionOf {
+                                    name: name.clone(),
+                                    position: position.clone(),
+                                },
+                                AstNode::Word("EXECUTE".to_string(), position.clone()),
+                            ];
+                            self.word_definitions.insert(name.clone(), body);
+                            self.does_behaviors.remove(name);
+                        }
+                        AstNode::Constant {
+                            name, value, cells, ..
+                        } => self.register_constant(name, value, *cells),
+                        _ => {}
                     }
                 }
 
@@ -74,47 +207,289 @@ impl IRLowering {
                     self.lower_definition(name, body);
                 }
 
-                // Third pass: generate main program (non-definitions)
+                // Third pass: generate main program (non-definitions), in
+                // which a name means what it was last defined as
                 for node in nodes {
+                    if let Some(name) = node.defined_name() {
+                        self.forget(name);
+                    }
                     if !matches!(node, AstNode::Definition { .. }) {
+                        if let Some(position) = node.position() {
+                            self.statement_starts
+                                .push((self.builder.next_index(), position.clone()));
+                        }
                         self.lower_node(node);
                     }
                 }
             }
             AstNode::Number(n, _) => {
                 self.builder.emit_comment(&format!("Push constant {}", n));
+                self.builder.emit(IRInstruction::Push(IRValue::Constant(
+                    self.cell_width.wrap(*n),
+                )));
+            }
+            AstNode::DoubleNumber(n, _) => {
                 self.builder
-                    .emit(IRInstruction::Push(IRValue::Constant(*n)));
+                    .emit_comment(&format!("Push double constant {}", n));
+                for cell in self.cell_width.split(*n) {
+                    self.builder
+                        .emit(IRInstruction::Push(IRValue::Constant(cell)));
+                }
+            }
+            AstNode::Float(r, _) => {
+                self.builder.emit_comment(&format!("Push float {:?}", r));
+                self.builder.emit(IRInstruction::PushFloat(*r));
             }
             AstNode::Word(name, _) => {
                 self.lower_word(name);
             }
             AstNode::StringLiteral(s, _) => {
+                self.builder.emit(IRInstruction::PushString(s.clone()));
+            }
+            AstNode::DotQuote(s, _) => {
+                self.builder.emit(IRInstruction::PushString(s.clone()));
+                self.builder.emit(IRInstruction::PrintString);
+            }
+            AstNode::CQuote(s, _) => {
+                self.builder
+                    .emit(IRInstruction::PushCountedString(s.clone()));
+            }
+            AstNode::Definition { .. } => {
+                // Definitions are handled in the Program case
+            }
+            AstNode::VariableDeclaration { name, kind, .. } => {
+                // Same as CREATE name 0 , with as many 0 , as the value takes
+                let cells = match kind {
+                    VariableKind::Cell => 1,
+                    VariableKind::Double => 2,
+                    VariableKind::Float => {
+                        std::mem::size_of::<Float>() as Cell / self.cell_width.bytes()
+                    }
+                };
                 self.builder
-                    .emit_comment(&format!("String literal: \"{}\"", s));
-                // Push each character of the string onto the stack
-                for ch in s.chars() {
+                    .emit_comment(&format!("{} {}", kind.defining_word(), name));
+                self.data_fields.insert(name.clone());
+                self.builder.emit(IRInstruction::Create(name.clone()));
+                for _ in 0..cells {
+                    self.builder.emit(IRInstruction::Push(IRValue::Constant(0)));
+                    self.builder.emit(IRInstruction::Comma);
+                }
+            }
+            AstNode::Does { .. } => {
+                // Split out of the definition's body by lower_definition
+            }
+            AstNode::DefinedBy { word, name, .. } => {
+                // Run the defining word with the name its CREATE should use
+                self.builder.emit_comment(&format!("{} {}", word, name));
+                self.register_defined_by(word, name);
+                self.builder.emit(IRInstruction::SupplyName(name.clone()));
+                self.builder.emit(IRInstruction::Call(word.clone()));
+            }
+            AstNode::Constant {
+                name, value, cells, ..
+            } => {
+                self.register_constant(name, value, *cells);
+                if value.is_empty() {
+                    // Same as CREATE name , with as many , as it has cells,
+                    // but the name fetches when used
+                    self.builder.emit_comment(&format!("CONSTANT {}", name));
+                    self.builder.emit(IRInstruction::Create(name.clone()));
+                    for _ in 0..*cells {
+                        self.builder.emit(IRInstruction::Comma);
+                    }
+                } else {
+                    // References fold to the value, so nothing runs here
                     self.builder
-                        .emit(IRInstruction::Push(IRValue::Constant(ch as i32)));
+                        .emit_comment(&format!("CONSTANT {} = {:?}", name, self.constants[name]));
+                }
+            }
+            AstNode::Value { name, .. } => {
+                // Same as CREATE name , but the name fetches when used
+                self.builder.emit_comment(&format!("VALUE {}", name));
+                self.add_known_value(name.clone());
+                self.builder.emit(IRInstruction::Create(name.clone()));
+                self.builder.emit(IRInstruction::Comma);
+            }
+            AstNode::To { name, .. } => {
+                self.builder.emit_comment(&format!("TO {}", name));
+                self.builder
+                    .emit(IRInstruction::PushDataField(name.clone()));
+                self.builder.emit(IRInstruction::Store(IRValue::StackTop));
+            }
+            AstNode::Defer { name, .. } => {
+                // The word itself is made in the first pass; its execution
+                // token starts out as 0, which no word has
+                self.builder.emit_comment(&format!("DEFER {}", name));
+                self.builder
+                    .emit(IRInstruction::Create(Self::action_field_name(name)));
+                self.builder.emit(IRInstruction::Push(IRValue::Constant(0)));
+                self.builder.emit(IRInstruction::Comma);
+            }
+            AstNode::Is { name, .. } => {
+                self.builder.emit_comment(&format!("IS {}", name));
+                self.builder
+                    .emit(IRInstruction::PushDataField(Self::action_field_name(name)));
+                self.builder.emit(IRInstruction::Store(IRValue::StackTop));
+            }
+            AstNode::ActionOf { name, .. } => {
+                self.builder.emit_comment(&format!("ACTION-OF {}", name));
+                self.builder
+                    .emit(IRInstruction::PushDataField(Self::action_field_name(name)));
+                self.builder.emit(IRInstruction::Load(IRValue::StackTop));
+            }
+            AstNode::Tick { name, .. } => {
+                self.builder
+                    .emit_comment(&format!("Execution token of {}", name));
+                self.lower_tick(name);
+            }
+            AstNode::Locals {
+                names, initialized, ..
+            } => {
+                // Every call gets its own slots, so recursion keeps them apart
+                self.builder
+                    .emit_comment(&format!("Locals {}", names.join(" ")));
+                self.locals = names
+                    .iter()
+                    .enumerate()
+                    .map(|(slot, name)| (name.clone(), slot))
+                    .collect();
+                self.builder.emit(IRInstruction::StackAlloc(names.len()));
+                // The last initialized local takes the top of the stack
+                for slot in (0..*initialized).rev() {
+                    self.builder.emit(IRInstruction::StackSet(slot));
                 }
-                // Push string length
+            }
+            AstNode::Local { name, .. } => {
+                self.builder.emit_comment(&format!("Local {}", name));
                 self.builder
-                    .emit(IRInstruction::Push(IRValue::Constant(s.len() as i32)));
+                    .emit(IRInstruction::StackGet(self.locals[name]));
             }
-            AstNode::Definition { .. } => {
-                // Definitions are handled in the Program case
+            AstNode::ToLocal { name, .. } => {
+                self.builder.emit_comment(&format!("TO local {}", name));
+                self.builder
+                    .emit(IRInstruction::StackSet(self.locals[name]));
+            }
+            AstNode::AbortQuote(text, _) => {
+                self.builder.emit(IRInstruction::AbortQuote(text.clone()));
+            }
+            AstNode::Postpone { name, .. } => {
+                // Runs inside the compiler, when the IMMEDIATE word it is in runs
+                self.builder.emit_comment(&format!("POSTPONE {}", name));
+                if name == "LITERAL" {
+                    self.builder.emit(IRInstruction::CompileLiteral);
+                } else {
+                    self.builder.emit(IRInstruction::CompileWord(name.clone()));
+                }
+            }
+            AstNode::Create { name, .. } => {
+                self.builder.emit_comment(&format!("CREATE {}", name));
+                self.data_fields.insert(name.clone());
+                self.builder.emit(IRInstruction::Create(name.clone()));
             }
-            AstNode::VariableDeclaration { name, .. } => {
-                // Allocate a new variable address
-                let addr = self.next_variable_address;
-                self.next_variable_address += 1;
-                self.variables.insert(name.clone(), addr);
+            AstNode::If {
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                self.builder.emit_comment("IF conditional");
+                let else_label = self.builder.create_label("else");
+                let endif_label = self.builder.create_label("endif");
+
+                // Jump to else/endif if top of stack is false (0)
+                self.builder
+                    .emit(IRInstruction::JumpIfNot(else_label.clone()));
+                let return_depth = self.return_depth;
+                self.lower_body(then_branch);
+
+                if let Some(else_branch) = else_branch {
+                    self.builder.emit_comment("ELSE");
+                    // Jump to endif (skip else part)
+                    self.builder.emit(IRInstruction::Jump(endif_label.clone()));
+                    self.builder.emit_label(else_label);
+                    // Both branches start with what was on the return stack
+                    let then_return_depth = std::mem::replace(&mut self.return_depth, return_depth);
+                    self.lower_body(else_branch);
+                    self.return_depth = then_return_depth;
+                } else {
+                    self.builder.emit_label(else_label);
+                }
+
+                self.builder.emit_comment("THEN (endif)");
+                self.builder.emit_label(endif_label);
+            }
+            AstNode::DoLoop {
+                body,
+                conditional,
+                plus_loop,
+                ..
+            } => {
+                self.builder.emit_comment(if *conditional {
+                    "Conditional DO loop"
+                } else {
+                    "DO loop"
+                });
+                let loop_start = self.builder.create_label("loop_start");
+                let loop_end = self.builder.create_label("loop_end");
+
+                // DO shares ?DO's entry check so that an empty range doesn't
+                // run through the whole cell range before reaching the limit
                 self.builder
-                    .emit_comment(&format!("VARIABLE {} allocated at address {}", name, addr));
+                    .emit(IRInstruction::DoLoop(loop_start.clone(), loop_end.clone()));
+                self.builder.emit_label(loop_start.clone());
+
+                self.loop_ends.push(loop_end.clone());
+                self.lower_body(body);
+                self.loop_ends.pop();
+
+                if *plus_loop {
+                    // Add the step to the index and jump back unless the limit was crossed
+                    self.builder.emit_comment("+LOOP");
+                    self.builder.emit(IRInstruction::PlusLoop(loop_start));
+                } else {
+                    // Increment loop index and jump back until it reaches the limit
+                    self.builder.emit_comment("LOOP");
+                    self.builder.emit(IRInstruction::Loop(loop_start));
+                }
+                self.builder.emit_label(loop_end);
+            }
+            AstNode::Begin { body, kind, .. } => {
+                self.builder.emit_comment("BEGIN loop");
+                let begin_label = self.builder.create_label("begin");
+                self.builder.emit_label(begin_label.clone());
+                self.lower_body(body);
+
+                match kind {
+                    BeginKind::Until => {
+                        // Loop back while the flag is false
+                        self.builder.emit_comment("UNTIL");
+                        self.builder.emit(IRInstruction::JumpIfNot(begin_label));
+                    }
+                    BeginKind::Again => {
+                        self.builder.emit_comment("AGAIN");
+                        self.builder.emit(IRInstruction::Jump(begin_label));
+                    }
+                    BeginKind::WhileRepeat(loop_body) => {
+                        // Leave the loop when the flag is false
+                        self.builder.emit_comment("WHILE");
+                        let exit_label = self.builder.create_label("repeat_end");
+                        self.builder
+                            .emit(IRInstruction::JumpIfNot(exit_label.clone()));
+                        self.lower_body(loop_body);
+                        self.builder.emit_comment("REPEAT");
+                        self.builder.emit(IRInstruction::Jump(begin_label));
+                        self.builder.emit_label(exit_label);
+                    }
+                }
             }
         }
     }
 
+    fn lower_body(&mut self, body: &[AstNode]) {
+        for node in body {
+            self.lower_node(node);
+        }
+    }
+
     fn lower_definition(&mut self, name: &str, body: &[AstNode]) {
         // Start a new function for this definition
         self.builder.start_function(name);
@@ -123,21 +498,123 @@ impl IRLowering {
         // Set compile mode flag and track current definition name for RECURSE
         let was_in_definition = self.in_definition;
         let prev_definition_name = self.current_definition_name.take();
+        let prev_return_depth = std::mem::take(&mut self.return_depth);
         self.in_definition = true;
         self.current_definition_name = Some(name.to_string());
 
-        for node in body {
-            self.lower_node(node);
+        // A DOES> part becomes a function of its own, run by every word this
+        // one defines after their data field address is pushed
+        match body.split_last() {
+            Some((
+                AstNode::Does {
+                    body: does_body, ..
+                },
+                create_part,
+            )) => {
+                self.lower_body(create_part);
+                self.builder.emit(IRInstruction::Return);
+
+                let does_name = Self::does_function_name(name);
+                self.builder.start_function(&does_name);
+                self.builder
+                    .emit_comment(&format!("DOES> part of {}", name));
+                self.current_definition_name = Some(does_name);
+                self.return_depth = 0;
+                self.lower_body(does_body);
+                self.builder.emit(IRInstruction::Return);
+            }
+            _ => {
+                self.lower_body(body);
+                self.builder.emit(IRInstruction::Return);
+            }
         }
 
-        self.builder.emit(IRInstruction::Return);
-
         // Restore previous mode and switch back to main function
+        self.locals.clear();
+        self.return_depth = prev_return_depth;
         self.in_definition = was_in_definition;
         self.current_definition_name = prev_definition_name;
         self.builder.start_function("main");
     }
 
+    /// Name of the function holding the DOES> part of defining word `word`
+    fn does_function_name(word: &str) -> String {
+        format!("__DOES_{}", word)
+    }
+
+    /// Name of the data field holding the execution token DEFERred word
+    /// `word` runs
+    fn action_field_name(word: &str) -> String {
+        format!("ACTION-OF {}", word)
+    }
+
+    /// Push the execution token of `name`. Anything but a definition is run
+    /// by a wrapper function made here, doing what using the name would.
+    fn lower_tick(&mut self, name: &str) {
+        let is_data = self.constants.contains_key(name)
+            || self.does_instances.contains_key(name)
+            || self.data_fields.contains(name);
+        let is_definition =
+            self.word_definitions.contains_key(name) || self.external_words.contains(name);
+        if (is_data || !is_definition) && !self.wrappers.contains_key(name) {
+            let main =
+                std::mem::replace(&mut self.builder, IRBuilder::new(&format!("__XT_{}", name)));
+            self.lower_word(name);
+            self.builder.emit(IRInstruction::Return);
+            let wrapper = std::mem::replace(&mut self.builder, main).build().main;
+            self.wrappers.insert(name.to_string(), wrapper);
+        }
+        self.builder.emit(IRInstruction::PushXt(name.to_string()));
+    }
+
+    /// Record `name` as created by `word`, running its DOES> part if any
+    fn register_defined_by(&mut self, word: &str, name: &str) {
+        self.data_fields.insert(name.to_string());
+        match self.does_behaviors.get(word) {
+            Some(function) => {
+                self.does_instances
+                    .insert(name.to_string(), function.clone());
+            }
+            None => {
+                self.does_instances.remove(name);
+            }
+        }
+    }
+
+    /// Forget what `name` was defined as, before it is defined again
+    fn forget(&mut self, name: &str) {
+        self.constants.remove(name);
+        self.stored_constants.remove(name);
+        self.values.remove(name);
+        self.data_fields.remove(name);
+        self.does_instances.remove(name);
+    }
+
+    /// Record the constant `name`, with literal `value`, or else `cells`
+    /// cells taken from the stack when it is defined
+    fn register_constant(&mut self, name: &str, value: &[AstNode], cells: usize) {
+        if value.is_empty() {
+            self.data_fields.insert(name.to_string());
+            self.stored_constants.insert(name.to_string(), cells);
+        } else {
+            let cells = self.literal_cells(value);
+            self.constants.insert(name.to_string(), cells);
+        }
+    }
+
+    /// Cells pushed by the literal nodes of a constant definition
+    fn literal_cells(&self, literals: &[AstNode]) -> Vec<Cell> {
+        let mut cells = Vec::new();
+        for literal in literals {
+            match literal {
+                AstNode::Number(n, _) => cells.push(self.cell_width.wrap(*n)),
+                AstNode::DoubleNumber(n, _) => cells.extend(self.cell_width.split(*n)),
+                _ => {}
+            }
+        }
+        cells
+    }
+
     fn lower_word(&mut self, name: &str) {
         match name {
             // Arithmetic operations
@@ -165,6 +642,108 @@ impl IRLowering {
                 self.builder.emit_comment("Negate");
                 self.builder.emit(IRInstruction::Neg);
             }
+            "2+" | "2-" => {
+                self.builder.emit_comment(name);
+                self.builder.emit(IRInstruction::Push(IRValue::Constant(2)));
+                self.builder.emit(if name == "2+" {
+                    IRInstruction::Add
+                } else {
+                    IRInstruction::Sub
+                });
+            }
+            "ABS" => {
+                self.builder.emit_comment("ABS - negate if negative");
+                let endif_label = self.builder.create_label("endif");
+                self.builder.emit(IRInstruction::Dup);
+                self.builder.emit(IRInstruction::Push(IRValue::Constant(0)));
+                self.builder.emit(IRInstruction::Less);
+                self.builder
+                    .emit(IRInstruction::JumpIfNot(endif_label.clone()));
+                self.builder.emit(IRInstruction::Neg);
+                self.builder.emit_label(endif_label);
+            }
+            // MIN is 2DUP > IF SWAP THEN DROP, and MAX the same with <
+            "MIN" | "MAX" => {
+                self.builder
+                    .emit_comment(&format!("{} - keep one of two", name));
+                let endif_label = self.builder.create_label("endif");
+                self.builder.emit(IRInstruction::Over);
+                self.builder.emit(IRInstruction::Over);
+                self.builder.emit(if name == "MIN" {
+                    IRInstruction::Greater
+                } else {
+                    IRInstruction::Less
+                });
+                self.builder
+                    .emit(IRInstruction::JumpIfNot(endif_label.clone()));
+                self.builder.emit(IRInstruction::Swap);
+                self.builder.emit_label(endif_label);
+                self.builder.emit(IRInstruction::Drop);
+            }
+            // /MOD ( n1 n2 -- rem quot ) is 2DUP MOD ROT ROT /
+            "/MOD" => {
+                self.builder.emit_comment("/MOD - remainder and quotient");
+                self.builder.emit(IRInstruction::Over);
+                self.builder.emit(IRInstruction::Over);
+                self.builder.emit(IRInstruction::Mod);
+                self.builder.emit(IRInstruction::Rot);
+                self.builder.emit(IRInstruction::Rot);
+                self.builder.emit(IRInstruction::Div);
+            }
+
+            // Double-cell and mixed-precision arithmetic
+            "D+" => {
+                self.builder.emit_comment("Double addition");
+                self.builder.emit(IRInstruction::DAdd);
+            }
+            "D-" => {
+                self.builder.emit_comment("Double subtraction");
+                self.builder.emit(IRInstruction::DSub);
+            }
+            "DNEGATE" => {
+                self.builder.emit_comment("Negate double");
+                self.builder.emit(IRInstruction::DNeg);
+            }
+            "M*" => {
+                self.builder
+                    .emit_comment("Signed multiplication to a double");
+                self.builder.emit(IRInstruction::MulMixed);
+            }
+            "UM*" => {
+                self.builder
+                    .emit_comment("Unsigned multiplication to a double");
+                self.builder.emit(IRInstruction::MulUnsigned);
+            }
+            "UM/MOD" => {
+                self.builder
+                    .emit_comment("Unsigned double division with remainder");
+                self.builder.emit(IRInstruction::DivModUnsigned);
+            }
+            "SM/REM" => {
+                self.builder
+                    .emit_comment("Symmetric double division with remainder");
+                self.builder.emit(IRInstruction::DivRemSymmetric);
+            }
+            "FM/MOD" => {
+                self.builder
+                    .emit_comment("Floored double division with remainder");
+                self.builder.emit(IRInstruction::DivModFloored);
+            }
+            // */MOD ( n1 n2 n3 -- rem quot ) is ROT ROT M* ROT SM/REM, so the
+            // product keeps its full width; division truncates, as / does
+            "*/MOD" | "*/" => {
+                self.builder
+                    .emit_comment(&format!("{} - scale through a double", name));
+                self.builder.emit(IRInstruction::Rot);
+                self.builder.emit(IRInstruction::Rot);
+                self.builder.emit(IRInstruction::MulMixed);
+                self.builder.emit(IRInstruction::Rot);
+                self.builder.emit(IRInstruction::DivRemSymmetric);
+                if name == "*/" {
+                    self.builder.emit(IRInstruction::Swap);
+                    self.builder.emit(IRInstruction::Drop);
+                }
+            }
 
             // Stack operations
             "DUP" => {
@@ -187,6 +766,84 @@ impl IRLowering {
                 self.builder.emit_comment("Rotate top three stack items");
                 self.builder.emit(IRInstruction::Rot);
             }
+            "NIP" => {
+                self.builder.emit_comment("Drop second stack item");
+                self.builder.emit(IRInstruction::Swap);
+                self.builder.emit(IRInstruction::Drop);
+            }
+            "TUCK" => {
+                self.builder
+                    .emit_comment("Copy top of stack below second item");
+                self.builder.emit(IRInstruction::Swap);
+                self.builder.emit(IRInstruction::Over);
+            }
+            "2DUP" => {
+                self.builder.emit_comment("Duplicate top two stack items");
+                self.builder.emit(IRInstruction::Over);
+                self.builder.emit(IRInstruction::Over);
+            }
+            "2DROP" => {
+                self.builder.emit_comment("Drop top two stack items");
+                self.builder.emit(IRInstruction::Drop);
+                self.builder.emit(IRInstruction::Drop);
+            }
+            // 2OVER is 3 PICK 3 PICK, and 2SWAP is 3 ROLL 3 ROLL
+            "2OVER" | "2SWAP" => {
+                self.builder
+                    .emit_comment(&format!("{} - pairs of items", name));
+                let reach = if name == "2OVER" {
+                    IRInstruction::Pick
+                } else {
+                    IRInstruction::Roll
+                };
+                for _ in 0..2 {
+                    self.builder.emit(IRInstruction::Push(IRValue::Constant(3)));
+                    self.builder.emit(reach.clone());
+                }
+            }
+            "PICK" => {
+                self.builder.emit_comment("Copy nth stack item to top");
+                self.builder.emit(IRInstruction::Pick);
+            }
+            "ROLL" => {
+                self.builder.emit_comment("Move nth stack item to top");
+                self.builder.emit(IRInstruction::Roll);
+            }
+            "?DUP" => {
+                self.builder
+                    .emit_comment("Duplicate top of stack if nonzero");
+                self.builder.emit(IRInstruction::DupIfNonZero);
+            }
+
+            // A definition takes back what it moves to the return stack, so
+            // those cells live in frame slots after its locals
+            ">R" => {
+                self.builder.emit_comment(">R - move to return stack");
+                let slot = self.locals.len() + self.return_depth;
+                self.return_depth += 1;
+                self.builder.emit(IRInstruction::StackAlloc(slot + 1));
+                self.builder.emit(IRInstruction::StackSet(slot));
+            }
+            "R>" | "R@" => {
+                self.builder
+                    .emit_comment(&format!("{} - from return stack", name));
+                match self.return_depth.checked_sub(1) {
+                    Some(depth) => {
+                        self.builder
+                            .emit(IRInstruction::StackGet(self.locals.len() + depth));
+                        if name == "R>" {
+                            self.return_depth = depth;
+                        }
+                    }
+                    // Nothing was moved there by this definition
+                    None => {
+                        self.builder.emit(IRInstruction::Push(IRValue::Constant(
+                            throw_code::RETURN_STACK_UNDERFLOW,
+                        )));
+                        self.builder.emit(IRInstruction::Throw);
+                    }
+                }
+            }
 
             // Comparison operations
             "=" => {
@@ -214,6 +871,36 @@ impl IRLowering {
                     .emit_comment("Greater than or equal comparison");
                 self.builder.emit(IRInstruction::GreaterEqual);
             }
+            "0=" | "0<>" | "0<" | "0>" => {
+                self.builder
+                    .emit_comment(&format!("{} - compare with zero", name));
+                self.builder.emit(IRInstruction::Push(IRValue::Constant(0)));
+                self.builder.emit(match name {
+                    "0=" => IRInstruction::Equal,
+                    "0<>" => IRInstruction::NotEqual,
+                    "0<" => IRInstruction::Less,
+                    _ => IRInstruction::Greater,
+                });
+            }
+            // WITHIN ( n lo hi -- flag ) is OVER - >R - R> U<, comparing
+            // unsigned by flipping the sign bits of both sides
+            "WITHIN" => {
+                self.builder.emit_comment("WITHIN - lo <= n < hi");
+                let sign_bit = Cell::MIN >> (Cell::BITS - self.cell_width.bits());
+                self.builder.emit(IRInstruction::Over);
+                self.builder.emit(IRInstruction::Sub);
+                self.builder.emit(IRInstruction::Rot);
+                self.builder.emit(IRInstruction::Rot);
+                self.builder.emit(IRInstruction::Sub);
+                self.builder
+                    .emit(IRInstruction::Push(IRValue::Constant(sign_bit)));
+                self.builder.emit(IRInstruction::Xor);
+                self.builder.emit(IRInstruction::Swap);
+                self.builder
+                    .emit(IRInstruction::Push(IRValue::Constant(sign_bit)));
+                self.builder.emit(IRInstruction::Xor);
+                self.builder.emit(IRInstruction::Less);
+            }
 
             // Logical operations
             "AND" => {
@@ -228,12 +915,62 @@ impl IRLowering {
                 self.builder.emit_comment("Logical NOT");
                 self.builder.emit(IRInstruction::Not);
             }
+            "XOR" => {
+                self.builder.emit_comment("Bitwise XOR");
+                self.builder.emit(IRInstruction::Xor);
+            }
+            "INVERT" => {
+                self.builder.emit_comment("Bitwise NOT");
+                self.builder
+                    .emit(IRInstruction::Push(IRValue::Constant(-1)));
+                self.builder.emit(IRInstruction::Xor);
+            }
 
             // I/O operations
             "." => {
                 self.builder.emit_comment("Print top of stack");
                 self.builder.emit(IRInstruction::Print);
             }
+            "U." => {
+                self.builder.emit_comment("Print unsigned");
+                self.builder.emit(IRInstruction::PrintUnsigned);
+            }
+            ".R" => {
+                self.builder.emit_comment("Print right-aligned");
+                self.builder.emit(IRInstruction::PrintAligned);
+            }
+            "U.R" => {
+                self.builder.emit_comment("Print unsigned right-aligned");
+                self.builder.emit(IRInstruction::PrintUnsignedAligned);
+            }
+            "D." => {
+                self.builder.emit_comment("Print double");
+                self.builder.emit(IRInstruction::PrintDouble);
+            }
+
+            // Pictured numeric output
+            "<#" => self.builder.emit(IRInstruction::HoldStart),
+            "#" => self.builder.emit(IRInstruction::HoldDigit),
+            "#S" => self.builder.emit(IRInstruction::HoldDigits),
+            "HOLD" => self.builder.emit(IRInstruction::Hold),
+            "SIGN" => self.builder.emit(IRInstruction::HoldSign),
+            "#>" => self.builder.emit(IRInstruction::HoldEnd),
+
+            // The radix numbers are printed in; the lexer has already read
+            // the literals that follow HEX, DECIMAL and `n BASE !` with it
+            "BASE" => {
+                self.builder
+                    .emit(IRInstruction::Push(IRValue::Constant(BASE_ADDRESS)));
+            }
+            "HEX" | "DECIMAL" => {
+                let radix = if name == "HEX" { 16 } else { 10 };
+                self.builder.emit_comment(&format!("{} - set BASE", name));
+                self.builder
+                    .emit(IRInstruction::Push(IRValue::Constant(radix)));
+                self.builder
+                    .emit(IRInstruction::Push(IRValue::Constant(BASE_ADDRESS)));
+                self.builder.emit(IRInstruction::Store(IRValue::StackTop));
+            }
             ".S" => {
                 self.builder.emit_comment("Print entire stack");
                 self.builder.emit(IRInstruction::PrintStack);
@@ -253,100 +990,69 @@ impl IRLowering {
                 self.builder.emit(IRInstruction::PrintChar);
             }
 
-            // Control flow operations
-            "?DO" => {
-                self.builder.emit_comment("Conditional DO loop");
-                let loop_start = self.builder.create_label("loop_start");
-                let loop_end = self.builder.create_label("loop_end");
-
-                // ?DO: skip loop if start >= limit
-                self.builder
-                    .emit(IRInstruction::DoLoop(loop_start.clone(), loop_end.clone()));
-                self.builder.emit_label(loop_start.clone());
-
-                // Push this loop onto the stack for LOOP to reference
-                self.loop_stack.push((loop_start, loop_end));
+            // Loop control
+            "LEAVE" => {
+                self.builder.emit_comment("LEAVE");
+                if let Some(loop_end) = self.loop_ends.last().cloned() {
+                    self.builder.emit(IRInstruction::Leave(loop_end));
+                } else {
+                    self.builder.emit_comment("ERROR: LEAVE outside of DO loop");
+                }
             }
-            "DO" => {
-                self.builder.emit_comment("DO loop");
-                let loop_start = self.builder.create_label("loop_start");
-                let loop_end = self.builder.create_label("loop_end");
-
-                // Unconditional DO: always enter the loop
-                // Still consume limit and start from stack
-                self.builder
-                    .emit(IRInstruction::DoLoop(loop_start.clone(), loop_end.clone()));
-                self.builder.emit_label(loop_start.clone());
-
-                self.loop_stack.push((loop_start, loop_end));
+            "UNLOOP" => {
+                self.builder.emit_comment("UNLOOP");
+                if !self.loop_ends.is_empty() {
+                    self.builder.emit(IRInstruction::Unloop);
+                } else {
+                    self.builder
+                        .emit_comment("ERROR: UNLOOP outside of DO loop");
+                }
             }
-            "LOOP" => {
-                self.builder.emit_comment("LOOP");
-                if let Some((loop_start, loop_end)) = self.loop_stack.pop() {
-                    // Increment loop index and jump back if index < limit
-                    self.builder.emit(IRInstruction::Loop(loop_start));
-                    self.builder.emit_label(loop_end);
+            "EXIT" => {
+                if self.in_definition {
+                    self.builder.emit_comment("EXIT");
+                    self.builder.emit(IRInstruction::Return);
                 } else {
-                    // Error: LOOP without matching DO
-                    self.builder.emit_comment("ERROR: LOOP without matching DO");
+                    self.builder
+                        .emit_comment("ERROR: EXIT outside of definition");
                 }
             }
+            "THROW" => {
+                self.builder
+                    .emit_comment("THROW - unwind to CATCH unless 0");
+                self.builder.emit(IRInstruction::Throw);
+            }
+            "ABORT" => {
+                self.builder.emit_comment("ABORT - THROW -1");
+                self.builder.emit(IRInstruction::Abort);
+            }
+            "EXECUTE" => {
+                self.builder
+                    .emit_comment("EXECUTE - run the word an xt names");
+                self.builder.emit(IRInstruction::Execute);
+            }
+            "CATCH" => {
+                self.builder
+                    .emit_comment("CATCH - run an xt, pushing what it throws");
+                self.builder.emit(IRInstruction::Catch);
+            }
             "I" => {
                 self.builder.emit_comment("Loop index I");
                 self.builder.emit(IRInstruction::PushLoopIndex);
             }
             "J" => {
                 self.builder.emit_comment("Loop index J (outer loop)");
-                // For now, just push the current loop index
-                // In a full implementation, this would be the outer loop index
-                self.builder.emit(IRInstruction::PushLoopIndex);
+                self.builder.emit(IRInstruction::PushOuterLoopIndex(1));
             }
-
-            // Conditional control flow
-            "IF" => {
-                self.builder.emit_comment("IF conditional");
-                let else_label = self.builder.create_label("else");
-                let endif_label = self.builder.create_label("endif");
-
-                // Jump to else/endif if top of stack is false (0)
+            "K" => {
                 self.builder
-                    .emit(IRInstruction::JumpIfNot(else_label.clone()));
-
-                // Push conditional info onto stack
-                self.conditional_stack.push((Some(else_label), endif_label));
-            }
-            "ELSE" => {
-                self.builder.emit_comment("ELSE");
-                if let Some((Some(else_label), endif_label)) = self.conditional_stack.pop() {
-                    // Jump to endif (skip else part)
-                    self.builder.emit(IRInstruction::Jump(endif_label.clone()));
-                    // Place else label here
-                    self.builder.emit_label(else_label);
-                    // Update stack with no else label (already used)
-                    self.conditional_stack.push((None, endif_label));
-                } else {
-                    self.builder.emit_comment("ERROR: ELSE without matching IF");
-                }
-            }
-            "THEN" => {
-                self.builder.emit_comment("THEN (endif)");
-                if let Some((else_label, endif_label)) = self.conditional_stack.pop() {
-                    // If there was an unused else label, place it here
-                    if let Some(else_lbl) = else_label {
-                        self.builder.emit_label(else_lbl);
-                    }
-                    // Place endif label
-                    self.builder.emit_label(endif_label);
-                } else {
-                    self.builder.emit_comment("ERROR: THEN without matching IF");
-                }
+                    .emit_comment("Loop index K (second outer loop)");
+                self.builder.emit(IRInstruction::PushOuterLoopIndex(2));
             }
 
             // Additional useful words
             "TYPE" => {
                 self.builder.emit_comment("TYPE - print string");
-                // Expects: addr count --
-                // For now, just print characters from stack
                 self.builder.emit(IRInstruction::PrintString);
             }
             "SPACE" => {
@@ -355,6 +1061,35 @@ impl IRLowering {
                     .emit(IRInstruction::Push(IRValue::Constant(32))); // ASCII space
                 self.builder.emit(IRInstruction::PrintChar);
             }
+            // SPACES is BEGIN DUP 0> WHILE SPACE 1- REPEAT DROP
+            "SPACES" => {
+                self.builder.emit_comment("SPACES - print n spaces");
+                let begin_label = self.builder.create_label("begin");
+                let exit_label = self.builder.create_label("repeat_end");
+                self.builder.emit_label(begin_label.clone());
+                self.builder.emit(IRInstruction::Dup);
+                self.builder.emit(IRInstruction::Push(IRValue::Constant(0)));
+                self.builder.emit(IRInstruction::Greater);
+                self.builder
+                    .emit(IRInstruction::JumpIfNot(exit_label.clone()));
+                self.builder
+                    .emit(IRInstruction::Push(IRValue::Constant(32))); // ASCII space
+                self.builder.emit(IRInstruction::PrintChar);
+                self.builder.emit(IRInstruction::Push(IRValue::Constant(1)));
+                self.builder.emit(IRInstruction::Sub);
+                self.builder.emit(IRInstruction::Jump(begin_label));
+                self.builder.emit_label(exit_label);
+                self.builder.emit(IRInstruction::Drop);
+            }
+            "TRUE" => {
+                self.builder.emit_comment("TRUE - push all bits set");
+                self.builder
+                    .emit(IRInstruction::Push(IRValue::Constant(-1)));
+            }
+            "FALSE" => {
+                self.builder.emit_comment("FALSE - push zero");
+                self.builder.emit(IRInstruction::Push(IRValue::Constant(0)));
+            }
             "BL" => {
                 self.builder.emit_comment("BL - push space character");
                 self.builder
@@ -387,27 +1122,252 @@ impl IRLowering {
                 self.builder.emit(IRInstruction::Push(IRValue::Constant(1)));
                 self.builder.emit(IRInstruction::Add);
             }
-
-            // @ (fetch from memory)
-            "@" => {
-                self.builder.emit_comment("@ - fetch from memory");
-                self.builder.emit(IRInstruction::Load(IRValue::StackTop));
+
+            // @ (fetch from memory)
+            "@" => {
+                self.builder.emit_comment("@ - fetch from memory");
+                self.builder.emit(IRInstruction::Load(IRValue::StackTop));
+            }
+
+            // ! (store to memory)
+            "!" => {
+                self.builder.emit_comment("! - store to memory");
+                self.builder.emit(IRInstruction::Store(IRValue::StackTop));
+            }
+
+            // +! ( n addr -- ) is SWAP OVER @ + SWAP !
+            "+!" => {
+                self.builder.emit_comment("+! - add to memory");
+                self.builder.emit(IRInstruction::Swap);
+                self.builder.emit(IRInstruction::Over);
+                self.builder.emit(IRInstruction::Load(IRValue::StackTop));
+                self.builder.emit(IRInstruction::Add);
+                self.builder.emit(IRInstruction::Swap);
+                self.builder.emit(IRInstruction::Store(IRValue::StackTop));
+            }
+
+            // Data space
+            "C@" => {
+                self.builder.emit_comment("C@ - fetch byte from memory");
+                self.builder.emit(IRInstruction::LoadChar);
+            }
+            "C!" => {
+                self.builder.emit_comment("C! - store byte to memory");
+                self.builder.emit(IRInstruction::StoreChar);
+            }
+            "HERE" => {
+                self.builder
+                    .emit_comment("HERE - next free data space address");
+                self.builder.emit(IRInstruction::Here);
+            }
+            "ALLOT" => {
+                self.builder.emit_comment("ALLOT - reserve data space");
+                self.builder.emit(IRInstruction::Allot);
+            }
+            "," => {
+                self.builder.emit_comment(", - append cell to data space");
+                self.builder.emit(IRInstruction::Comma);
+            }
+            "C," => {
+                self.builder.emit_comment("C, - append byte to data space");
+                self.builder.emit(IRInstruction::CharComma);
+            }
+            "ALIGN" => {
+                self.builder.emit_comment("ALIGN - align data space");
+                self.builder.emit(IRInstruction::Align);
+            }
+
+            // CELLS and CELL+ scale by the target's cell size in bytes
+            "CELLS" => {
+                self.builder.emit_comment("CELLS - cells to bytes");
+                self.builder.emit(IRInstruction::Push(IRValue::Constant(
+                    self.cell_width.bytes(),
+                )));
+                self.builder.emit(IRInstruction::Mul);
+            }
+            // Inside a defining word; the caller supplies the name
+            "CREATE" => {
+                self.builder
+                    .emit_comment("CREATE - define the supplied name");
+                self.builder.emit(IRInstruction::CreateSupplied);
+            }
+            "CELL+" => {
+                self.builder
+                    .emit_comment("CELL+ - advance address by one cell");
+                self.builder.emit(IRInstruction::Push(IRValue::Constant(
+                    self.cell_width.bytes(),
+                )));
+                self.builder.emit(IRInstruction::Add);
+            }
+
+            // 2@ ( addr -- x1 x2 ) fetches x2 from addr and x1 from the next cell
+            "2@" => {
+                self.builder.emit_comment("2@ - fetch cell pair");
+                self.builder.emit(IRInstruction::Dup);
+                self.builder.emit(IRInstruction::Push(IRValue::Constant(
+                    self.cell_width.bytes(),
+                )));
+                self.builder.emit(IRInstruction::Add);
+                self.builder.emit(IRInstruction::Load(IRValue::StackTop));
+                self.builder.emit(IRInstruction::Swap);
+                self.builder.emit(IRInstruction::Load(IRValue::StackTop));
+            }
+            // 2! ( x1 x2 addr -- ) is SWAP OVER ! CELL+ !
+            "2!" => {
+                self.builder.emit_comment("2! - store cell pair");
+                self.builder.emit(IRInstruction::Swap);
+                self.builder.emit(IRInstruction::Over);
+                self.builder.emit(IRInstruction::Store(IRValue::StackTop));
+                self.builder.emit(IRInstruction::Push(IRValue::Constant(
+                    self.cell_width.bytes(),
+                )));
+                self.builder.emit(IRInstruction::Add);
+                self.builder.emit(IRInstruction::Store(IRValue::StackTop));
+            }
+
+            // Strings
+            // COUNT ( c-addr1 -- c-addr2 u ) is DUP 1+ SWAP C@
+            "COUNT" => {
+                self.builder
+                    .emit_comment("COUNT - address and length of a counted string");
+                self.builder.emit(IRInstruction::Dup);
+                self.builder.emit(IRInstruction::Push(IRValue::Constant(1)));
+                self.builder.emit(IRInstruction::Add);
+                self.builder.emit(IRInstruction::Swap);
+                self.builder.emit(IRInstruction::LoadChar);
+            }
+            // /STRING ( c-addr1 u1 n -- c-addr2 u2 ) is ROT OVER + ROT ROT -
+            "/STRING" => {
+                self.builder
+                    .emit_comment("/STRING - drop characters from the front");
+                self.builder.emit(IRInstruction::Rot);
+                self.builder.emit(IRInstruction::Over);
+                self.builder.emit(IRInstruction::Add);
+                self.builder.emit(IRInstruction::Rot);
+                self.builder.emit(IRInstruction::Rot);
+                self.builder.emit(IRInstruction::Sub);
+            }
+            "COMPARE" => {
+                self.builder.emit_comment("COMPARE - order two strings");
+                self.builder.emit(IRInstruction::Compare);
+            }
+            "SEARCH" => {
+                self.builder
+                    .emit_comment("SEARCH - find a string in another");
+                self.builder.emit(IRInstruction::Search);
+            }
+            "-TRAILING" => {
+                self.builder
+                    .emit_comment("-TRAILING - drop trailing spaces");
+                self.builder.emit(IRInstruction::TrimTrailing);
+            }
+            "CMOVE" => {
+                self.builder
+                    .emit_comment("CMOVE - copy bytes upwards one at a time");
+                self.builder.emit(IRInstruction::CMove);
+            }
+            "MOVE" => {
+                self.builder.emit_comment("MOVE - copy bytes");
+                self.builder.emit(IRInstruction::Move);
+            }
+
+            // Floating point
+            "F+" => {
+                self.builder.emit_comment("Float addition");
+                self.builder.emit(IRInstruction::FAdd);
+            }
+            "F-" => {
+                self.builder.emit_comment("Float subtraction");
+                self.builder.emit(IRInstruction::FSub);
+            }
+            "F*" => {
+                self.builder.emit_comment("Float multiplication");
+                self.builder.emit(IRInstruction::FMul);
+            }
+            "F/" => {
+                self.builder.emit_comment("Float division");
+                self.builder.emit(IRInstruction::FDiv);
+            }
+            "F." => {
+                self.builder.emit_comment("Print top of float stack");
+                self.builder.emit(IRInstruction::PrintFloat);
+            }
+            "FDUP" => {
+                self.builder.emit_comment("Duplicate top of float stack");
+                self.builder.emit(IRInstruction::FDup);
+            }
+            "FSWAP" => {
+                self.builder.emit_comment("Swap top two floats");
+                self.builder.emit(IRInstruction::FSwap);
+            }
+            "FDROP" => {
+                self.builder.emit_comment("Drop top of float stack");
+                self.builder.emit(IRInstruction::FDrop);
+            }
+            "S>F" => {
+                self.builder.emit_comment("S>F - cell to float");
+                self.builder.emit(IRInstruction::IntToFloat);
+            }
+            "F>S" => {
+                self.builder.emit_comment("F>S - float to cell");
+                self.builder.emit(IRInstruction::FloatToInt);
+            }
+            "F@" => {
+                self.builder.emit_comment("F@ - fetch float from memory");
+                self.builder.emit(IRInstruction::LoadFloat);
+            }
+            "F!" => {
+                self.builder.emit_comment("F! - store float to memory");
+                self.builder.emit(IRInstruction::StoreFloat);
             }
-
-            // ! (store to memory)
-            "!" => {
-                self.builder.emit_comment("! - store to memory");
-                self.builder.emit(IRInstruction::Store(IRValue::StackTop));
+            "F<" => {
+                self.builder.emit_comment("Float less than comparison");
+                self.builder.emit(IRInstruction::FLess);
+            }
+            "F0=" => {
+                self.builder.emit_comment("Float zero comparison");
+                self.builder.emit(IRInstruction::FZeroEqual);
             }
 
             // User-defined words and variables
             _ => {
-                // Check if it's a variable - push its address
-                if let Some(&addr) = self.variables.get(name) {
+                // Constants fold to their value, VALUEs and constants set as
+                // the program runs fetch theirs, words
+                // made by DOES> run its part after pushing their address, and
+                // CREATEd words and variables push their data space address
+                if let Some(cells) = self.constants.get(name) {
+                    self.builder.emit_comment(&format!("Constant {}", name));
+                    for &cell in cells {
+                        self.builder.emit(IRInstruction::LoadConst(cell));
+                    }
+                } else if let Some(function) = self.does_instances.get(name) {
+                    self.builder.emit_comment(&format!(
+                        "Push data field of {} and run its DOES> part",
+                        name
+                    ));
+                    self.builder
+                        .emit(IRInstruction::PushDataField(name.to_string()));
+                    self.builder.emit(IRInstruction::Call(function.clone()));
+                } else if self.values.contains(name) {
+                    self.builder.emit_comment(&format!("Fetch value {}", name));
+                    self.builder
+                        .emit(IRInstruction::PushDataField(name.to_string()));
+                    self.builder.emit(IRInstruction::Load(IRValue::StackTop));
+                } else if let Some(&cells) = self.stored_constants.get(name) {
+                    self.builder
+                        .emit_comment(&format!("Fetch constant {}", name));
+                    self.builder
+                        .emit(IRInstruction::PushDataField(name.to_string()));
+                    if cells == 2 {
+                        self.lower_word("2@");
+                    } else {
+                        self.builder.emit(IRInstruction::Load(IRValue::StackTop));
+                    }
+                } else if self.data_fields.contains(name) {
                     self.builder
-                        .emit_comment(&format!("Push address of variable {}", name));
+                        .emit_comment(&format!("Push data field of {}", name));
                     self.builder
-                        .emit(IRInstruction::Push(IRValue::Constant(addr)));
+                        .emit(IRInstruction::PushDataField(name.to_string()));
                 } else if self.word_definitions.contains_key(name)
                     || self.external_words.contains(name)
                 {
@@ -431,38 +1391,706 @@ pub struct StackEffectAnalyzer;
 
 impl StackEffectAnalyzer {
     pub fn analyze_program(program: &mut IRProgram) {
-        Self::analyze_function(&mut program.main);
+        let (flows, main) = Self::infer_flows(program, &HashMap::new(), &HashMap::new(), None);
+        Self::record_effects(program, &flows, &main);
+    }
+
+    /// Check the stack use of `program` before it runs. Top-level code
+    /// starts with `entry_depth` items on the stack and `float_entry_depth`
+    /// on the float stack and must not take more, and definitions in `ast`
+    /// must match their stack-effect comments.
+    /// `statement_starts` locates problems in top-level code, and `known`
+    /// holds the effects of words defined elsewhere, such as in earlier REPL
+    /// input. It gains the effects of this program's words.
+    ///
+    /// Returns warnings for code that reaches the same point with different
+    /// stack depths, which is legal but often a mistake.
+    pub fn check_program(
+        program: &mut IRProgram,
+        ast: &AstNode,
+        statement_starts: &[(usize, Position)],
+        entry_depth: usize,
+        float_entry_depth: usize,
+        known: &mut HashMap<String, StackEffect>,
+    ) -> Result<Vec<Warning>, ParseError> {
+        let mut definitions = Vec::new();
+        if let AstNode::Program(nodes) = ast {
+            for node in nodes {
+                if let AstNode::Definition {
+                    name,
+                    signature,
+                    position,
+                    ..
+                } = node
+                {
+                    definitions.push((name, signature, position));
+                }
+            }
+        }
+
+        // Words defined here replace earlier ones. Calls to a word with a
+        // signature trust it, which also covers recursion; the word itself is
+        // checked below. A signature without an `F:` group says nothing about
+        // the float stack, so calls to it follow its body instead, and it can
+        // take any number of floats.
+        for name in program.functions.keys() {
+            known.remove(name);
+        }
+        let mut floors = HashMap::new();
+        for (name, signature, _) in &definitions {
+            let Some(signature) = signature else {
+                continue;
+            };
+            let floats = signature.float_consumes().zip(signature.float_produces());
+            floors.insert(
+                name.to_string(),
+                Depth {
+                    data: -(signature.consumes() as i64),
+                    float: floats.map_or(i64::MIN, |(consumes, _)| -(consumes as i64)),
+                },
+            );
+            if let Some((float_consumes, float_produces)) = floats {
+                known.insert(
+                    name.to_string(),
+                    StackEffect {
+                        consumes: signature.consumes(),
+                        produces: signature.produces(),
+                        float_consumes,
+                        float_produces,
+                    },
+                );
+            }
+        }
+        let floor = Depth {
+            data: -(entry_depth as i64),
+            float: -(float_entry_depth as i64),
+        };
+        let (mut flows, mut main) = Self::infer_flows(program, known, &floors, Some(floor));
+
+        // Calls to a word whose body does not match its signature follow the
+        // body instead
+        let mut wrong_signatures = HashMap::new();
+        for (name, signature, _) in &definitions {
+            let (Some(signature), Some(effect)) =
+                (signature, flows.get(*name).and_then(Flow::effect))
+            else {
+                continue;
+            };
+            if let Some(problem) = Self::signature_mismatch(name, signature, &effect) {
+                floors.remove(*name);
+                known.remove(*name);
+                wrong_signatures.insert(name.to_string(), problem);
+            }
+        }
+        if !wrong_signatures.is_empty() {
+            (flows, main) = Self::infer_flows(program, known, &floors, Some(floor));
+        }
+
+        let mut warnings = Vec::new();
+        for (name, _, position) in definitions {
+            if let Some(mismatch) = flows.get(name).and_then(|flow| flow.mismatch.as_ref()) {
+                warnings.push(Warning::new(
+                    WarningKind::StackEffect,
+                    ParseError::new(mismatch.describe(Some(name)), position.clone()),
+                ));
+            }
+            if let Some(problem) = wrong_signatures.remove(name) {
+                warnings.push(Warning::new(WarningKind::Signature, problem));
+            }
+        }
 
-        for (_, function) in program.functions.iter_mut() {
-            Self::analyze_function(function);
+        let statement_at = |pc: usize| {
+            statement_starts
+                .iter()
+                .take_while(|(start, _)| *start <= pc)
+                .last()
+                .or(statement_starts.first())
+                .map(|(_, position)| position.clone())
+                .unwrap_or(Position {
+                    line: 1,
+                    column: 1,
+                    offset: 0,
+                    file: 0,
+                })
+        };
+        if let Some(underflow) = &main.underflow {
+            let (kind, stack) = if underflow.float {
+                ("Float stack", "float stack")
+            } else {
+                ("Stack", "stack")
+            };
+            let held = match underflow.available {
+                0 => format!("the {} is empty", stack),
+                n => format!("the {} only holds {}", stack, count(n, underflow.float)),
+            };
+            return Err(ParseError::new(
+                format!(
+                    "{} underflow: {} needed but {}",
+                    kind,
+                    count(underflow.needed, underflow.float),
+                    held
+                ),
+                statement_at(underflow.pc),
+            ));
         }
+        if let Some(mismatch) = &main.mismatch {
+            warnings.push(Warning::new(
+                WarningKind::StackEffect,
+                ParseError::new(mismatch.describe(None), statement_at(mismatch.pc)),
+            ));
+        }
+
+        Self::record_effects(program, &flows, &main);
+        known.extend(
+            flows
+                .iter()
+                .filter_map(|(name, flow)| Some((name.clone(), flow.effect()?))),
+        );
+        Ok(warnings)
     }
 
-    fn analyze_function(function: &mut IRFunction) {
-        let mut stack_depth = 0i32;
-        let mut max_depth = 0i32;
-        let mut min_depth = 0i32;
+    /// Follow every path through `program`'s functions and its main code,
+    /// which underflows if its depth goes below `floor`. `floors` holds the
+    /// lowest depth functions with a signature can reach.
+    fn infer_flows(
+        program: &IRProgram,
+        known: &HashMap<String, StackEffect>,
+        floors: &HashMap<String, Depth>,
+        floor: Option<Depth>,
+    ) -> (HashMap<String, Flow>, Flow) {
+        let mut inference = EffectInference {
+            program,
+            known,
+            floors,
+            results: HashMap::new(),
+            in_progress: HashMap::new(),
+            recursive: HashSet::new(),
+        };
+        for name in program.functions.keys() {
+            inference.infer_function(name);
+        }
+        let main = inference.infer(&program.main, floor);
+        (inference.results, main)
+    }
 
-        for instruction in &function.instructions {
-            let effect = instruction.stack_effect();
-            stack_depth = stack_depth - effect.consumes as i32 + effect.produces as i32;
+    /// Set the effect of every function whose stack depth is fixed
+    /// How the body of `name`, with `effect`, breaks its signature, if it
+    /// does
+    fn signature_mismatch(
+        name: &str,
+        signature: &StackSignature,
+        effect: &StackEffect,
+    ) -> Option<ParseError> {
+        for float in [false, true] {
+            let (declared, body) = if float {
+                let (Some(consumes), Some(produces)) =
+                    (signature.float_consumes(), signature.float_produces())
+                else {
+                    continue;
+                };
+                (
+                    (consumes, produces),
+                    (effect.float_consumes, effect.float_produces),
+                )
+            } else {
+                (
+                    (signature.consumes(), signature.produces()),
+                    (effect.consumes, effect.produces),
+                )
+            };
+            if body.0 > declared.0 {
+                return Some(ParseError::new(
+                    format!(
+                        "{} can underflow{}: it is declared {} but its body takes {}",
+                        name,
+                        if float { " the float stack" } else { "" },
+                        signature,
+                        count(body.0, float)
+                    ),
+                    signature.position.clone(),
+                ));
+            }
+            // Items listed on both sides but never touched still match
+            let net = body.1 as i64 - body.0 as i64;
+            let declared_net = declared.1 as i64 - declared.0 as i64;
+            if net != declared_net {
+                return Some(ParseError::new(
+                    format!(
+                        "{} is declared {} but its body consumes {} and produces {}{}",
+                        name,
+                        signature,
+                        body.0,
+                        body.1,
+                        if float { " floats" } else { "" }
+                    ),
+                    signature.position.clone(),
+                ));
+            }
+        }
+        None
+    }
+
+    fn record_effects(program: &mut IRProgram, flows: &HashMap<String, Flow>, main: &Flow) {
+        for (name, function) in program.functions.iter_mut() {
+            if let Some(effect) = flows.get(name).and_then(Flow::effect) {
+                function.stack_effect = effect;
+            }
+        }
+        if let Some(effect) = main.effect() {
+            program.main.stack_effect = effect;
+        }
+    }
+}
+
+/// "1 item" or "n items", or "1 float" or "n floats" for the float stack
+fn count(n: usize, float: bool) -> String {
+    let noun = if float { "float" } else { "item" };
+    if n == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}s", n, noun)
+    }
+}
+
+/// Passes over a recursive function before giving up on its effect settling
+const MAX_FIXPOINT_PASSES: usize = 8;
+
+/// Depths of the data and float stacks
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Depth {
+    data: i64,
+    float: i64,
+}
+
+impl Depth {
+    /// The depth once `effect` has taken its inputs
+    fn taking(self, effect: &StackEffect) -> Depth {
+        Depth {
+            data: self.data - effect.consumes as i64,
+            float: self.float - effect.float_consumes as i64,
+        }
+    }
+
+    /// The depth once `effect` has left its outputs
+    fn giving(self, effect: &StackEffect) -> Depth {
+        Depth {
+            data: self.data + effect.produces as i64,
+            float: self.float + effect.float_produces as i64,
+        }
+    }
+
+    /// Whether either stack is below its depth in `floor`
+    fn below(self, floor: Depth) -> bool {
+        self.data < floor.data || self.float < floor.float
+    }
+
+    fn min(self, other: Depth) -> Depth {
+        Depth {
+            data: self.data.min(other.data),
+            float: self.float.min(other.float),
+        }
+    }
+}
+
+/// What following every path through a function found, with depths
+/// relative to its entry
+#[derive(Debug, Clone, Default)]
+struct Flow {
+    exit_depth: Option<Depth>, // None if no path returns
+    lowest: Depth,
+    unknown: bool, // Some path calls a word or prints a string whose effect isn't known
+    underflow: Option<Underflow>,
+    mismatch: Option<Mismatch>,
+}
+
+impl Flow {
+    /// The function's effect, if its depth is the same on every path
+    fn effect(&self) -> Option<StackEffect> {
+        if self.unknown || self.mismatch.is_some() {
+            return None;
+        }
+        let exit_depth = self.exit_depth?;
+        Some(StackEffect {
+            consumes: (-self.lowest.data) as usize,
+            produces: (exit_depth.data - self.lowest.data) as usize,
+            float_consumes: (-self.lowest.float) as usize,
+            float_produces: (exit_depth.float - self.lowest.float) as usize,
+        })
+    }
+
+    fn call_effect(&self) -> CallEffect {
+        match self.effect() {
+            Some(effect) => CallEffect::Returns(effect),
+            None if self.unknown || self.mismatch.is_some() => CallEffect::Unknown,
+            None => CallEffect::NoReturn,
+        }
+    }
+
+    fn reach_exit(&mut self, pc: usize, depth: Depth) {
+        match self.exit_depth {
+            Some(exit_depth) if exit_depth != depth => {
+                self.mismatch(Mismatch::between(pc, Join::Exit, depth, exit_depth))
+            }
+            _ => self.exit_depth = Some(depth),
+        }
+    }
+
+    fn mismatch(&mut self, mismatch: Mismatch) {
+        self.mismatch.get_or_insert(mismatch);
+    }
+}
+
+/// An instruction that needs more items than the stack holds
+#[derive(Debug, Clone)]
+struct Underflow {
+    pc: usize,
+    needed: usize,
+    available: usize,
+    float: bool, // On the float stack
+}
+
+/// A point reached by two paths with different stack depths
+#[derive(Debug, Clone)]
+struct Mismatch {
+    pc: usize,
+    join: Join,
+    difference: i64, // Depth of the later path minus that of the first
+    float: bool,     // The float stack differs, and the data stack doesn't
+}
 
-            max_depth = max_depth.max(stack_depth);
-            min_depth = min_depth.min(stack_depth);
+#[derive(Debug, Clone)]
+enum Join {
+    Branch,
+    Loop,
+    Exit,
+}
+
+impl Mismatch {
+    /// The mismatch between a path reaching `pc` at `later` and one that
+    /// reached it at `first`
+    fn between(pc: usize, join: Join, later: Depth, first: Depth) -> Mismatch {
+        let float = later.data == first.data;
+        Mismatch {
+            pc,
+            join,
+            difference: if float {
+                later.float - first.float
+            } else {
+                later.data - first.data
+            },
+            float,
+        }
+    }
+
+    /// Describe the mismatch in definition `word`, or in top-level code
+    fn describe(&self, word: Option<&str>) -> String {
+        let count = self.difference.unsigned_abs();
+        let noun = match (self.float, count) {
+            (true, 1) => "float",
+            (true, _) => "floats",
+            (false, 1) => "item",
+            (false, _) => "items",
+        };
+        let place = word.map(|word| format!(" in {}", word)).unwrap_or_default();
+        match self.join {
+            Join::Branch => format!(
+                "One branch of IF{} leaves {} more {} than the other",
+                place, count, noun
+            ),
+            Join::Loop => format!(
+                "Each pass through the loop{} leaves {} {} {} on the {}",
+                place,
+                count,
+                if self.difference > 0 { "more" } else { "fewer" },
+                noun,
+                if self.float { "float stack" } else { "stack" }
+            ),
+            Join::Exit => format!(
+                "Some paths through {} return {} more {} than others",
+                word.unwrap_or("the program"),
+                count,
+                noun
+            ),
+        }
+    }
+}
+
+/// The instruction before `pc`, skipping comments
+fn instruction_before(instructions: &[IRInstruction], pc: usize) -> Option<&IRInstruction> {
+    instructions[..pc]
+        .iter()
+        .rev()
+        .find(|instruction| !matches!(instruction, IRInstruction::Comment(_)))
+}
+
+/// The effect of PICK or ROLL at `pc` on the items it reaches, if the
+/// instruction before pushed how deep it reaches
+fn reach_effect(instructions: &[IRInstruction], pc: usize) -> Option<StackEffect> {
+    let depth = match instruction_before(instructions, pc)? {
+        IRInstruction::Push(IRValue::Constant(n)) | IRInstruction::LoadConst(n) => {
+            usize::try_from(*n).ok()?
         }
+        _ => return None,
+    };
+    let produces = match instructions[pc] {
+        IRInstruction::Pick => depth + 2,
+        _ => depth + 1,
+    };
+    Some(StackEffect {
+        consumes: depth + 2,
+        produces,
+        ..StackEffect::default()
+    })
+}
+
+/// The effect of calling a function
+enum CallEffect {
+    Returns(StackEffect),
+    NoReturn,
+    Unknown,
+}
 
-        // The function's stack effect is the net change
-        if stack_depth >= 0 {
-            function.stack_effect = StackEffect {
-                consumes: (-min_depth) as usize,
-                produces: (stack_depth + (-min_depth)) as usize,
+/// Infers function effects, following calls into the functions they call
+struct EffectInference<'a> {
+    program: &'a IRProgram,
+    known: &'a HashMap<String, StackEffect>,
+    floors: &'a HashMap<String, Depth>,
+    results: HashMap<String, Flow>,
+    // Functions being inferred, with the effect assumed for calls back into
+    // them; None assumes they don't return
+    in_progress: HashMap<String, Option<StackEffect>>,
+    recursive: HashSet<String>, // Functions whose assumed effect was used
+}
+
+impl EffectInference<'_> {
+    fn call_effect(&mut self, name: &str) -> CallEffect {
+        if let Some(effect) = self.known.get(name) {
+            return CallEffect::Returns(effect.clone());
+        }
+        if let Some(assumed) = self.in_progress.get(name) {
+            let effect = match assumed {
+                Some(effect) => CallEffect::Returns(effect.clone()),
+                None => CallEffect::NoReturn,
             };
-        } else {
-            function.stack_effect = StackEffect {
-                consumes: (-min_depth) as usize,
-                produces: 0,
+            self.recursive.insert(name.to_string());
+            return effect;
+        }
+        match self.infer_function(name) {
+            Some(flow) => flow.call_effect(),
+            None => CallEffect::Unknown,
+        }
+    }
+
+    /// The effect of running the execution token on top of the stack at
+    /// `pc`, if the instruction before pushed it
+    fn xt_effect(&mut self, instructions: &[IRInstruction], pc: usize) -> CallEffect {
+        let Some(IRInstruction::PushXt(name)) = instruction_before(instructions, pc) else {
+            return CallEffect::Unknown;
+        };
+        match self.program.wrappers.get(name) {
+            Some(wrapper) => self.infer(wrapper, None).call_effect(),
+            None => self.call_effect(name),
+        }
+    }
+
+    /// Infer function `name`, ignoring `known`. Recursive calls start out
+    /// assumed not to return, so only the paths that end the recursion count,
+    /// and are then given the effect found until it stops changing.
+    fn infer_function(&mut self, name: &str) -> Option<Flow> {
+        if let Some(flow) = self.results.get(name) {
+            return Some(flow.clone());
+        }
+        let program = self.program;
+        let function = program.functions.get(name)?;
+        let floor = self.floors.get(name).copied();
+        self.in_progress.insert(name.to_string(), None);
+
+        let mut passes = 0;
+        let flow = loop {
+            self.recursive.remove(name);
+            let mut flow = self.infer(function, floor);
+            let effect = flow.effect();
+            if !self.recursive.contains(name)
+                || effect.is_none()
+                || effect == self.in_progress[name]
+            {
+                break flow;
+            }
+            passes += 1;
+            if passes == MAX_FIXPOINT_PASSES {
+                flow.unknown = true;
+                break flow;
+            }
+            self.in_progress.insert(name.to_string(), effect);
+        };
+
+        self.in_progress.remove(name);
+        self.recursive.remove(name);
+        // A result resting on what was assumed about a word still being
+        // inferred may not hold once that word is done
+        if self.recursive.is_empty() {
+            self.results.insert(name.to_string(), flow.clone());
+        }
+        Some(flow)
+    }
+
+    /// Follow every path through `function`, tracking the stack depth
+    /// relative to its entry. An instruction taking the depth below `floor`
+    /// underflows.
+    fn infer(&mut self, function: &IRFunction, floor: Option<Depth>) -> Flow {
+        let instructions = &function.instructions;
+        let labels: HashMap<&IRLabel, usize> = instructions
+            .iter()
+            .enumerate()
+            .filter_map(|(pc, instruction)| match instruction {
+                IRInstruction::Label(label) => Some((label, pc)),
+                _ => None,
+            })
+            .collect();
+
+        let mut flow = Flow::default();
+        let mut depths: Vec<Option<Depth>> = vec![None; instructions.len() + 1];
+        let mut pending = vec![(0usize, Depth::default())];
+
+        while let Some((pc, depth)) = pending.pop() {
+            match depths[pc] {
+                Some(seen) if seen != depth => {
+                    let join = match instructions.get(pc) {
+                        Some(IRInstruction::Label(label))
+                            if label.name != "else" && label.name != "endif" =>
+                        {
+                            Join::Loop
+                        }
+                        Some(_) => Join::Branch,
+                        None => Join::Exit,
+                    };
+                    flow.mismatch(Mismatch::between(pc, join, depth, seen));
+                    continue;
+                }
+                Some(_) => continue,
+                None => depths[pc] = Some(depth),
+            }
+
+            let Some(instruction) = instructions.get(pc) else {
+                // Ran off the end
+                flow.reach_exit(pc, depth);
+                continue;
+            };
+
+            let effect = match instruction {
+                IRInstruction::Call(name) => match self.call_effect(name) {
+                    CallEffect::Returns(effect) => effect,
+                    CallEffect::NoReturn => continue,
+                    CallEffect::Unknown => {
+                        flow.unknown = true;
+                        continue;
+                    }
+                },
+                // Only an execution token pushed just before is known
+                IRInstruction::Execute => match self.xt_effect(instructions, pc) {
+                    CallEffect::Returns(effect) => StackEffect {
+                        consumes: effect.consumes + 1,
+                        ..effect
+                    },
+                    CallEffect::NoReturn => continue,
+                    CallEffect::Unknown => {
+                        flow.unknown = true;
+                        continue;
+                    }
+                },
+                // Only a depth pushed just before is known
+                IRInstruction::Pick | IRInstruction::Roll => {
+                    reach_effect(instructions, pc).unwrap_or_else(|| instruction.stack_effect())
+                }
+                // ?DUP leaves one item or two, depending on the value
+                IRInstruction::DupIfNonZero => {
+                    flow.unknown = true;
+                    continue;
+                }
+                // Checked as the word returning, with 0 pushed after it,
+                // unless taking its items would underflow, which throws
+                // instead. A word that never returns only ever leaves the code
+                // it threw. Without a signature, items from below the entry
+                // depend on the caller, so either can happen.
+                IRInstruction::Catch => {
+                    let thrown = StackEffect {
+                        consumes: 1,
+                        produces: 1,
+                        ..StackEffect::default()
+                    };
+                    match self.xt_effect(instructions, pc) {
+                        CallEffect::Returns(effect) => {
+                            let returned = StackEffect {
+                                consumes: effect.consumes + 1,
+                                produces: effect.produces + 1,
+                                ..effect
+                            };
+                            match floor {
+                                Some(floor) if depth.taking(&returned).below(floor) => thrown,
+                                None if depth.taking(&returned).below(Depth::default()) => {
+                                    flow.unknown = true;
+                                    continue;
+                                }
+                                _ => returned,
+                            }
+                        }
+                        CallEffect::NoReturn => thrown,
+                        CallEffect::Unknown => {
+                            flow.unknown = true;
+                            continue;
+                        }
+                    }
+                }
+                _ => instruction.stack_effect(),
             };
+            let lowest = depth.taking(&effect);
+            if let Some(floor) = floor
+                && flow.underflow.is_none()
+            {
+                if lowest.data < floor.data {
+                    flow.underflow = Some(Underflow {
+                        pc,
+                        needed: effect.consumes,
+                        available: (depth.data - floor.data) as usize,
+                        float: false,
+                    });
+                } else if lowest.float < floor.float {
+                    flow.underflow = Some(Underflow {
+                        pc,
+                        needed: effect.float_consumes,
+                        available: (depth.float - floor.float) as usize,
+                        float: true,
+                    });
+                }
+            }
+            flow.lowest = flow.lowest.min(lowest);
+            let after = lowest.giving(&effect);
+
+            let target = |label: &IRLabel| labels.get(label).copied();
+            match instruction {
+                IRInstruction::Jump(label) | IRInstruction::Leave(label) => match target(label) {
+                    Some(target) => pending.push((target, after)),
+                    None => flow.unknown = true,
+                },
+                IRInstruction::JumpIf(label)
+                | IRInstruction::JumpIfNot(label)
+                | IRInstruction::Loop(label)
+                | IRInstruction::PlusLoop(label)
+                | IRInstruction::DoLoop(_, label) => {
+                    match target(label) {
+                        Some(target) => pending.push((target, after)),
+                        None => flow.unknown = true,
+                    }
+                    // Explored first, so loop bodies are seen before the
+                    // paths that skip or leave them
+                    pending.push((pc + 1, after));
+                }
+                IRInstruction::Return => flow.reach_exit(pc, after),
+                _ => pending.push((pc + 1, after)),
+            }
         }
+
+        flow
     }
 }
 
@@ -513,6 +2141,8 @@ impl IRPrettyPrinter {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
     use crate::types::{AstNode, Position};
 
     #[test]
@@ -522,6 +2152,7 @@ mod tests {
             line: 1,
             column: 1,
             offset: 0,
+            file: 0,
         };
 
         let ast = AstNode::Program(vec![
@@ -564,6 +2195,7 @@ mod tests {
             line: 1,
             column: 1,
             offset: 0,
+            file: 0,
         };
 
         let ast = AstNode::Program(vec![
@@ -573,6 +2205,7 @@ mod tests {
                     AstNode::Word("DUP".to_string(), pos.clone()),
                     AstNode::Word("+".to_string(), pos.clone()),
                 ],
+                signature: None,
                 position: pos.clone(),
             },
             AstNode::Number(5, pos.clone()),
@@ -594,9 +2227,11 @@ mod tests {
             .filter(|instr| !matches!(instr, IRInstruction::Comment(_)))
             .collect();
 
-        assert!(main_instructions
-            .iter()
-            .any(|instr| matches!(instr, IRInstruction::Call(name) if name == "DOUBLE")));
+        assert!(
+            main_instructions
+                .iter()
+                .any(|instr| matches!(instr, IRInstruction::Call(name) if name == "DOUBLE"))
+        );
     }
 
     #[test]
@@ -606,6 +2241,7 @@ mod tests {
             line: 1,
             column: 1,
             offset: 0,
+            file: 0,
         };
 
         // Test: 5 DUP + (should consume 0, produce 1)
@@ -622,4 +2258,494 @@ mod tests {
         assert_eq!(program.main.stack_effect.consumes, 0);
         assert_eq!(program.main.stack_effect.produces, 1);
     }
+
+    fn lower_source(source: &str) -> Vec<IRInstruction> {
+        let tokens = Lexer::new(source.to_string()).tokenize().unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        let program = IRLowering::new().lower(&ast);
+        program
+            .main
+            .instructions
+            .into_iter()
+            .filter(|instr| !matches!(instr, IRInstruction::Comment(_)))
+            .collect()
+    }
+
+    #[test]
+    fn test_begin_until_lowering() {
+        let instructions = lower_source("BEGIN DUP UNTIL");
+
+        let IRInstruction::Label(begin) = &instructions[0] else {
+            panic!("Expected BEGIN label, got {:?}", instructions[0]);
+        };
+        assert_eq!(instructions[1], IRInstruction::Dup);
+        assert_eq!(instructions[2], IRInstruction::JumpIfNot(begin.clone()));
+    }
+
+    #[test]
+    fn test_begin_while_repeat_lowering() {
+        let instructions = lower_source("BEGIN DUP WHILE DROP REPEAT");
+
+        let IRInstruction::Label(begin) = &instructions[0] else {
+            panic!("Expected BEGIN label, got {:?}", instructions[0]);
+        };
+        let IRInstruction::JumpIfNot(exit) = &instructions[2] else {
+            panic!("Expected WHILE exit jump, got {:?}", instructions[2]);
+        };
+        assert_eq!(instructions[4], IRInstruction::Jump(begin.clone()));
+        assert_eq!(instructions[5], IRInstruction::Label(exit.clone()));
+    }
+
+    #[test]
+    fn test_begin_again_nested_in_if() {
+        let instructions = lower_source("IF BEGIN AGAIN THEN");
+
+        assert!(matches!(instructions[0], IRInstruction::JumpIfNot(_)));
+        let IRInstruction::Label(begin) = &instructions[1] else {
+            panic!("Expected BEGIN label, got {:?}", instructions[1]);
+        };
+        assert_eq!(instructions[2], IRInstruction::Jump(begin.clone()));
+    }
+
+    #[test]
+    fn test_if_else_lowering() {
+        let instructions = lower_source("IF 1 ELSE 2 THEN");
+
+        let IRInstruction::JumpIfNot(else_label) = &instructions[0] else {
+            panic!("Expected IF jump, got {:?}", instructions[0]);
+        };
+        let IRInstruction::Jump(endif_label) = &instructions[2] else {
+            panic!("Expected jump over ELSE, got {:?}", instructions[2]);
+        };
+        assert_eq!(instructions[3], IRInstruction::Label(else_label.clone()));
+        assert_eq!(instructions[5], IRInstruction::Label(endif_label.clone()));
+    }
+
+    #[test]
+    fn test_leave_targets_innermost_loop_end() {
+        let instructions = lower_source("DO DO IF LEAVE THEN J LOOP 2 +LOOP");
+
+        let inner_end = match &instructions[2] {
+            IRInstruction::DoLoop(_, end) => end.clone(),
+            other => panic!("expected inner DoLoop, got {:?}", other),
+        };
+        assert!(instructions.contains(&IRInstruction::Leave(inner_end.clone())));
+        assert!(instructions.contains(&IRInstruction::PushOuterLoopIndex(1)));
+
+        // The inner loop's end label comes straight after its LOOP
+        let loop_pos = instructions
+            .iter()
+            .position(|instr| matches!(instr, IRInstruction::Loop(_)))
+            .unwrap();
+        assert_eq!(instructions[loop_pos + 1], IRInstruction::Label(inner_end));
+        assert!(matches!(
+            instructions[instructions.len() - 2],
+            IRInstruction::PlusLoop(_)
+        ));
+    }
+
+    #[test]
+    fn test_constants_lower_to_load_const() {
+        let instructions = lower_source("7 CONSTANT SEVEN -1. 2CONSTANT DMINUS SEVEN DMINUS");
+        assert_eq!(
+            instructions,
+            [
+                IRInstruction::LoadConst(7),
+                IRInstruction::LoadConst(-1),
+                IRInstruction::LoadConst(-1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_constants_from_the_stack_are_stored() {
+        let instructions = lower_source("DUP CONSTANT D D OVER 2CONSTANT P P");
+        let field = |name: &str| IRInstruction::PushDataField(name.to_string());
+        assert_eq!(
+            instructions[1..7],
+            [
+                IRInstruction::Create("D".to_string()),
+                IRInstruction::Comma,
+                field("D"),
+                IRInstruction::Load(IRValue::StackTop),
+                IRInstruction::Over,
+                IRInstruction::Create("P".to_string()),
+            ]
+        );
+        assert_eq!(
+            instructions[7..10],
+            [IRInstruction::Comma, IRInstruction::Comma, field("P")]
+        );
+    }
+
+    #[test]
+    fn test_later_definitions_replace_earlier_ones() {
+        let tokens = Lexer::new(
+            "2 CONSTANT FOO FOO : FOO 1 ; FOO 3 VALUE BAR : BAR 4 ; BAR
+             VARIABLE Z : Z 9 ; Z : W 7 ; 8 CONSTANT W W : D 6 ; D : USE FOO BAR Z W D ;"
+                .to_string(),
+        )
+        .tokenize()
+        .unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        // As with -D D=5
+        let mut lowering = IRLowering::new();
+        lowering.add_known_constant("D", vec![5]);
+        let program = lowering.lower(&ast);
+
+        let uses = |instructions: &[IRInstruction]| -> Vec<IRInstruction> {
+            instructions
+                .iter()
+                .filter(|instr| {
+                    matches!(instr, IRInstruction::Call(_) | IRInstruction::LoadConst(_))
+                })
+                .cloned()
+                .collect()
+        };
+        let call = |name: &str| IRInstruction::Call(name.to_string());
+        assert_eq!(
+            uses(&program.main.instructions),
+            [
+                IRInstruction::LoadConst(2),
+                call("FOO"),
+                call("BAR"),
+                call("Z"),
+                IRInstruction::LoadConst(8),
+                call("D")
+            ]
+        );
+        assert_eq!(
+            uses(&program.functions["USE"].instructions),
+            [
+                call("FOO"),
+                call("BAR"),
+                call("Z"),
+                IRInstruction::LoadConst(8),
+                call("D")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_value_fetches_and_to_stores() {
+        let instructions = lower_source("1 VALUE X X 2 TO X");
+        let field = IRInstruction::PushDataField("X".to_string());
+        assert_eq!(
+            instructions[1..],
+            [
+                IRInstruction::Create("X".to_string()),
+                IRInstruction::Comma,
+                field.clone(),
+                IRInstruction::Load(IRValue::StackTop),
+                IRInstruction::Push(IRValue::Constant(2)),
+                field,
+                IRInstruction::Store(IRValue::StackTop),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_does_part_becomes_its_own_function() {
+        let tokens = Lexer::new(": CONST CREATE , DOES> @ ; 3 CONST THREE THREE".to_string())
+            .tokenize()
+            .unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        let program = IRLowering::new().lower(&ast);
+
+        let create_part: Vec<_> = program.functions["CONST"]
+            .instructions
+            .iter()
+            .filter(|instr| !matches!(instr, IRInstruction::Comment(_)))
+            .collect();
+        assert_eq!(
+            create_part,
+            [
+                &IRInstruction::CreateSupplied,
+                &IRInstruction::Comma,
+                &IRInstruction::Return
+            ]
+        );
+        assert!(program.functions.contains_key("__DOES_CONST"));
+
+        let main: Vec<_> = program
+            .main
+            .instructions
+            .iter()
+            .filter(|instr| !matches!(instr, IRInstruction::Comment(_)))
+            .collect();
+        assert_eq!(
+            main[1..],
+            [
+                &IRInstruction::SupplyName("THREE".to_string()),
+                &IRInstruction::Call("CONST".to_string()),
+                &IRInstruction::PushDataField("THREE".to_string()),
+                &IRInstruction::Call("__DOES_CONST".to_string()),
+            ]
+        );
+    }
+
+    fn check_source(source: &str) -> Result<(IRProgram, Vec<Warning>), ParseError> {
+        let tokens = Lexer::new(source.to_string()).tokenize().unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        let mut lowering = IRLowering::new();
+        let mut program = lowering.lower(&ast);
+        let warnings = StackEffectAnalyzer::check_program(
+            &mut program,
+            &ast,
+            lowering.statement_starts(),
+            0,
+            0,
+            &mut HashMap::new(),
+        )?;
+        Ok((program, warnings))
+    }
+
+    #[test]
+    fn test_stack_effect_inference_follows_branches() {
+        let (program, warnings) = check_source(
+            ": ABS ( n -- u ) DUP 0 < IF 0 SWAP - THEN ;
+             : SUM ( n -- sum ) 0 SWAP 0 ?DO I + LOOP ;
+             : KEEP ( a b -- a b ) ;
+             : SUM-ABS SUM ABS ;",
+        )
+        .unwrap();
+        assert!(warnings.is_empty());
+
+        let effect = |name: &str| {
+            let effect = &program.functions[name].stack_effect;
+            (effect.consumes, effect.produces)
+        };
+        assert_eq!(effect("ABS"), (1, 1));
+        assert_eq!(effect("SUM"), (1, 1));
+        assert_eq!(effect("KEEP"), (0, 0));
+        assert_eq!(effect("SUM-ABS"), (1, 1));
+    }
+
+    #[test]
+    fn test_stack_effect_of_recursion_reaches_fixpoint() {
+        let (program, _) = check_source(
+            ": FACT DUP 1 > IF DUP 1 - FACT * ELSE DROP 1 THEN ;
+             : COUNTDOWN DUP 0 > IF DUP . 1 - COUNTDOWN ELSE DROP THEN ;",
+        )
+        .unwrap();
+
+        let effect = |name: &str| {
+            let effect = &program.functions[name].stack_effect;
+            (effect.consumes, effect.produces)
+        };
+        assert_eq!(effect("FACT"), (1, 1));
+        assert_eq!(effect("COUNTDOWN"), (1, 0));
+    }
+
+    /// The signature warnings for `source`, which must otherwise check
+    fn signature_warnings(source: &str) -> Vec<ParseError> {
+        let (_, warnings) = check_source(source).unwrap();
+        warnings
+            .into_iter()
+            .filter(|warning| warning.kind == WarningKind::Signature)
+            .map(|warning| warning.diagnostic)
+            .collect()
+    }
+
+    #[test]
+    fn test_declared_stack_effect_mismatch() {
+        let warnings = signature_warnings(": SQUARE ( n -- ) DUP * ;");
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            warnings[0].message,
+            "SQUARE is declared ( n -- ) but its body consumes 1 and produces 1"
+        );
+        assert_eq!(warnings[0].position.column, 10);
+
+        // Words called with a declared effect are checked by that effect
+        let warnings = signature_warnings(": SQ ( n -- n ) DUP * ;\n: QUAD ( n -- ) SQ SQ ;");
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].message.starts_with("QUAD is declared"));
+
+        let warnings = signature_warnings(": ADD ( n -- n ) + ;");
+        assert_eq!(
+            warnings[0].message,
+            "ADD can underflow: it is declared ( n -- n ) but its body takes 2 items"
+        );
+
+        // Calls to a word whose body does not match follow the body
+        let warnings = signature_warnings(": HALF ( F: r -- ) 2e0 F/ ;\n1e0 HALF F.");
+        assert_eq!(
+            warnings[0].message,
+            "HALF is declared ( F: r -- ) but its body consumes 1 and produces 1 floats"
+        );
+    }
+
+    #[test]
+    fn test_branches_with_different_depths_warn() {
+        let (_, warnings) = check_source(
+            ": ?DUP ( n -- n n | 0 ) DUP IF DUP THEN ;
+             : PUSHES 0 ?DO I LOOP ;
+             1 IF 2 THEN",
+        )
+        .unwrap();
+        let messages: Vec<_> = warnings
+            .iter()
+            .map(|warning| {
+                let warning = &warning.diagnostic;
+                (warning.position.line, warning.message.as_str())
+            })
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                (
+                    1,
+                    "One branch of IF in ?DUP leaves 1 more item than the other"
+                ),
+                (
+                    2,
+                    "Each pass through the loop in PUSHES leaves 1 more item on the stack"
+                ),
+                (3, "One branch of IF leaves 1 more item than the other"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_float_stack_is_checked_apart_from_the_data_stack() {
+        let (program, _) =
+            check_source(": HALF ( r -- r ) 2e0 F/ ;\n: ROUND ( r -- n ) F>S ;").unwrap();
+        let effect = |name: &str| {
+            let effect = &program.functions[name].stack_effect;
+            (
+                effect.consumes,
+                effect.produces,
+                effect.float_consumes,
+                effect.float_produces,
+            )
+        };
+        assert_eq!(effect("HALF"), (0, 0, 1, 1));
+        assert_eq!(effect("ROUND"), (0, 1, 1, 0));
+
+        let err = check_source("1 2 F+").unwrap_err();
+        assert_eq!(
+            err.message,
+            "Float stack underflow: 2 floats needed but the float stack is empty"
+        );
+        let warnings = signature_warnings(": BAD ( F: r -- ) F+ ;");
+        assert_eq!(
+            warnings[0].message,
+            "BAD can underflow the float stack: it is declared ( F: r -- ) but its body takes 2 floats"
+        );
+
+        // Floats are only checked against a signature with an F: group, which
+        // can be a comment of its own
+        let (program, _) = check_source(
+            ": PUSH-HALF ( -- ) 5e-1 ;\n: SCALE ( n -- n ) ( F: r1 r2 -- r3 ) F* ;\nPUSH-HALF 3e0 2 SCALE F. .",
+        )
+        .unwrap();
+        let effect = &program.functions["PUSH-HALF"].stack_effect;
+        assert_eq!((effect.float_consumes, effect.float_produces), (0, 1));
+        let warnings = signature_warnings(": SCALE ( n -- n ) ( F: r1 r2 -- r3 ) F* F* ;");
+        assert_eq!(
+            warnings[0].message,
+            "SCALE can underflow the float stack: it is declared ( n -- n ) ( F: r1 r2 -- r3 ) but its body takes 3 floats"
+        );
+    }
+
+    #[test]
+    fn test_top_level_underflow_is_an_error() {
+        let err = check_source("1 2 +\n. DUP").unwrap_err();
+        assert_eq!(
+            err.message,
+            "Stack underflow: 1 item needed but the stack is empty"
+        );
+        assert_eq!((err.position.line, err.position.column), (2, 3));
+        assert!(check_source(": PAIR 1 2 ;\nPAIR + .").is_ok());
+    }
+
+    #[test]
+    fn test_caught_underflow_leaves_the_code_thrown() {
+        let (program, warnings) = check_source(
+            ": UNDERFLOWS DROP ;
+             : SAFE ( -- code ) ['] UNDERFLOWS CATCH ;
+             ['] UNDERFLOWS CATCH . SAFE . 5 ['] UNDERFLOWS CATCH .
+             : UND DROP DROP DROP ;
+             : T3 ['] UND CATCH . ;
+             T3",
+        )
+        .unwrap();
+        assert!(warnings.is_empty());
+        let effect = &program.functions["SAFE"].stack_effect;
+        assert_eq!((effect.consumes, effect.produces), (0, 1));
+
+        // Without CATCH the underflow is still an error
+        let err = check_source(": UNDERFLOWS DROP ;\nUNDERFLOWS").unwrap_err();
+        assert_eq!(
+            err.message,
+            "Stack underflow: 1 item needed but the stack is empty"
+        );
+    }
+
+    #[test]
+    fn test_builtins_lowered_to_instruction_sequences_match_signatures() {
+        let (program, warnings) = check_source(
+            ": SMALLER ( a b -- c ) MIN ;
+             : IN-RANGE ( n lo hi -- flag ) WITHIN ;
+             : SWAP-PAIRS ( a b c d -- c d a b ) 2SWAP ;
+             : THIRD ( a b c -- a b c a ) 2 PICK ;
+             : VIA-RETURN ( a b -- a b ) >R R@ DROP R> ;
+             : PAD ( n -- ) SPACES ;
+             : .NONZERO ( n -- ) ?DUP IF . THEN ;",
+        )
+        .unwrap();
+        assert!(warnings.is_empty());
+
+        let effect = |name: &str| {
+            let effect = &program.functions[name].stack_effect;
+            (effect.consumes, effect.produces)
+        };
+        assert_eq!(effect("SMALLER"), (2, 1));
+        assert_eq!(effect("IN-RANGE"), (3, 1));
+        assert_eq!(effect("SWAP-PAIRS"), (4, 4));
+        assert_eq!(effect("THIRD"), (3, 4));
+        assert_eq!(effect("VIA-RETURN"), (1, 1));
+        assert_eq!(effect("PAD"), (1, 0));
+
+        let err = check_source("1 2 3 2 ROLL\n2SWAP").unwrap_err();
+        assert_eq!(
+            err.message,
+            "Stack underflow: 5 items needed but the stack only holds 4 items"
+        );
+        assert_eq!(err.position.line, 2);
+    }
+
+    #[test]
+    fn test_return_stack_cells_live_in_frame_slots() {
+        let instructions = lower_source("1 2 >R DROP R>");
+        let slots: Vec<_> = instructions
+            .iter()
+            .filter(|instruction| {
+                matches!(
+                    instruction,
+                    IRInstruction::StackAlloc(_)
+                        | IRInstruction::StackSet(_)
+                        | IRInstruction::StackGet(_)
+                )
+            })
+            .collect();
+        assert_eq!(
+            slots,
+            [
+                &IRInstruction::StackAlloc(1),
+                &IRInstruction::StackSet(0),
+                &IRInstruction::StackGet(0),
+            ]
+        );
+
+        // R> with nothing moved there throws
+        let instructions = lower_source("R>");
+        assert!(
+            instructions.contains(&IRInstruction::Push(IRValue::Constant(
+                throw_code::RETURN_STACK_UNDERFLOW
+            )))
+        );
+        assert!(instructions.contains(&IRInstruction::Throw));
+    }
 }
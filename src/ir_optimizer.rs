@@ -1,5 +1,6 @@
 use crate::ir::{BinaryOpKind, IRFunction, IRInstruction, IRProgram, IRValue, UnaryOpKind};
-use std::collections::HashMap;
+use crate::types::{Cell, CellWidth};
+use std::collections::{HashMap, HashSet};
 
 /// Trait for IR optimization passes
 pub trait IROptimizationPass {
@@ -11,51 +12,50 @@ pub trait IROptimizationPass {
 /// Constant folding optimization pass
 pub struct ConstantFoldingPass {
     optimizations_applied: usize,
+    cell_width: CellWidth, // Folded results wrap exactly like the target
 }
 
 impl ConstantFoldingPass {
     pub fn new() -> Self {
         Self {
             optimizations_applied: 0,
+            cell_width: CellWidth::default(),
         }
     }
 
-    fn try_fold_binary_op(&self, op: &IRInstruction, a: i32, b: i32) -> Option<IRInstruction> {
-        match op {
-            IRInstruction::Add => Some(IRInstruction::LoadConst(a + b)),
-            IRInstruction::Sub => Some(IRInstruction::LoadConst(a - b)),
-            IRInstruction::Mul => Some(IRInstruction::LoadConst(a * b)),
-            IRInstruction::Div if b != 0 => Some(IRInstruction::LoadConst(a / b)),
-            IRInstruction::Mod if b != 0 => Some(IRInstruction::LoadConst(a % b)),
-            IRInstruction::Equal => Some(IRInstruction::LoadConst(if a == b { -1 } else { 0 })),
-            IRInstruction::NotEqual => Some(IRInstruction::LoadConst(if a != b { -1 } else { 0 })),
-            IRInstruction::Less => Some(IRInstruction::LoadConst(if a < b { -1 } else { 0 })),
-            IRInstruction::Greater => Some(IRInstruction::LoadConst(if a > b { -1 } else { 0 })),
-            IRInstruction::LessEqual => Some(IRInstruction::LoadConst(if a <= b { -1 } else { 0 })),
-            IRInstruction::GreaterEqual => {
-                Some(IRInstruction::LoadConst(if a >= b { -1 } else { 0 }))
-            }
-            IRInstruction::And => Some(IRInstruction::LoadConst(if a != 0 && b != 0 {
-                -1
-            } else {
-                0
-            })),
-            IRInstruction::Or => Some(IRInstruction::LoadConst(if a != 0 || b != 0 {
-                -1
-            } else {
-                0
-            })),
-            _ => None,
-        }
+    fn try_fold_binary_op(&self, op: &IRInstruction, a: Cell, b: Cell) -> Option<IRInstruction> {
+        let value = match op {
+            IRInstruction::Add => a.wrapping_add(b),
+            IRInstruction::Sub => a.wrapping_sub(b),
+            IRInstruction::Mul => a.wrapping_mul(b),
+            IRInstruction::Div if b != 0 => a.wrapping_div(b),
+            IRInstruction::Mod if b != 0 => a.wrapping_rem(b),
+            IRInstruction::Equal => forth_flag(a == b),
+            IRInstruction::NotEqual => forth_flag(a != b),
+            IRInstruction::Less => forth_flag(a < b),
+            IRInstruction::Greater => forth_flag(a > b),
+            IRInstruction::LessEqual => forth_flag(a <= b),
+            IRInstruction::GreaterEqual => forth_flag(a >= b),
+            IRInstruction::And => forth_flag(a != 0 && b != 0),
+            IRInstruction::Or => forth_flag(a != 0 || b != 0),
+            IRInstruction::Xor => a ^ b,
+            _ => return None,
+        };
+        Some(IRInstruction::LoadConst(self.cell_width.wrap(value)))
+    }
+
+    fn try_fold_unary_op(&self, op: &IRInstruction, a: Cell) -> Option<IRInstruction> {
+        let value = match op {
+            IRInstruction::Neg => a.wrapping_neg(),
+            IRInstruction::Not => forth_flag(a == 0),
+            _ => return None,
+        };
+        Some(IRInstruction::LoadConst(self.cell_width.wrap(value)))
     }
+}
 
-    fn try_fold_unary_op(&self, op: &IRInstruction, a: i32) -> Option<IRInstruction> {
-        match op {
-            IRInstruction::Neg => Some(IRInstruction::LoadConst(-a)),
-            IRInstruction::Not => Some(IRInstruction::LoadConst(if a == 0 { -1 } else { 0 })),
-            _ => None,
-        }
-    }
+fn forth_flag(condition: bool) -> Cell {
+    if condition { -1 } else { 0 }
 }
 
 impl IROptimizationPass for ConstantFoldingPass {
@@ -64,6 +64,7 @@ impl IROptimizationPass for ConstantFoldingPass {
     }
 
     fn optimize_program(&mut self, program: &mut IRProgram) -> bool {
+        self.cell_width = program.cell_width;
         let mut changed = false;
         changed |= self.optimize_function(&mut program.main);
 
@@ -247,12 +248,14 @@ impl IROptimizationPass for DeadCodeEliminationPass {
 /// Peephole optimization pass for stack operations
 pub struct PeepholeOptimizationPass {
     optimizations_applied: usize,
+    cell_width: CellWidth,
 }
 
 impl PeepholeOptimizationPass {
     pub fn new() -> Self {
         Self {
             optimizations_applied: 0,
+            cell_width: CellWidth::default(),
         }
     }
 }
@@ -263,6 +266,7 @@ impl IROptimizationPass for PeepholeOptimizationPass {
     }
 
     fn optimize_program(&mut self, program: &mut IRProgram) -> bool {
+        self.cell_width = program.cell_width;
         let mut changed = false;
         changed |= self.optimize_function(&mut program.main);
 
@@ -291,7 +295,8 @@ impl IROptimizationPass for PeepholeOptimizationPass {
                     &function.instructions[i + 1],
                     &function.instructions[i + 2],
                 ) {
-                    function.instructions[i] = IRInstruction::LoadConst(a * 2);
+                    function.instructions[i] =
+                        IRInstruction::LoadConst(self.cell_width.wrap(a.wrapping_mul(2)));
                     function.instructions.remove(i + 1);
                     function.instructions.remove(i + 1);
                     changed = true;
@@ -499,15 +504,22 @@ impl FunctionInliningPass {
         }
 
         // Check for problematic instructions that make inlining unsafe
-        for instr in &function.instructions {
+        let last = function.instructions.len().saturating_sub(1);
+        for (i, instr) in function.instructions.iter().enumerate() {
             match instr {
-                // Don't inline recursive functions
-                IRInstruction::Call(name) if name == &function.name => return false,
                 // Don't inline functions with control flow (for now)
                 IRInstruction::Jump(_)
                 | IRInstruction::JumpIf(_)
                 | IRInstruction::JumpIfNot(_)
-                | IRInstruction::Label(_) => return false,
+                | IRInstruction::Label(_)
+                | IRInstruction::DoLoop(_, _)
+                | IRInstruction::Loop(_)
+                | IRInstruction::PlusLoop(_)
+                | IRInstruction::Leave(_) => return false,
+                // An early EXIT would be dropped along with the trailing Return
+                IRInstruction::Return if i != last => return false,
+                // Locals live in the function's own frame
+                IRInstruction::StackAlloc(_) => return false,
                 _ => {}
             }
         }
@@ -515,6 +527,39 @@ impl FunctionInliningPass {
         true
     }
 
+    /// Functions that can call themselves, directly or through others.
+    /// Inlining one would only ever leave another call to inline.
+    fn recursive_functions(program: &IRProgram) -> HashSet<String> {
+        let calls = |function: &'_ IRFunction| {
+            function
+                .instructions
+                .iter()
+                .filter_map(|instr| match instr {
+                    IRInstruction::Call(name) => Some(name.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut recursive = HashSet::new();
+        for (name, function) in &program.functions {
+            let mut seen = HashSet::new();
+            let mut pending = calls(function);
+            while let Some(callee) = pending.pop() {
+                if &callee == name {
+                    recursive.insert(name.clone());
+                    break;
+                }
+                if let Some(callee_function) = program.functions.get(&callee)
+                    && seen.insert(callee)
+                {
+                    pending.extend(calls(callee_function));
+                }
+            }
+        }
+        recursive
+    }
+
     /// Get the inlinable body of a function (excluding Return instruction)
     fn get_inline_body(&self, function: &IRFunction) -> Vec<IRInstruction> {
         function
@@ -535,9 +580,10 @@ impl IROptimizationPass for FunctionInliningPass {
         let mut changed = false;
 
         // First, identify which functions are inlinable
+        let recursive = Self::recursive_functions(program);
         let mut inlinable_functions = HashMap::new();
         for (name, function) in &program.functions {
-            if self.is_inlinable(function) {
+            if self.is_inlinable(function) && !recursive.contains(name) {
                 inlinable_functions.insert(name.clone(), self.get_inline_body(function));
             }
         }
@@ -659,7 +705,7 @@ impl IROptimizer {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::ir::{IRBuilder, IRValue};
+    use crate::ir::{IRBuilder, IRValue, StackEffect};
 
     #[test]
     fn test_constant_folding() {
@@ -717,6 +763,24 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_constant_folding_wraps_to_cell_width() {
+        let mut builder = IRBuilder::new("test");
+        builder.emit(IRInstruction::Push(IRValue::Constant(2147483647)));
+        builder.emit(IRInstruction::Push(IRValue::Constant(1)));
+        builder.emit(IRInstruction::Add);
+
+        let mut program = builder.build();
+        program.cell_width = CellWidth::Bits32;
+        let mut pass = ConstantFoldingPass::new();
+
+        assert!(pass.optimize_program(&mut program));
+        assert_eq!(
+            program.main.instructions,
+            vec![IRInstruction::LoadConst(-2147483648)]
+        );
+    }
+
     #[test]
     fn test_dead_code_elimination() {
         let mut builder = IRBuilder::new("test");
@@ -799,4 +863,111 @@ mod tests {
             IRInstruction::LoadConst(10)
         ));
     }
+
+    #[test]
+    fn test_inlining_skips_early_exit() {
+        let function = |name: &str, instructions| IRFunction {
+            name: name.to_string(),
+            instructions,
+            stack_effect: StackEffect::default(),
+        };
+        let mut program = IRProgram {
+            main: function(
+                "main",
+                vec![
+                    IRInstruction::Call("EARLY".to_string()),
+                    IRInstruction::Call("PLAIN".to_string()),
+                ],
+            ),
+            functions: HashMap::new(),
+            wrappers: HashMap::new(),
+            cell_width: CellWidth::default(),
+        };
+        // : EARLY 1 . EXIT 2 . ;
+        program.functions.insert(
+            "EARLY".to_string(),
+            function(
+                "EARLY",
+                vec![
+                    IRInstruction::Push(IRValue::Constant(1)),
+                    IRInstruction::Print,
+                    IRInstruction::Return,
+                    IRInstruction::Push(IRValue::Constant(2)),
+                    IRInstruction::Print,
+                    IRInstruction::Return,
+                ],
+            ),
+        );
+        // : PLAIN 3 . ;
+        program.functions.insert(
+            "PLAIN".to_string(),
+            function(
+                "PLAIN",
+                vec![
+                    IRInstruction::Push(IRValue::Constant(3)),
+                    IRInstruction::Print,
+                    IRInstruction::Return,
+                ],
+            ),
+        );
+
+        let mut pass = FunctionInliningPass::new();
+        assert!(pass.optimize_program(&mut program));
+
+        assert_eq!(
+            program.main.instructions,
+            vec![
+                IRInstruction::Call("EARLY".to_string()),
+                IRInstruction::Push(IRValue::Constant(3)),
+                IRInstruction::Print,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inlining_skips_call_cycles() {
+        let function = |name: &str, callee: &str| IRFunction {
+            name: name.to_string(),
+            instructions: vec![
+                IRInstruction::Call(callee.to_string()),
+                IRInstruction::Return,
+            ],
+            stack_effect: StackEffect::default(),
+        };
+        // : PING PONG ; : PONG PING ; : START PING ;
+        let mut program = IRBuilder::new("main").build();
+        program
+            .main
+            .instructions
+            .push(IRInstruction::Call("START".to_string()));
+        for (name, callee) in [("PING", "PONG"), ("PONG", "PING"), ("START", "PING")] {
+            program
+                .functions
+                .insert(name.to_string(), function(name, callee));
+        }
+
+        let mut optimizer = IROptimizer::new();
+        optimizer.optimize(&mut program);
+
+        // Only START, outside the cycle, is inlined
+        let calls = |function: &IRFunction| {
+            function
+                .instructions
+                .iter()
+                .filter(|instr| !matches!(instr, IRInstruction::Comment(_)))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            calls(&program.main),
+            vec![IRInstruction::Call("PING".to_string())]
+        );
+        assert_eq!(
+            calls(&program.functions["PING"]),
+            vec![
+                IRInstruction::Call("PONG".to_string()),
+                IRInstruction::Return
+            ]
+        );
+    }
 }
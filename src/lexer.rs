@@ -1,10 +1,142 @@
-use crate::types::{ParseError, Position, Token, TokenType};
+use crate::types::{
+    Cell, CellWidth, DoubleCell, Float, ParseError, ParseErrors, Position, Token, TokenType,
+};
+
+/// Whether a whitespace-delimited word starts a comment that runs to the end of
+/// the line
+pub fn is_line_comment_word(word: &str) -> bool {
+    matches!(word, "\\" | "\\G" | "\\g")
+}
+
+/// Why text could not be read as a number
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberError {
+    /// Not spelled as a number in the radix, so it may be a word
+    NotANumber,
+    /// Spelled as a number, but too wide for its cell or cell pair
+    OutOfRange,
+}
+
+/// Parse a numeric literal in the given radix.
+///
+/// `$`, `#` and `%` prefixes select hex, decimal and binary regardless of
+/// `base`, `'c'` is a character literal and a trailing `.` makes a double-cell
+/// number. Values that overflow a `width` cell are out of range, except that
+/// unsigned spellings such as `$FFFFFFFF` on 32-bit cells wrap into the
+/// negative range.
+pub fn parse_number(text: &str, base: u32, width: CellWidth) -> Result<TokenType, NumberError> {
+    let chars: Vec<char> = text.chars().collect();
+    if let ['\'', c, '\''] = chars[..] {
+        return Ok(TokenType::Number(c as Cell));
+    }
+
+    let (mut negative, mut rest) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let mut radix = base;
+    if let Some(prefix) = rest.chars().next() {
+        let prefix_radix = match prefix {
+            '$' => Some(16),
+            '#' => Some(10),
+            '%' => Some(2),
+            _ => None,
+        };
+        if let Some(prefix_radix) = prefix_radix {
+            radix = prefix_radix;
+            rest = &rest[1..];
+            if !negative && let Some(unsigned) = rest.strip_prefix('-') {
+                negative = true;
+                rest = unsigned;
+            }
+        }
+    }
+    let (digits, double) = match rest.strip_suffix('.') {
+        Some(digits) => (digits, true),
+        None => (rest, false),
+    };
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return Err(NumberError::NotANumber);
+    }
+
+    let mut value: u128 = 0;
+    for c in digits.chars() {
+        let digit = c.to_digit(radix).unwrap_or_default();
+        value = value
+            .checked_mul(radix as u128)
+            .and_then(|value| value.checked_add(digit as u128))
+            .ok_or(NumberError::OutOfRange)?;
+    }
+
+    // Literals must fit the unsigned range of their cells and then wrap
+    if double {
+        if value.checked_shr(2 * width.bits()).unwrap_or(0) != 0 {
+            return Err(NumberError::OutOfRange);
+        }
+        let value = value as DoubleCell;
+        Ok(TokenType::DoubleNumber(width.wrap_double(if negative {
+            value.wrapping_neg()
+        } else {
+            value
+        })))
+    } else {
+        if value >> width.bits() != 0 {
+            return Err(NumberError::OutOfRange);
+        }
+        let value = value as Cell;
+        Ok(TokenType::Number(width.wrap(if negative {
+            value.wrapping_neg()
+        } else {
+            value
+        })))
+    }
+}
+
+/// Parse a floating-point literal such as `1.5e0`, `-2E3` or `1e`.
+///
+/// As in standard Forth the exponent marker is required, since `1.5` is a
+/// double-cell number, and floats are only recognised in decimal.
+pub fn parse_float(text: &str) -> Option<Float> {
+    let (mantissa, exponent) = text.split_once(['e', 'E'])?;
+    let unsigned = mantissa.strip_prefix(['-', '+']).unwrap_or(mantissa);
+    let (whole, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+    let digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+    if whole.len() + fraction.len() == 0 || !digits(whole) || !digits(fraction) {
+        return None;
+    }
+    let exponent_digits = exponent.strip_prefix(['-', '+']).unwrap_or(exponent);
+    if !digits(exponent_digits) {
+        return None;
+    }
+
+    // Rust wants digits on both sides of the point and after the marker
+    let sign = if mantissa.starts_with('-') { "-" } else { "" };
+    let exponent = if exponent_digits.is_empty() {
+        "0"
+    } else {
+        exponent
+    };
+    format!("{}0{}.{}0e{}", sign, whole, fraction, exponent)
+        .parse()
+        .ok()
+}
+
+/// Words that take a string up to the next `"`; `S\"` before `S"`, which
+/// it starts with
+const STRING_OPENERS: [&str; 5] = ["S\\\"", "S\"", "C\"", ".\"", "ABORT\""];
+
+/// Longest text a counted string's length byte can describe
+pub const MAX_COUNTED_STRING: usize = 255;
 
 pub struct Lexer {
     input: String,
     position: usize,
     line: usize,
     column: usize,
+    base: u32,           // Radix for number literals, changed by HEX/DECIMAL/BASE !
+    in_definition: bool, // Radix changes only take effect outside definitions
+    cell_width: CellWidth,
+    file: usize, // Recorded in every position, see `SourceMap`
 }
 
 impl Lexer {
@@ -14,13 +146,63 @@ impl Lexer {
             position: 0,
             line: 1,
             column: 1,
+            base: 10,
+            in_definition: false,
+            cell_width: CellWidth::default(),
+            file: 0,
         }
     }
 
-    pub fn tokenize(&mut self) -> Result<Vec<Token>, ParseError> {
+    /// Start lexing with a radix carried over from earlier input (used by the REPL).
+    pub fn with_base(mut self, base: u32) -> Self {
+        self.base = base;
+        self
+    }
+
+    /// Range-check and wrap number literals for the given cell width
+    pub fn with_cell_width(mut self, cell_width: CellWidth) -> Self {
+        self.cell_width = cell_width;
+        self
+    }
+
+    /// Position tokens in file `file` of a `SourceMap`
+    pub fn with_file(mut self, file: usize) -> Self {
+        self.file = file;
+        self
+    }
+
+    /// The radix in effect after the last tokenized input
+    pub fn base(&self) -> u32 {
+        self.base
+    }
+
+    /// Change the radix for the rest of the input, as an included file may
+    pub fn set_base(&mut self, base: u32) {
+        self.base = base;
+    }
+
+    /// Whether all of the input has been tokenized
+    pub fn finished(&self) -> bool {
+        self.position >= self.input.len()
+    }
+
+    /// Split the input into tokens, reporting every malformed token rather
+    /// than stopping at the first
+    pub fn tokenize(&mut self) -> Result<Vec<Token>, ParseErrors> {
+        self.tokenize_until(|_| false)
+    }
+
+    /// Tokenize until `stop` holds for the tokens so far, such as at an
+    /// INCLUDE that should be read with the radix in effect at that point.
+    /// Call again until `finished` for the rest of the input.
+    pub fn tokenize_until(
+        &mut self,
+        stop: impl Fn(&[Token]) -> bool,
+    ) -> Result<Vec<Token>, ParseErrors> {
         let mut tokens = Vec::new();
+        let mut errors = Vec::new();
 
-        while self.position < self.input.len() {
+        while self.position < self.input.len() && !stop(&tokens) {
             self.skip_whitespace();
 
             if self.position >= self.input.len() {
@@ -31,27 +213,83 @@ impl Lexer {
                 line: self.line,
                 column: self.column,
                 offset: self.position,
+                file: self.file,
             };
 
-            let token = self.next_token(start_pos)?;
-            tokens.push(token);
+            let start = self.position;
+            let token = match self.next_token(start_pos) {
+                Ok(token) => token,
+                Err(error) => {
+                    errors.push(error);
+                    if self.position == start {
+                        self.advance();
+                    }
+                    continue;
+                }
+            };
+            match self.apply_base_directive(&token, &tokens) {
+                Ok(()) => tokens.push(token),
+                Err(error) => errors.push(error),
+            }
         }
 
+        ParseErrors::result(errors)?;
         Ok(tokens)
     }
 
+    /// Follow HEX, DECIMAL and `n BASE !` outside definitions, since they
+    /// change how the rest of the file is read. The tokens are kept, so that
+    /// they set BASE for printing at run time as well.
+    fn apply_base_directive(&mut self, token: &Token, tokens: &[Token]) -> Result<(), ParseError> {
+        match &token.token_type {
+            TokenType::StartDefinition => self.in_definition = true,
+            TokenType::EndDefinition => self.in_definition = false,
+            TokenType::Word(word) if !self.in_definition => match word.as_str() {
+                "HEX" => self.base = 16,
+                "DECIMAL" => self.base = 10,
+                "!" => {
+                    if let [.., number, base_word] = tokens
+                        && let TokenType::Number(radix) = number.token_type
+                        && base_word.token_type == TokenType::Word("BASE".to_string())
+                    {
+                        if !(2..=36).contains(&radix) {
+                            return Err(ParseError::new(
+                                format!("Invalid BASE: {}", radix),
+                                number.position.clone(),
+                            )
+                            .with_help("BASE must be between 2 and 36"));
+                        }
+                        self.base = radix as u32;
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        Ok(())
+    }
+
     fn next_token(&mut self, start_pos: Position) -> Result<Token, ParseError> {
         let ch = self.current_char();
 
-        // Check for S" string literal pattern (S followed by ")
-        if (ch == 'S' || ch == 's') && self.peek_char() == Some('"') {
-            return self.read_s_quote_literal(start_pos);
+        // S" S\" C" ." and ABORT" take the text up to the closing "
+        let word = self.current_word().to_uppercase();
+        if let Some(opener) = STRING_OPENERS
+            .into_iter()
+            .find(|opener| word.starts_with(opener))
+        {
+            return self.read_quoted_string(opener, start_pos);
+        }
+
+        if ch == '\\' && is_line_comment_word(&self.current_word()) {
+            return self.read_line_comment(start_pos);
         }
 
         match ch {
             '(' => self.read_comment(start_pos),
             '"' => self.read_string_literal(start_pos),
-            ':' => {
+            // Words such as :} only start with a colon
+            ':' if self.current_word() == ":" => {
                 self.advance();
                 Ok(Token {
                     token_type: TokenType::StartDefinition,
@@ -87,16 +325,49 @@ impl Lexer {
         }
 
         if token_str.is_empty() {
-            return Err(ParseError {
-                message: "Unexpected character".to_string(),
-                position: start_pos,
+            let error = ParseError::new(
+                format!("Unexpected character '{}'", self.current_char()),
+                start_pos,
+            );
+            return Err(if self.current_char() == ')' {
+                error.with_help("')' only closes a comment opened with '('")
+            } else {
+                error
             });
         }
 
         // Check if the token is a valid number
-        if let Ok(num) = token_str.parse::<i32>() {
+        match parse_number(&token_str, self.base, self.cell_width) {
+            Ok(number) => {
+                return Ok(Token {
+                    token_type: number,
+                    position: start_pos,
+                    raw: token_str,
+                });
+            }
+            Err(NumberError::OutOfRange) => {
+                let bits = self.cell_width.bits();
+                let cells = if token_str.ends_with('.') {
+                    format!("two {}-bit cells", bits)
+                } else {
+                    format!("a {}-bit cell", bits)
+                };
+                let error = ParseError::new(
+                    format!("{} does not fit in {}", token_str, cells),
+                    start_pos,
+                );
+                return Err(match self.cell_width {
+                    CellWidth::Bits32 => error.with_help("use --cell-bits 64 for wider cells"),
+                    CellWidth::Bits64 => error,
+                });
+            }
+            Err(NumberError::NotANumber) => {}
+        }
+        if self.base == 10
+            && let Some(float) = parse_float(&token_str)
+        {
             return Ok(Token {
-                token_type: TokenType::Number(num),
+                token_type: TokenType::Float(float),
                 position: start_pos,
                 raw: token_str,
             });
@@ -110,11 +381,16 @@ impl Lexer {
         })
     }
 
-    fn read_s_quote_literal(&mut self, start_pos: Position) -> Result<Token, ParseError> {
-        self.advance(); // skip 'S'
-        self.advance(); // skip '"'
+    fn read_quoted_string(
+        &mut self,
+        opener: &str,
+        start_pos: Position,
+    ) -> Result<Token, ParseError> {
+        for _ in 0..opener.len() {
+            self.advance();
+        }
 
-        // Skip optional space after S"
+        // Skip optional space after the opener
         if self.position < self.input.len() && self.current_char() == ' ' {
             self.advance();
         }
@@ -127,22 +403,97 @@ impl Lexer {
                 self.advance();
                 break;
             }
-            string.push(ch);
-            self.advance();
+            if ch == '\\' && opener == "S\\\"" {
+                self.read_escape(&mut string);
+            } else {
+                string.push(ch);
+                self.advance();
+            }
         }
 
+        let raw = format!("{} {}\"", opener, string);
+        let token_type = match opener {
+            "C\"" if string.len() > MAX_COUNTED_STRING => {
+                return Err(ParseError::new(
+                    format!(
+                        "C\" string is {} bytes long but its count byte holds at most {}",
+                        string.len(),
+                        MAX_COUNTED_STRING
+                    ),
+                    start_pos,
+                )
+                .with_help("use S\" for longer strings"));
+            }
+            "C\"" => TokenType::CQuote(string),
+            ".\"" => TokenType::DotQuote(string),
+            "ABORT\"" => TokenType::AbortQuote(string),
+            _ => TokenType::StringLiteral(string),
+        };
         Ok(Token {
-            token_type: TokenType::StringLiteral(string.clone()),
+            token_type,
             position: start_pos,
-            raw: format!("S\" {}\"", string),
+            raw,
         })
     }
 
+    /// Read the escape sequence at a `\` into `string`: the escapes of
+    /// `S\"`, such as `\n`, `\q` and `\x41`. Any other character stands for
+    /// itself.
+    fn read_escape(&mut self, string: &mut String) {
+        self.advance(); // skip '\'
+        if self.position >= self.input.len() {
+            return;
+        }
+        let escaped = match self.current_char() {
+            'a' => '\x07',
+            'b' => '\x08',
+            'e' => '\x1b',
+            'f' => '\x0c',
+            'l' | 'n' => '\n',
+            'm' => {
+                string.push('\r');
+                '\n'
+            }
+            'q' => '"',
+            'r' => '\r',
+            't' => '\t',
+            'v' => '\x0b',
+            'z' => '\0',
+            'x' => {
+                let digits: String = self
+                    .input
+                    .chars()
+                    .skip(self.position + 1)
+                    .take(2)
+                    .take_while(|c| c.is_ascii_hexdigit())
+                    .collect();
+                if let Ok(code) = u8::from_str_radix(&digits, 16) {
+                    for _ in 0..digits.len() {
+                        self.advance();
+                    }
+                    char::from(code)
+                } else {
+                    'x'
+                }
+            }
+            c => c,
+        };
+        string.push(escaped);
+        self.advance();
+    }
+
     fn read_comment(&mut self, start_pos: Position) -> Result<Token, ParseError> {
         let mut comment = String::new();
         self.advance(); // skip '('
 
-        while self.position < self.input.len() {
+        loop {
+            if self.position >= self.input.len() {
+                return Err(ParseError::new(
+                    "Unterminated comment: missing ')'".to_string(),
+                    start_pos,
+                )
+                .with_help("end the comment with ')'"));
+            }
             let ch = self.current_char();
             if ch == ')' {
                 self.advance();
@@ -159,6 +510,23 @@ impl Lexer {
         })
     }
 
+    /// `\` and `\G` comments run to the end of the line
+    fn read_line_comment(&mut self, start_pos: Position) -> Result<Token, ParseError> {
+        let mut raw = String::new();
+        while self.position < self.input.len() && self.current_char() != '\n' {
+            raw.push(self.current_char());
+            self.advance();
+        }
+
+        // Drop the `\` or `\G` marker itself
+        let text = raw.trim_start_matches(|c: char| !c.is_whitespace()).trim();
+        Ok(Token {
+            token_type: TokenType::Comment(text.to_string()),
+            position: start_pos,
+            raw,
+        })
+    }
+
     fn read_string_literal(&mut self, start_pos: Position) -> Result<Token, ParseError> {
         let mut string = String::new();
         self.advance(); // skip opening '"'
@@ -170,19 +538,7 @@ impl Lexer {
                 break;
             }
             if ch == '\\' {
-                self.advance();
-                if self.position < self.input.len() {
-                    let escaped = match self.current_char() {
-                        'n' => '\n',
-                        't' => '\t',
-                        'r' => '\r',
-                        '\\' => '\\',
-                        '"' => '"',
-                        c => c,
-                    };
-                    string.push(escaped);
-                    self.advance();
-                }
+                self.read_escape(&mut string);
             } else {
                 string.push(ch);
                 self.advance();
@@ -200,8 +556,13 @@ impl Lexer {
         self.input.chars().nth(self.position).unwrap_or('\0')
     }
 
-    fn peek_char(&self) -> Option<char> {
-        self.input.chars().nth(self.position + 1)
+    /// The whitespace-delimited word starting at the current position
+    fn current_word(&self) -> String {
+        self.input
+            .chars()
+            .skip(self.position)
+            .take_while(|c| !c.is_whitespace())
+            .collect()
     }
 
     fn advance(&mut self) {
@@ -1,11 +1,15 @@
 pub mod analyzer;
 pub mod codegen;
+pub mod diagnostics;
+pub mod expander;
 pub mod highlighter;
 pub mod ir;
 pub mod ir_codegen;
+pub mod ir_interpreter;
 pub mod ir_lowering;
 pub mod ir_optimizer;
 pub mod lexer;
 pub mod parser;
 pub mod repl;
+pub mod source;
 pub mod types;
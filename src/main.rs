@@ -1,25 +1,34 @@
 mod analyzer;
 mod codegen;
+mod diagnostics;
+mod expander;
 mod highlighter;
 mod ir;
 mod ir_codegen;
+mod ir_interpreter;
 mod ir_lowering;
 mod ir_optimizer;
 mod lexer;
 mod parser;
 mod repl;
+mod source;
 mod types;
 
 use crate::analyzer::SemanticAnalyzer;
+use crate::codegen::backends::create_target_info;
 use crate::codegen::{Backend, CodeGenerator};
+use crate::diagnostics::{DiagnosticRenderer, WarningOptions};
+use crate::expander::Expander;
 use crate::highlighter::SyntaxHighlighter;
 use crate::ir_codegen::IRRustGenerator;
-use crate::ir_lowering::IRLowering;
+use crate::ir_lowering::{IRLowering, StackEffectAnalyzer};
 use crate::ir_optimizer::IROptimizer;
-use crate::lexer::Lexer;
+use crate::lexer::NumberError;
 use crate::parser::Parser;
+use crate::source::SourceLoader;
+use crate::types::{Cell, CellWidth, TokenType};
 use clap::Parser as ClapParser;
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::{self, Command};
@@ -53,152 +62,80 @@ struct Args {
     #[arg(long, short, help = "Output file name")]
     output: Option<String>,
 
+    #[arg(long, default_value = "64", help = "Cell width in bits (32 or 64)")]
+    cell_bits: u32,
+
     #[arg(long, help = "Compile and run the generated code")]
     run: bool,
 
     #[arg(long, short = 'i', help = "Start interactive REPL")]
     interactive: bool,
-}
-
-/// Preprocesses source code to handle INCLUDE statements
-/// Recursively expands all INCLUDE directives
-fn preprocess_includes(
-    content: &str,
-    base_path: &Path,
-    included_files: &mut HashSet<PathBuf>,
-    debug: u8,
-) -> Result<String, String> {
-    let mut result = String::new();
-    let mut chars = content.chars().peekable();
-
-    while let Some(c) = chars.next() {
-        // Handle comments - pass them through as-is
-        if c == '(' {
-            result.push(c);
-            // Skip until closing paren
-            while let Some(ch) = chars.next() {
-                result.push(ch);
-                if ch == ')' {
-                    break;
-                }
-            }
-            continue;
-        }
-
-        // Check for INCLUDE keyword
-        if c == 'I' || c == 'i' {
-            let mut word = String::new();
-            word.push(c);
-
-            // Collect the rest of the word
-            while let Some(&ch) = chars.peek() {
-                if ch.is_alphabetic() {
-                    word.push(chars.next().unwrap());
-                } else {
-                    break;
-                }
-            }
 
-            if word.to_uppercase() == "INCLUDE" {
-                // Skip whitespace
-                while let Some(&ch) = chars.peek() {
-                    if ch.is_whitespace() {
-                        chars.next();
-                    } else {
-                        break;
-                    }
-                }
+    #[arg(
+        short = 'W',
+        value_name = "WARNING",
+        help = "Turn a warning on (-Wunused), off (-Wno-shadow), all on (-Wall) or into errors (-Werror)"
+    )]
+    warnings: Vec<String>,
 
-                // Parse filename (can be quoted or unquoted)
-                let mut filename = String::new();
-                if let Some(&ch) = chars.peek() {
-                    if ch == '"' {
-                        chars.next(); // consume opening quote
-                        while let Some(ch) = chars.next() {
-                            if ch == '"' {
-                                break;
-                            }
-                            filename.push(ch);
-                        }
-                    } else {
-                        // Unquoted filename - read until whitespace or end
-                        while let Some(&ch) = chars.peek() {
-                            if ch.is_whitespace() {
-                                break;
-                            }
-                            filename.push(chars.next().unwrap());
-                        }
-                    }
-                }
+    #[arg(
+        short = 'I',
+        value_name = "DIR",
+        help = "Look for INCLUDE and REQUIRE files in DIR (before ROTH_PATH and the bundled std library)"
+    )]
+    include_dirs: Vec<PathBuf>,
 
-                if filename.is_empty() {
-                    return Err("INCLUDE requires a filename".to_string());
-                }
+    #[arg(
+        short = 'D',
+        value_name = "NAME[=VALUE]",
+        help = "Define constant NAME as VALUE, or as TRUE without one, for [IF] and [DEFINED]"
+    )]
+    defines: Vec<String>,
+}
 
-                // Resolve the filename: try relative to base path first, then relative to CWD
-                let include_path = base_path.join(&filename);
-                let canonical_path = if include_path.exists() {
-                    include_path.canonicalize().map_err(|e| {
-                        format!("Cannot canonicalize include file '{}': {}", filename, e)
-                    })?
-                } else {
-                    // Try relative to current working directory
-                    let cwd_path = Path::new(&filename);
-                    if cwd_path.exists() {
-                        cwd_path.canonicalize().map_err(|e| {
-                            format!("Cannot canonicalize include file '{}': {}", filename, e)
-                        })?
-                    } else {
-                        return Err(format!("Cannot find include file '{}': file not found in '{}' or current directory", 
-                            filename, base_path.display()));
-                    }
-                };
-
-                // Check for circular includes
-                if included_files.contains(&canonical_path) {
-                    if debug >= 2 {
-                        println!(
-                            "Skipping already included file: {}",
-                            canonical_path.display()
-                        );
-                    }
-                    result.push_str(&format!("( Already included: {} )", filename));
-                    continue;
+/// Parse a `-D NAME=VALUE` or `-D NAME` define
+fn parse_define(define: &str, cell_width: CellWidth) -> Result<(String, Cell), String> {
+    let (name, value) = match define.split_once('=') {
+        Some((name, value)) => {
+            let value = match lexer::parse_number(value, 10, cell_width) {
+                Ok(TokenType::Number(value)) => value,
+                Err(NumberError::OutOfRange) => {
+                    return Err(format!(
+                        "Invalid define: -D {}. The value does not fit in a {}-bit cell",
+                        define,
+                        cell_width.bits()
+                    ));
                 }
-
-                included_files.insert(canonical_path.clone());
-
-                if debug >= 2 {
-                    println!("Including file: {}", canonical_path.display());
+                _ => {
+                    return Err(format!(
+                        "Invalid define: -D {}. The value must be a number",
+                        define
+                    ));
                 }
-
-                // Read the included file
-                let included_content = fs::read_to_string(&canonical_path)
-                    .map_err(|e| format!("Error reading include file '{}': {}", filename, e))?;
-
-                // Get the directory of the included file for nested includes
-                let included_dir = canonical_path
-                    .parent()
-                    .ok_or_else(|| format!("Cannot get parent directory of '{}'", filename))?;
-
-                // Recursively preprocess the included content
-                let processed =
-                    preprocess_includes(&included_content, included_dir, included_files, debug)?;
-
-                // Add a comment indicating the included file and append its content
-                result.push_str(&format!("\n( Begin included file: {} )\n", filename));
-                result.push_str(&processed);
-                result.push_str(&format!("\n( End included file: {} )\n", filename));
-            } else {
-                // Not INCLUDE, just a regular word starting with 'I'
-                result.push_str(&word);
-            }
-        } else {
-            result.push(c);
+            };
+            (name, value)
         }
+        None => (define, -1),
+    };
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return Err(format!("Invalid define: -D {}. Expected -D NAME=VALUE", define));
+    }
+    let name = name.to_uppercase();
+    if SemanticAnalyzer::new().is_builtin(&name) {
+        return Err(format!(
+            "Invalid define: -D {}. Cannot redefine builtin word: {}",
+            define, name
+        ));
     }
+    Ok((name, value))
+}
 
-    Ok(result)
+/// How the source is read and checked, whatever the backend
+struct CompileOptions {
+    cell_width: CellWidth,
+    warnings: WarningOptions,
+    search_paths: Vec<PathBuf>, // Where INCLUDE and REQUIRE look for files
+    defines: Vec<(String, Cell)>, // -D constants
 }
 
 fn compile_file(
@@ -208,49 +145,79 @@ fn compile_file(
     debug: u8,
     no_color: bool,
     run: bool,
+    options: CompileOptions,
 ) -> Result<(), String> {
+    let CompileOptions {
+        cell_width,
+        warnings: warning_options,
+        search_paths,
+        mut defines,
+    } = options;
     let content = fs::read_to_string(filename)
         .map_err(|e| format!("Error reading file '{}': {}", filename, e))?;
 
-    // Preprocess to handle INCLUDE statements
-    let source_path = Path::new(filename);
-    let base_dir = source_path.parent().unwrap_or(Path::new("."));
-    let canonical_source = source_path
-        .canonicalize()
-        .map_err(|e| format!("Cannot canonicalize source path: {}", e))?;
-
-    let mut included_files = HashSet::new();
-    included_files.insert(canonical_source);
-
-    let preprocessed = preprocess_includes(&content, base_dir, &mut included_files, debug)?;
-
-    if debug >= 2 {
-        println!("Preprocessed source:\n{}", preprocessed);
-    }
+    // Every stage below agrees on the target's cell width
+    let mut target = create_target_info(backend.to_registry_name());
+    target.cell_width = cell_width;
 
-    let mut lexer = Lexer::new(preprocessed);
-    let tokens = lexer
-        .tokenize()
-        .map_err(|e| format!("Lexer error: {}", e))?;
+    // INCLUDEd files are lexed in place, keeping their own positions
+    let mut loader = SourceLoader::new()
+        .with_cell_width(target.cell_width)
+        .with_search_paths(search_paths)
+        .with_debug(debug);
+    let loaded = loader.load(Path::new(filename), content);
+    let renderer = DiagnosticRenderer::new(loader.sources())
+        .with_color(!no_color && atty::is(atty::Stream::Stderr));
+    let tokens = loaded.map_err(|errors| renderer.report_errors("lexing", &errors))?;
 
     if debug >= 2 {
         println!("Tokens: {:?}", tokens);
     }
 
+    // IMMEDIATE words and [ ... ] run here, before the definitions using them are parsed
+    // -D defines and the backend flag are constants in every stage
+    defines.push(expander::backend_define(backend.to_registry_name()));
+    let mut expander = Expander::new()
+        .with_cell_width(target.cell_width)
+        .with_defines(defines);
+    let tokens = expander
+        .expand(&tokens)
+        .map_err(|errors| renderer.report_errors("parsing", &errors))?;
+
     let mut parser = Parser::new(tokens);
-    let ast = parser.parse().map_err(|e| format!("Parser error: {}", e))?;
+    let ast = parser
+        .parse()
+        .map_err(|errors| renderer.report_errors("parsing", &errors))?;
 
     if debug >= 2 {
         println!("AST: {:#?}", ast);
     }
 
-    let mut analyzer = SemanticAnalyzer::new();
-    analyzer
-        .analyze(&ast)
-        .map_err(|e| format!("Semantic analysis error: {}", e))?;
-
-    let mut ir_lowering = IRLowering::new();
+    let mut analyzer = SemanticAnalyzer::new().with_forward_references();
+    for (name, _) in expander.defines() {
+        analyzer.add_constant(name.clone());
+    }
+    // Errors come first when there are both, even if warnings are errors
+    let analyzed = analyzer.analyze(&ast);
+    let warned = renderer.report_warnings(&warning_options, analyzer.warnings());
+    analyzed.map_err(|errors| renderer.report_errors("semantic analysis", &errors))?;
+    warned?;
+
+    let mut ir_lowering = IRLowering::new().with_cell_width(target.cell_width);
+    for (name, value) in expander.defines() {
+        ir_lowering.add_known_constant(name.clone(), vec![*value]);
+    }
     let mut ir = ir_lowering.lower(&ast);
+    let warnings = StackEffectAnalyzer::check_program(
+        &mut ir,
+        &ast,
+        ir_lowering.statement_starts(),
+        0,
+        0,
+        &mut HashMap::new(),
+    )
+    .map_err(|error| renderer.report_errors("semantic analysis", &[error]))?;
+    renderer.report_warnings(&warning_options, &warnings)?;
 
     if debug >= 2 {
         println!("IR: {}", ir);
@@ -471,11 +438,44 @@ fn compile_and_run(source_file: &str, backend: Backend, debug: u8) -> Result<(),
 fn main() {
     let args = Args::parse();
 
+    let mut warning_options = WarningOptions::default();
+    for flag in &args.warnings {
+        if let Err(e) = warning_options.apply(flag) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+
+    let cell_width = match CellWidth::from_bits(args.cell_bits) {
+        Some(width) => width,
+        None => {
+            eprintln!(
+                "Unsupported cell width: {} bits. Available widths: 32, 64",
+                args.cell_bits
+            );
+            process::exit(1);
+        }
+    };
+
+    let mut defines = Vec::new();
+    for define in &args.defines {
+        match parse_define(define, cell_width) {
+            Ok(define) => defines.push(define),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+    }
+
     // Start REPL if no file provided or -i flag
     if args.interactive || args.file.is_none() {
         let config = repl::ReplConfig {
             debug: args.debug,
             show_welcome: true,
+            warnings: warning_options,
+            defines,
+            cell_width,
             ..Default::default()
         };
 
@@ -505,7 +505,21 @@ fn main() {
         }
     };
 
+    // -I directories come before ROTH_PATH and the bundled library
+    let search_paths = args
+        .include_dirs
+        .iter()
+        .cloned()
+        .chain(source::default_search_paths())
+        .collect();
+
     if let Some(filename) = &args.file {
+        let options = CompileOptions {
+            cell_width,
+            warnings: warning_options,
+            search_paths,
+            defines,
+        };
         if let Err(e) = compile_file(
             filename,
             backend,
@@ -513,6 +527,7 @@ fn main() {
             args.debug,
             args.no_color,
             args.run,
+            options,
         ) {
             eprintln!("Compilation failed: {}", e);
             process::exit(1);
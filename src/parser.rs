@@ -1,8 +1,35 @@
-use crate::types::{AstNode, ParseError, Token, TokenType};
+use crate::types::{
+    AstNode, BeginKind, ParseError, ParseErrors, Position, StackSignature, Token, TokenType,
+    VariableKind,
+};
+use std::collections::HashSet;
+
+/// Words that close a control structure opened earlier
+const CLOSING_WORDS: &[&str] = &[
+    "ELSE", "THEN", "LOOP", "+LOOP", "UNTIL", "AGAIN", "WHILE", "REPEAT",
+];
+
+/// Words that give the next word in the source a meaning
+pub(crate) const DEFINING_WORDS: &[&str] = &[
+    "VARIABLE",
+    "2VARIABLE",
+    "FVARIABLE",
+    "CREATE",
+    "CONSTANT",
+    "2CONSTANT",
+    "VALUE",
+    "DEFER",
+];
 
 pub struct Parser {
     tokens: Vec<Token>,
     position: usize,
+    loop_depth: usize,               // Open DO loops, for LEAVE and UNLOOP
+    in_definition: bool,             // Defining words can't run inside a colon definition
+    definition_creates: bool,        // The definition being parsed uses CREATE
+    defining_words: HashSet<String>, // User words that CREATE, so take a name
+    locals: Vec<String>,             // Locals of the definition being parsed
+    errors: Vec<ParseError>,         // Errors parsing carried on past
 }
 
 impl Parser {
@@ -10,11 +37,30 @@ impl Parser {
         Self {
             tokens,
             position: 0,
+            loop_depth: 0,
+            in_definition: false,
+            definition_creates: false,
+            defining_words: HashSet::new(),
+            locals: Vec::new(),
+            errors: Vec::new(),
         }
     }
 
-    pub fn parse(&mut self) -> Result<AstNode, ParseError> {
+    /// Register a user defining word from a previous REPL session, so that
+    /// uses of it take the following word as a name.
+    pub fn add_defining_word(&mut self, name: impl Into<String>) {
+        self.defining_words.insert(name.into());
+    }
+
+    /// User words whose definitions use CREATE.
+    pub fn defining_words(&self) -> &HashSet<String> {
+        &self.defining_words
+    }
+
+    /// Parse the whole program, reporting every error it can recover from
+    pub fn parse(&mut self) -> Result<AstNode, ParseErrors> {
         let mut nodes = Vec::new();
+        self.errors.clear();
 
         while self.position < self.tokens.len() {
             // Skip comments at the top level
@@ -33,13 +79,26 @@ impl Parser {
                 break;
             }
 
-            let node = self.parse_statement()?;
-            nodes.push(node);
+            let start = self.position;
+            match self.parse_statement() {
+                Ok(node) => nodes.push(node),
+                Err(error) => self.recover(error, start),
+            }
         }
 
+        ParseErrors::result(std::mem::take(&mut self.errors))?;
         Ok(AstNode::Program(nodes))
     }
 
+    /// Record `error` and carry on after the token that caused it, or after
+    /// whatever the failed statement consumed
+    fn recover(&mut self, error: ParseError, start: usize) {
+        self.errors.push(error);
+        if self.position == start {
+            self.position += 1;
+        }
+    }
+
     fn parse_statement(&mut self) -> Result<AstNode, ParseError> {
         // Skip comments
         while self.position < self.tokens.len() {
@@ -53,56 +112,328 @@ impl Parser {
         }
 
         if self.position >= self.tokens.len() {
-            return Err(ParseError {
-                message: "Unexpected end of input".to_string(),
-                position: if self.tokens.is_empty() {
+            return Err(ParseError::new(
+                "Unexpected end of input".to_string(),
+                if self.tokens.is_empty() {
                     crate::types::Position {
                         line: 1,
                         column: 1,
                         offset: 0,
+                        file: 0,
                     }
                 } else {
                     self.tokens[self.tokens.len() - 1].position.clone()
                 },
-            });
+            ));
         }
 
         let token = &self.tokens[self.position];
 
         match &token.token_type {
+            TokenType::Number(_) | TokenType::DoubleNumber(_)
+                if self.constant_literal_count().is_some() =>
+            {
+                self.parse_constant()
+            }
             TokenType::Number(n) => {
                 let pos = token.position.clone();
                 self.position += 1;
                 Ok(AstNode::Number(*n, pos))
             }
+            TokenType::DoubleNumber(n) => {
+                let pos = token.position.clone();
+                self.position += 1;
+                Ok(AstNode::DoubleNumber(*n, pos))
+            }
+            TokenType::Float(r) => {
+                let pos = token.position.clone();
+                self.position += 1;
+                Ok(AstNode::Float(*r, pos))
+            }
             TokenType::Word(w) => {
                 let pos = token.position.clone();
                 self.position += 1;
 
-                // Handle VARIABLE declaration
-                if w == "VARIABLE" {
-                    return self.parse_variable_declaration(pos);
+                // Locals hide any word with the same name
+                if self.locals.contains(w) {
+                    return Ok(AstNode::Local {
+                        name: w.clone(),
+                        position: pos,
+                    });
+                }
+
+                if w == "{:" {
+                    // parse_definition handles locals at the start of a body
+                    return Err(if self.in_definition {
+                        ParseError::new(
+                            "Locals must be declared at the start of a definition".to_string(),
+                            pos,
+                        )
+                        .with_help("move the {: ... :} right after the name")
+                    } else {
+                        ParseError::new("{: outside of a definition".to_string(), pos)
+                    });
+                }
+
+                if w == "CREATE" && self.in_definition {
+                    // The defining word's caller supplies the name
+                    self.definition_creates = true;
+                    return Ok(AstNode::Word(w.clone(), pos));
+                }
+
+                if w == "DOES>" {
+                    // parse_definition handles DOES> at the top level of a body
+                    return Err(ParseError::new(
+                        if self.in_definition {
+                            "DOES> inside a control structure is not supported".to_string()
+                        } else {
+                            "DOES> outside of a definition".to_string()
+                        },
+                        pos,
+                    ));
+                }
+
+                if self.defining_words.contains(w) {
+                    let word = w.clone();
+                    if self.in_definition {
+                        return Err(Self::inside_definition_error(&word, pos));
+                    }
+                    let name = self.parse_defined_name(&word, &pos)?;
+                    return Ok(AstNode::DefinedBy {
+                        word,
+                        name,
+                        position: pos,
+                    });
+                }
+
+                // Defining words name the next word
+                if DEFINING_WORDS.contains(&w.as_str()) {
+                    let defining_word = w.clone();
+                    if self.in_definition {
+                        return Err(Self::inside_definition_error(&defining_word, pos));
+                    }
+                    if defining_word.ends_with("CONSTANT") {
+                        // Literal values are picked up in parse_constant; any
+                        // other value is on the stack when this runs
+                        let name = self.parse_defined_name(&defining_word, &pos)?;
+                        return Ok(AstNode::Constant {
+                            name,
+                            value: Vec::new(),
+                            cells: Self::constant_cells(&defining_word),
+                            position: pos,
+                        });
+                    }
+                    let name = self.parse_defined_name(&defining_word, &pos)?;
+                    return Ok(match defining_word.as_str() {
+                        "CREATE" => AstNode::Create {
+                            name,
+                            position: pos,
+                        },
+                        "VALUE" => AstNode::Value {
+                            name,
+                            position: pos,
+                        },
+                        "DEFER" => AstNode::Defer {
+                            name,
+                            position: pos,
+                        },
+                        _ => AstNode::VariableDeclaration {
+                            name,
+                            kind: match defining_word.as_str() {
+                                "2VARIABLE" => VariableKind::Double,
+                                "FVARIABLE" => VariableKind::Float,
+                                _ => VariableKind::Cell,
+                            },
+                            position: pos,
+                        },
+                    });
+                }
+
+                if w == "POSTPONE" {
+                    if !self.in_definition {
+                        return Err(ParseError::new(
+                            "POSTPONE outside of a definition".to_string(),
+                            pos,
+                        ));
+                    }
+                    let name = self.parse_defined_name("POSTPONE", &pos)?;
+                    return Ok(AstNode::Postpone {
+                        name,
+                        position: pos,
+                    });
+                }
+
+                if w == "TO" {
+                    let name = self.parse_defined_name("TO", &pos)?;
+                    if self.locals.contains(&name) {
+                        return Ok(AstNode::ToLocal {
+                            name,
+                            position: pos,
+                        });
+                    }
+                    return Ok(AstNode::To {
+                        name,
+                        position: pos,
+                    });
                 }
 
-                Ok(AstNode::Word(w.clone(), pos))
+                if w == "IS" || w == "ACTION-OF" {
+                    let word = w.clone();
+                    let name = self.parse_defined_name(&word, &pos)?;
+                    return Ok(if word == "IS" {
+                        AstNode::Is {
+                            name,
+                            position: pos,
+                        }
+                    } else {
+                        AstNode::ActionOf {
+                            name,
+                            position: pos,
+                        }
+                    });
+                }
+
+                // Both ticks name the word at compile time, as nothing here
+                // runs while the source is read
+                if w == "'" || w == "[']" {
+                    let tick = w.clone();
+                    let name = self.parse_defined_name(&tick, &pos)?;
+                    return Ok(AstNode::Tick {
+                        name,
+                        position: pos,
+                    });
+                }
+
+                match w.as_str() {
+                    "IF" => self.parse_if(pos),
+                    "DO" | "?DO" => {
+                        let conditional = w == "?DO";
+                        self.parse_do_loop(conditional, pos)
+                    }
+                    "BEGIN" => self.parse_begin(pos),
+                    closer if CLOSING_WORDS.contains(&closer) => Err(ParseError::new(
+                        format!("{} without matching {}", closer, opener_for(closer)),
+                        pos,
+                    )),
+                    "LEAVE" | "UNLOOP" if self.loop_depth == 0 => {
+                        Err(ParseError::new(format!("{} outside of DO loop", w), pos))
+                    }
+                    // J and K index loops outside the innermost one
+                    "J" if self.loop_depth < 2 => {
+                        Err(ParseError::new("J outside of two nested DO loops", pos)
+                            .with_help("use I for the index of the innermost loop"))
+                    }
+                    "K" if self.loop_depth < 3 => {
+                        Err(ParseError::new("K outside of three nested DO loops", pos)
+                            .with_help("use I or J for the index of an inner loop"))
+                    }
+                    _ => Ok(AstNode::Word(w.clone(), pos)),
+                }
             }
             TokenType::StringLiteral(s) => {
                 let pos = token.position.clone();
                 self.position += 1;
                 Ok(AstNode::StringLiteral(s.clone(), pos))
             }
+            TokenType::DotQuote(s) => {
+                let pos = token.position.clone();
+                self.position += 1;
+                Ok(AstNode::DotQuote(s.clone(), pos))
+            }
+            TokenType::CQuote(s) => {
+                let pos = token.position.clone();
+                self.position += 1;
+                Ok(AstNode::CQuote(s.clone(), pos))
+            }
+            TokenType::AbortQuote(s) => {
+                let pos = token.position.clone();
+                self.position += 1;
+                Ok(AstNode::AbortQuote(s.clone(), pos))
+            }
             TokenType::StartDefinition => self.parse_definition(),
-            _ => Err(ParseError {
-                message: format!("Unexpected token: {:?}", token.token_type),
-                position: token.position.clone(),
-            }),
+            TokenType::EndDefinition => Err(ParseError::new(
+                "; without matching :".to_string(),
+                token.position.clone(),
+            )),
+            _ => Err(ParseError::new(
+                format!("Unexpected token: {:?}", token.token_type),
+                token.position.clone(),
+            )),
+        }
+    }
+
+    /// Number of literal tokens at the current position that are the value
+    /// of a following CONSTANT or 2CONSTANT. The expander leaves values
+    /// worked out at compile time as such literals.
+    fn constant_literal_count(&self) -> Option<usize> {
+        let token_type = |offset: usize| {
+            self.tokens
+                .get(self.position + offset)
+                .map(|token| &token.token_type)
+        };
+        let is_word = |offset: usize, word: &str| matches!(token_type(offset), Some(TokenType::Word(w)) if w == word);
+
+        match (token_type(0), token_type(1)) {
+            (Some(TokenType::Number(_)), _) if is_word(1, "CONSTANT") => Some(1),
+            (Some(TokenType::DoubleNumber(_)), _) if is_word(1, "2CONSTANT") => Some(1),
+            (Some(TokenType::Number(_)), Some(TokenType::Number(_))) if is_word(2, "2CONSTANT") => {
+                Some(2)
+            }
+            _ => None,
         }
     }
 
-    fn parse_variable_declaration(
+    /// `n CONSTANT name`, `d 2CONSTANT name` or `lo hi 2CONSTANT name`, with
+    /// literal values
+    fn parse_constant(&mut self) -> Result<AstNode, ParseError> {
+        let literal_count = self.constant_literal_count().unwrap_or(0);
+        let mut value = Vec::new();
+        for token in &self.tokens[self.position..self.position + literal_count] {
+            value.push(match token.token_type {
+                TokenType::Number(n) => AstNode::Number(n, token.position.clone()),
+                TokenType::DoubleNumber(n) => AstNode::DoubleNumber(n, token.position.clone()),
+                _ => unreachable!("constant_literal_count only counts literals"),
+            });
+        }
+        self.position += literal_count;
+
+        let word_token = &self.tokens[self.position];
+        let pos = word_token.position.clone();
+        let TokenType::Word(defining_word) = word_token.token_type.clone() else {
+            unreachable!("constant_literal_count checked for CONSTANT or 2CONSTANT");
+        };
+        self.position += 1;
+
+        if self.in_definition {
+            return Err(Self::inside_definition_error(&defining_word, pos));
+        }
+        let name = self.parse_defined_name(&defining_word, &pos)?;
+        Ok(AstNode::Constant {
+            name,
+            value,
+            cells: Self::constant_cells(&defining_word),
+            position: pos,
+        })
+    }
+
+    /// Cells held by a constant made with `defining_word`
+    fn constant_cells(defining_word: &str) -> usize {
+        if defining_word == "2CONSTANT" { 2 } else { 1 }
+    }
+
+    fn inside_definition_error(defining_word: &str, position: Position) -> ParseError {
+        ParseError::new(
+            format!("{} inside a definition is not supported", defining_word),
+            position,
+        )
+    }
+
+    /// The name following a defining word such as VARIABLE
+    fn parse_defined_name(
         &mut self,
-        start_pos: crate::types::Position,
-    ) -> Result<AstNode, ParseError> {
+        defining_word: &str,
+        start_pos: &Position,
+    ) -> Result<String, ParseError> {
         // Skip any comments
         while self.position < self.tokens.len() {
             if let TokenType::Comment(_) = &self.tokens[self.position].token_type {
@@ -113,27 +444,24 @@ impl Parser {
         }
 
         if self.position >= self.tokens.len() {
-            return Err(ParseError {
-                message: "Expected variable name after VARIABLE".to_string(),
-                position: start_pos,
-            });
+            return Err(ParseError::new(
+                format!("Expected name after {}", defining_word),
+                start_pos.clone(),
+            ));
         }
 
         let name = match &self.tokens[self.position].token_type {
             TokenType::Word(w) => w.clone(),
             _ => {
-                return Err(ParseError {
-                    message: "Expected variable name after VARIABLE".to_string(),
-                    position: self.tokens[self.position].position.clone(),
-                });
+                return Err(ParseError::new(
+                    format!("Expected name after {}", defining_word),
+                    self.tokens[self.position].position.clone(),
+                ));
             }
         };
         self.position += 1;
 
-        Ok(AstNode::VariableDeclaration {
-            name,
-            position: start_pos,
-        })
+        Ok(name)
     }
 
     fn parse_definition(&mut self) -> Result<AstNode, ParseError> {
@@ -141,51 +469,326 @@ impl Parser {
         self.position += 1; // skip ':'
 
         if self.position >= self.tokens.len() {
-            return Err(ParseError {
-                message: "Expected word name after ':'".to_string(),
-                position: start_pos,
-            });
+            return Err(ParseError::new(
+                "Expected word name after ':'".to_string(),
+                start_pos,
+            ));
         }
 
         let name = match &self.tokens[self.position].token_type {
             TokenType::Word(w) => w.clone(),
             _ => {
-                return Err(ParseError {
-                    message: "Expected word name after ':'".to_string(),
-                    position: self.tokens[self.position].position.clone(),
-                });
+                return Err(ParseError::new(
+                    "Expected word name after ':'".to_string(),
+                    self.tokens[self.position].position.clone(),
+                ));
             }
         };
         self.position += 1;
 
+        // A stack-effect comment right after the name declares the signature,
+        // and a `( F: ... )` comment after it the float stack's part
+        let mut signature = self.stack_comment(self.position);
+        if let Some(signature) = &mut signature
+            && !signature.is_float_comment()
+            && let Some(float_comment) = self
+                .stack_comment(self.position + 1)
+                .filter(StackSignature::is_float_comment)
+        {
+            signature.float_comment = Some(Box::new(float_comment));
+        }
+
         let mut body = Vec::new();
+        let mut does: Option<(Vec<AstNode>, Position)> = None;
+        let mut closed = false;
+        self.in_definition = true;
+        self.definition_creates = false;
         while self.position < self.tokens.len() {
             match &self.tokens[self.position].token_type {
+                TokenType::Word(w) if w == "DOES>" => {
+                    let pos = self.tokens[self.position].position.clone();
+                    let message = if does.is_some() {
+                        Some("Only one DOES> per definition is supported".to_string())
+                    } else if !self.definition_creates {
+                        Some(format!(
+                            "DOES> without CREATE in the definition of {}",
+                            name
+                        ))
+                    } else {
+                        None
+                    };
+                    self.position += 1;
+                    match message {
+                        Some(message) => self.errors.push(ParseError::new(message, pos)),
+                        None => does = Some((Vec::new(), pos)),
+                    }
+                    // The DOES> part runs as a word of its own
+                    self.locals.clear();
+                }
+                TokenType::Word(w) if w == "{:" && body.is_empty() && does.is_none() => {
+                    let pos = self.tokens[self.position].position.clone();
+                    self.position += 1;
+                    match self.parse_locals(pos) {
+                        Ok(locals) => body.push(locals),
+                        Err(error) => self.errors.push(error),
+                    }
+                }
                 TokenType::EndDefinition => {
                     self.position += 1;
+                    closed = true;
                     break;
                 }
                 TokenType::StartDefinition => {
-                    return Err(ParseError {
-                        message: "Cannot nest word definitions".to_string(),
-                        position: self.tokens[self.position].position.clone(),
-                    });
+                    self.errors.push(
+                        ParseError::new(
+                            "Cannot nest word definitions".to_string(),
+                            self.tokens[self.position].position.clone(),
+                        )
+                        .with_note(
+                            format!("definition of {} starts here", name),
+                            start_pos.clone(),
+                        )
+                        .with_help(format!(
+                            "end {} with ';' before starting another definition",
+                            name
+                        )),
+                    );
+                    // Most likely the ';' was forgotten, so carry on with the
+                    // new definition
+                    closed = true;
+                    break;
                 }
                 TokenType::Comment(_) => {
                     self.position += 1;
                     continue;
                 }
                 _ => {
-                    let node = self.parse_statement()?;
-                    body.push(node);
+                    let start = self.position;
+                    match self.parse_statement() {
+                        Ok(node) => match &mut does {
+                            Some((does_body, _)) => does_body.push(node),
+                            None => body.push(node),
+                        },
+                        Err(error) => self.recover(error, start),
+                    }
                 }
             }
         }
 
+        self.in_definition = false;
+        self.locals.clear();
+
+        if !closed {
+            return Err(ParseError::new(
+                format!("Definition of {} is missing ';'", name),
+                start_pos,
+            )
+            .with_help(format!("end the definition of {} with ';'", name)));
+        }
+
+        if let Some((does_body, position)) = does {
+            body.push(AstNode::Does {
+                body: does_body,
+                position,
+            });
+        }
+        if self.definition_creates {
+            self.defining_words.insert(name.clone());
+        } else {
+            self.defining_words.remove(&name);
+        }
+
         Ok(AstNode::Definition {
             name,
             body,
+            signature,
             position: start_pos,
         })
     }
+
+    /// The `( ... -- ... )` comment at `position`, read as a signature
+    fn stack_comment(&self, position: usize) -> Option<StackSignature> {
+        match self.tokens.get(position) {
+            Some(Token {
+                token_type: TokenType::Comment(text),
+                position,
+                raw,
+            }) if raw.starts_with('(') => StackSignature::parse(text, position.clone()),
+            _ => None,
+        }
+    }
+
+    /// `{: a b | c -- outputs :}`, from after the `{:` at `start_pos`. The
+    /// names become locals for the rest of the definition.
+    fn parse_locals(&mut self, start_pos: Position) -> Result<AstNode, ParseError> {
+        let mut names: Vec<(String, Position)> = Vec::new();
+        let mut initialized = None; // Set at `|`
+        let mut outputs = false; // After `--`
+
+        while let Some(token) = self.tokens.get(self.position) {
+            let position = token.position.clone();
+            match &token.token_type {
+                TokenType::Word(w) if w == ":}" => {
+                    self.position += 1;
+                    self.locals = names.iter().map(|(name, _)| name.clone()).collect();
+                    return Ok(AstNode::Locals {
+                        initialized: initialized.unwrap_or(names.len()),
+                        names: self.locals.clone(),
+                        position: start_pos,
+                    });
+                }
+                TokenType::Comment(_) => {}
+                TokenType::Word(w) if w == "--" => outputs = true,
+                TokenType::Word(_) if outputs => {}
+                TokenType::Word(w) if w == "|" && initialized.is_none() => {
+                    initialized = Some(names.len());
+                }
+                TokenType::Word(w) if w != "|" => {
+                    if let Some((_, first)) = names.iter().find(|(name, _)| name == w) {
+                        return Err(ParseError::new(
+                            format!("Local {} is declared twice", w),
+                            position,
+                        )
+                        .with_note(format!("{} is first declared here", w), first.clone()));
+                    }
+                    names.push((w.clone(), position));
+                }
+                TokenType::StartDefinition | TokenType::EndDefinition => break,
+                _ => {
+                    return Err(ParseError::new(
+                        format!("Expected the name of a local, found '{}'", token.raw),
+                        position,
+                    )
+                    .with_note("locals declared here", start_pos));
+                }
+            }
+            self.position += 1;
+        }
+
+        Err(
+            ParseError::new("Unterminated {: (expected :})".to_string(), start_pos)
+                .with_help("close the locals with :}"),
+        )
+    }
+
+    /// IF ... [ELSE ...] THEN
+    fn parse_if(&mut self, start_pos: Position) -> Result<AstNode, ParseError> {
+        let (then_branch, closer) = self.parse_block("IF", &start_pos, &["ELSE", "THEN"])?;
+        let else_branch = if closer == "ELSE" {
+            let (else_branch, _) = self.parse_block("IF", &start_pos, &["THEN"])?;
+            Some(else_branch)
+        } else {
+            None
+        };
+
+        Ok(AstNode::If {
+            then_branch,
+            else_branch,
+            position: start_pos,
+        })
+    }
+
+    /// DO/?DO ... LOOP/+LOOP
+    fn parse_do_loop(
+        &mut self,
+        conditional: bool,
+        start_pos: Position,
+    ) -> Result<AstNode, ParseError> {
+        let opener = if conditional { "?DO" } else { "DO" };
+        self.loop_depth += 1;
+        let result = self.parse_block(opener, &start_pos, &["LOOP", "+LOOP"]);
+        self.loop_depth -= 1;
+        let (body, closer) = result?;
+
+        Ok(AstNode::DoLoop {
+            body,
+            conditional,
+            plus_loop: closer == "+LOOP",
+            position: start_pos,
+        })
+    }
+
+    /// BEGIN ... UNTIL, BEGIN ... AGAIN or BEGIN ... WHILE ... REPEAT
+    fn parse_begin(&mut self, start_pos: Position) -> Result<AstNode, ParseError> {
+        let (body, closer) = self.parse_block("BEGIN", &start_pos, &["UNTIL", "AGAIN", "WHILE"])?;
+        let kind = match closer.as_str() {
+            "UNTIL" => BeginKind::Until,
+            "AGAIN" => BeginKind::Again,
+            _ => {
+                let (loop_body, _) = self.parse_block("BEGIN", &start_pos, &["REPEAT"])?;
+                BeginKind::WhileRepeat(loop_body)
+            }
+        };
+
+        Ok(AstNode::Begin {
+            body,
+            kind,
+            position: start_pos,
+        })
+    }
+
+    /// Parse statements up to one of `closers`, consuming the closer and returning
+    /// its name. Any other closing word, a ';' or the end of input is reported
+    /// against the structure opened at `start_pos`.
+    fn parse_block(
+        &mut self,
+        opener: &str,
+        start_pos: &Position,
+        closers: &[&str],
+    ) -> Result<(Vec<AstNode>, String), ParseError> {
+        let expected = closers.join(" or ");
+        let mut body = Vec::new();
+
+        while self.position < self.tokens.len() {
+            let token = &self.tokens[self.position];
+            match &token.token_type {
+                TokenType::Comment(_) => {
+                    self.position += 1;
+                }
+                TokenType::Word(w) if closers.contains(&w.as_str()) => {
+                    let closer = w.clone();
+                    self.position += 1;
+                    return Ok((body, closer));
+                }
+                TokenType::Word(w) if CLOSING_WORDS.contains(&w.as_str()) => {
+                    return Err(ParseError::new(
+                        format!("{} does not match {} (expected {})", w, opener, expected),
+                        token.position.clone(),
+                    )
+                    .with_note(format!("{} opened here", opener), start_pos.clone()));
+                }
+                TokenType::EndDefinition | TokenType::StartDefinition => {
+                    return Err(ParseError::new(
+                        format!("'{}' inside {} (expected {})", token.raw, opener, expected),
+                        token.position.clone(),
+                    )
+                    .with_note(format!("{} opened here", opener), start_pos.clone())
+                    .with_help(format!("close the {} with {} first", opener, expected)));
+                }
+                _ => {
+                    let start = self.position;
+                    match self.parse_statement() {
+                        Ok(node) => body.push(node),
+                        Err(error) => self.recover(error, start),
+                    }
+                }
+            }
+        }
+
+        Err(ParseError::new(
+            format!("Unterminated {}: expected {}", opener, expected),
+            start_pos.clone(),
+        )
+        .with_help(format!("close the {} with {}", opener, expected)))
+    }
+}
+
+/// The word that opens the structure a closing word belongs to
+fn opener_for(closer: &str) -> &'static str {
+    match closer {
+        "ELSE" | "THEN" => "IF",
+        "LOOP" | "+LOOP" => "DO",
+        "WHILE" | "UNTIL" | "AGAIN" => "BEGIN",
+        _ => "BEGIN ... WHILE",
+    }
 }
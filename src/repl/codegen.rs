@@ -3,8 +3,9 @@
 //! Generates Rust code that can be compiled to a shared library for the REPL.
 
 use crate::ir::{
-    BinaryOpKind, IRFunction, IRInstruction, IRLabel, IRProgram, IRValue, UnaryOpKind,
+    BinaryOpKind, IRFunction, IRInstruction, IRLabel, IRProgram, IRValue, UnaryOpKind, frame_size,
 };
+use crate::ir_codegen::{IRRustGenerator, function_name};
 use crate::repl::state::CompilerContext;
 
 /// REPL code generator.
@@ -57,14 +58,15 @@ impl ReplCodegen {
             self.emit_line("");
         }
 
-        // Generate user-defined words
-        for (name, func) in &ir.functions {
+        // Generate user-defined words, and the functions execution tokens of
+        // other words run
+        for (name, func) in ir.functions.iter().chain(&ir.wrappers) {
             self.generate_word_function(name, func);
             self.emit_line("");
         }
 
         // Generate entry point that registers words and executes main code
-        self.generate_entry_point_with_registration(&ir.main, &defined_words);
+        self.generate_entry_point_with_registration(ir, &defined_words);
         self.emit_line("");
 
         // Generate defined words metadata (still useful for tracking)
@@ -97,25 +99,27 @@ impl ReplCodegen {
 
     fn collect_strings_from_function(&mut self, func: &IRFunction) {
         for instr in &func.instructions {
-            if let IRInstruction::Push(IRValue::Variable(s)) = instr {
-                // Check if this looks like a string literal marker
-                if s.starts_with("__str_") {
-                    // Extract the actual string
-                    if let Some(idx) = s.strip_prefix("__str_") {
-                        if let Ok(n) = idx.parse::<usize>() {
-                            while self.string_literals.len() <= n {
-                                self.string_literals.push(String::new());
-                            }
-                        }
-                    }
-                }
+            if let IRInstruction::PushString(text) | IRInstruction::PushCountedString(text) = instr
+                && !self.string_literals.contains(text)
+            {
+                self.string_literals.push(text.clone());
             }
         }
     }
 
+    /// The constant a string literal was given
+    fn string_constant(&self, text: &str) -> String {
+        let index = self
+            .string_literals
+            .iter()
+            .position(|literal| literal == text)
+            .expect("string literals are collected before code is generated");
+        format!("STRING_{}", index)
+    }
+
     /// Generate a user-defined word function.
     fn generate_word_function(&mut self, name: &str, func: &IRFunction) {
-        let fn_name = format!("word_{}", name.to_lowercase().replace("-", "_"));
+        let fn_name = function_name(&func.name);
 
         // Word functions are internal to the library - they'll be registered via __repl_entry
         self.emit_line(&format!(
@@ -136,11 +140,7 @@ impl ReplCodegen {
     }
 
     /// Generate the REPL entry point with word registration.
-    fn generate_entry_point_with_registration(
-        &mut self,
-        main: &IRFunction,
-        defined_words: &[String],
-    ) {
+    fn generate_entry_point_with_registration(&mut self, ir: &IRProgram, defined_words: &[String]) {
         self.emit_line("#[unsafe(no_mangle)]");
         self.emit_line(
             "pub extern \"C\" fn __repl_entry(ctx: &mut RuntimeContext) -> ForthResult<()> {",
@@ -150,13 +150,24 @@ impl ReplCodegen {
         // Register all defined words first (except "main" which is special)
         for word in defined_words {
             if word != "main" {
-                let fn_name = format!("word_{}", word.to_lowercase().replace("-", "_"));
+                let fn_name = function_name(&ir.functions[word].name);
                 self.emit_line(&format!("ctx.register_word({:?}, {});", word, fn_name));
             }
         }
 
+        // Execution tokens of other words run them through these
+        for (word, wrapper) in &ir.wrappers {
+            let fn_name = function_name(&wrapper.name);
+            self.emit_line(&format!("ctx.register_word({:?}, {});", word, fn_name));
+        }
+
+        // Lay the string literals down before the code can build data space
+        for i in 0..self.string_literals.len() {
+            self.emit_line(&format!("ctx.intern_string(STRING_{})?;", i));
+        }
+
         // Then execute the main code
-        self.generate_function_body(&main.instructions);
+        self.generate_function_body(&ir.main.instructions);
 
         self.emit_line("Ok(())");
         self.indent -= 1;
@@ -165,22 +176,40 @@ impl ReplCodegen {
 
     /// Generate code for a function body.
     fn generate_function_body(&mut self, instructions: &[IRInstruction]) {
-        // Generate loop control variables if needed
-        let has_loops = instructions
-            .iter()
-            .any(|i| matches!(i, IRInstruction::DoLoop(_, _)));
-        if has_loops {
-            self.emit_line("let mut loop_stack: Vec<(i64, i64)> = Vec::new();");
-        }
-
-        // Track if we need a state machine for control flow
+        // Loops branch back to their start, so they need the state machine too
         let has_jumps = instructions.iter().any(|i| {
             matches!(
                 i,
-                IRInstruction::Jump(_) | IRInstruction::JumpIf(_) | IRInstruction::JumpIfNot(_)
+                IRInstruction::Jump(_)
+                    | IRInstruction::JumpIf(_)
+                    | IRInstruction::JumpIfNot(_)
+                    | IRInstruction::DoLoop(_, _)
+                    | IRInstruction::Loop(_)
+                    | IRInstruction::PlusLoop(_)
+                    | IRInstruction::Leave(_)
             )
         });
 
+        // Loop frames live for the duration of the word
+        let uses_loop_stack = instructions.iter().any(|i| {
+            matches!(
+                i,
+                IRInstruction::DoLoop(_, _)
+                    | IRInstruction::Unloop
+                    | IRInstruction::PushLoopIndex
+                    | IRInstruction::PushOuterLoopIndex(_)
+                    | IRInstruction::PushLoopLimit
+            )
+        });
+        if uses_loop_stack {
+            self.emit_line("let mut loop_stack: Vec<(i64, i64)> = Vec::new();");
+        }
+
+        // Locals are native variables, so every call has its own
+        for slot in 0..frame_size(instructions) {
+            self.emit_line(&format!("let mut local_{}: i64 = 0;", slot));
+        }
+
         if has_jumps {
             self.generate_state_machine(instructions);
         } else {
@@ -202,7 +231,6 @@ impl ReplCodegen {
         }
 
         // Generate state enum
-        self.emit_line("let mut loop_stack: Vec<(i64, i64)> = Vec::new();");
         self.emit_line("let mut state = 0usize;");
         self.emit_line("loop {");
         self.indent += 1;
@@ -263,6 +291,60 @@ impl ReplCodegen {
                         target_state
                     ));
                 }
+                IRInstruction::DoLoop(_, end_label) => {
+                    // DO/?DO: (limit start -- ), skip the body when start == limit
+                    let target_state = label_to_state
+                        .get(&end_label.to_string())
+                        .copied()
+                        .unwrap_or(0);
+                    self.emit_line("let start = ctx.pop()?;");
+                    self.emit_line("let limit = ctx.pop()?;");
+                    self.emit_line(&format!(
+                        "if start == limit {{ state = {}; continue; }}",
+                        target_state
+                    ));
+                    self.emit_line("loop_stack.push((start, limit));");
+                }
+                IRInstruction::Loop(loop_label) => {
+                    // LOOP: increment index, branch back until it reaches the limit
+                    let target_state = label_to_state
+                        .get(&loop_label.to_string())
+                        .copied()
+                        .unwrap_or(0);
+                    self.emit_line("let (idx, limit) = loop_stack.last_mut().unwrap();");
+                    self.emit_line("*idx = ctx.wrap(idx.wrapping_add(1));");
+                    self.emit_line(&format!(
+                        "if *idx != *limit {{ state = {}; continue; }}",
+                        target_state
+                    ));
+                    self.emit_line("loop_stack.pop();");
+                }
+                IRInstruction::PlusLoop(loop_label) => {
+                    // +LOOP: (n -- ), branch back unless the index crossed
+                    // the boundary between limit-1 and limit
+                    let target_state = label_to_state
+                        .get(&loop_label.to_string())
+                        .copied()
+                        .unwrap_or(0);
+                    self.emit_line("let step = ctx.pop()?;");
+                    self.emit_line("let (idx, limit) = loop_stack.last_mut().unwrap();");
+                    self.emit_line("let diff = ctx.wrap(idx.wrapping_sub(*limit));");
+                    self.emit_line("*idx = ctx.wrap(idx.wrapping_add(step));");
+                    self.emit_line(&format!(
+                        "if ((diff ^ ctx.wrap(diff.wrapping_add(step))) & (diff ^ step)) >= 0 {{ state = {}; continue; }}",
+                        target_state
+                    ));
+                    self.emit_line("loop_stack.pop();");
+                }
+                IRInstruction::Leave(end_label) => {
+                    let target_state = label_to_state
+                        .get(&end_label.to_string())
+                        .copied()
+                        .unwrap_or(0);
+                    self.emit_line("loop_stack.pop();");
+                    self.emit_line(&format!("state = {};", target_state));
+                    self.emit_line("continue;");
+                }
                 IRInstruction::Return => {
                     self.emit_line("return Ok(());");
                 }
@@ -310,6 +392,15 @@ impl ReplCodegen {
             IRInstruction::Rot => {
                 self.emit_line("ctx.rot()?;");
             }
+            IRInstruction::Pick => {
+                self.emit_line("ctx.pick()?;");
+            }
+            IRInstruction::Roll => {
+                self.emit_line("ctx.roll()?;");
+            }
+            IRInstruction::DupIfNonZero => {
+                self.emit_line("ctx.dup_if_nonzero()?;");
+            }
             IRInstruction::Add => {
                 self.emit_line("ctx.add()?;");
             }
@@ -352,22 +443,50 @@ impl ReplCodegen {
             IRInstruction::Or => {
                 self.emit_line("ctx.or()?;");
             }
+            IRInstruction::Xor => {
+                self.emit_line("ctx.xor()?;");
+            }
             IRInstruction::Not => {
                 self.emit_line("ctx.invert()?;");
             }
-            IRInstruction::Load(addr) => {
-                if let IRValue::Variable(name) = addr {
-                    self.emit_line(&format!("ctx.fetch({:?})?;", name));
-                } else {
-                    self.emit_line("// TODO: Load from computed address");
-                }
+            IRInstruction::Load(_) => {
+                self.emit_line("ctx.fetch()?;");
             }
-            IRInstruction::Store(addr) => {
-                if let IRValue::Variable(name) = addr {
-                    self.emit_line(&format!("ctx.store({:?})?;", name));
-                } else {
-                    self.emit_line("// TODO: Store to computed address");
-                }
+            IRInstruction::Store(_) => {
+                self.emit_line("ctx.store()?;");
+            }
+            IRInstruction::LoadChar => {
+                self.emit_line("ctx.c_fetch()?;");
+            }
+            IRInstruction::StoreChar => {
+                self.emit_line("ctx.c_store()?;");
+            }
+            IRInstruction::Here => {
+                self.emit_line("ctx.here()?;");
+            }
+            IRInstruction::Allot => {
+                self.emit_line("ctx.allot()?;");
+            }
+            IRInstruction::Comma => {
+                self.emit_line("ctx.comma()?;");
+            }
+            IRInstruction::CharComma => {
+                self.emit_line("ctx.c_comma()?;");
+            }
+            IRInstruction::Align => {
+                self.emit_line("ctx.align()?;");
+            }
+            IRInstruction::Create(name) => {
+                self.emit_line(&format!("ctx.create({:?})?;", name));
+            }
+            IRInstruction::SupplyName(name) => {
+                self.emit_line(&format!("ctx.supply_name({:?});", name));
+            }
+            IRInstruction::CreateSupplied => {
+                self.emit_line("ctx.create_supplied()?;");
+            }
+            IRInstruction::PushDataField(name) => {
+                self.emit_line(&format!("ctx.push_data_field({:?})?;", name));
             }
             IRInstruction::Call(name) => {
                 // Check if this is a call to a word defined in the same library
@@ -379,47 +498,56 @@ impl ReplCodegen {
             IRInstruction::Return => {
                 self.emit_line("return Ok(());");
             }
-            IRInstruction::DoLoop(loop_label, end_label) => {
-                // ?DO implementation: (limit start -- )
-                self.emit_line("{ // DO/?DO");
-                self.indent += 1;
-                self.emit_line("let start = ctx.pop()?;");
-                self.emit_line("let limit = ctx.pop()?;");
-                self.emit_line("loop_stack.push((start, limit));");
-                self.emit_line("if start >= limit {");
-                self.indent += 1;
+            IRInstruction::PushXt(name) => {
+                self.emit_line(&format!("ctx.push_xt({:?})?;", name));
+            }
+            IRInstruction::Execute => {
+                self.emit_line("ctx.execute()?;");
+            }
+            IRInstruction::Catch => {
+                self.emit_line("ctx.catch()?;");
+            }
+            IRInstruction::Throw => {
+                self.emit_line("ctx.throw()?;");
+            }
+            IRInstruction::Abort => {
+                self.emit_line("ctx.abort()?;");
+            }
+            IRInstruction::AbortQuote(text) => {
+                self.emit_line(&format!("ctx.abort_quote({:?})?;", text));
+            }
+            IRInstruction::DoLoop(_, _)
+            | IRInstruction::Loop(_)
+            | IRInstruction::PlusLoop(_)
+            | IRInstruction::Leave(_) => {
+                // Handled by state machine
+            }
+            IRInstruction::Unloop => {
                 self.emit_line("loop_stack.pop();");
-                // Jump to end will be handled by state machine
-                self.indent -= 1;
-                self.emit_line("}");
-                self.indent -= 1;
-                self.emit_line("}");
             }
-            IRInstruction::Loop(loop_label) => {
-                // LOOP: increment and check
-                self.emit_line("{ // LOOP");
-                self.indent += 1;
-                self.emit_line("if let Some((idx, limit)) = loop_stack.last_mut() {");
-                self.indent += 1;
-                self.emit_line("*idx += 1;");
-                self.emit_line("if *idx >= *limit {");
+            IRInstruction::PushLoopIndex => {
+                // I: push current loop index
+                self.emit_line("if let Some((idx, _)) = loop_stack.last() {");
                 self.indent += 1;
-                self.emit_line("loop_stack.pop();");
-                // Continue to after loop (handled by state machine)
+                self.emit_line("ctx.push(*idx)?;");
                 self.indent -= 1;
                 self.emit_line("} else {");
                 self.indent += 1;
-                // Jump back to loop start (handled by state machine)
-                self.indent -= 1;
-                self.emit_line("}");
+                self.emit_line("return Err(ForthError::RuntimeError {");
+                self.indent += 1;
+                self.emit_line("message: \"I used outside loop\".to_string(),");
+                self.emit_line("location: SourceLocation::default(),");
                 self.indent -= 1;
-                self.emit_line("}");
+                self.emit_line("});");
                 self.indent -= 1;
                 self.emit_line("}");
             }
-            IRInstruction::PushLoopIndex => {
-                // I: push current loop index
-                self.emit_line("if let Some((idx, _)) = loop_stack.last() {");
+            IRInstruction::PushOuterLoopIndex(depth) => {
+                // J/K: push the index of an enclosing loop
+                self.emit_line(&format!(
+                    "if let Some((idx, _)) = loop_stack.iter().rev().nth({}) {{",
+                    depth
+                ));
                 self.indent += 1;
                 self.emit_line("ctx.push(*idx)?;");
                 self.indent -= 1;
@@ -427,7 +555,10 @@ impl ReplCodegen {
                 self.indent += 1;
                 self.emit_line("return Err(ForthError::RuntimeError {");
                 self.indent += 1;
-                self.emit_line("message: \"I used outside loop\".to_string(),");
+                self.emit_line(&format!(
+                    "message: \"{} used outside nested loop\".to_string(),",
+                    if *depth == 1 { "J" } else { "K" }
+                ));
                 self.emit_line("location: SourceLocation::default(),");
                 self.indent -= 1;
                 self.emit_line("});");
@@ -460,18 +591,28 @@ impl ReplCodegen {
                 self.emit_line("ctx.emit()?;");
             }
             IRInstruction::PrintString => {
-                // String printing: (addr len -- )
-                self.emit_line("{ // Print string");
-                self.indent += 1;
-                self.emit_line("let len = ctx.pop()?;");
-                self.emit_line("let addr = ctx.pop()?;");
-                self.emit_line("// String printing handled via literals");
-                self.indent -= 1;
-                self.emit_line("}");
+                self.emit_line("ctx.type_string()?;");
+            }
+            IRInstruction::PushString(text) => {
+                let constant = self.string_constant(text);
+                self.emit_line(&format!("ctx.push_string({})?;", constant));
+            }
+            IRInstruction::PushCountedString(text) => {
+                let constant = self.string_constant(text);
+                self.emit_line(&format!("ctx.push_counted_string({})?;", constant));
             }
+            IRInstruction::Compare => self.emit_line("ctx.compare()?;"),
+            IRInstruction::Search => self.emit_line("ctx.search()?;"),
+            IRInstruction::TrimTrailing => self.emit_line("ctx.minus_trailing()?;"),
+            IRInstruction::CMove => self.emit_line("ctx.cmove()?;"),
+            IRInstruction::Move => self.emit_line("ctx.move_bytes()?;"),
             IRInstruction::ReadChar => {
                 self.emit_line("ctx.key()?;");
             }
+            IRInstruction::CompileWord(_) | IRInstruction::CompileLiteral => {
+                // IMMEDIATE words run inside the compiler and never get here
+                self.emit_line(&format!("// {} only runs at compile time", instr));
+            }
             IRInstruction::Label(label) => {
                 // Labels are handled by state machine
                 self.emit_line(&format!("// Label: {}", label));
@@ -517,29 +658,63 @@ impl ReplCodegen {
             IRInstruction::UnaryOp(op, a) => {
                 let a_code = self.generate_value(a);
                 let op_code = match op {
-                    UnaryOpKind::Neg => format!("-{}", a_code),
+                    UnaryOpKind::Neg => format!("{}.wrapping_neg()", a_code),
                     UnaryOpKind::Not => format!("!{}", a_code),
                 };
                 self.emit_line(&format!("ctx.push({})?;", op_code));
             }
-            IRInstruction::StackGet(pos) => {
-                self.emit_line(&format!("let val = ctx.peek_n({})?;", pos));
-                self.emit_line("ctx.push(val)?;");
+            IRInstruction::StackGet(slot) => {
+                self.emit_line(&format!("ctx.push(local_{})?;", slot));
             }
-            IRInstruction::StackSet(pos, val) => {
-                // Not commonly used, implement if needed
-                self.emit_line(&format!("// StackSet {} to {:?}", pos, val));
+            IRInstruction::StackSet(slot) => {
+                self.emit_line(&format!("local_{} = ctx.pop()?;", slot));
             }
-            IRInstruction::StackAlloc(size) => {
-                for _ in 0..*size {
-                    self.emit_line("ctx.push(0)?;");
-                }
+            IRInstruction::StackAlloc(_) => {
+                // The locals are declared at the top of the function
             }
             IRInstruction::StackFree(size) => {
                 for _ in 0..*size {
                     self.emit_line("ctx.pop()?;");
                 }
             }
+            IRInstruction::DAdd => self.emit_line("ctx.d_plus()?;"),
+            IRInstruction::DSub => self.emit_line("ctx.d_minus()?;"),
+            IRInstruction::DNeg => self.emit_line("ctx.dnegate()?;"),
+            IRInstruction::MulMixed => self.emit_line("ctx.m_star()?;"),
+            IRInstruction::MulUnsigned => self.emit_line("ctx.um_star()?;"),
+            IRInstruction::DivModUnsigned => self.emit_line("ctx.um_slash_mod()?;"),
+            IRInstruction::DivRemSymmetric => self.emit_line("ctx.sm_slash_rem()?;"),
+            IRInstruction::DivModFloored => self.emit_line("ctx.fm_slash_mod()?;"),
+            IRInstruction::PrintDouble => self.emit_line("ctx.print_double()?;"),
+            IRInstruction::PrintUnsigned => self.emit_line("ctx.print_unsigned()?;"),
+            IRInstruction::PrintAligned => self.emit_line("ctx.print_aligned()?;"),
+            IRInstruction::PrintUnsignedAligned => self.emit_line("ctx.print_unsigned_aligned()?;"),
+            IRInstruction::HoldStart => self.emit_line("ctx.less_number_sign()?;"),
+            IRInstruction::HoldDigit => self.emit_line("ctx.number_sign()?;"),
+            IRInstruction::HoldDigits => self.emit_line("ctx.number_sign_s()?;"),
+            IRInstruction::Hold => self.emit_line("ctx.hold()?;"),
+            IRInstruction::HoldSign => self.emit_line("ctx.sign()?;"),
+            IRInstruction::HoldEnd => self.emit_line("ctx.number_sign_greater()?;"),
+            IRInstruction::PushFloat(r) => {
+                self.emit_line(&format!(
+                    "ctx.fpush({})?;",
+                    IRRustGenerator::float_literal(*r)
+                ));
+            }
+            IRInstruction::FAdd => self.emit_line("ctx.fadd()?;"),
+            IRInstruction::FSub => self.emit_line("ctx.fsub()?;"),
+            IRInstruction::FMul => self.emit_line("ctx.fmul()?;"),
+            IRInstruction::FDiv => self.emit_line("ctx.fdiv()?;"),
+            IRInstruction::FDup => self.emit_line("ctx.fdup()?;"),
+            IRInstruction::FSwap => self.emit_line("ctx.fswap()?;"),
+            IRInstruction::FDrop => self.emit_line("ctx.fdrop()?;"),
+            IRInstruction::FLess => self.emit_line("ctx.f_less()?;"),
+            IRInstruction::FZeroEqual => self.emit_line("ctx.f_zero_eq()?;"),
+            IRInstruction::IntToFloat => self.emit_line("ctx.s_to_f()?;"),
+            IRInstruction::FloatToInt => self.emit_line("ctx.f_to_s()?;"),
+            IRInstruction::LoadFloat => self.emit_line("ctx.f_fetch()?;"),
+            IRInstruction::StoreFloat => self.emit_line("ctx.f_store()?;"),
+            IRInstruction::PrintFloat => self.emit_line("ctx.print_float()?;"),
             IRInstruction::Nop => {
                 // No operation
             }
@@ -555,7 +730,7 @@ impl ReplCodegen {
             IRValue::Constant(n) => format!("{}_i64", n),
             IRValue::StackTop => "ctx.peek()?".to_string(),
             IRValue::StackPos(pos) => format!("ctx.peek_n({})?", pos),
-            IRValue::Variable(name) => format!("*ctx.memory.get({:?}).unwrap_or(&0)", name),
+            IRValue::Variable(name) => format!("ctx.data_field({:?})?", name),
             IRValue::Temporary(id) => format!("tmp_{}", id),
         }
     }
@@ -8,12 +8,17 @@ pub mod loader;
 pub mod state;
 
 use crate::analyzer::SemanticAnalyzer;
-use crate::ir_lowering::IRLowering;
+use crate::diagnostics::{DiagnosticRenderer, WarningOptions};
+use crate::expander::backend_define;
+use crate::ir_lowering::{IRLowering, StackEffectAnalyzer};
 use crate::ir_optimizer::IROptimizer;
 use crate::lexer::Lexer;
 use crate::parser::Parser;
+use crate::source::SourceMap;
+use crate::types::{Cell, CellWidth};
 use colored::Colorize;
 use roth_runtime::RuntimeContext;
+use roth_runtime::throw_code;
 use std::io::{self, BufRead, Write};
 
 use self::codegen::ReplCodegen;
@@ -31,6 +36,12 @@ pub struct ReplConfig {
     pub prompt: String,
     /// Continue prompt (for multi-line input)
     pub continue_prompt: String,
+    /// Which warnings to show, and whether they reject the input
+    pub warnings: WarningOptions,
+    /// Constants defined before any input, from `-D` flags
+    pub defines: Vec<(String, Cell)>,
+    /// Width of a cell, from `--cell-bits`
+    pub cell_width: CellWidth,
 }
 
 impl Default for ReplConfig {
@@ -40,6 +51,9 @@ impl Default for ReplConfig {
             show_welcome: true,
             prompt: "roth> ".to_string(),
             continue_prompt: "  ... ".to_string(),
+            warnings: WarningOptions::default(),
+            defines: Vec::new(),
+            cell_width: CellWidth::default(),
         }
     }
 }
@@ -59,7 +73,7 @@ impl Repl {
         let loader = LibraryLoader::new()?;
 
         Ok(Self {
-            state: REPLState::new(),
+            state: Self::initial_state(&config),
             codegen: ReplCodegen::new(),
             loader,
             config,
@@ -67,6 +81,14 @@ impl Repl {
         })
     }
 
+    /// State before any input, with the `-D` defines and the REPL's backend
+    /// flag as constants.
+    fn initial_state(config: &ReplConfig) -> REPLState {
+        let mut defines = config.defines.clone();
+        defines.push(backend_define("repl"));
+        REPLState::with_defines(defines, config.cell_width)
+    }
+
     /// Run the REPL loop.
     pub fn run(&mut self) -> io::Result<()> {
         if self.config.show_welcome {
@@ -128,18 +150,35 @@ impl Repl {
             println!("{}  {}", "Input:".cyan(), input);
         }
 
-        let mut lexer = Lexer::new(input.to_string());
+        let sources = SourceMap::unnamed(input);
+        let renderer = DiagnosticRenderer::new(&sources).with_color(true);
+        let mut lexer = Lexer::new(input.to_string())
+            .with_base(self.state.compiler_ctx.base)
+            .with_cell_width(self.config.cell_width);
         let tokens = lexer
             .tokenize()
-            .map_err(|e| format!("Lexer error: {}", e))?;
+            .map_err(|errors| renderer.report_errors("lexing", &errors))?;
+        self.state.compiler_ctx.base = lexer.base();
 
         if self.config.debug >= 2 {
             println!("{}  {:?}", "Tokens:".cyan(), tokens);
         }
 
-        // Step 2: Parsing
+        // Step 2: Parsing, after running IMMEDIATE words. The expander is only
+        // kept if the whole input succeeds.
+        let mut expander = self.state.compiler_ctx.expander.clone();
+        let tokens = expander
+            .expand(&tokens)
+            .map_err(|errors| renderer.report_errors("parsing", &errors))?;
+
         let mut parser = Parser::new(tokens);
-        let ast = parser.parse().map_err(|e| format!("Parser error: {}", e))?;
+        for name in &self.state.compiler_ctx.defining_words {
+            parser.add_defining_word(name.clone());
+        }
+        let ast = parser
+            .parse()
+            .map_err(|errors| renderer.report_errors("parsing", &errors))?;
+        let defining_words = parser.defining_words().clone();
 
         if self.config.debug >= 2 {
             println!("{}  {:?}", "AST:".cyan(), ast);
@@ -158,13 +197,30 @@ impl Repl {
         for name in &self.state.compiler_ctx.variables {
             analyzer.add_variable(name.clone());
         }
+        for name in self
+            .state
+            .compiler_ctx
+            .constants
+            .keys()
+            .chain(self.state.compiler_ctx.stored_constants.keys())
+        {
+            analyzer.add_constant(name.clone());
+        }
+        for name in &self.state.compiler_ctx.values {
+            analyzer.add_value(name.clone());
+        }
+        for name in &self.state.compiler_ctx.deferred {
+            analyzer.add_deferred_word(name.clone());
+        }
 
-        analyzer
-            .analyze(&ast)
-            .map_err(|e| format!("Semantic error: {}", e))?;
+        // Errors come first when there are both, even if warnings are errors
+        let analyzed = analyzer.analyze(&ast);
+        let warned = renderer.report_warnings(&self.config.warnings, analyzer.warnings());
+        analyzed.map_err(|errors| renderer.report_errors("semantic analysis", &errors))?;
+        warned?;
 
         // Step 4: IR Lowering
-        let mut ir_lowering = IRLowering::new();
+        let mut ir_lowering = IRLowering::new().with_cell_width(self.config.cell_width);
 
         // Add all known user-defined words from previous REPL entries
         for name in self.state.compiler_ctx.definitions.keys() {
@@ -173,10 +229,40 @@ impl Repl {
 
         // Add all known variables from previous REPL entries
         for name in &self.state.compiler_ctx.variables {
-            ir_lowering.add_known_variable(name.clone());
+            ir_lowering.add_known_data_field(name.clone());
+        }
+        for (name, cells) in &self.state.compiler_ctx.constants {
+            ir_lowering.add_known_constant(name.clone(), cells.clone());
+        }
+        for (name, cells) in &self.state.compiler_ctx.stored_constants {
+            ir_lowering.add_known_stored_constant(name.clone(), *cells);
+        }
+        for name in &self.state.compiler_ctx.values {
+            ir_lowering.add_known_value(name.clone());
+        }
+        for (word, function) in &self.state.compiler_ctx.does_behaviors {
+            ir_lowering.add_known_does_behavior(word.clone(), function.clone());
+        }
+        for (name, function) in &self.state.compiler_ctx.does_instances {
+            ir_lowering.add_known_does_instance(name.clone(), function.clone());
         }
 
         let mut ir = ir_lowering.lower(&ast);
+        let constants = ir_lowering.constants().clone();
+        let stored_constants = ir_lowering.stored_constants().clone();
+        let does_behaviors = ir_lowering.does_behaviors().clone();
+        let does_instances = ir_lowering.does_instances().clone();
+        let mut stack_effects = self.state.compiler_ctx.stack_effects.clone();
+        let warnings = StackEffectAnalyzer::check_program(
+            &mut ir,
+            &ast,
+            ir_lowering.statement_starts(),
+            self.state.runtime_ctx.depth(),
+            self.state.runtime_ctx.float_depth(),
+            &mut stack_effects,
+        )
+        .map_err(|error| renderer.report_errors("semantic analysis", &[error]))?;
+        renderer.report_warnings(&self.config.warnings, &warnings)?;
 
         if self.config.debug >= 2 {
             println!("{}  {}", "IR:".cyan(), ir);
@@ -219,19 +305,52 @@ impl Repl {
                     }
                 }
 
-                // Track new variables
+                // Track new variables, CREATEd words, VALUEs, DEFERred words,
+                // defining words. A name only keeps its latest meaning.
                 if let crate::types::AstNode::Program(nodes) = &ast {
                     for node in nodes {
-                        if let crate::types::AstNode::VariableDeclaration { name, .. } = node {
-                            self.state.compiler_ctx.variables.insert(name.clone());
-                            self.state.runtime_ctx.declare_variable(name.clone());
+                        if let Some(name) = node.defined_name() {
+                            let ctx = &mut self.state.compiler_ctx;
+                            ctx.variables.remove(name);
+                            ctx.values.remove(name);
+                            ctx.deferred.remove(name);
+                        }
+                        match node {
+                            crate::types::AstNode::VariableDeclaration { name, .. }
+                            | crate::types::AstNode::Create { name, .. }
+                            | crate::types::AstNode::DefinedBy { name, .. } => {
+                                self.state.compiler_ctx.variables.insert(name.clone());
+                            }
+                            crate::types::AstNode::Value { name, .. } => {
+                                self.state.compiler_ctx.values.insert(name.clone());
+                            }
+                            crate::types::AstNode::Defer { name, .. } => {
+                                self.state.compiler_ctx.deferred.insert(name.clone());
+                            }
+                            _ => {}
                         }
                     }
                 }
+                self.state.compiler_ctx.constants = constants;
+                self.state.compiler_ctx.stored_constants = stored_constants;
+                self.state.compiler_ctx.defining_words = defining_words;
+                self.state.compiler_ctx.does_behaviors = does_behaviors;
+                self.state.compiler_ctx.does_instances = does_instances;
+                self.state.compiler_ctx.expander = expander;
+                self.state.compiler_ctx.stack_effects = stack_effects;
 
                 Ok(())
             }
-            Err(e) => Err(format!("Runtime error: {}", e)),
+            Err(e) => {
+                // ABORT empties the stacks on its way back to the prompt
+                if matches!(e.code(), throw_code::ABORT | throw_code::ABORT_QUOTE) {
+                    let runtime = &mut self.state.runtime_ctx;
+                    runtime.stack.clear();
+                    runtime.fstack.clear();
+                    runtime.rstack.clear();
+                }
+                Err(format!("Runtime error: {}", e))
+            }
         }
     }
 
@@ -262,7 +381,7 @@ impl Repl {
                 println!("Stack cleared.");
             }
             ":reset" | ":r" => {
-                self.state = REPLState::new();
+                self.state = Self::initial_state(&self.config);
                 println!("State reset.");
             }
             ":debug" => {
@@ -358,8 +477,14 @@ impl Repl {
         } else {
             println!("{}", "Variables:".bold());
             for var in &self.state.compiler_ctx.variables {
-                let value = self.state.runtime_ctx.memory.get(var).copied().unwrap_or(0);
-                println!("  {} = {}", var, value);
+                let runtime_ctx = &self.state.runtime_ctx;
+                let value = runtime_ctx
+                    .data_field(var)
+                    .and_then(|addr| runtime_ctx.read_cell(addr));
+                match value {
+                    Ok(value) => println!("  {} = {}", var, value),
+                    Err(_) => println!("  {}", var),
+                }
             }
         }
     }
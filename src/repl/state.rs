@@ -2,12 +2,14 @@
 //!
 //! Contains the runtime and compiler contexts that persist between REPL inputs.
 
-use crate::ir::IRFunction;
+use crate::expander::Expander;
+use crate::ir::{IRFunction, StackEffect};
+use crate::types::{Cell, CellWidth};
 use roth_runtime::RuntimeContext;
 use std::collections::{HashMap, HashSet};
 
 /// Compiler context containing compilation state for optimization across inputs.
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct CompilerContext {
     /// Accumulated word definitions (IR form for optimization).
     pub definitions: HashMap<String, IRFunction>,
@@ -15,8 +17,61 @@ pub struct CompilerContext {
     /// Declared variables.
     pub variables: HashSet<String>,
 
+    /// Constants, with the cells each one pushes.
+    pub constants: HashMap<String, Vec<Cell>>,
+
+    /// Constants whose value was taken from the stack, with the cells each
+    /// one holds.
+    pub stored_constants: HashMap<String, usize>,
+
+    /// Declared VALUEs.
+    pub values: HashSet<String>,
+
+    /// DEFERred words.
+    pub deferred: HashSet<String>,
+
+    /// User words that use CREATE, so take a name when run.
+    pub defining_words: HashSet<String>,
+
+    /// Defining words with a DOES> part, with the function holding it.
+    pub does_behaviors: HashMap<String, String>,
+
+    /// Words created by defining words with a DOES> part, with the function
+    /// each one runs.
+    pub does_instances: HashMap<String, String>,
+
+    /// Runs IMMEDIATE words, keeping every input so far for them to call.
+    pub expander: Expander,
+
+    /// Stack effects of the words defined so far, where they could be inferred
+    /// or were declared.
+    pub stack_effects: HashMap<String, StackEffect>,
+
     /// Counter for generating unique library names.
     pub lib_counter: usize,
+
+    /// Radix for number literals, set by HEX, DECIMAL and `BASE !`.
+    pub base: u32,
+}
+
+impl Default for CompilerContext {
+    fn default() -> Self {
+        Self {
+            definitions: HashMap::new(),
+            variables: HashSet::new(),
+            constants: HashMap::new(),
+            stored_constants: HashMap::new(),
+            values: HashSet::new(),
+            deferred: HashSet::new(),
+            defining_words: HashSet::new(),
+            does_behaviors: HashMap::new(),
+            does_instances: HashMap::new(),
+            expander: Expander::new(),
+            stack_effects: HashMap::new(),
+            lib_counter: 0,
+            base: 10,
+        }
+    }
 }
 
 impl CompilerContext {
@@ -25,6 +80,19 @@ impl CompilerContext {
         Self::default()
     }
 
+    /// Create a compiler context where `defines` are already constants, and
+    /// compile-time code runs with `cell_width` cells.
+    pub fn with_defines(defines: Vec<(String, Cell)>, cell_width: CellWidth) -> Self {
+        let mut ctx = Self::new();
+        for (name, value) in &defines {
+            ctx.constants.insert(name.clone(), vec![*value]);
+        }
+        ctx.expander = Expander::new()
+            .with_cell_width(cell_width)
+            .with_defines(defines);
+        ctx
+    }
+
     /// Get the next unique library ID and increment the counter.
     pub fn next_lib_id(&mut self) -> usize {
         let id = self.lib_counter;
@@ -60,6 +128,15 @@ impl REPLState {
             compiler_ctx: CompilerContext::new(),
         }
     }
+
+    /// Create a new REPL state where `defines` are already constants, with
+    /// `cell_width` cells.
+    pub fn with_defines(defines: Vec<(String, Cell)>, cell_width: CellWidth) -> Self {
+        Self {
+            runtime_ctx: RuntimeContext::with_cell_bits(cell_width.bits()),
+            compiler_ctx: CompilerContext::with_defines(defines, cell_width),
+        }
+    }
 }
 
 impl Default for REPLState {
@@ -0,0 +1,385 @@
+use crate::lexer::Lexer;
+use crate::types::{CellWidth, ParseError, ParseErrors, Position, Token, TokenType};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A file read during compilation
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub name: Option<String>, // How messages refer to it; REPL input has no name
+    pub text: String,
+    pub included_from: Option<Position>, // The INCLUDE that read it
+    pub bundled: bool,                   // Part of the library shipped with the compiler
+}
+
+/// Every file read for one compilation, indexed by `Position::file`
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A map holding only `text`, with no file name
+    pub fn unnamed(text: &str) -> Self {
+        let mut sources = Self::new();
+        sources.add(None, text.to_string(), None);
+        sources
+    }
+
+    /// Add a file, returning its index
+    pub fn add(
+        &mut self,
+        name: Option<String>,
+        text: String,
+        included_from: Option<Position>,
+    ) -> usize {
+        self.files.push(SourceFile {
+            name,
+            text,
+            included_from,
+            bundled: false,
+        });
+        self.files.len() - 1
+    }
+
+    pub fn file(&self, file: usize) -> Option<&SourceFile> {
+        self.files.get(file)
+    }
+
+    /// Whether `file` is part of the bundled library, whose warnings are not
+    /// the user's to fix
+    pub fn is_bundled(&self, file: usize) -> bool {
+        self.file(file).is_some_and(|file| file.bundled)
+    }
+
+    /// `position` as `name:line:column`, or `line:column` in unnamed input
+    pub fn locate(&self, position: &Position) -> String {
+        match self.file(position.file).and_then(|file| file.name.as_ref()) {
+            Some(name) => format!("{}:{}:{}", name, position.line, position.column),
+            None => format!("{}:{}", position.line, position.column),
+        }
+    }
+
+    /// The INCLUDEs that led to `file`, innermost first
+    pub fn include_chain(&self, file: usize) -> Vec<&Position> {
+        let mut chain = Vec::new();
+        let mut current = self.file(file);
+        while let Some(position) = current.and_then(|file| file.included_from.as_ref()) {
+            chain.push(position);
+            current = self.file(position.file);
+        }
+        chain
+    }
+}
+
+/// Where the bundled `std/` library is looked for, relative to the directory
+/// of the running executable: beside it, as in a release archive, or under
+/// `share/roth` of the prefix it is installed in
+const INSTALLED_LIBRARY_DIRS: &[&str] = &[".", "../share/roth"];
+
+/// Directory holding the bundled `std/` library, searched after every other
+/// include path. It is found from the running executable, or else in the
+/// source tree the compiler was built from, as when it runs from `target/`.
+pub fn library_dir() -> Option<PathBuf> {
+    let holds_library = |dir: &Path| dir.join("std").is_dir();
+    let installed = std::env::current_exe().ok().and_then(|exe| {
+        let exe_dir = exe.parent()?.to_path_buf();
+        INSTALLED_LIBRARY_DIRS
+            .iter()
+            .map(|relative| exe_dir.join(relative))
+            .find(|dir| holds_library(dir))
+    });
+    installed.or_else(|| {
+        let source_tree = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        holds_library(&source_tree).then_some(source_tree)
+    })
+}
+
+/// Include paths from `ROTH_PATH`, followed by the bundled library
+pub fn default_search_paths() -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = std::env::var_os("ROTH_PATH")
+        .map(|paths| std::env::split_paths(&paths).collect())
+        .unwrap_or_default();
+    paths.extend(library_dir());
+    paths
+}
+
+/// An INCLUDE, REQUIRE, INCLUDED or REQUIRED at the end of a run of tokens
+struct Directive {
+    word: String,
+    file: Option<String>, // Missing when INCLUDED or REQUIRED has no string literal
+    position: Position,
+    once: bool,    // REQUIRE and REQUIRED skip files that were already read
+    length: usize, // Tokens making up the directive
+}
+
+impl Directive {
+    fn ending(tokens: &[Token]) -> Option<Directive> {
+        let directive = |word: &str, file, position: &Position, length| Directive {
+            word: word.to_string(),
+            file,
+            position: position.clone(),
+            once: word.starts_with("REQUIRE"),
+            length,
+        };
+        match tokens {
+            [.., word, name] => match &word.token_type {
+                TokenType::Word(w) if w == "INCLUDE" || w == "REQUIRE" => {
+                    let file = match &name.token_type {
+                        TokenType::StringLiteral(file) => file.clone(),
+                        _ => name.raw.clone(),
+                    };
+                    Some(directive(w, Some(file), &word.position, 2))
+                }
+                _ => Self::taking_string(tokens, directive),
+            },
+            _ => Self::taking_string(tokens, directive),
+        }
+    }
+
+    /// `S" file" INCLUDED` and `S" file" REQUIRED`
+    fn taking_string(
+        tokens: &[Token],
+        directive: impl Fn(&str, Option<String>, &Position, usize) -> Directive,
+    ) -> Option<Directive> {
+        let (word, before) = tokens.split_last()?;
+        let TokenType::Word(w) = &word.token_type else {
+            return None;
+        };
+        if w != "INCLUDED" && w != "REQUIRED" {
+            return None;
+        }
+        Some(match before.last().map(|token| &token.token_type) {
+            Some(TokenType::StringLiteral(file)) => {
+                directive(w, Some(file.clone()), &word.position, 2)
+            }
+            _ => directive(w, None, &word.position, 1),
+        })
+    }
+}
+
+/// Lexes a source file together with the files it includes, so that every
+/// token is positioned in the file it came from.
+///
+/// INCLUDE reads a file every time; REQUIRE only if it has not been read
+/// yet. `S" file" INCLUDED` and `S" file" REQUIRED` take the name from a
+/// string literal instead. Files are looked for next to the including file,
+/// then in the search paths, then in the working directory.
+pub struct SourceLoader {
+    sources: SourceMap,
+    search_paths: Vec<PathBuf>,
+    included: HashSet<PathBuf>,       // Every file read so far
+    including: Vec<(PathBuf, usize)>, // Files being read and their ids, outermost first
+    library: Option<PathBuf>,         // The bundled std/ directory, canonicalized
+    cell_width: CellWidth,
+    base: u32, // Radix carried from each file into the files it includes and back
+    debug: u8,
+}
+
+impl Default for SourceLoader {
+    fn default() -> Self {
+        Self {
+            sources: SourceMap::new(),
+            search_paths: Vec::new(),
+            included: HashSet::new(),
+            including: Vec::new(),
+            library: library_dir().and_then(|dir| dir.join("std").canonicalize().ok()),
+            cell_width: CellWidth::default(),
+            base: 10,
+            debug: 0,
+        }
+    }
+}
+
+impl SourceLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_cell_width(mut self, cell_width: CellWidth) -> Self {
+        self.cell_width = cell_width;
+        self
+    }
+
+    /// Look for included files in `paths`, in order, after the directory of
+    /// the including file
+    pub fn with_search_paths(mut self, paths: Vec<PathBuf>) -> Self {
+        self.search_paths = paths;
+        self
+    }
+
+    /// Report each included file at debug level 2 and above
+    pub fn with_debug(mut self, debug: u8) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    /// Every file read so far
+    pub fn sources(&self) -> &SourceMap {
+        &self.sources
+    }
+
+    /// Lex `text`, read from the file at `path`, and everything it includes
+    pub fn load(&mut self, path: &Path, text: String) -> Result<Vec<Token>, ParseErrors> {
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let mut errors = Vec::new();
+        let tokens = self.lex(path, canonical, text, None, &mut errors);
+        ParseErrors::result(errors)?;
+        Ok(tokens)
+    }
+
+    fn lex(
+        &mut self,
+        path: &Path,
+        canonical: PathBuf,
+        text: String,
+        included_from: Option<Position>,
+        errors: &mut Vec<ParseError>,
+    ) -> Vec<Token> {
+        let name = path.display().to_string();
+        let file = self.sources.add(Some(name), text.clone(), included_from);
+        self.sources.files[file].bundled = self
+            .library
+            .as_ref()
+            .is_some_and(|library| canonical.starts_with(library));
+        self.included.insert(canonical.clone());
+        self.including.push((canonical, file));
+        let mut lexer = Lexer::new(text)
+            .with_base(self.base)
+            .with_cell_width(self.cell_width)
+            .with_file(file);
+
+        let mut tokens = Vec::new();
+        while !lexer.finished() {
+            let mut chunk = match lexer.tokenize_until(|tokens| Directive::ending(tokens).is_some())
+            {
+                Ok(chunk) => chunk,
+                Err(chunk_errors) => {
+                    errors.extend(chunk_errors);
+                    continue;
+                }
+            };
+            let directive = Directive::ending(&chunk);
+            if let Some(directive) = &directive {
+                chunk.truncate(chunk.len() - directive.length);
+            }
+            tokens.extend(chunk);
+
+            if let Some(directive) = directive {
+                self.base = lexer.base();
+                match self.include(path, &directive, errors) {
+                    Ok(included) => tokens.extend(included),
+                    Err(error) => errors.push(error),
+                }
+                lexer.set_base(self.base);
+            }
+        }
+        if let Some(last) = tokens.last()
+            && let TokenType::Word(word) = &last.token_type
+            && (word == "INCLUDE" || word == "REQUIRE")
+        {
+            errors.push(
+                ParseError::new(
+                    format!("{} requires a filename", word),
+                    last.position.clone(),
+                )
+                .with_help(format!("name the file to include after {}", word)),
+            );
+            tokens.pop();
+        }
+        self.base = lexer.base();
+        self.including.pop();
+        tokens
+    }
+
+    /// Read the file `directive` in the file at `path` names, and lex it
+    fn include(
+        &mut self,
+        path: &Path,
+        directive: &Directive,
+        errors: &mut Vec<ParseError>,
+    ) -> Result<Vec<Token>, ParseError> {
+        let position = &directive.position;
+        let Some(name) = &directive.file else {
+            return Err(ParseError::new(
+                format!("{} needs the file name in a string literal", directive.word),
+                position.clone(),
+            )
+            .with_help(format!("write S\" file.fs\" {}", directive.word)));
+        };
+
+        let directory = path
+            .parent()
+            .filter(|directory| !directory.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let include_path = std::iter::once(directory)
+            .chain(self.search_paths.iter().map(PathBuf::as_path))
+            .map(|directory| directory.join(name))
+            .chain(std::iter::once(PathBuf::from(name)))
+            .find(|candidate| candidate.is_file())
+            .ok_or_else(|| {
+                ParseError::new(
+                    format!("Cannot find include file '{}'", name),
+                    position.clone(),
+                )
+                .with_help(format!(
+                    "looked in '{}', the search paths and the current directory; add a directory with -I or ROTH_PATH",
+                    directory.display()
+                ))
+            })?;
+        let canonical = include_path.canonicalize().map_err(|e| {
+            ParseError::new(
+                format!("Cannot canonicalize include file '{}': {}", name, e),
+                position.clone(),
+            )
+        })?;
+
+        if directive.once && self.included.contains(&canonical) {
+            if self.debug >= 2 {
+                println!("Skipping already included file: {}", canonical.display());
+            }
+            return Ok(Vec::new());
+        }
+        if let Some((_, file)) = self.including.iter().find(|(path, _)| *path == canonical) {
+            let error = ParseError::new(
+                format!("Circular include: '{}' is already being read", name),
+                position.clone(),
+            )
+            .with_help(format!("use REQUIRE {} to read it only once", name));
+            return Err(
+                match self
+                    .sources
+                    .file(*file)
+                    .and_then(|file| file.included_from.clone())
+                {
+                    Some(first) => {
+                        error.with_note(format!("'{}' is first included here", name), first)
+                    }
+                    None => error,
+                },
+            );
+        }
+        if self.debug >= 2 {
+            println!("Including file: {}", canonical.display());
+        }
+
+        let text = fs::read_to_string(&include_path).map_err(|e| {
+            ParseError::new(
+                format!("Error reading include file '{}': {}", name, e),
+                position.clone(),
+            )
+        })?;
+        Ok(self.lex(
+            &include_path,
+            canonical,
+            text,
+            Some(position.clone()),
+            errors,
+        ))
+    }
+}
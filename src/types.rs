@@ -3,16 +3,105 @@ pub struct Position {
     pub line: usize,
     pub column: usize,
     pub offset: usize,
+    pub file: usize, // Index into the compilation's `SourceMap`
+}
+
+/// A single stack cell as written in source
+pub type Cell = i64;
+/// A double-cell number such as `123.`
+pub type DoubleCell = i128;
+/// A floating-point number such as `1.5e0`, kept on the float stack
+pub type Float = f64;
+
+/// Width of a stack cell on the compilation target.
+///
+/// The compiler always carries values as `Cell`; `wrap` narrows them to the
+/// target width so constants and folded arithmetic match the generated code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CellWidth {
+    Bits32,
+    #[default]
+    Bits64,
+}
+
+impl CellWidth {
+    pub fn from_bits(bits: u32) -> Option<Self> {
+        match bits {
+            32 => Some(CellWidth::Bits32),
+            64 => Some(CellWidth::Bits64),
+            _ => None,
+        }
+    }
+
+    pub fn bits(self) -> u32 {
+        match self {
+            CellWidth::Bits32 => 32,
+            CellWidth::Bits64 => 64,
+        }
+    }
+
+    /// Size of a cell in data space
+    pub fn bytes(self) -> Cell {
+        (self.bits() / 8) as Cell
+    }
+
+    /// Two's-complement wrap of `value` into a single cell
+    pub fn wrap(self, value: Cell) -> Cell {
+        match self {
+            CellWidth::Bits32 => value as i32 as Cell,
+            CellWidth::Bits64 => value,
+        }
+    }
+
+    /// Two's-complement wrap of `value` into a cell pair
+    pub fn wrap_double(self, value: DoubleCell) -> DoubleCell {
+        match self {
+            CellWidth::Bits32 => value as i64 as DoubleCell,
+            CellWidth::Bits64 => value,
+        }
+    }
+
+    /// `value` read as an unsigned cell
+    pub fn unsigned(self, value: Cell) -> u128 {
+        value as u128 & ((1 << self.bits()) - 1)
+    }
+
+    /// `value` read as an unsigned cell pair
+    pub fn unsigned_double(self, value: DoubleCell) -> u128 {
+        match self {
+            CellWidth::Bits32 => value as u64 as u128,
+            CellWidth::Bits64 => value as u128,
+        }
+    }
+
+    /// The double made of a low and a high cell
+    pub fn join(self, low: Cell, high: Cell) -> DoubleCell {
+        ((high as DoubleCell) << self.bits()) | self.unsigned(low) as DoubleCell
+    }
+
+    /// A double-cell number as cells pushed in order: low cell first, high
+    /// cell on top
+    pub fn split(self, value: DoubleCell) -> [Cell; 2] {
+        [
+            self.wrap(value as Cell),
+            self.wrap((value >> self.bits()) as Cell),
+        ]
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenType {
-    Number(i32),
+    Number(Cell),
+    DoubleNumber(DoubleCell),
+    Float(Float),
     Word(String),
     StartDefinition,
     EndDefinition,
     Comment(String),
-    StringLiteral(String),
+    StringLiteral(String), // S" text", S\" text" or "text"
+    DotQuote(String),      // ." text"
+    CQuote(String),        // C" text"
+    AbortQuote(String),    // ABORT" text"
 }
 
 #[derive(Debug, Clone)]
@@ -24,25 +113,384 @@ pub struct Token {
 
 #[derive(Debug, Clone)]
 pub enum AstNode {
-    Number(i32, Position),
+    Number(Cell, Position),
+    DoubleNumber(DoubleCell, Position),
+    Float(Float, Position),
     Word(String, Position),
+    /// `S" text"`, pushing its address and length
     StringLiteral(String, Position),
+    /// `." text"`, printing it
+    DotQuote(String, Position),
+    /// `C" text"`, pushing the address of its count byte
+    CQuote(String, Position),
+    /// `ABORT" text"`, throwing with the text if the flag on the stack is
+    /// true
+    AbortQuote(String, Position),
     Definition {
         name: String,
         body: Vec<AstNode>,
+        /// The stack-effect comment right after the name, if there is one
+        signature: Option<StackSignature>,
         position: Position,
     },
+    /// `VARIABLE name`, `2VARIABLE name` or `FVARIABLE name`
     VariableDeclaration {
+        name: String,
+        kind: VariableKind,
+        position: Position,
+    },
+    /// `n CONSTANT name` or `d 2CONSTANT name`, holding `cells` cells.
+    /// `value` holds the literal nodes that precede it: a number, a double
+    /// number or two numbers. It is empty when the value is only known when
+    /// the program runs, and taken from the stack then.
+    Constant {
+        name: String,
+        value: Vec<AstNode>,
+        cells: usize,
+        position: Position,
+    },
+    /// `x VALUE name`: a cell initialised from the stack
+    Value {
+        name: String,
+        position: Position,
+    },
+    /// `DEFER name`: a word that runs whatever execution token IS gave it
+    Defer {
+        name: String,
+        position: Position,
+    },
+    /// `DOES> body`: the behavior shared by words a defining word creates.
+    /// Only appears as the last node of a definition's body.
+    Does {
+        body: Vec<AstNode>,
+        position: Position,
+    },
+    /// `defining-word name`: runs a user defining word (one that uses
+    /// CREATE), giving `name` to the word it creates
+    DefinedBy {
+        word: String,
+        name: String,
+        position: Position,
+    },
+    /// `x TO name`: store into a VALUE
+    To {
+        name: String,
+        position: Position,
+    },
+    /// `xt IS name`: set what a DEFERred word runs
+    Is {
+        name: String,
+        position: Position,
+    },
+    /// `ACTION-OF name`: push the execution token a DEFERred word runs
+    ActionOf {
+        name: String,
+        position: Position,
+    },
+    /// `{: a b | c -- outputs :}` at the start of a definition: locals `a`
+    /// and `b` take their values from the stack, `c` starts at zero, and the
+    /// names after `--` only document what the word leaves
+    Locals {
+        names: Vec<String>,
+        initialized: usize, // Leading names that take a value from the stack
+        position: Position,
+    },
+    /// A local of the definition it is in, pushing its value
+    Local {
+        name: String,
+        position: Position,
+    },
+    /// `x TO name` where `name` is a local
+    ToLocal {
+        name: String,
+        position: Position,
+    },
+    /// `POSTPONE name` inside an IMMEDIATE word: compiles `name` into the
+    /// definition the IMMEDIATE word is used in
+    Postpone {
         name: String,
         position: Position,
     },
+    /// `' name` or `['] name`: pushes the execution token of `name`
+    Tick {
+        name: String,
+        position: Position,
+    },
+    /// `CREATE name`: names the current end of data space
+    Create {
+        name: String,
+        position: Position,
+    },
+    If {
+        then_branch: Vec<AstNode>,
+        else_branch: Option<Vec<AstNode>>,
+        position: Position,
+    },
+    DoLoop {
+        body: Vec<AstNode>,
+        conditional: bool, // ?DO
+        plus_loop: bool,   // closed by +LOOP instead of LOOP
+        position: Position,
+    },
+    Begin {
+        body: Vec<AstNode>,
+        kind: BeginKind,
+        position: Position,
+    },
     Program(Vec<AstNode>),
 }
 
-#[derive(Debug)]
+impl AstNode {
+    /// Where the node starts in the source; a whole program has no position
+    pub fn position(&self) -> Option<&Position> {
+        match self {
+            AstNode::Number(_, position)
+            | AstNode::DoubleNumber(_, position)
+            | AstNode::Float(_, position)
+            | AstNode::Word(_, position)
+            | AstNode::StringLiteral(_, position)
+            | AstNode::DotQuote(_, position)
+            | AstNode::CQuote(_, position)
+            | AstNode::AbortQuote(_, position)
+            | AstNode::Definition { position, .. }
+            | AstNode::VariableDeclaration { position, .. }
+            | AstNode::Constant { position, .. }
+            | AstNode::Value { position, .. }
+            | AstNode::Defer { position, .. }
+            | AstNode::Does { position, .. }
+            | AstNode::DefinedBy { position, .. }
+            | AstNode::To { position, .. }
+            | AstNode::Is { position, .. }
+            | AstNode::ActionOf { position, .. }
+            | AstNode::Locals { position, .. }
+            | AstNode::Local { position, .. }
+            | AstNode::ToLocal { position, .. }
+            | AstNode::Postpone { position, .. }
+            | AstNode::Tick { position, .. }
+            | AstNode::Create { position, .. }
+            | AstNode::If { position, .. }
+            | AstNode::DoLoop { position, .. }
+            | AstNode::Begin { position, .. } => Some(position),
+            AstNode::Program(_) => None,
+        }
+    }
+
+    /// The name the node defines, if it is a definition or a defining word
+    pub fn defined_name(&self) -> Option<&String> {
+        match self {
+            AstNode::Definition { name, .. }
+            | AstNode::DefinedBy { name, .. }
+            | AstNode::VariableDeclaration { name, .. }
+            | AstNode::Create { name, .. }
+            | AstNode::Constant { name, .. }
+            | AstNode::Value { name, .. }
+            | AstNode::Defer { name, .. } => Some(name),
+            _ => None,
+        }
+    }
+}
+
+/// A stack-effect comment such as `( n1 n2 -- sum )`, declaring what a word
+/// takes from the stack and leaves on it
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackSignature {
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    pub position: Position,
+    /// A separate `( F: r1 -- r2 )` comment after this one
+    pub float_comment: Option<Box<StackSignature>>,
+}
+
+impl StackSignature {
+    /// Read the text of a comment as a signature, if it has exactly one `--`
+    pub fn parse(comment: &str, position: Position) -> Option<Self> {
+        let items: Vec<&str> = comment.split_whitespace().collect();
+        let mut sides = items.split(|item| *item == "--");
+        let (Some(inputs), Some(outputs), None) = (sides.next(), sides.next(), sides.next()) else {
+            return None;
+        };
+        let names = |side: &[&str]| side.iter().map(|item| item.to_string()).collect();
+        Some(Self {
+            inputs: names(inputs),
+            outputs: names(outputs),
+            position,
+            float_comment: None,
+        })
+    }
+
+    /// Whether the comment is all about the float stack, as in `( F: r -- )`
+    pub fn is_float_comment(&self) -> bool {
+        self.inputs
+            .first()
+            .is_some_and(|item| is_float_marker(item))
+    }
+
+    /// Cells taken from the stack
+    pub fn consumes(&self) -> usize {
+        self.cells(&self.inputs) + self.float_comment.as_ref().map_or(0, |f| f.consumes())
+    }
+
+    /// Cells left on the stack
+    pub fn produces(&self) -> usize {
+        self.cells(&self.outputs) + self.float_comment.as_ref().map_or(0, |f| f.produces())
+    }
+
+    /// Floats taken from the float stack, if the signature has an `F:` group
+    pub fn float_consumes(&self) -> Option<usize> {
+        self.has_floats().then(|| {
+            self.floats(&self.inputs)
+                + self
+                    .float_comment
+                    .as_ref()
+                    .map_or(0, |f| f.floats(&f.inputs))
+        })
+    }
+
+    /// Floats left on the float stack, if the signature has an `F:` group
+    pub fn float_produces(&self) -> Option<usize> {
+        self.has_floats().then(|| {
+            self.floats(&self.outputs)
+                + self
+                    .float_comment
+                    .as_ref()
+                    .map_or(0, |f| f.floats(&f.outputs))
+        })
+    }
+
+    /// Whether `F:` sets some items apart for the float stack
+    fn has_floats(&self) -> bool {
+        self.float_comment.is_some()
+            || self
+                .inputs
+                .iter()
+                .chain(&self.outputs)
+                .any(|item| is_float_marker(item))
+    }
+
+    /// Items of `side` on the data stack and on the float stack. Items after
+    /// `F:` are floats, and a comment starting with it is all floats.
+    fn split<'a>(&self, side: &'a [String]) -> (&'a [String], &'a [String]) {
+        match side.iter().position(|item| is_float_marker(item)) {
+            Some(marker) => (&side[..marker], &side[marker + 1..]),
+            None if self.is_float_comment() => (&[], side),
+            None => (side, &[]),
+        }
+    }
+
+    /// Items named `d` or `ud`, optionally numbered, are double cells. Items
+    /// named `r` are floats even outside an `F:` group, so they are not cells.
+    fn cells(&self, side: &[String]) -> usize {
+        self.split(side)
+            .0
+            .iter()
+            .map(|item| match Self::item_kind(item) {
+                "d" | "ud" => 2,
+                "r" => 0,
+                _ => 1,
+            })
+            .sum()
+    }
+
+    fn floats(&self, side: &[String]) -> usize {
+        self.split(side).1.len()
+    }
+
+    fn item_kind(item: &str) -> &str {
+        item.trim_end_matches(|c: char| c.is_ascii_digit())
+    }
+}
+
+/// `F:`, which starts the float stack items in the standard's notation
+fn is_float_marker(item: &str) -> bool {
+    item.eq_ignore_ascii_case("F:")
+}
+
+impl std::fmt::Display for StackSignature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(")?;
+        for item in &self.inputs {
+            write!(f, " {}", item)?;
+        }
+        write!(f, " --")?;
+        for item in &self.outputs {
+            write!(f, " {}", item)?;
+        }
+        write!(f, " )")?;
+        if let Some(float_comment) = &self.float_comment {
+            write!(f, " {}", float_comment)?;
+        }
+        Ok(())
+    }
+}
+
+/// What a variable holds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VariableKind {
+    Cell,
+    Double,
+    Float,
+}
+
+impl VariableKind {
+    /// The word that declares this kind of variable
+    pub fn defining_word(self) -> &'static str {
+        match self {
+            VariableKind::Cell => "VARIABLE",
+            VariableKind::Double => "2VARIABLE",
+            VariableKind::Float => "FVARIABLE",
+        }
+    }
+}
+
+/// How a BEGIN loop is closed
+#[derive(Debug, Clone)]
+pub enum BeginKind {
+    Until,
+    Again,
+    /// BEGIN condition WHILE body REPEAT: the node's `body` is the condition,
+    /// this holds the words between WHILE and REPEAT
+    WhileRepeat(Vec<AstNode>),
+}
+
+#[derive(Debug, Clone)]
 pub struct ParseError {
     pub message: String,
     pub position: Position,
+    pub notes: Vec<Note>, // Related places in the source, shown with the message
+    pub help: Option<String>, // How to fix it, when there is an obvious way
+}
+
+/// Another place in the source that explains an error, such as where the
+/// construct it breaks was opened
+#[derive(Debug, Clone)]
+pub struct Note {
+    pub message: String,
+    pub position: Position,
+}
+
+impl ParseError {
+    pub fn new(message: impl Into<String>, position: Position) -> Self {
+        Self {
+            message: message.into(),
+            position,
+            notes: Vec::new(),
+            help: None,
+        }
+    }
+
+    /// Point at a related place, such as where an unclosed IF was opened
+    pub fn with_note(mut self, message: impl Into<String>, position: Position) -> Self {
+        self.notes.push(Note {
+            message: message.into(),
+            position,
+        });
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
 }
 
 impl std::fmt::Display for ParseError {
@@ -56,3 +504,58 @@ impl std::fmt::Display for ParseError {
 }
 
 impl std::error::Error for ParseError {}
+
+/// Every error found in one pass over the source, in source order
+#[derive(Debug, Clone)]
+pub struct ParseErrors(pub Vec<ParseError>);
+
+impl ParseErrors {
+    /// The errors in source order, or Ok if there are none
+    pub fn result(mut errors: Vec<ParseError>) -> Result<(), ParseErrors> {
+        if errors.is_empty() {
+            return Ok(());
+        }
+        errors.sort_by_key(|error| {
+            let position = &error.position;
+            (position.file, position.line, position.column)
+        });
+        Err(ParseErrors(errors))
+    }
+}
+
+impl From<ParseError> for ParseErrors {
+    fn from(error: ParseError) -> Self {
+        ParseErrors(vec![error])
+    }
+}
+
+impl std::ops::Deref for ParseErrors {
+    type Target = [ParseError];
+
+    fn deref(&self) -> &[ParseError] {
+        &self.0
+    }
+}
+
+impl IntoIterator for ParseErrors {
+    type Item = ParseError;
+    type IntoIter = std::vec::IntoIter<ParseError>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl std::fmt::Display for ParseErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, error) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseErrors {}
@@ -0,0 +1,19 @@
+( Number literal syntax: prefixes, characters and doubles )
+$FF . #99 . %1010 . 'A' . $-10 . CR
+
+( HEX and BASE change how later literals are read )
+HEX
+: HEX-VALUES 10 FF ;
+DECIMAL
+HEX-VALUES . . CR
+2 BASE !
+: BINARY-VALUE 1010 ;
+DECIMAL
+BINARY-VALUE . CR
+
+( A trailing dot makes a double: low cell, then high cell )
+123. . . CR
+
+( Names of words are never read as numbers, even when spelled like one )
+: ADD + ;
+HEX 1 2 ADD . 1. D. DECIMAL CR
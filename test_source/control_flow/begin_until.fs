@@ -0,0 +1,9 @@
+( Test BEGIN/UNTIL construct )
+( Post-tested indefinite loop )
+
+: COUNTDOWN BEGIN DUP . 1 - DUP 0 = UNTIL DROP ;
+5 COUNTDOWN
+CR
+: ONCE BEGIN 7 . -1 UNTIL ;
+ONCE
+CR
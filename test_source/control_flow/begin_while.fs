@@ -0,0 +1,9 @@
+( Test BEGIN/WHILE/REPEAT construct )
+( Pre-tested indefinite loop )
+
+: COUNTUP 0 BEGIN DUP 5 < WHILE DUP . 1 + REPEAT DROP ;
+COUNTUP
+CR
+: NONE 10 BEGIN DUP 5 < WHILE DUP . 1 + REPEAT DROP ;
+NONE
+CR
@@ -0,0 +1,20 @@
+( +LOOP with positive and negative steps )
+: EVENS 10 0 DO I . 2 +LOOP CR ;
+: DOWN 0 10 DO I . -1 +LOOP CR ;
+: THIRDS 0 10 DO I . -3 +LOOP CR ;
+
+( LEAVE only exits the innermost loop )
+: FIND-FIRST ( n -- ) 10 0 DO DUP I = IF I . LEAVE THEN LOOP DROP CR ;
+: GRID 3 0 DO 3 0 DO I J > IF LEAVE THEN I . LOOP 124 EMIT LOOP CR ;
+
+( UNLOOP EXIT returns from inside a loop )
+: EARLY 10 0 DO I 3 = IF UNLOOP EXIT THEN I . LOOP 99 . ;
+
+( K reaches the third nesting level )
+: CUBE 2 0 DO 2 0 DO 2 0 DO K . J . I . CR LOOP LOOP LOOP ;
+
+EVENS DOWN THIRDS
+4 FIND-FIRST 20 FIND-FIRST
+GRID
+EARLY CR
+CUBE
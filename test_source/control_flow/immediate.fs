@@ -0,0 +1,22 @@
+( Test IMMEDIATE words, [ ], LITERAL and POSTPONE )
+( Words from std/control.rt are built at compile time )
+
+INCLUDE std/control.rt
+
+: SQUARES 5 TIMES I DUP * . LOOP ;
+SQUARES
+CR
+
+: DOWN FOR I . NEXT ;
+3 DOWN
+0 DOWN
+CR
+
+: SECONDS-PER-DAY [ 60 60 * 24 * ] LITERAL ;
+SECONDS-PER-DAY .
+CR
+
+: TWICE 2 POSTPONE LITERAL POSTPONE * ; IMMEDIATE
+: DOUBLE TWICE ;
+21 DOUBLE .
+CR
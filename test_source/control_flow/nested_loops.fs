@@ -0,0 +1,19 @@
+( Test nesting of BEGIN loops with IF and DO )
+( Mixed control structures inside each other )
+
+( DO inside BEGIN/UNTIL )
+: ROWS 1 BEGIN DUP 0 DO I . LOOP CR 1 + DUP 4 = UNTIL DROP ;
+ROWS
+
+( IF inside BEGIN/WHILE )
+: ODDS 0 BEGIN DUP 10 < WHILE DUP 2 MOD 1 = IF DUP . THEN 1 + REPEAT DROP ;
+ODDS
+CR
+
+( BEGIN/UNTIL inside DO with IF/ELSE )
+: HALVINGS 3 0 DO I 0 = IF 100 ELSE 8 THEN BEGIN DUP . 2 / DUP 0 = UNTIL DROP CR LOOP ;
+HALVINGS
+
+( BEGIN/WHILE inside BEGIN/UNTIL )
+: TRIANGLE 1 BEGIN 0 BEGIN OVER OVER > WHILE 42 EMIT 1 + REPEAT DROP CR 1 + DUP 4 > UNTIL DROP ;
+TRIANGLE
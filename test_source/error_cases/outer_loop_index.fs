@@ -0,0 +1,8 @@
+( Test J and K without enough enclosing loops )
+( Each needs the loops it indexes to be open )
+
+( J in a single loop )
+: ROW 3 0 DO J . LOOP ;
+
+( K in two nested loops )
+: GRID 3 0 DO 3 0 DO K . LOOP LOOP ;
@@ -0,0 +1,16 @@
+( Test strings printed from conditionals )
+
+: TEST-IF ( n -- )
+  0 > IF
+    ." Positive"
+  ELSE
+    ." Non-positive"
+  THEN
+  CR ;
+
+5 TEST-IF
+-3 TEST-IF
+
+( The conditional consumed its argument )
+1 2 3 .S CR
+DROP DROP DROP
@@ -2,5 +2,5 @@
 ( String with length )
 
 S" Hello World" TYPE CR
-S" Test" .S CR
+S" Test" NIP . CR
 S" " TYPE CR
\ No newline at end of file
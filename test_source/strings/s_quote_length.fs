@@ -0,0 +1,8 @@
+( Test S" leaves an address and a length )
+( Dropping the address leaves the length )
+
+S" Hello World" TYPE CR
+S" Test" SWAP DROP . CR
+S" " SWAP DROP . CR
+S" abc" S" abc" COMPARE . CR
+S" hello world" 6 /STRING TYPE CR
@@ -0,0 +1,24 @@
+\ CREATE, ALLOT and the comma words share one byte-addressed data space
+CREATE TABLE 10 , 20 , 30 ,
+TABLE @ . TABLE CELL+ @ . TABLE 2 CELLS + @ .
+CR
+
+CREATE BYTES 65 C, 66 C, 67 C,
+BYTES C@ EMIT BYTES 1+ C@ EMIT BYTES 2 + C@ EMIT
+CR
+
+\ CREATE aligns, so the next field starts on a cell boundary
+CREATE BUFFER 4 CELLS ALLOT
+BUFFER 1 CELLS MOD .
+7 BUFFER 3 CELLS + !
+BUFFER 3 CELLS + @ .
+CR
+
+VARIABLE COUNTER
+5 COUNTER ! 3 COUNTER +! COUNTER @ .
+300 BYTES C! BYTES C@ .
+CR
+
+HERE 3 ALLOT -3 ALLOT HERE = .
+HERE ALIGN HERE SWAP - 1 CELLS < .
+CR
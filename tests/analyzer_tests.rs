@@ -1,4 +1,5 @@
 use roth::analyzer::SemanticAnalyzer;
+use roth::diagnostics::WarningKind;
 use roth::lexer::Lexer;
 use roth::parser::Parser;
 
@@ -86,6 +87,69 @@ fn test_analyze_definition_order_independence() {
     assert!(analyze_input(input).is_err());
 }
 
+#[test]
+fn test_analyze_forward_references() {
+    let input = ": EVEN? DUP IF 1- ODD? ELSE DROP TRUE THEN ;
+                 : ODD? DUP IF 1- EVEN? ELSE 0= THEN ;
+                 ODD? LATER
+                 : LATER ;";
+    let mut lexer = Lexer::new(input.to_string());
+    let ast = Parser::new(lexer.tokenize().unwrap()).parse().unwrap();
+    let mut analyzer = SemanticAnalyzer::new().with_forward_references();
+    let errors = analyzer.analyze(&ast).unwrap_err();
+
+    // Only top-level code has to come after the words it uses
+    let errors: Vec<_> = errors
+        .iter()
+        .map(|err| (err.message.as_str(), err.help.as_deref()))
+        .collect();
+    assert_eq!(
+        errors,
+        [(
+            "LATER is used before it is defined",
+            Some("move the definition of LATER earlier, or declare it first with DEFER LATER")
+        )]
+    );
+    // Calls made before the definition still count as uses
+    let warnings: Vec<_> = analyzer
+        .warnings()
+        .iter()
+        .map(|warning| warning.diagnostic.message.as_str())
+        .collect();
+    assert_eq!(warnings, ["LATER is never used"]);
+}
+
+#[test]
+fn test_analyze_use_before_variable_or_constant() {
+    let input = ": SHOW X @ . LIMIT . ;
+                 VARIABLE X 10 CONSTANT LIMIT";
+    let mut lexer = Lexer::new(input.to_string());
+    let ast = Parser::new(lexer.tokenize().unwrap()).parse().unwrap();
+    let errors = SemanticAnalyzer::new()
+        .with_forward_references()
+        .analyze(&ast)
+        .unwrap_err();
+
+    // Only colon definitions can be called before they are defined
+    let errors: Vec<_> = errors
+        .iter()
+        .map(|err| (err.message.as_str(), err.help.as_deref()))
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            (
+                "X is used before it is defined",
+                Some("move the definition of X earlier")
+            ),
+            (
+                "LIMIT is used before it is defined",
+                Some("move the definition of LIMIT earlier")
+            ),
+        ]
+    );
+}
+
 #[test]
 fn test_analyze_error_undefined_word() {
     assert!(analyze_input("UNDEFINED_WORD").is_err());
@@ -93,6 +157,29 @@ fn test_analyze_error_undefined_word() {
     assert!(analyze_input(": TEST UNDEFINED_HELPER ;").is_err());
 }
 
+#[test]
+fn test_analyze_reports_every_undefined_word() {
+    let mut lexer = Lexer::new(": TEST FIRST ;\nSECOND 1 +\n: + THIRD ;".to_string());
+    let tokens = lexer.tokenize().unwrap();
+    let ast = Parser::new(tokens).parse().unwrap();
+    let errors = SemanticAnalyzer::new().analyze(&ast).unwrap_err();
+
+    let messages: Vec<_> = errors.iter().map(|err| err.message.as_str()).collect();
+    assert_eq!(messages.len(), 4);
+    assert!(messages[0].contains("FIRST"));
+    assert!(messages[1].contains("SECOND"));
+    assert!(messages[2].contains("+"));
+    assert!(messages[3].contains("THIRD"));
+}
+
+#[test]
+fn test_analyze_constants_and_values() {
+    assert!(analyze_input("10 CONSTANT TEN 1 2 2CONSTANT PAIR TEN PAIR").is_ok());
+    assert!(analyze_input("0 VALUE X : SET ( n -- ) TO X ; 5 SET X").is_ok());
+    assert!(analyze_input("VARIABLE Y 5 TO Y").is_err());
+    assert!(analyze_input("5 TO Z").is_err());
+}
+
 #[test]
 fn test_analyze_error_redefine_builtin() {
     assert!(analyze_input(": + 42 ;").is_err());
@@ -178,3 +265,68 @@ fn test_analyze_empty_program() {
     assert!(analyze_input("   ").is_ok());
     assert!(analyze_input("( just a comment )").is_ok());
 }
+
+#[test]
+fn test_analyze_suggests_similar_word() {
+    let mut lexer = Lexer::new(": SQUARE DUP * ;\n5 SQAURE DUPP".to_string());
+    let ast = Parser::new(lexer.tokenize().unwrap()).parse().unwrap();
+    let errors = SemanticAnalyzer::new().analyze(&ast).unwrap_err();
+
+    let help: Vec<_> = errors.iter().map(|err| err.help.as_deref()).collect();
+    assert_eq!(
+        help,
+        [Some("did you mean SQUARE?"), Some("did you mean DUP?")]
+    );
+}
+
+#[test]
+fn test_analyze_warnings() {
+    let input = ": HELPER 1 ;
+                 : EARLY 2 EXIT 3 ;
+                 : EARLY HELPER IF EXIT THEN 4 ;
+                 : SOLO DUP IF 1- SOLO THEN ;";
+    let mut lexer = Lexer::new(input.to_string());
+    let ast = Parser::new(lexer.tokenize().unwrap()).parse().unwrap();
+    let mut analyzer = SemanticAnalyzer::new();
+    analyzer.analyze(&ast).unwrap();
+
+    let warnings: Vec<_> = analyzer
+        .warnings()
+        .iter()
+        .map(|warning| (warning.kind, warning.diagnostic.message.as_str()))
+        .collect();
+    assert_eq!(
+        warnings,
+        [
+            (WarningKind::Unreachable, "Unreachable code after EXIT"),
+            (
+                WarningKind::Shadow,
+                "EARLY is redefined, replacing the earlier definition"
+            ),
+            (WarningKind::Unused, "EARLY is never used"),
+            (WarningKind::Unused, "SOLO is never used"),
+        ]
+    );
+}
+
+#[test]
+fn test_analyze_execution_tokens() {
+    assert!(analyze_input("DEFER D ' DUP IS D ACTION-OF D EXECUTE ['] D CATCH").is_ok());
+
+    let mut lexer = Lexer::new(": HELPER 1 ; ' IF ' NOPE VARIABLE V ' HELPER IS V".to_string());
+    let ast = Parser::new(lexer.tokenize().unwrap()).parse().unwrap();
+    let mut analyzer = SemanticAnalyzer::new();
+    let errors = analyzer.analyze(&ast).unwrap_err();
+
+    let messages: Vec<_> = errors.iter().map(|err| err.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        [
+            "Cannot take the execution token of IF",
+            "Undefined word: NOPE",
+            "IS needs a DEFERred word, but V is not one",
+        ]
+    );
+    // Taking the execution token of a word uses it
+    assert!(analyzer.warnings().is_empty());
+}
@@ -60,7 +60,7 @@ fn test_codegen_stack_operations() {
 fn test_codegen_simple_definition() {
     let result = debug_compile(": DOUBLE 2 * ;");
 
-    assert!(result.contains("fn double(&mut self)"));
+    assert!(result.contains("fn word_double(&mut self)"));
     assert!(result.contains("pub struct OptimizedForth"));
     assert!(result.contains("impl OptimizedForth"));
 }
@@ -70,7 +70,7 @@ fn test_codegen_definition_usage() {
     let result = debug_compile(": SQUARE DUP * ; 5 SQUARE");
 
     // Should contain the definition
-    assert!(result.contains("fn square(&mut self)"));
+    assert!(result.contains("fn word_square(&mut self)"));
 
     // Should contain the usage in main
     assert!(result.contains("self.stack.push(5)"));
@@ -82,8 +82,8 @@ fn test_codegen_definition_usage() {
 fn test_codegen_multiple_definitions() {
     let result = debug_compile(": ADD2 2 + ; : MUL3 3 * ; 10 ADD2 MUL3");
 
-    assert!(result.contains("fn add2(&mut self)"));
-    assert!(result.contains("fn mul3(&mut self)"));
+    assert!(result.contains("fn word_add2(&mut self)"));
+    assert!(result.contains("fn word_mul3(&mut self)"));
     // The optimizer may fold constants or inline functions
     assert!(result.contains("self.stack.push") || result.contains("stack.push"));
 }
@@ -116,8 +116,8 @@ fn test_codegen_complex_program() {
     "#;
     let result = debug_compile(input);
 
-    assert!(result.contains("fn square(&mut self)"));
-    assert!(result.contains("fn cube(&mut self)"));
+    assert!(result.contains("fn word_square(&mut self)"));
+    assert!(result.contains("fn word_cube(&mut self)"));
     assert!(result.contains("self.stack.push(5)"));
     // Functions may be inlined by optimizer
     assert!(result.contains("cube") || result.contains("square"));
@@ -132,8 +132,8 @@ fn test_codegen_nested_function_calls() {
     "#;
     let result = debug_compile(input);
 
-    assert!(result.contains("fn helper(&mut self)"));
-    assert!(result.contains("fn main(&mut self)") || result.contains("fn main_word(&mut self)"));
+    assert!(result.contains("fn word_helper(&mut self)"));
+    assert!(result.contains("fn word_main(&mut self)"));
     // The optimizer may fold the entire computation: 10 * 2 + 1 = 21
     assert!(result.contains("self.stack.push(21)") || result.contains("self.stack.push(10)"));
     // Functions may be inlined by optimizer
@@ -1,6 +1,7 @@
 use std::fs;
+use std::io::Write;
 use std::path::Path;
-use std::process::Command;
+use std::process::{Command, Stdio};
 
 fn build_output_path(output_file: &str) -> String {
     let filename = Path::new(output_file)
@@ -75,7 +76,7 @@ fn test_compile_definition() {
     assert!(result.is_ok());
 
     let generated_code = fs::read_to_string(&build_output_file).unwrap();
-    assert!(generated_code.contains("fn square"));
+    assert!(generated_code.contains("fn word_square"));
     assert!(generated_code.contains("self.stack.push(5)"));
     // Function may be inlined by optimizer
     assert!(generated_code.contains("square"));
@@ -199,9 +200,9 @@ fn test_compile_multiple_definitions() {
     assert!(result.is_ok());
 
     let generated_code = fs::read_to_string(&build_output_file).unwrap();
-    assert!(generated_code.contains("fn double"));
-    assert!(generated_code.contains("fn triple"));
-    assert!(generated_code.contains("fn quadruple"));
+    assert!(generated_code.contains("fn word_double"));
+    assert!(generated_code.contains("fn word_triple"));
+    assert!(generated_code.contains("fn word_quadruple"));
     // The optimizer may fold the entire computation: 10 * 2 * 3 * 4 = 240
     assert!(generated_code.contains("self.stack.push") || generated_code.contains("stack.push"));
 
@@ -226,6 +227,31 @@ fn test_compile_error_undefined_word() {
     }
 }
 
+#[test]
+fn test_compile_error_in_included_file() {
+    let test_dir = Path::new("test_include_positions");
+    fs::create_dir_all(test_dir.join("lib")).unwrap();
+    let test_file = test_dir.join("app.rt");
+    create_test_file(
+        test_file.to_str().unwrap(),
+        "\\ Uses a helper\n\nINCLUDE lib/helpers.rt\n5 TWICE .\n",
+    )
+    .unwrap();
+    create_test_file(
+        test_dir.join("lib/helpers.rt").to_str().unwrap(),
+        ": TWICE 2 * ;\n: BROKEN UNKNOWN ;\n",
+    )
+    .unwrap();
+
+    let result = compile_forth_file(test_file.to_str().unwrap(), "test_include_positions.rs");
+    let _ = fs::remove_dir_all(test_dir);
+
+    // The error names the included file and line, and where it was included
+    let message = result.unwrap_err().to_string();
+    assert!(message.contains("test_include_positions/lib/helpers.rt:2:10"));
+    assert!(message.contains("included from test_include_positions/app.rt:3"));
+}
+
 #[test]
 fn test_compile_error_redefine_builtin() {
     let test_file = "test_redefine.rt";
@@ -331,3 +357,775 @@ fn test_different_backends() {
 
     cleanup_test_file(test_file);
 }
+
+/// Backends that compile a program ahead of time and run the executable
+const COMPILED_BACKENDS: [&str; 2] = ["rust-ir", "c-ir"];
+
+/// Every way to run a program: the compiled backends, and "repl", which feeds
+/// it to the REPL to run on the roth runtime
+const RUN_BACKENDS: [&str; 3] = ["rust-ir", "c-ir", "repl"];
+
+/// The REPL's prompt, which comes before the output of each input line
+const REPL_PROMPT: &str = "roth> ";
+
+/// A test file that is removed when dropped, even if an assertion failed
+struct TestFile(String);
+
+impl TestFile {
+    fn create(filename: String, content: &str) -> Self {
+        create_test_file(&filename, content).unwrap();
+        TestFile(filename)
+    }
+}
+
+impl Drop for TestFile {
+    fn drop(&mut self) {
+        cleanup_test_file(&self.0);
+    }
+}
+
+fn run_forth_file(
+    input_file: &str,
+    backend: &str,
+    extra_args: &[&str],
+) -> Result<String, Box<dyn std::error::Error>> {
+    if backend == "repl" {
+        return run_forth_file_in_repl(input_file, extra_args);
+    }
+
+    let output = Command::new("cargo")
+        .args(["run", "--", input_file, "--backend", backend, "--run"])
+        .args(extra_args)
+        .output()?;
+
+    if !output.status.success() {
+        return Err(format!("Run failed: {}", String::from_utf8_lossy(&output.stderr)).into());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Run a file as a single line of REPL input, returning what it printed
+/// between the prompt and " ok"
+fn run_forth_file_in_repl(
+    input_file: &str,
+    extra_args: &[&str],
+) -> Result<String, Box<dyn std::error::Error>> {
+    let input = fs::read_to_string(input_file)?.replace('\n', " ");
+    let mut child = Command::new("cargo")
+        .args(["run", "--", "--interactive", "--no-color"])
+        .args(extra_args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    child.stdin.take().unwrap().write_all(input.as_bytes())?;
+    let output = child.wait_with_output()?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() || stderr.contains("Error:") {
+        return Err(format!("Run failed: {}", stderr).into());
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let session = stdout.split_once(REPL_PROMPT).map_or("", |(_, rest)| rest);
+    let printed = session.trim_end_matches(&format!("\n{}\n", REPL_PROMPT));
+    Ok(printed.strip_suffix(" ok").unwrap_or(printed).to_string())
+}
+
+/// Write `program` to a file named after `name` and `backend` and run it,
+/// removing the file afterwards
+fn run_forth_program(
+    name: &str,
+    program: &str,
+    backend: &str,
+    extra_args: &[&str],
+) -> Result<String, Box<dyn std::error::Error>> {
+    let test_file = TestFile::create(
+        format!("{}_{}.rt", name, backend.replace("-", "_")),
+        program,
+    );
+    run_forth_file(&test_file.0, backend, extra_args)
+}
+
+#[test]
+fn test_run_indefinite_loops_all_backends() {
+    let program = r#"
+        : COUNTDOWN BEGIN DUP . 1 - DUP 0 = UNTIL DROP ;
+        : ODDS 0 BEGIN DUP 10 < WHILE DUP 2 MOD 1 = IF DUP . THEN 1 + REPEAT DROP ;
+        : ROWS 1 BEGIN DUP 0 DO I . LOOP 1 + DUP 4 = UNTIL DROP ;
+        3 COUNTDOWN ODDS ROWS
+    "#;
+
+    for backend in RUN_BACKENDS {
+        let stdout = run_forth_program("test_loops", program, backend, &[]).unwrap();
+        let words: Vec<&str> = stdout.split_whitespace().collect();
+        assert_eq!(
+            words,
+            [
+                "3", "2", "1", "1", "3", "5", "7", "9", "0", "0", "1", "0", "1", "2"
+            ],
+            "backend {}",
+            backend
+        );
+    }
+}
+
+#[test]
+fn test_run_locals_all_backends() {
+    let program = r#"
+        : QUAD {: a b c x | r -- r :} a x * x * b x * + c + TO r r ;
+        : FACT {: n :} n 1 > IF n 1 - RECURSE n * ELSE 1 THEN ;
+        : SUM-TO {: n | acc :} n 1 + 1 DO acc I + TO acc LOOP acc ;
+        1 2 3 4 QUAD . 10 FACT . 100 SUM-TO .
+    "#;
+
+    for backend in RUN_BACKENDS {
+        let stdout = run_forth_program("test_locals", program, backend, &[]).unwrap();
+        let words: Vec<&str> = stdout.split_whitespace().collect();
+        assert_eq!(words, ["27", "3628800", "5050"], "backend {}", backend);
+    }
+}
+
+#[test]
+fn test_run_floats_all_backends() {
+    let program = r#"
+        FVARIABLE X
+        : AVERAGE ( r1 r2 -- r ) F+ 2e0 F/ ;
+        1.5e0 2e0 F+ F. 7 S>F 2e0 F/ F. 3e0 X F! X F@ F>S .
+        1e0 2e0 F< . 0e0 F0= . 1e0 3e0 AVERAGE F. 1e0 3e0 F/ F. 1e-3 F. -25e-1 F.
+    "#;
+
+    for backend in RUN_BACKENDS {
+        let stdout = run_forth_program("test_floats", program, backend, &[]).unwrap();
+        let words: Vec<&str> = stdout.split_whitespace().collect();
+        assert_eq!(
+            words,
+            [
+                "3.5",
+                "3.5",
+                "3",
+                "-1",
+                "-1",
+                "2.",
+                "0.3333333333333333",
+                "0.001",
+                "-2.5"
+            ],
+            "backend {}",
+            backend
+        );
+    }
+}
+
+#[test]
+fn test_run_double_cell_arithmetic_on_all_backends() {
+    // Products and dividends here need more than one cell at either width
+    let program = r#"
+        : SCALE ( n1 n2 n3 -- n4 ) */ ;
+        1. 2. D+ D. 5. 7. D- D. 3. DNEGATE D. -1 2 UM* D. -7 2 M* D.
+        7. -2 SM/REM . . 7. -2 FM/MOD . . -7. 2 FM/MOD . . 10. 3 UM/MOD . .
+        -7 3 2 */MOD . . 100 3 7 SCALE .
+        2147483647 2 UM* 2 UM/MOD . . 100000 100000 M* D. 100000 100000 100000 */ .
+    "#;
+    let common = [
+        "3",
+        "-2",
+        "-3",
+        "-14",
+        "-3",
+        "1",
+        "-4",
+        "-1",
+        "-4",
+        "1",
+        "3",
+        "1",
+        "-10",
+        "-1",
+        "42",
+        "2147483647",
+        "0",
+        "10000000000",
+        "100000",
+    ];
+    let expected = [("32", "8589934590"), ("64", "36893488147419103230")];
+
+    for (cell_bits, product) in expected {
+        let mut expected = common.to_vec();
+        expected.insert(3, product);
+        for backend in RUN_BACKENDS {
+            let name = format!("test_double_{}", cell_bits);
+            let stdout =
+                run_forth_program(&name, program, backend, &["--cell-bits", cell_bits]).unwrap();
+            let words: Vec<&str> = stdout.split_whitespace().collect();
+            assert_eq!(
+                words, expected,
+                "backend {} with {}-bit cells",
+                backend, cell_bits
+            );
+        }
+    }
+}
+
+#[test]
+fn test_run_stack_and_arithmetic_builtins_on_all_backends() {
+    // Words keep the results from being folded away at compile time
+    let program = r#"
+        : SMALLER ( a b -- c ) MIN ; : LARGER ( a b -- c ) MAX ;
+        3 7 SMALLER . 3 7 LARGER . -5 ABS . 1 2 NIP . 1 2 TUCK . . .
+        1 2 2DUP . . . . 3 4 2DROP 1 2 3 4 2OVER . . . . . .
+        1 2 3 4 2SWAP . . . . 10 20 30 2 PICK . . . . 1 2 3 2 ROLL . . . CR
+        5 2+ . 5 2- . 6 3 XOR . 0 INVERT . 17 5 /MOD . . 0 0= . 5 0<> . TRUE . FALSE .
+        5 1 10 WITHIN . 10 1 10 WITHIN . 15 10 1 WITHIN . 0 ?DUP . 4 ?DUP . . CR
+        ." [" 3 SPACES ." ]" -2 SPACES
+        : BRANCHES ( n -- m ) 10 >R IF R> 1+ ELSE R@ 1- R> DROP THEN ;
+        1 BRANCHES . 0 BRANCHES .
+        : UNDERFLOWS R> ; ' UNDERFLOWS CATCH .
+    "#;
+
+    for backend in RUN_BACKENDS {
+        let stdout = run_forth_program("test_builtins", program, backend, &[]).unwrap();
+        let lines: Vec<&str> = stdout.lines().map(str::trim_end).collect();
+        assert_eq!(
+            lines,
+            [
+                "3 7 5 2 2 1 2 2 1 2 1 2 1 4 3 2 1 2 1 4 3 10 30 20 10 1 3 2",
+                "7 3 5 -1 3 2 -1 -1 -1 0 -1 0 -1 0 4 4",
+                "[   ]11 9 -6",
+            ],
+            "backend {}",
+            backend
+        );
+    }
+}
+
+#[test]
+fn test_run_strings_all_backends() {
+    let program = r#"
+        CREATE BUF 8 ALLOT
+        : GREET ( c-addr u -- ) ." Hi " TYPE ;
+        S" you" GREET C" counted" COUNT TYPE
+        S" abc" S" abd" COMPARE . S" abc" S" abc" COMPARE . S" abcd" S" abc" COMPARE .
+        S" hello world" S" wor" SEARCH . TYPE S" hello" S" xyz" SEARCH . . DROP
+        S" hello world" 6 /STRING TYPE S" a   " -TRAILING TYPE ." |"
+        S" copy" BUF SWAP CMOVE BUF BUF 1+ 3 MOVE BUF 4 TYPE
+    "#;
+
+    for backend in RUN_BACKENDS {
+        let stdout = run_forth_program("test_strings", program, backend, &[]).unwrap();
+        assert_eq!(
+            stdout.trim_end(),
+            "Hi youcounted-1 0 1 -1 world0 5 worlda|ccop",
+            "backend {}",
+            backend
+        );
+    }
+}
+
+#[test]
+fn test_run_number_formatting_on_all_backends() {
+    let program = r#"
+        : MONEY ( n -- ) DUP DUP 0 < IF NEGATE THEN 0 <# # # 46 HOLD #S ROT SIGN #> TYPE ;
+        : SHOW-HEX ( n -- ) BASE @ SWAP HEX . BASE ! ;
+        255 . HEX FF . 10 . DECIMAL 255 . 255 SHOW-HEX 255 .
+        2 BASE ! 101 . DECIMAL -1 U. CR
+        42 6 .R ." |" -42 6 .R ." |" 7 1 .R ." |" 5 3 U.R ." |" CR
+        12345 MONEY SPACE -705 MONEY SPACE 0 MONEY CR
+        -1. D. HEX 10 -2 .S DECIMAL 2DROP -1 1 <# #S #> TYPE
+    "#;
+    let expected = [
+        ("32", "4294967295", "8589934591"),
+        ("64", "18446744073709551615", "36893488147419103231"),
+    ];
+
+    for (cell_bits, all_ones, double) in expected {
+        for backend in RUN_BACKENDS {
+            let name = format!("test_numbers_{}", cell_bits);
+            let stdout =
+                run_forth_program(&name, program, backend, &["--cell-bits", cell_bits]).unwrap();
+            let lines: Vec<&str> = stdout.lines().map(str::trim_end).collect();
+            assert_eq!(
+                lines,
+                [
+                    format!("255 FF 10 255 FF 255 101 {}", all_ones),
+                    "    42|   -42|7|  5|".to_string(),
+                    "123.45 -7.05 0.00".to_string(),
+                    format!("-1 <2> 10 -2 {}", double),
+                ],
+                "backend {} with {}-bit cells",
+                backend,
+                cell_bits
+            );
+        }
+    }
+}
+
+#[test]
+fn test_run_cell_overflow_wraps_on_all_backends() {
+    // Folded at compile time at the top level, computed at run time in words
+    let program = r#"
+        2147483647 1 + . 2147483647 DUP * . -2147483648 -1 / . -2147483648 NEGATE .
+        : ADD + ; : MUL * ; : DIV / ; : NEG NEGATE ;
+        2147483647 1 ADD . 65536 65536 MUL . -2147483648 -1 DIV . -2147483648 NEG .
+        $FFFFFFFF .
+    "#;
+    let expected = [
+        (
+            "32",
+            [
+                "-2147483648",
+                "1",
+                "-2147483648",
+                "-2147483648",
+                "-2147483648",
+                "0",
+                "-2147483648",
+                "-2147483648",
+                "-1",
+            ],
+        ),
+        (
+            "64",
+            [
+                "2147483648",
+                "4611686014132420609",
+                "2147483648",
+                "2147483648",
+                "2147483648",
+                "4294967296",
+                "2147483648",
+                "2147483648",
+                "4294967295",
+            ],
+        ),
+    ];
+
+    for (cell_bits, expected) in expected {
+        for backend in RUN_BACKENDS {
+            let name = format!("test_overflow_{}", cell_bits);
+            let stdout =
+                run_forth_program(&name, program, backend, &["--cell-bits", cell_bits]).unwrap();
+            let words: Vec<&str> = stdout.split_whitespace().collect();
+            assert_eq!(
+                words, expected,
+                "backend {} with {}-bit cells",
+                backend, cell_bits
+            );
+        }
+    }
+}
+
+#[test]
+fn test_run_64_bit_extremes_on_all_backends() {
+    let program = r#"
+        : ADD + ; : DIV / ;
+        9223372036854775807 1 ADD . -9223372036854775808 -1 DIV . $FFFFFFFFFFFFFFFF .
+    "#;
+
+    for backend in RUN_BACKENDS {
+        let stdout = run_forth_program("test_overflow64", program, backend, &[]).unwrap();
+        let words: Vec<&str> = stdout.split_whitespace().collect();
+        assert_eq!(
+            words,
+            ["-9223372036854775808", "-9223372036854775808", "-1"],
+            "backend {}",
+            backend
+        );
+    }
+}
+
+#[test]
+fn test_run_data_space_on_all_backends() {
+    // Words keep the accesses from being folded away at compile time
+    let program = r#"
+        CREATE TABLE 10 , 20 , 30 ,
+        : NTH ( n -- x ) CELLS TABLE + @ ;
+        0 NTH . 2 NTH . TABLE CELL+ @ .
+        CREATE BYTES 65 C, 300 C, 7 C,
+        : BYTE ( n -- c ) BYTES + C@ ;
+        0 BYTE . 1 BYTE . 2 BYTE .
+        CREATE BUFFER 3 CELLS ALLOT
+        BUFFER 1 CELLS MOD . BUFFER 2 CELLS + @ .
+        VARIABLE COUNTER
+        : BUMP ( -- ) 1 COUNTER +! ;
+        BUMP BUMP BUMP COUNTER @ .
+        HERE 5 ALLOT -5 ALLOT HERE - .
+    "#;
+
+    for cell_bits in ["32", "64"] {
+        for backend in RUN_BACKENDS {
+            let name = format!("test_data_space_{}", cell_bits);
+            let stdout =
+                run_forth_program(&name, program, backend, &["--cell-bits", cell_bits]).unwrap();
+            let words: Vec<&str> = stdout.split_whitespace().collect();
+            assert_eq!(
+                words,
+                ["10", "30", "20", "65", "44", "7", "0", "0", "3", "0"],
+                "backend {} with {}-bit cells",
+                backend,
+                cell_bits
+            );
+        }
+    }
+}
+
+#[test]
+fn test_run_constants_and_values_on_all_backends() {
+    let program = r#"
+        10 CONSTANT TEN
+        -1. 2CONSTANT DMINUS
+        : SCALED ( n -- n ) TEN * ;
+        7 SCALED . DMINUS . .
+        TEN 3 * 1 + CONSTANT AREA 1 TEN 2CONSTANT SPAN AREA . SPAN . .
+        5 VALUE TALLY
+        : BUMP ( -- ) TALLY 1+ TO TALLY ;
+        BUMP BUMP TALLY .
+        2VARIABLE PAIR
+        3 4 PAIR 2! PAIR 2@ . . PAIR @ .
+        : SQ ( n -- n ) DUP * ; ' SQ CONSTANT XT
+        CREATE T 1 , T CONSTANT TA
+        TEN 1 CELLS * CONSTANT X
+        5 XT EXECUTE . TA @ . X 1 CELLS / .
+        2 CONSTANT FOO : FOO ( -- n ) 1 ; FOO .
+    "#;
+
+    for backend in RUN_BACKENDS {
+        let stdout = run_forth_program("test_constants", program, backend, &[]).unwrap();
+        let words: Vec<&str> = stdout.split_whitespace().collect();
+        assert_eq!(
+            words,
+            ["70", "-1", "-1", "31", "10", "1", "7", "4", "3", "4", "25", "1", "10", "1"],
+            "backend {}",
+            backend
+        );
+    }
+}
+
+#[test]
+fn test_run_create_does_on_all_backends() {
+    let program = r#"
+        : ARRAY ( n -- ) CREATE CELLS ALLOT DOES> ( i -- addr ) SWAP CELLS + ;
+        5 ARRAY DATA
+        42 3 DATA ! 7 0 DATA !
+        3 DATA @ . 0 DATA @ .
+        : COLOR ( n -- ) CREATE , DOES> @ ;
+        0 COLOR RED 1 COLOR GREEN 2 COLOR BLUE
+        : SHOW ( -- ) BLUE GREEN RED . . . ;
+        SHOW
+        : TABLE ( -- ) CREATE ;
+        TABLE T 10 , 20 ,
+        T CELL+ @ .
+    "#;
+
+    for backend in RUN_BACKENDS {
+        let stdout = run_forth_program("test_does", program, backend, &[]).unwrap();
+        let words: Vec<&str> = stdout.split_whitespace().collect();
+        assert_eq!(
+            words,
+            ["42", "7", "0", "1", "2", "20"],
+            "backend {}",
+            backend
+        );
+    }
+}
+
+#[test]
+fn test_run_immediate_words_on_all_backends() {
+    let program = r#"
+        : TIMES ( n -- ) 0 POSTPONE LITERAL POSTPONE ?DO ; IMMEDIATE
+        : SUM ( n -- sum ) 0 SWAP TIMES I + LOOP ;
+        4 SUM .
+        : CELLS-PER-KB [ 1024 1 CELLS / ] LITERAL ;
+        CELLS-PER-KB 0 > .
+        : UNLESS 0 POSTPONE LITERAL POSTPONE = POSTPONE IF ; IMMEDIATE
+        : SIGN-WORD 0 < UNLESS 1 ELSE -1 THEN ;
+        -5 SIGN-WORD . 5 SIGN-WORD .
+    "#;
+
+    for backend in RUN_BACKENDS {
+        let stdout = run_forth_program("test_immediate", program, backend, &[]).unwrap();
+        let words: Vec<&str> = stdout.split_whitespace().collect();
+        assert_eq!(words, ["6", "-1", "-1", "1"], "backend {}", backend);
+    }
+}
+
+#[test]
+fn test_run_standard_library_on_compiled_backends() {
+    // The bundled library must not redefine builtins such as SIGN
+    let program = r#"
+        INCLUDE std/std.rt
+        -5 SIGNUM . 0 SIGNUM . 8 SIGNUM . 6 SQUARE . 4 EVEN? . 5 ODD? .
+        15 5 10 CLAMP . 10 20 30 NIP . . 3 COUNTDOWN
+        -42 DUP ABS 0 <# #S ROT SIGN #> TYPE CR
+    "#;
+
+    // The REPL does not read files with INCLUDE
+    for backend in COMPILED_BACKENDS {
+        let stdout = run_forth_program("test_std", program, backend, &[]).unwrap();
+        let lines: Vec<&str> = stdout.lines().map(str::trim_end).collect();
+        assert_eq!(
+            lines,
+            [
+                "Roth Forth Standard Library v1.0 loaded",
+                "-1 0 1 36 -1 -1 10 30 10 3 2 1 -42",
+            ],
+            "backend {}",
+            backend
+        );
+    }
+}
+
+#[test]
+fn test_compile_standard_library_without_warnings() {
+    // Warnings in the bundled library are not the user's to fix
+    let test_file = "test_std_warnings.rt";
+    let output_file = "test_std_warnings.rs";
+    create_test_file(test_file, "INCLUDE std/std.rt\n1 .\n").unwrap();
+
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            test_file,
+            "--output",
+            output_file,
+            "-Wall",
+            "-Werror",
+        ])
+        .output()
+        .unwrap();
+    cleanup_test_file(test_file);
+    cleanup_test_file(&build_output_path(output_file));
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    assert!(!stderr.contains("[-W"), "{}", stderr);
+}
+
+#[test]
+fn test_include_ignores_commented_out_includes() {
+    let test_file = "test_line_comments.rt";
+    let included_file = "test_line_comments_lib.rt";
+    let program = format!(
+        "\\ INCLUDE missing.rt is only mentioned here\n\
+         ( INCLUDE missing.rt ) INCLUDE {}\n\
+         \\G TWICE doubles the top of stack\n\
+         5 TWICE . CR\n",
+        included_file
+    );
+    create_test_file(included_file, ": TWICE 2 * ; \\ trailing comment").unwrap();
+    create_test_file(test_file, &program).unwrap();
+
+    let stdout = run_forth_file(test_file, "rust-ir", &[]).unwrap();
+    assert_eq!(stdout.trim(), "10");
+
+    cleanup_test_file(test_file);
+    cleanup_test_file(included_file);
+}
+
+#[test]
+fn test_require_reads_search_path_file_once() {
+    let test_dir = Path::new("test_require");
+    fs::create_dir_all(test_dir.join("lib")).unwrap();
+    let test_file = test_dir.join("app.rt");
+    create_test_file(
+        test_file.to_str().unwrap(),
+        "REQUIRE twice.rt\nREQUIRE twice.rt\nS\" twice.rt\" REQUIRED\n5 TWICE . CR\n",
+    )
+    .unwrap();
+    create_test_file(
+        test_dir.join("lib/twice.rt").to_str().unwrap(),
+        ": TWICE 2 * ;\n",
+    )
+    .unwrap();
+    let loop_file = test_dir.join("loop.rt");
+    create_test_file(loop_file.to_str().unwrap(), "INCLUDE loop.rt\n").unwrap();
+
+    let found = run_forth_file(
+        test_file.to_str().unwrap(),
+        "rust-ir",
+        &["-I", "test_require/lib"],
+    );
+    let circular = run_forth_file(loop_file.to_str().unwrap(), "rust-ir", &[]);
+    let _ = fs::remove_dir_all(test_dir);
+
+    assert_eq!(found.unwrap().trim(), "10");
+    let message = circular.unwrap_err().to_string();
+    assert!(message.contains("Circular include: 'loop.rt' is already being read"));
+}
+
+#[test]
+fn test_bundled_library_is_found_from_the_installed_executable() {
+    let prefix = Path::new("test_install");
+    fs::create_dir_all(prefix.join("bin")).unwrap();
+    fs::create_dir_all(prefix.join("share/roth/std")).unwrap();
+    let roth = prefix.join("bin/roth");
+    fs::copy(env!("CARGO_BIN_EXE_roth"), &roth).unwrap();
+    create_test_file(
+        prefix.join("share/roth/std/installed.rt").to_str().unwrap(),
+        ": INSTALLED 7 ;\n",
+    )
+    .unwrap();
+    let test_file = prefix.join("app.rt");
+    create_test_file(
+        test_file.to_str().unwrap(),
+        "INCLUDE std/installed.rt\nINSTALLED . CR\n",
+    )
+    .unwrap();
+
+    let output = Command::new(&roth)
+        .arg(&test_file)
+        .args(["--backend", "c-ir", "--run"])
+        .output();
+    let _ = fs::remove_dir_all(prefix);
+
+    let output = output.unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "7");
+}
+
+#[test]
+fn test_conditional_compilation_with_defines_and_backend() {
+    let test_file = "test_conditional.rt";
+    create_test_file(
+        test_file,
+        r#"
+        LEVEL 2 > [IF]
+          : REPORT 100 + . ;
+        [ELSE]
+          : REPORT . ;
+        [THEN]
+        [DEFINED] BACKEND-C-IR [IF] 1 [ELSE] 2 [THEN] REPORT
+        "#,
+    )
+    .unwrap();
+
+    let c_ir = run_forth_file(test_file, "c-ir", &["-D", "LEVEL=1"]);
+    let verbose = run_forth_file(test_file, "rust-ir", &["-D", "LEVEL=3"]);
+    cleanup_test_file(test_file);
+
+    assert_eq!(c_ir.unwrap().trim(), "1");
+    assert_eq!(verbose.unwrap().trim(), "102");
+}
+
+#[test]
+fn test_define_cannot_name_builtin() {
+    let test_file = "test_define_builtin.rt";
+    create_test_file(test_file, "1 DUP . .").unwrap();
+
+    let result = run_forth_file(test_file, "rust-ir", &["-D", "dup=7"]);
+    cleanup_test_file(test_file);
+
+    let err = result.unwrap_err().to_string();
+    assert!(
+        err.contains("Invalid define: -D dup=7. Cannot redefine builtin word: DUP"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn test_run_catch_throw_on_all_backends() {
+    let program = r#"
+        : DIVIDE / ;
+        : BOOM 42 THROW ;
+        : FINE 7 ;
+        : INNER 99 THROW ;
+        : OUTER ['] INNER CATCH . 5 THROW ;
+        : CHECK ABORT" bad value" ;
+        : GIVE-UP ABORT ;
+        ['] BOOM CATCH . ['] FINE CATCH . . ['] OUTER CATCH .
+        0 ['] CHECK CATCH . ' GIVE-UP CATCH . 0 THROW
+        ( A throw puts back as many items as there were, but not their values )
+        1 ['] CHECK CATCH . 10 0 ['] DIVIDE CATCH . .S
+    "#;
+
+    for backend in RUN_BACKENDS {
+        let stdout = run_forth_program("test_catch", program, backend, &[]).unwrap();
+        let words: Vec<&str> = stdout.split_whitespace().collect();
+        assert_eq!(
+            words,
+            [
+                "42", "0", "7", "99", "5", "0", "-1", "-2", "-10", "<3>", "0", "0", "0"
+            ],
+            "backend {}",
+            backend
+        );
+    }
+}
+
+#[test]
+fn test_run_caught_stack_underflow_on_all_backends() {
+    let program = r#"
+        : UNDERFLOWS DROP ;
+        : SAFE ( -- code ) ['] UNDERFLOWS CATCH ;
+        : UND DROP DROP DROP ;
+        : T3 ['] UND CATCH . ;
+        ['] UNDERFLOWS CATCH . SAFE . 5 ['] UNDERFLOWS CATCH . T3 .S
+    "#;
+
+    for backend in RUN_BACKENDS {
+        let stdout = run_forth_program("test_catch_underflow", program, backend, &[]).unwrap();
+        let words: Vec<&str> = stdout.split_whitespace().collect();
+        assert_eq!(words, ["-4", "-4", "0", "-4", "<0>"], "backend {}", backend);
+    }
+}
+
+#[test]
+fn test_run_execution_tokens_on_all_backends() {
+    let program = r#"
+        VARIABLE OP
+        : APPLY OP @ EXECUTE ;
+        : SQUARE DUP * ;
+        3 4 ' + EXECUTE . ' * OP ! 5 6 APPLY . ['] SQUARE OP ! 7 APPLY .
+        DEFER GREET
+        : HELLO ." hello" ;
+        ' HELLO IS GREET GREET ACTION-OF GREET ' HELLO = .
+        DEFER NOTHING
+        ['] NOTHING CATCH . 1 2 ['] . CATCH . ' OP EXECUTE OP = . .S
+    "#;
+
+    for backend in RUN_BACKENDS {
+        let stdout = run_forth_program("test_xt", program, backend, &[]).unwrap();
+        let words: Vec<&str> = stdout.split_whitespace().collect();
+        assert_eq!(
+            words,
+            [
+                "7", "30", "49", "hello-1", "-13", "2", "0", "-1", "<1>", "1"
+            ],
+            "backend {}",
+            backend
+        );
+    }
+}
+
+#[test]
+fn test_run_mutual_recursion_on_all_backends() {
+    let program = r#"
+        : EVEN? ( n -- f ) DUP IF 1- ODD? ELSE DROP -1 THEN ;
+        : ODD? ( n -- f ) DUP IF 1- EVEN? ELSE DROP 0 THEN ;
+        10 EVEN? . 7 EVEN? . 7 ODD? . 0 ODD? .
+        DEFER PONG
+        : PING ( n -- ) DUP . DUP IF 1- PONG ELSE DROP THEN ;
+        : PONG-IMPL ( n -- ) DUP . DUP IF 1- PING ELSE DROP THEN ;
+        ' PONG-IMPL IS PONG
+        3 PING
+    "#;
+
+    // Forward references are only allowed in compiled files, not the REPL
+    for backend in COMPILED_BACKENDS {
+        let stdout = run_forth_program("test_mutual", program, backend, &[]).unwrap();
+        let words: Vec<&str> = stdout.split_whitespace().collect();
+        assert_eq!(
+            words,
+            ["-1", "0", "-1", "0", "3", "2", "1", "0"],
+            "backend {}",
+            backend
+        );
+    }
+}
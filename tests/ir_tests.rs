@@ -1,4 +1,5 @@
 use roth::ir::{IRFunction, IRInstruction, IRProgram, IRValue, StackEffect};
+use roth::types::CellWidth;
 use std::collections::HashMap;
 
 #[test]
@@ -13,12 +14,15 @@ fn test_ir_program_creation() {
         stack_effect: StackEffect {
             consumes: 0,
             produces: 1,
+            ..StackEffect::default()
         },
     };
 
     let program = IRProgram {
         functions: HashMap::new(),
         main: main_function,
+        wrappers: HashMap::new(),
+        cell_width: CellWidth::default(),
     };
 
     assert_eq!(program.main.name, "main");
@@ -40,6 +44,7 @@ fn test_ir_function_with_stack_operations() {
         stack_effect: StackEffect {
             consumes: 0,
             produces: 2,
+            ..StackEffect::default()
         },
     };
 
@@ -103,14 +108,17 @@ fn test_stack_effect_calculation() {
     let effect1 = StackEffect {
         consumes: 2,
         produces: 1,
+        ..StackEffect::default()
     }; // Like ADD: takes 2, produces 1
     let effect2 = StackEffect {
         consumes: 1,
         produces: 2,
+        ..StackEffect::default()
     }; // Like DUP: takes 1, produces 2
     let effect3 = StackEffect {
         consumes: 0,
         produces: 1,
+        ..StackEffect::default()
     }; // Like literal: takes 0, produces 1
 
     assert_eq!(effect1.consumes, 2);
@@ -133,6 +141,7 @@ fn test_ir_program_with_functions() {
         stack_effect: StackEffect {
             consumes: 1,
             produces: 1,
+            ..StackEffect::default()
         },
     };
 
@@ -147,11 +156,14 @@ fn test_ir_program_with_functions() {
         stack_effect: StackEffect {
             consumes: 0,
             produces: 1,
+            ..StackEffect::default()
         },
     };
     let program = IRProgram {
         functions,
         main: main_function,
+        wrappers: HashMap::new(),
+        cell_width: CellWidth::default(),
     };
 
     assert_eq!(program.functions.len(), 1);
@@ -281,6 +293,7 @@ fn test_complex_ir_program() {
         stack_effect: StackEffect {
             consumes: 1,
             produces: 1,
+            ..StackEffect::default()
         },
     };
 
@@ -293,15 +306,14 @@ fn test_complex_ir_program() {
             IRInstruction::Call("FACTORIAL".to_string()),
             IRInstruction::Print,
         ],
-        stack_effect: StackEffect {
-            consumes: 0,
-            produces: 0,
-        },
+        stack_effect: StackEffect::default(),
     };
 
     let program = IRProgram {
         functions,
         main: main_function,
+        wrappers: HashMap::new(),
+        cell_width: CellWidth::default(),
     };
 
     assert_eq!(program.functions.len(), 1);
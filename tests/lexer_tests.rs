@@ -1,5 +1,5 @@
 use roth::lexer::Lexer;
-use roth::types::TokenType;
+use roth::types::{CellWidth, TokenType};
 
 #[test]
 fn test_tokenize_numbers() {
@@ -32,6 +32,11 @@ fn test_tokenize_definition_markers() {
     assert_eq!(tokens[0].token_type, TokenType::StartDefinition);
     assert_eq!(tokens[1].token_type, TokenType::Word("TEST".to_string()));
     assert_eq!(tokens[2].token_type, TokenType::EndDefinition);
+
+    // Only a colon on its own starts a definition
+    let tokens = Lexer::new("{: a :}".to_string()).tokenize().unwrap();
+    assert_eq!(tokens[0].token_type, TokenType::Word("{:".to_string()));
+    assert_eq!(tokens[2].token_type, TokenType::Word(":}".to_string()));
 }
 
 #[test]
@@ -108,24 +113,51 @@ fn test_whitespace_only() {
 #[test]
 fn test_invalid_number() {
     let mut lexer = Lexer::new("999999999999999999999".to_string());
-    // Numbers that don't fit into i32 are treated as words.
-    let tokens = lexer.tokenize().unwrap();
-    assert_eq!(tokens.len(), 1);
+    // Numbers that don't fit into a cell are reported, not treated as words.
+    let errors = lexer.tokenize().unwrap_err();
+    assert_eq!(errors.len(), 1);
     assert_eq!(
-        tokens[0].token_type,
-        TokenType::Word("999999999999999999999".to_string())
+        errors[0].message,
+        "999999999999999999999 does not fit in a 64-bit cell"
     );
 }
 
 #[test]
 fn test_unclosed_comment() {
-    let mut lexer = Lexer::new("( unclosed comment".to_string());
+    let mut lexer = Lexer::new("1 2\n  ( unclosed comment\n: FOO ;".to_string());
+    let err = lexer.tokenize().unwrap_err();
+    assert_eq!(err[0].position.line, 2);
+    assert_eq!(err[0].position.column, 3);
+}
+
+#[test]
+fn test_line_comments() {
+    let mut lexer = Lexer::new("1 \\ one ( not a paren comment\n\\G glossary entry\n2".to_string());
     let tokens = lexer.tokenize().unwrap();
-    assert_eq!(tokens.len(), 1);
+
+    assert_eq!(tokens.len(), 4);
+    assert_eq!(tokens[0].token_type, TokenType::Number(1));
     assert_eq!(
-        tokens[0].token_type,
-        TokenType::Comment(" unclosed comment".to_string())
+        tokens[1].token_type,
+        TokenType::Comment("one ( not a paren comment".to_string())
+    );
+    assert_eq!(
+        tokens[2].token_type,
+        TokenType::Comment("glossary entry".to_string())
     );
+    assert_eq!(tokens[3].token_type, TokenType::Number(2));
+    assert_eq!(tokens[3].position.line, 3);
+}
+
+#[test]
+fn test_backslash_inside_word_is_not_a_comment() {
+    let mut lexer = Lexer::new("\\X 1\n\\".to_string());
+    let tokens = lexer.tokenize().unwrap();
+
+    assert_eq!(tokens.len(), 3);
+    assert_eq!(tokens[0].token_type, TokenType::Word("\\X".to_string()));
+    assert_eq!(tokens[1].token_type, TokenType::Number(1));
+    assert_eq!(tokens[2].token_type, TokenType::Comment(String::new()));
 }
 
 #[test]
@@ -151,6 +183,33 @@ fn test_escape_sequences() {
     );
 }
 
+#[test]
+fn test_quoted_string_words() {
+    let mut lexer = Lexer::new(
+        ".\" Hi there\" s\" a\\nb\" S\\\" a\\tb\\x41\\m\" C\" counted\" .\"\" abort\" no\""
+            .to_string(),
+    );
+    let tokens = lexer.tokenize().unwrap();
+
+    let values: Vec<_> = tokens.into_iter().map(|t| t.token_type).collect();
+    assert_eq!(
+        values,
+        vec![
+            TokenType::DotQuote("Hi there".to_string()),
+            // Only S\" reads escapes
+            TokenType::StringLiteral("a\\nb".to_string()),
+            TokenType::StringLiteral("a\tbA\r\n".to_string()),
+            TokenType::CQuote("counted".to_string()),
+            TokenType::DotQuote(String::new()),
+            TokenType::AbortQuote("no".to_string()),
+        ]
+    );
+
+    let long = format!("C\" {}\"", "x".repeat(256));
+    let errors = Lexer::new(long).tokenize().unwrap_err();
+    assert!(errors[0].message.contains("256 bytes"));
+}
+
 #[test]
 fn test_negative_numbers() {
     let mut lexer = Lexer::new("-42 - -0".to_string());
@@ -175,3 +234,127 @@ fn test_special_characters_in_words() {
         }
     }
 }
+
+#[test]
+fn test_prefixed_and_character_literals() {
+    let mut lexer = Lexer::new("$FF #99 %1010 $-1A 'A' -9999999999".to_string());
+    let tokens = lexer.tokenize().unwrap();
+
+    let values: Vec<_> = tokens.into_iter().map(|t| t.token_type).collect();
+    assert_eq!(
+        values,
+        vec![
+            TokenType::Number(255),
+            TokenType::Number(99),
+            TokenType::Number(10),
+            TokenType::Number(-26),
+            TokenType::Number(65),
+            TokenType::Number(-9999999999),
+        ]
+    );
+}
+
+#[test]
+fn test_double_cell_literals() {
+    let mut lexer = Lexer::new("123. -5. $10. .".to_string());
+    let tokens = lexer.tokenize().unwrap();
+
+    assert_eq!(tokens[0].token_type, TokenType::DoubleNumber(123));
+    assert_eq!(tokens[1].token_type, TokenType::DoubleNumber(-5));
+    assert_eq!(tokens[2].token_type, TokenType::DoubleNumber(16));
+    assert_eq!(tokens[3].token_type, TokenType::Word(".".to_string()));
+}
+
+#[test]
+fn test_float_literals() {
+    let mut lexer = Lexer::new("1.5e0 2e0 -25E-1 1.E 1e 1.5 HEX 1e0".to_string());
+    let tokens = lexer.tokenize().unwrap();
+
+    let values: Vec<_> = tokens.into_iter().map(|t| t.token_type).collect();
+    assert_eq!(
+        values,
+        vec![
+            TokenType::Float(1.5),
+            TokenType::Float(2.0),
+            TokenType::Float(-2.5),
+            TokenType::Float(1.0),
+            TokenType::Float(1.0),
+            // Without an exponent it is not a number at all
+            TokenType::Word("1.5".to_string()),
+            TokenType::Word("HEX".to_string()),
+            // In hex the E is a digit
+            TokenType::Number(0x1e0),
+        ]
+    );
+}
+
+#[test]
+fn test_base_switching() {
+    let mut lexer = Lexer::new("HEX FF : X HEX 10 ; DECIMAL 10 8 BASE ! 17 #17".to_string());
+    let tokens = lexer.tokenize().unwrap();
+
+    let values: Vec<_> = tokens.into_iter().map(|t| t.token_type).collect();
+    assert_eq!(
+        values,
+        vec![
+            // The words stay, to set BASE for printing at run time
+            TokenType::Word("HEX".to_string()),
+            TokenType::Number(255),
+            TokenType::StartDefinition,
+            TokenType::Word("X".to_string()),
+            // Inside a definition HEX does not change how literals are read
+            TokenType::Word("HEX".to_string()),
+            TokenType::Number(16),
+            TokenType::EndDefinition,
+            TokenType::Word("DECIMAL".to_string()),
+            TokenType::Number(10),
+            TokenType::Number(8),
+            TokenType::Word("BASE".to_string()),
+            TokenType::Word("!".to_string()),
+            TokenType::Number(15),
+            TokenType::Number(17),
+        ]
+    );
+    assert_eq!(lexer.base(), 8);
+}
+
+#[test]
+fn test_invalid_base() {
+    let mut lexer = Lexer::new("1 BASE !".to_string());
+    let err = lexer.tokenize().unwrap_err();
+    assert_eq!(err[0].position.column, 1);
+}
+
+#[test]
+fn test_literals_wrap_to_cell_width() {
+    let mut lexer =
+        Lexer::new("$FFFFFFFF 2147483648 -1. $FFFFFFFFFFFFFFFF.".to_string())
+            .with_cell_width(CellWidth::Bits32);
+    let tokens = lexer.tokenize().unwrap();
+
+    let values: Vec<_> = tokens.into_iter().map(|t| t.token_type).collect();
+    assert_eq!(
+        values,
+        vec![
+            TokenType::Number(-1),
+            TokenType::Number(-2147483648),
+            TokenType::DoubleNumber(-1),
+            TokenType::DoubleNumber(-1),
+        ]
+    );
+}
+
+#[test]
+fn test_literals_too_wide_for_cell() {
+    let errors = Lexer::new("1 4294967296 $1FFFFFFFFFFFFFFFF.".to_string())
+        .with_cell_width(CellWidth::Bits32)
+        .tokenize()
+        .unwrap_err();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].message, "4294967296 does not fit in a 32-bit cell");
+    assert_eq!(errors[0].position.column, 3);
+    assert_eq!(
+        errors[1].message,
+        "$1FFFFFFFFFFFFFFFF. does not fit in two 32-bit cells"
+    );
+}
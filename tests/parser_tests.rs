@@ -1,6 +1,6 @@
 use roth::lexer::Lexer;
 use roth::parser::Parser;
-use roth::types::AstNode;
+use roth::types::{AstNode, BeginKind, ParseError};
 
 fn parse_input(input: &str) -> Result<AstNode, Box<dyn std::error::Error>> {
     let mut lexer = Lexer::new(input.to_string());
@@ -280,3 +280,346 @@ fn test_parse_multiple_definitions() {
         _ => panic!("Expected program node"),
     }
 }
+
+#[test]
+fn test_parse_if_else_then() {
+    let ast = parse_input(": SIGN 0 < IF -1 ELSE 1 THEN ;").unwrap();
+
+    match ast {
+        AstNode::Program(nodes) => match &nodes[0] {
+            AstNode::Definition { body, .. } => {
+                assert_eq!(body.len(), 3);
+                match &body[2] {
+                    AstNode::If {
+                        then_branch,
+                        else_branch: Some(else_branch),
+                        ..
+                    } => {
+                        assert!(matches!(then_branch[..], [AstNode::Number(-1, _)]));
+                        assert!(matches!(else_branch[..], [AstNode::Number(1, _)]));
+                    }
+                    other => panic!("Expected IF node, got {:?}", other),
+                }
+            }
+            _ => panic!("Expected definition node"),
+        },
+        _ => panic!("Expected program node"),
+    }
+}
+
+#[test]
+fn test_parse_nested_loops() {
+    let ast = parse_input("10 0 ?DO BEGIN DUP WHILE 1 - REPEAT 2 +LOOP").unwrap();
+
+    match ast {
+        AstNode::Program(nodes) => {
+            assert_eq!(nodes.len(), 3);
+            match &nodes[2] {
+                AstNode::DoLoop {
+                    body,
+                    conditional: true,
+                    plus_loop: true,
+                    ..
+                } => match &body[..] {
+                    [
+                        AstNode::Begin {
+                            body: condition,
+                            kind: BeginKind::WhileRepeat(loop_body),
+                            ..
+                        },
+                        AstNode::Number(2, _),
+                    ] => {
+                        assert_eq!(condition.len(), 1);
+                        assert_eq!(loop_body.len(), 2);
+                    }
+                    other => panic!("Expected BEGIN ... REPEAT and step, got {:?}", other),
+                },
+                other => panic!("Expected ?DO ... +LOOP node, got {:?}", other),
+            }
+        }
+        _ => panic!("Expected program node"),
+    }
+}
+
+#[test]
+fn test_parse_error_cross_nested_control_flow() {
+    let err = parse_error(": BAD\n  BEGIN IF UNTIL THEN ;");
+
+    // Points at the UNTIL and names the IF it would have to close
+    assert_eq!((err.position.line, err.position.column), (2, 12));
+    assert!(err.message.contains("UNTIL"));
+    assert_eq!(err.notes[0].message, "IF opened here");
+    assert_eq!(
+        (err.notes[0].position.line, err.notes[0].position.column),
+        (2, 9)
+    );
+}
+
+#[test]
+fn test_parse_error_unmatched_closer() {
+    let err = parse_error("1 2 THEN");
+    assert_eq!(err.position.column, 5);
+    assert!(err.message.contains("THEN without matching IF"));
+}
+
+#[test]
+fn test_parse_error_unterminated_if() {
+    let err = parse_error(": BAD IF 1 ;");
+    assert_eq!(err.position.column, 12);
+    assert_eq!(err.notes[0].message, "IF opened here");
+    assert_eq!(err.notes[0].position.column, 7);
+
+    let err = parse_error("0 IF 1");
+    assert_eq!(err.position.column, 3);
+    assert!(err.message.contains("Unterminated IF"));
+}
+
+#[test]
+fn test_parse_collects_every_error() {
+    let mut lexer = Lexer::new(": A IF ;\n1 THEN\n: B : C ;\n: D 2 ;".to_string());
+    let tokens = lexer.tokenize().unwrap();
+    let mut parser = Parser::new(tokens);
+    let errors = parser.parse().unwrap_err();
+
+    let lines: Vec<_> = errors.iter().map(|err| err.position.line).collect();
+    assert_eq!(lines, vec![1, 2, 3]);
+    assert!(errors[1].message.contains("THEN without matching IF"));
+    assert!(errors[2].message.contains("Cannot nest word definitions"));
+    assert_eq!(errors[2].notes[0].position.column, 1);
+}
+
+#[test]
+fn test_parse_error_leave_outside_loop() {
+    let err = parse_error(": BAD IF LEAVE THEN ;");
+    assert!(err.message.contains("LEAVE outside of DO loop"));
+}
+
+#[test]
+fn test_parse_error_outer_loop_index_without_outer_loop() {
+    let err = parse_error(": BAD 3 0 DO J . LOOP ;");
+    assert!(err.message.contains("J outside of two nested DO loops"));
+
+    let err = parse_error(": BAD 3 0 DO 3 0 DO K . LOOP LOOP ;");
+    assert!(err.message.contains("K outside of three nested DO loops"));
+
+    let mut lexer =
+        Lexer::new(": OK 2 0 DO 2 0 DO 2 0 DO I J K + + . LOOP LOOP LOOP ;".to_string());
+    let tokens = lexer.tokenize().unwrap();
+    assert!(Parser::new(tokens).parse().is_ok());
+}
+
+#[test]
+fn test_parse_constants() {
+    let mut lexer = Lexer::new("1 2 3 2CONSTANT PAIR 4 CONSTANT FOUR".to_string());
+    let tokens = lexer.tokenize().unwrap();
+    let mut parser = Parser::new(tokens);
+    let AstNode::Program(nodes) = parser.parse().unwrap() else {
+        panic!("Expected program node");
+    };
+
+    // The 1 is left on the stack; 2 and 3 form the pair
+    assert_eq!(nodes.len(), 3);
+    assert!(matches!(nodes[0], AstNode::Number(1, _)));
+    match &nodes[1] {
+        AstNode::Constant { name, value, .. } => {
+            assert_eq!(name, "PAIR");
+            assert!(matches!(
+                value[..],
+                [AstNode::Number(2, _), AstNode::Number(3, _)]
+            ));
+        }
+        other => panic!("Expected constant, got {:?}", other),
+    }
+    assert!(matches!(&nodes[2], AstNode::Constant { name, .. } if name == "FOUR"));
+
+    // Any other value is taken from the stack when it runs
+    let mut lexer = Lexer::new("HERE 2CONSTANT SPOT".to_string());
+    let AstNode::Program(nodes) = Parser::new(lexer.tokenize().unwrap()).parse().unwrap() else {
+        panic!("Expected program node");
+    };
+    assert!(matches!(&nodes[0], AstNode::Word(word, _) if word == "HERE"));
+    assert!(matches!(
+        &nodes[1],
+        AstNode::Constant { name, value, cells: 2, .. } if name == "SPOT" && value.is_empty()
+    ));
+}
+
+#[test]
+fn test_parse_create_does() {
+    let mut lexer =
+        Lexer::new(": ARRAY CREATE CELLS ALLOT DOES> SWAP CELLS + ; 5 ARRAY DATA".to_string());
+    let tokens = lexer.tokenize().unwrap();
+    let mut parser = Parser::new(tokens);
+    let AstNode::Program(nodes) = parser.parse().unwrap() else {
+        panic!("Expected program node");
+    };
+
+    let AstNode::Definition { body, .. } = &nodes[0] else {
+        panic!("Expected definition, got {:?}", nodes[0]);
+    };
+    assert_eq!(body.len(), 4);
+    assert!(matches!(&body[0], AstNode::Word(w, _) if w == "CREATE"));
+    assert!(matches!(&body[3], AstNode::Does { body, .. } if body.len() == 3));
+
+    assert!(matches!(
+        &nodes[2],
+        AstNode::DefinedBy { word, name, .. } if word == "ARRAY" && name == "DATA"
+    ));
+    assert!(parser.defining_words().contains("ARRAY"));
+
+    let err = parse_error(": BAD 1 DOES> 2 ;");
+    assert!(err.message.contains("DOES> without CREATE"));
+}
+
+#[test]
+fn test_parse_postpone() {
+    let mut lexer = Lexer::new(": UNLESS POSTPONE 0= POSTPONE IF ;".to_string());
+    let tokens = lexer.tokenize().unwrap();
+    let mut parser = Parser::new(tokens);
+    let AstNode::Program(nodes) = parser.parse().unwrap() else {
+        panic!("Expected program node");
+    };
+
+    let AstNode::Definition { body, .. } = &nodes[0] else {
+        panic!("Expected definition, got {:?}", nodes[0]);
+    };
+    assert!(matches!(&body[0], AstNode::Postpone { name, .. } if name == "0="));
+    assert!(matches!(&body[1], AstNode::Postpone { name, .. } if name == "IF"));
+
+    let err = parse_error("POSTPONE DUP");
+    assert_eq!(err.message, "POSTPONE outside of a definition");
+    let err = parse_error(": BAD POSTPONE ;");
+    assert_eq!(err.message, "Expected name after POSTPONE");
+}
+
+#[test]
+fn test_parse_stack_signature() {
+    let mut lexer = Lexer::new(": SWAP-ADD ( a b ud -- sum ) ( not a signature ) ;".to_string());
+    let tokens = lexer.tokenize().unwrap();
+    let mut parser = Parser::new(tokens);
+    let AstNode::Program(nodes) = parser.parse().unwrap() else {
+        panic!("Expected program node");
+    };
+
+    let AstNode::Definition {
+        signature: Some(signature),
+        ..
+    } = &nodes[0]
+    else {
+        panic!("Expected definition with a signature, got {:?}", nodes[0]);
+    };
+    assert_eq!(signature.inputs, ["a", "b", "ud"]);
+    assert_eq!(signature.outputs, ["sum"]);
+    assert_eq!((signature.consumes(), signature.produces()), (4, 1));
+    assert_eq!(signature.position.column, 12);
+
+    // A float stack comment after it joins the signature
+    let mut lexer = Lexer::new(": SCALE ( n -- ) ( F: r1 r2 -- r3 ) ;".to_string());
+    let mut parser = Parser::new(lexer.tokenize().unwrap());
+    let AstNode::Program(nodes) = parser.parse().unwrap() else {
+        panic!("Expected program node");
+    };
+    let AstNode::Definition {
+        signature: Some(signature),
+        ..
+    } = &nodes[0]
+    else {
+        panic!("Expected definition with a signature, got {:?}", nodes[0]);
+    };
+    assert_eq!((signature.consumes(), signature.produces()), (1, 0));
+    assert_eq!(
+        (signature.float_consumes(), signature.float_produces()),
+        (Some(2), Some(1))
+    );
+    assert_eq!(signature.to_string(), "( n -- ) ( F: r1 r2 -- r3 )");
+
+    let mut lexer = Lexer::new(": NOTE ( just a comment ) ;".to_string());
+    let mut parser = Parser::new(lexer.tokenize().unwrap());
+    let AstNode::Program(nodes) = parser.parse().unwrap() else {
+        panic!("Expected program node");
+    };
+    assert!(matches!(
+        &nodes[0],
+        AstNode::Definition {
+            signature: None,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn test_parse_locals() {
+    let mut lexer = Lexer::new(": F {: a b | c -- r :} a TO c c DUP ;".to_string());
+    let mut parser = Parser::new(lexer.tokenize().unwrap());
+    let AstNode::Program(nodes) = parser.parse().unwrap() else {
+        panic!("Expected program node");
+    };
+
+    let AstNode::Definition { body, .. } = &nodes[0] else {
+        panic!("Expected definition, got {:?}", nodes[0]);
+    };
+    assert!(matches!(
+        &body[0],
+        AstNode::Locals { names, initialized: 2, .. } if names == &["A", "B", "C"]
+    ));
+    assert!(matches!(&body[1], AstNode::Local { name, .. } if name == "A"));
+    assert!(matches!(&body[2], AstNode::ToLocal { name, .. } if name == "C"));
+    assert!(matches!(&body[3], AstNode::Local { name, .. } if name == "C"));
+    assert!(matches!(&body[4], AstNode::Word(w, _) if w == "DUP"));
+
+    let err = parse_error(": F DUP {: a :} ;");
+    assert_eq!(
+        err.message,
+        "Locals must be declared at the start of a definition"
+    );
+    let err = parse_error(": F {: a b a :} ;");
+    assert_eq!(err.message, "Local A is declared twice");
+    let err = parse_error(": F {: a ;");
+    assert_eq!(err.message, "Unterminated {: (expected :})");
+}
+
+#[test]
+fn test_parse_execution_tokens() {
+    let mut lexer = Lexer::new(
+        ": TRY ['] RUN CATCH ; ' RUN EXECUTE DEFER D ' RUN IS D ACTION-OF D ABORT\" failed\""
+            .to_string(),
+    );
+    let mut parser = Parser::new(lexer.tokenize().unwrap());
+    let AstNode::Program(nodes) = parser.parse().unwrap() else {
+        panic!("Expected program node");
+    };
+
+    let AstNode::Definition { body, .. } = &nodes[0] else {
+        panic!("Expected definition, got {:?}", nodes[0]);
+    };
+    assert!(matches!(&body[0], AstNode::Tick { name, .. } if name == "RUN"));
+    assert!(matches!(&body[1], AstNode::Word(word, _) if word == "CATCH"));
+    assert!(matches!(&nodes[1], AstNode::Tick { name, .. } if name == "RUN"));
+    assert!(matches!(&nodes[2], AstNode::Word(word, _) if word == "EXECUTE"));
+    assert!(matches!(&nodes[3], AstNode::Defer { name, .. } if name == "D"));
+    assert!(matches!(&nodes[5], AstNode::Is { name, .. } if name == "D"));
+    assert!(matches!(&nodes[6], AstNode::ActionOf { name, .. } if name == "D"));
+    assert!(matches!(&nodes[7], AstNode::AbortQuote(text, _) if text == "failed"));
+
+    let err = parse_error("1 IS");
+    assert_eq!(err.message, "Expected name after IS");
+    let err = parse_error(": BAD DEFER D ;");
+    assert_eq!(err.message, "DEFER inside a definition is not supported");
+}
+
+#[test]
+fn test_parse_error_defining_word_inside_definition() {
+    let err = parse_error(": BAD VARIABLE X ;");
+    assert_eq!(err.position.column, 7);
+    assert!(err.message.contains("VARIABLE inside a definition"));
+
+    let err = parse_error("VARIABLE");
+    assert!(err.message.contains("Expected name after VARIABLE"));
+}
+
+fn parse_error(input: &str) -> ParseError {
+    let mut lexer = Lexer::new(input.to_string());
+    let tokens = lexer.tokenize().unwrap();
+    let mut parser = Parser::new(tokens);
+    parser.parse().unwrap_err().into_iter().next().unwrap()
+}